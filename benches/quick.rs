@@ -0,0 +1,41 @@
+// Title and artist read with quick::title_artist, against parsing the whole tag for them. Run
+// with cargo bench --bench quick
+use mp3_tool::{Tag, quick};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const FILE: &str = "test/Polygondwanaland.mp3";
+
+// Best of several rounds, the others are mostly noise from the rest of the machine
+fn time(runs: u32, mut f: impl FnMut()) -> Duration {
+    let mut round = || {
+        let start = Instant::now();
+        for _ in 0..runs {
+            f();
+        }
+        start.elapsed() / runs
+    };
+    (0..20).map(|_| round()).min().unwrap()
+}
+
+fn full(path: &str) -> Option<(String, String)> {
+    let tag = Tag::from_file(path).ok()?;
+    Some((tag.title()?, tag.artist()?))
+}
+
+fn main() {
+    // The fixture's tag is mostly a 180 KB cover after the title and artist, read by the full parse
+    // and never reached by quick
+    assert_eq!(quick::title_artist(FILE), full(FILE));
+
+    let runs = 50;
+    let before = time(runs, || {
+        black_box(full(black_box(FILE)));
+    });
+    let after = time(runs, || {
+        black_box(quick::title_artist(black_box(FILE)));
+    });
+    println!("full parse {before:>12?} per file");
+    println!("quick      {after:>12?} per file");
+    println!("speedup    {:>11.2}x", before.as_secs_f64() / after.as_secs_f64());
+}
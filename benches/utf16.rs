@@ -0,0 +1,50 @@
+// UTF-16 decoding of lyrics-sized text, against the decoder it replaced that collected the code
+// units into a Vec first. Run with cargo bench --bench utf16
+use mp3_tool::Frame;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// The whole path parse_text took: split off the terminated string, skip the BOM, then find the
+// terminator again while collecting the units
+fn previous(bytes: &[u8]) -> String {
+    let end = (0..bytes.len() / 2).map(|i| 2*i).find(|i| bytes[*i] == 0 && bytes[i+1] == 0).unwrap_or(bytes.len());
+    let bytes = &bytes[2..end];
+    let end = (0..bytes.len() / 2).map(|i| 2*i).find(|i| bytes[*i] == 0 && bytes[i+1] == 0).unwrap_or(bytes.len());
+    let units: Vec<u16> = bytes[..end].chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+// Best of several rounds, the others are mostly noise from the rest of the machine
+fn time(runs: u32, mut f: impl FnMut()) -> Duration {
+    let mut round = || {
+        let start = Instant::now();
+        for _ in 0..runs {
+            f();
+        }
+        start.elapsed() / runs
+    };
+    (0..20).map(|_| round()).min().unwrap()
+}
+
+fn main() {
+    // About 360 KB of mostly Latin lines with some CJK and a surrogate pair on each
+    let line = "Crumbling castle, Ōkami no mori 狼の森 🐺 and the lizard wizard\n";
+    let lyrics = line.repeat(3000);
+    let mut data = vec![1, 0xFF, 0xFE];
+    data.extend(lyrics.encode_utf16().flat_map(u16::to_le_bytes));
+    let frame = Frame::new("TEXT", data.clone()).unwrap();
+    assert_eq!(frame.parse_text(), lyrics);
+    assert_eq!(previous(&data[1..]), lyrics);
+
+    let runs = 20;
+    let before = time(runs, || {
+        black_box(previous(black_box(&data[1..])));
+    });
+    let after = time(runs, || {
+        black_box(black_box(&frame).parse_text());
+    });
+    println!("{} bytes of UTF-16", data.len());
+    println!("previous   {before:>12?} per decode");
+    println!("current    {after:>12?} per decode");
+    println!("speedup    {:>11.2}x", before.as_secs_f64() / after.as_secs_f64());
+}
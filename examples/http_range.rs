@@ -0,0 +1,70 @@
+// Reads the tag of an MP3 served over plain HTTP without downloading its audio, every range is
+// one request with a Range header. Run with cargo run --example http_range http://host/file.mp3
+use mp3_tool::{RangeReader, Tag};
+use std::env;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+struct HttpRanges {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl HttpRanges {
+    // Only http:// URLs, a TLS client would go where the TcpStream is opened for https
+    fn new(url: &str) -> io::Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| Error::new(ErrorKind::InvalidInput, "expected an http:// URL"))?;
+        let (authority, path) = match rest.split_once('/') {
+            Some((authority, path)) => (authority, format!("/{path}")),
+            None => (rest, "/".to_string()),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| Error::new(ErrorKind::InvalidInput, format!("invalid port {port}")))?),
+            None => (authority, 80),
+        };
+        Ok(Self { host: host.to_string(), port, path })
+    }
+}
+
+impl RangeReader for HttpRanges {
+    fn read_range(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        // HTTP/1.0 so the body comes whole rather than chunked and the server closes after it
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        let last = offset + len as u64 - 1;
+        write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nRange: bytes={offset}-{last}\r\n\r\n", self.path, self.host)?;
+
+        let mut response = BufReader::new(stream);
+        let mut status = String::new();
+        response.read_line(&mut status)?;
+        let mut line = String::new();
+        while response.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        match status.split_whitespace().nth(1) {
+            Some("206") => {}
+            // The range starts past the end of the file
+            Some("416") => return Ok(Vec::new()),
+            // Servers that don't do ranges send the whole file, everything before the range is skipped
+            Some("200") => {
+                io::copy(&mut (&mut response).take(offset), &mut io::sink())?;
+            }
+            _ => return Err(Error::other(format!("unexpected response {}", status.trim_end()))),
+        }
+        let mut bytes = Vec::with_capacity(len);
+        response.take(len as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let Some(url) = env::args().nth(1) else {
+        return Err(Error::new(ErrorKind::InvalidInput, "usage: http_range <http://host/file.mp3>"));
+    };
+    let tag = Tag::from_ranges(&mut HttpRanges::new(&url)?)?;
+    println!("{tag}");
+    Ok(())
+}
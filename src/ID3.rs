@@ -1,36 +1,105 @@
+use crate::diagnostics::{Diagnostics, Finding, is_known};
+use crate::digest::{crc32, sha1};
+use crate::lazy::LazyFrame;
+use crate::paths::long_path;
+use crate::sidecar::{has_sidecar, sidecar_path};
+use crate::transcode;
+use crate::wire;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
 use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
 
-fn utf16_from_bytes(bytes: &[u8]) -> String {
-    let bom = ((bytes[0] as u16) << 8) + bytes[1] as u16;
-    let normal_order = if bom == 65534 {
-        true
-    } else if bom == 65279 {
-        false
-    } else {
-        return String::new();
-    };
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextError {
+    MissingBom,
+    OddLength,
+    UnpairedSurrogate(u16),
+    InvalidUtf8,
+    UnknownEncoding(u8),
+}
 
-    let mut string = String::new();
-    for i in (2..bytes.len()).step_by(2) {
-        if bytes[i] + bytes[i+1] == 0 {
-            break;
+impl std::fmt::Display for TextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TextError::MissingBom => write!(f, "UTF-16 text has no byte order mark"),
+            TextError::OddLength => write!(f, "UTF-16 text has an odd number of bytes"),
+            TextError::UnpairedSurrogate(unit) => write!(f, "UTF-16 text has an unpaired surrogate {unit:#06X}"),
+            TextError::InvalidUtf8 => write!(f, "Text is not valid UTF-8"),
+            TextError::UnknownEncoding(encoding) => write!(f, "Unknown text encoding {encoding}"),
         }
+    }
+}
 
-        let (first, second): (u16, u16) = if !normal_order {
-            (bytes[i] as u16, bytes[i+1] as u16)
-        } else {
-            (bytes[i+1] as u16, bytes[i] as u16)
+impl std::error::Error for TextError {}
+
+// Code units up to the first aligned terminator, a trailing odd byte is left out
+fn utf16_units(bytes: &[u8], big_endian: bool) -> impl Iterator<Item = u16> + Clone + '_ {
+    let unit = move |pair: &[u8]| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) };
+    bytes.chunks_exact(2).map(unit).take_while(|unit| *unit != 0)
+}
+
+// Decodes straight from the bytes into a string sized once, see benches/utf16.rs. ASCII and
+// other units outside the surrogate range are characters of their own, only surrogates go
+// through decode_utf16 with the unit after them. Bad units become U+FFFD
+fn utf16_lossy(bytes: &[u8], big_endian: bool) -> String {
+    let mut text = String::with_capacity(bytes.len() / 2 * 3);
+    let mut units = utf16_units(bytes, big_endian);
+    while let Some(unit) = units.next() {
+        if unit < 0x80 {
+            text.push(unit as u8 as char);
+            continue;
+        }
+        let c = match char::from_u32(unit as u32) {
+            Some(c) => c,
+            None => {
+                let c = char::decode_utf16(std::iter::once(unit).chain(units.clone().next())).next().unwrap();
+                let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                if c.len_utf16() == 2 {
+                    units.next();
+                }
+                c
+            }
         };
+        text.push(c);
+    }
+    text
+}
 
-        let utf_val = (first << 8) + second;
-        string.push_str(&String::from_utf16_lossy(&[utf_val]));
-    };
+fn utf16_bom(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [0xFF, 0xFE, ..] => Some(false),
+        [0xFE, 0xFF, ..] => Some(true),
+        _ => None,
+    }
+}
 
-    string
+fn utf16_strict(bytes: &[u8], big_endian: bool) -> Result<String, TextError> {
+    // An odd byte only matters when no terminator comes before it
+    if bytes.len() % 2 == 1 && !bytes.chunks_exact(2).any(|pair| pair == [0, 0]) {
+        return Err(TextError::OddLength);
+    }
+    char::decode_utf16(utf16_units(bytes, big_endian)).map(|c| c.map_err(|e| TextError::UnpairedSurrogate(e.unpaired_surrogate()))).collect()
+}
+
+// Encoding 1, every string starts with its own BOM
+fn utf16_from_bytes_strict(bytes: &[u8]) -> Result<String, TextError> {
+    if bytes.is_empty() {
+        return Ok(String::new());
+    }
+    let big_endian = utf16_bom(bytes).ok_or(TextError::MissingBom)?;
+    utf16_strict(&bytes[2..], big_endian)
+}
+
+// Missing BOMs are read as little endian, bad units become U+FFFD and an odd last byte is dropped
+fn utf16_from_bytes(bytes: &[u8]) -> String {
+    let (bytes, big_endian) = match utf16_bom(bytes) {
+        Some(big_endian) => (&bytes[2..], big_endian),
+        None => (bytes, false),
+    };
+    utf16_lossy(bytes, big_endian)
 }
 
 fn ascii_from_bytes(bytes: &[u8]) -> String {
@@ -44,6 +113,105 @@ fn ascii_from_bytes(bytes: &[u8]) -> String {
     string
 }
 
+fn utf8_from_bytes(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+// Decode text using the encoding byte that starts text frames, through the transcoder in use
+pub(crate) fn text_from_bytes(encoding: u8, bytes: &[u8]) -> String {
+    transcode::decode_lossy(encoding, bytes)
+}
+
+// Like text_from_bytes but reports text that can't be decoded exactly
+pub(crate) fn text_from_bytes_strict(encoding: u8, bytes: &[u8]) -> Result<String, TextError> {
+    transcode::decode(encoding, bytes)
+}
+
+// Encode text without a terminator
+pub(crate) fn bytes_from_text(encoding: u8, text: &str) -> Vec<u8> {
+    transcode::encode(encoding, text)
+}
+
+pub(crate) fn spec_text_from_bytes(encoding: u8, bytes: &[u8]) -> String {
+    match encoding {
+        0 => ascii_from_bytes(bytes),
+        1 => utf16_from_bytes(bytes),
+        2 => utf16_lossy(bytes, true),
+        3 => utf8_from_bytes(bytes),
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn spec_text_from_bytes_strict(encoding: u8, bytes: &[u8]) -> Result<String, TextError> {
+    match encoding {
+        0 => Ok(ascii_from_bytes(bytes)),
+        1 => utf16_from_bytes_strict(bytes),
+        2 => utf16_strict(bytes, true),
+        3 => {
+            let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+            String::from_utf8(bytes[..end].to_vec()).map_err(|_| TextError::InvalidUtf8)
+        }
+        _ => Err(TextError::UnknownEncoding(encoding)),
+    }
+}
+
+// UTF-16 is written little endian with a BOM
+pub(crate) fn spec_bytes_from_text(encoding: u8, text: &str) -> Vec<u8> {
+    match encoding {
+        0 => text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }).collect(),
+        1 => utf16_bytes(text, false, true),
+        2 => utf16_bytes(text, true, false),
+        _ => text.as_bytes().to_vec(),
+    }
+}
+
+pub(crate) fn utf16_bytes(text: &str, big_endian: bool, bom: bool) -> Vec<u8> {
+    let bom = bom.then_some(0xFEFF);
+    bom.into_iter().chain(text.encode_utf16()).flat_map(|x| if big_endian { x.to_be_bytes() } else { x.to_le_bytes() }).collect()
+}
+
+// Split off the first terminated string, terminators are two aligned zero bytes for UTF-16
+pub(crate) fn split_terminated(encoding: u8, bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = if encoding == 1 || encoding == 2 {
+        bytes.chunks_exact(2).position(|pair| pair == [0, 0]).map(|i| 2*i)
+    } else {
+        bytes.iter().position(|x| *x == 0)
+    };
+
+    match end {
+        Some(end) if encoding == 1 || encoding == 2 => (&bytes[..end], &bytes[end+2..]),
+        Some(end) => (&bytes[..end], &bytes[end+1..]),
+        None => (bytes, &[]),
+    }
+}
+
+pub(crate) fn terminator(encoding: u8) -> &'static [u8] {
+    if encoding == 1 || encoding == 2 { &[0, 0] } else { &[0] }
+}
+
+// Decode the first terminated string and return the bytes after its terminator
+pub(crate) fn read_terminated(encoding: u8, bytes: &[u8]) -> (String, &[u8]) {
+    let (text, rest) = split_terminated(encoding, bytes);
+    (text_from_bytes(encoding, text), rest)
+}
+
+pub(crate) fn write_terminated(data: &mut Vec<u8>, encoding: u8, text: &str) {
+    data.extend(bytes_from_text(encoding, text));
+    data.extend_from_slice(terminator(encoding));
+}
+
+// Latin-1 when every character fits, otherwise UTF-8 for v2.4 and UTF-16 before that
+pub(crate) fn encoding_for(text: &str, major_ver: u8) -> u8 {
+    if text.chars().all(|c| (c as u32) < 256) {
+        0
+    } else if major_ver == 4 {
+        3
+    } else {
+        1
+    }
+}
+
 fn string_from_bytes(bytes: &[u8]) -> Option<String>{
     let mut string = String::new();
     for byte in bytes {
@@ -59,40 +227,113 @@ fn header_exists(file: &[u8]) -> bool {
     // Data must be atleast 10 bytes
     if file.len() < 10 { return false; }
 
+    // v2.4 adds a fourth flag bit for the footer: https://id3.org/id3v2.4.0-structure
+    // and v2.2 has only two: https://id3.org/id3v2-00
+    let unused_flags = match file[3] { 2 => 6, 4 => 4, _ => 5 };
+
     // Check if header matches format given by: https://id3.org/id3v2.3.0#ID3v2_header 
-    file[0..3] == "ID3".bytes().collect::<Vec<u8>>() &&    // ID3
-    file[3] == 3 &&                                        // Major ver
-    file[4] == 0 &&                                        // Minor ver
-    (0..5).map(|x| (1 << x) & file[5]).all(|x| x == 0) &&  // Only 3 flag bits allowed
-    file[6..10].iter().all(|x| *x < 128)                   // Size in sync-safe int
+    file[0..3] == "ID3".bytes().collect::<Vec<u8>>() &&                // ID3
+    (2..=4).contains(&file[3]) &&                                      // Major ver
+    file[4] == 0 &&                                                    // Minor ver
+    (0..unused_flags).map(|x| (1 << x) & file[5]).all(|x| x == 0) &&  // Only 2, 3 or 4 flag bits allowed
+    file[6..10].iter().all(|x| *x < 128)                               // Size in sync-safe int
+}
+
+pub(crate) fn u32_from_sync_safe(bytes: &[u8]) -> u32 {
+    (0..4).map(|x| { ((bytes[x] & 0x7F) as u32) << (7*(3-x)) }).sum()
 }
 
-struct Reader {
-    reader: BufReader<File>,
+pub(crate) fn sync_safe_from_u32(value: u32) -> [u8; 4] {
+    [(value >> 21) as u8 & 0x7F, (value >> 14) as u8 & 0x7F, (value >> 7) as u8 & 0x7F, value as u8 & 0x7F]
+}
+
+enum Source {
+    File(BufReader<File>),
+    // Pipes and stdin can't seek so skipping reads and throws the bytes away
+    Stream(BufReader<Box<dyn Read + Send>>),
+}
+
+pub struct Reader {
+    reader: Source,
+    path: Option<PathBuf>,
+    position: u64,
 }
 
 impl Reader {
-    fn from_file(filename: &str) -> io::Result<Self>{
-        let file = File::open(filename)?;
-        let reader = BufReader::new(file);
+    pub fn from_file(filename: impl AsRef<Path>) -> io::Result<Self>{
+        let file = File::open(long_path(&filename))?;
+        let reader = Source::File(BufReader::new(file));
         Ok(Self{
-            reader 
+            reader,
+            path: Some(filename.as_ref().to_path_buf()),
+            position: 0,
         })
     }
 
-    fn skip_n_bytes(&mut self, n: usize) -> io::Result<()>{
-        self.reader.seek_relative(n as i64)
+    pub fn from_stream(stream: impl Read + Send + 'static) -> Self {
+        Self {
+            reader: Source::Stream(BufReader::new(Box::new(stream))),
+            path: None,
+            position: 0,
+        }
+    }
+
+    pub fn skip_n_bytes(&mut self, n: usize) -> io::Result<()>{
+        match &mut self.reader {
+            Source::File(reader) => reader.seek_relative(n as i64)?,
+            Source::Stream(reader) => {
+                let skipped = io::copy(&mut reader.take(n as u64), &mut io::sink())?;
+                if skipped < n as u64 {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "Stream ended while skipping"));
+                }
+            }
+        }
+        self.position += n as u64;
+        Ok(())
     }
 
-    fn read_n_bytes(&mut self, n: usize) -> io::Result<Vec<u8>> {
+    pub fn read_n_bytes(&mut self, n: usize) -> io::Result<Vec<u8>> {
         let mut buf: Vec<u8> = vec![0; n];
-        self.reader.read_exact(&mut buf)?;
+        match &mut self.reader {
+            Source::File(reader) => reader.read_exact(&mut buf)?,
+            Source::Stream(reader) => reader.read_exact(&mut buf)?,
+        }
+        self.position += n as u64;
         Ok(buf)
     }
 
+    // Bytes read or skipped so far
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // The file being read, streams don't have one
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // Up to n bytes starting offset bytes ahead, without moving past anything. None for a
+    // stream that hasn't buffered that far
+    pub(crate) fn peek_at(&mut self, offset: usize, n: usize) -> io::Result<Option<Vec<u8>>> {
+        let mut buf = Vec::new();
+        match &mut self.reader {
+            Source::File(reader) => {
+                reader.seek_relative(offset as i64)?;
+                reader.take(n as u64).read_to_end(&mut buf)?;
+                reader.seek_relative(-((offset + buf.len()) as i64))?;
+            }
+            Source::Stream(reader) => match reader.fill_buf()?.get(offset..offset + n) {
+                Some(bytes) => buf.extend_from_slice(bytes),
+                None => return Ok(None),
+            },
+        }
+        Ok(Some(buf))
+    }
+
 }
 
-struct Header {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
     major_ver: u8,
     minor_ver: u8,
     flags: u8,
@@ -100,9 +341,9 @@ struct Header {
 }
 
 impl Header {
-    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
         // Return none if no valid header
-        if header_exists(bytes) == false {
+        if !header_exists(bytes) {
             return None;
         }
 
@@ -114,10 +355,10 @@ impl Header {
         })
     }
 
-    fn from_reader(reader: &mut Reader) -> io::Result<Self> {
+    pub fn from_reader(reader: &mut Reader) -> io::Result<Self> {
         let bytes = reader.read_n_bytes(10)?;
 
-        if header_exists(&bytes) == false {
+        if !header_exists(&bytes) {
             return Err(Error::new(ErrorKind::InvalidData, "File contains no ID3 header"));
         }
         
@@ -129,43 +370,61 @@ impl Header {
         })
     }
 
-    fn size(&self) -> u64 {
-        (0..4).map(|x| { (self.size[x] as u64) << 7*(3-x) }).sum()
+    pub fn version(&self) -> (u8, u8) {
+        (self.major_ver, self.minor_ver)
+    }
+
+    pub fn size(&self) -> u64 {
+        (0..4).map(|x| { (self.size[x] as u64) << (7*(3-x)) }).sum()
     }
 
-    fn unsynchronisation(&self) -> bool {
+    pub fn unsynchronisation(&self) -> bool {
         // Check if first flag bit is set
         (self.flags & 0b_10000000) >> 7 == 1
     }
 
-    fn extended_header(&self) -> bool {
-        // Check if second flag bit is set
-        (self.flags & 0b_01000000) >> 6 == 1
+    pub fn extended_header(&self) -> bool {
+        // Check if second flag bit is set, which v2.2 uses for compression instead
+        self.major_ver != 2 && (self.flags & 0b_01000000) >> 6 == 1
     }
 
-    fn experimental(&self) -> bool {
+    pub fn experimental(&self) -> bool {
         // Check if third flag bit is set
         (self.flags & 0b_00100000) >> 5 == 1
     }
+
+    pub fn footer(&self) -> bool {
+        // Check if fourth flag bit is set, only defined for v2.4
+        self.major_ver == 4 && (self.flags & 0b_00010000) >> 4 == 1
+    }
+
+    // Bytes taken up by the whole tag including header and footer
+    pub fn tag_size(&self) -> u64 {
+        10 + self.size() + if self.footer() { 10 } else { 0 }
+    }
 }
 
-struct ExtendedHeader {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedHeader {
+    major_ver: u8,
     size: [u8; 4],
+    // v2.4 has a single flag byte, kept in the first
     flags: [u8; 2],
     padding_size: [u8; 4],
-    crc: Option<[u8; 4]>
+    crc: Option<[u8; 4]>,
+    // v2.4 tag restrictions byte
+    restrictions: Option<u8>,
 }
 
 impl ExtendedHeader {
-    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
         // Skip if note enough bytes to get len
         if bytes.len() < 4{
             return None;
         }
 
         // Skip if not enough bytes for entire extended header
-        let length: u64 = (0..4).map(|x| {(bytes[x] as u64) << 8*(3-x)}).sum();
-        println!("{length}");
+        let length: u64 = (0..4).map(|x| {(bytes[x] as u64) << (8*(3-x))}).sum();
         if (bytes.len() as u64) < length + 4 {
             return None;
         }
@@ -179,16 +438,57 @@ impl ExtendedHeader {
         
         // Create and return extended header
         Some(Self{
+            major_ver: 3,
             size: [bytes[0], bytes[1], bytes[2], bytes[3]],
             flags: [bytes[4], bytes[5]],
             padding_size: [bytes[6], bytes[7], bytes[8], bytes[9]],
-            crc
+            crc,
+            restrictions: None,
+        })
+    }
+
+    // The v2.4 layout: https://id3.org/id3v2.4.0-structure section 3.2. A sync-safe size of the
+    // whole header, the number of flag bytes, the flags, then the data of each set flag in order
+    pub fn from_bytes_v24(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 6 || bytes[4] != 1 || u32_from_sync_safe(bytes) as usize > bytes.len() {
+            return None;
+        }
+        let flags = bytes[5];
+        let mut rest = &bytes[6..u32_from_sync_safe(bytes) as usize];
+        let mut field = |flag: u8| -> Option<Option<&[u8]>> {
+            if flags & flag == 0 {
+                return Some(None);
+            }
+            let (length, data) = rest.split_first()?;
+            let (value, remaining) = data.split_at_checked(*length as usize)?;
+            rest = remaining;
+            Some(Some(value))
+        };
+        field(0b_01000000)?;
+        // A 35 bit sync-safe integer, the top bits don't fit the 32 bit CRC
+        let crc = field(0b_00100000)?.map(|bytes| {
+            let crc = bytes.iter().fold(0u64, |crc, byte| (crc << 7) | (*byte & 0x7F) as u64);
+            (crc as u32).to_be_bytes()
+        });
+        let restrictions = field(0b_00010000)?.and_then(|bytes| bytes.first().copied());
+
+        Some(Self {
+            major_ver: 4,
+            size: bytes[..4].try_into().unwrap(),
+            flags: [flags, 0],
+            padding_size: [0; 4],
+            crc,
+            restrictions,
         })
     }
 
-    fn from_reader(reader: &mut Reader) -> io::Result<Self> {
+    // body_size is what is left of the tag, an extended header can't be larger
+    pub fn from_reader(reader: &mut Reader, body_size: u64) -> io::Result<Self> {
         let size = reader.read_n_bytes(4)?;
-        let more: u64 = (0..4).map(|x| {(size[x] as u64) << 8*(3-x)}).sum();
+        let more: u64 = (0..4).map(|x| {(size[x] as u64) << (8*(3-x))}).sum();
+        if more < 6 || more + 4 > body_size {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid ID3v2.3 extended header"));
+        }
         let remaining = reader.read_n_bytes(more as usize)?;
 
         // Get CRC if header is big enough
@@ -199,70 +499,908 @@ impl ExtendedHeader {
         };
 
         Ok(Self{
+            major_ver: 3,
             size: [size[0], size[1], size[2], size[3]],
             flags: [remaining[0], remaining[1]],
             padding_size: [remaining[2], remaining[3], remaining[4], remaining[5]],
-            crc
+            crc,
+            restrictions: None,
         })
     }
 
-    fn padding_size(&self) -> u64 {
-        (0..4).map(|i| {(self.padding_size[i] as u64) << 8*(3-i)}).sum()
+    pub fn from_reader_v24(reader: &mut Reader, body_size: u64) -> io::Result<Self> {
+        let invalid = || Error::new(ErrorKind::InvalidData, "Invalid ID3v2.4 extended header");
+        let size = reader.read_n_bytes(4)?;
+        let total = u32_from_sync_safe(&size) as u64;
+        if total < 6 || total > body_size {
+            return Err(invalid());
+        }
+        let remaining = reader.read_n_bytes(total as usize - 4)?;
+        Self::from_bytes_v24(&[size, remaining].concat()).ok_or_else(invalid)
     }
 
-    fn size(&self) -> u64 {
-        (0..4).map(|i| {(self.size[i] as u64) << 8*(3-i)}).sum()
+    // A v2.4 extended header marking the tag as an update of one found earlier in the file
+    pub fn update() -> Self {
+        Self {
+            major_ver: 4,
+            size: sync_safe_from_u32(7),
+            flags: [0b_01000000, 0],
+            padding_size: [0; 4],
+            crc: None,
+            restrictions: None,
+        }
     }
 
-    fn has_padding(&self) -> bool {
-        (self.flags[0] & 0b_10000000) >> 7 == 1
+    pub fn padding_size(&self) -> u64 {
+        (0..4).map(|i| {(self.padding_size[i] as u64) << (8*(3-i))}).sum()
+    }
+
+    // Bytes after the size field
+    pub fn size(&self) -> u64 {
+        match self.major_ver {
+            4 => (u32_from_sync_safe(&self.size) as u64).saturating_sub(4),
+            _ => (0..4).map(|i| {(self.size[i] as u64) << (8*(3-i))}).sum(),
+        }
     }
+
+    pub fn has_padding(&self) -> bool {
+        self.major_ver != 4 && (self.flags[0] & 0b_10000000) >> 7 == 1
+    }
+
+    pub fn crc(&self) -> Option<[u8; 4]> {
+        self.crc
+    }
+
+    // Frames in this tag replace the same frames in an earlier tag, only defined for v2.4
+    pub fn is_update(&self) -> bool {
+        self.major_ver == 4 && self.flags[0] & 0b_01000000 != 0
+    }
+
+    pub fn restrictions(&self) -> Option<u8> {
+        self.restrictions
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.major_ver == 4 {
+            return self.to_bytes_v24();
+        }
+        let mut bytes = self.size.to_vec();
+        bytes.extend_from_slice(&self.flags);
+        bytes.extend_from_slice(&self.padding_size);
+        if let Some(crc) = self.crc {
+            bytes.extend_from_slice(&crc);
+        }
+        bytes
+    }
+
+    // Built again from the fields so the size always matches what is written
+    fn to_bytes_v24(&self) -> Vec<u8> {
+        let flags = (self.flags[0] & 0b_01000000)
+            | if self.crc.is_some() { 0b_00100000 } else { 0 }
+            | if self.restrictions.is_some() { 0b_00010000 } else { 0 };
+        let mut body = vec![1, flags];
+        if self.is_update() {
+            body.push(0);
+        }
+        if let Some(crc) = self.crc {
+            let crc = u32::from_be_bytes(crc);
+            body.push(5);
+            body.extend((0..5).rev().map(|x| (crc as u64 >> (7 * x)) as u8 & 0x7F));
+        }
+        if let Some(restrictions) = self.restrictions {
+            body.push(1);
+            body.push(restrictions);
+        }
+        let mut bytes = sync_safe_from_u32(body.len() as u32 + 4).to_vec();
+        bytes.extend(body);
+        bytes
+    }
+}
+
+// Frame status and format flags shared by every version that has them. Grouping, encryption
+// and the data length are kept apart, see Frame::group, Frame::encryption and Frame::data_length
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameFlags {
+    // Drop the frame when a tagger that doesn't know it changes the tag
+    pub discard_on_tag_change: bool,
+    // Drop the frame when the audio is changed
+    pub discard_on_file_change: bool,
+    pub read_only: bool,
+    // The data is zlib compressed
+    pub compressed: bool,
 }
 
-struct Frame {
+// A frame independent of the version it was read from, see wire for how each version lays it out
+#[derive(Clone)]
+pub struct Frame {
     id: [u8; 4],
     size: [u8; 4],
-    flags: [u8; 2],
+    flags: FrameFlags,
+    group: Option<u8>,
+    // Encryption method symbol, the data stays encrypted
+    encryption: Option<u8>,
+    // The size of the data before compression, or as a v2.4 data length indicator gave it
+    data_length: Option<u32>,
     data: Vec<u8>,
+    // Bytes as read from the file, dropped as soon as the frame is modified
+    raw: Option<(u8, Vec<u8>)>,
+    // Offset of the header and the version of the tag it was read from
+    read_at: Option<(u64, u8)>,
+}
+
+// Where a frame came from, so what the parser saw can be shown next to what will be written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    // Offset of the frame header from the start of what was read, None for frames made since
+    pub offset: Option<u64>,
+    // Major version of the tag the frame was read from
+    pub version: Option<u8>,
+    // Whether the frame will be written other than byte for byte as it was read. Frames made
+    // since reading and ones whose header had the wrong size count as modified
+    pub modified: bool,
+}
+
+// Undoes frame level unsynchronisation, every 0xFF 0x00 had the zero inserted
+pub(crate) fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (i, byte) in data.iter().enumerate() {
+        if !(*byte == 0x00 && i > 0 && data[i - 1] == 0xFF) {
+            out.push(*byte);
+        }
+    }
+    out
 }
 
 impl Frame {
-    fn from_reader(reader: &mut Reader) -> io::Result<Self> {
-        let header = reader.read_n_bytes(10)?;
-        let size: u64 = (0..4).map(|x| {(header[4+x] as u64) << 8*(3-x)}).sum();
+    pub fn new(id: &str, data: Vec<u8>) -> Option<Self> {
+        let id: [u8; 4] = id.as_bytes().try_into().ok()?;
+        if !wire::valid_id(&id) {
+            return None;
+        }
+
+        Some(Self::from_parts(id, FrameFlags::default(), data))
+    }
+
+    pub(crate) fn from_parts(id: [u8; 4], flags: FrameFlags, data: Vec<u8>) -> Self {
+        Self {
+            id,
+            size: (data.len() as u32).to_be_bytes(),
+            flags,
+            group: None,
+            encryption: None,
+            data_length: None,
+            data,
+            raw: None,
+            read_at: None,
+        }
+    }
+
+    // The parts a version stores around the data, for the wire adapters
+    pub(crate) fn set_layout(&mut self, encryption: Option<u8>, group: Option<u8>, data_length: Option<u32>) {
+        self.encryption = encryption;
+        self.group = group;
+        self.data_length = data_length;
+    }
+
+    pub fn from_reader(reader: &mut Reader, major_ver: u8) -> io::Result<Self> {
+        let header = reader.read_n_bytes(wire::header_len(major_ver))?;
+        let size = wire::body_size(&header, major_ver);
+        Self::from_header(reader, header, size, major_ver)
+    }
+
+    // Reads the body of a frame whose header has already been read. A size other than the
+    // header's means the header was wrong, so the raw bytes aren't kept to be written again
+    pub(crate) fn from_header(reader: &mut Reader, header: Vec<u8>, size: u32, major_ver: u8) -> io::Result<Self> {
+        let offset = reader.position() - header.len() as u64;
         let data = reader.read_n_bytes(size as usize)?;
+        let raw = (size == wire::body_size(&header, major_ver)).then(|| (major_ver, [&header[..], &data[..]].concat()));
+        let Some(mut frame) = wire::decode(&header, data, major_ver) else {
+            if major_ver != 2 {
+                let id = String::from_utf8_lossy(&header[..4]);
+                return Err(Error::new(ErrorKind::InvalidData, format!("Frame {id} has an invalid id")));
+            }
+            let id = String::from_utf8_lossy(&header[..3]);
+            return Err(Error::new(ErrorKind::InvalidData, format!("Frame {id} has no ID3v2.3 equivalent")));
+        };
+        frame.size = size.to_be_bytes();
+        frame.raw = raw;
+        frame.read_at = Some((offset, major_ver));
+        Ok(frame)
+    }
 
-        Ok(Self{
-            id: [header[0], header[1], header[2], header[3]],
-            size: [header[4], header[5], header[6], header[7]],
-            flags: [header[8], header[9]],
-            data
-        })
+    // Empty for a v2.2 tag when the frame has no v2.2 id
+    pub fn to_bytes(&self, major_ver: u8) -> Vec<u8> {
+        wire::encode(self, major_ver)
+    }
+
+    // Ids are checked when frames are made or read, lossy only so a bad one can never panic
+    pub fn id(&self) -> String {
+        String::from_utf8_lossy(&self.id).into_owned()
+    }
+
+    pub fn size(&self) -> u64 {
+        (0..4).map(|x| {(self.size[x] as u64) << (8*(3-x))}).sum()
+    }
+
+    pub fn flags(&self) -> FrameFlags {
+        self.flags
+    }
+
+    pub fn encryption(&self) -> Option<u8> {
+        self.encryption
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Length from the v2.4 data length indicator, which data() no longer starts with
+    pub fn data_length(&self) -> Option<u32> {
+        self.data_length
+    }
+
+    // Frames that weren't read from a file count as modified
+    pub fn is_modified(&self) -> bool {
+        self.raw.is_none()
+    }
+
+    pub fn provenance(&self) -> Provenance {
+        Provenance {
+            offset: self.read_at.map(|(offset, _)| offset),
+            version: self.read_at.map(|(_, version)| version),
+            modified: self.is_modified(),
+        }
+    }
+
+    // For frames read from a stream that started partway into the file
+    pub(crate) fn read_at_offset(mut self, offset: u64) -> Self {
+        self.read_at = self.read_at.map(|(_, version)| (offset, version));
+        self
+    }
+
+    // Original bytes, only usable when writing the same version they were read as
+    pub(crate) fn raw_bytes(&self, major_ver: u8) -> Option<&[u8]> {
+        self.raw.as_ref().filter(|(version, _)| *version == major_ver).map(|(_, raw)| raw.as_slice())
+    }
+
+    pub fn group(&self) -> Option<u8> {
+        self.group
+    }
+
+    // Flags, encryption and data length as they were read, for formats that carry a frame whole
+    pub(crate) fn with_flags(mut self, flags: FrameFlags, encryption: Option<u8>, data_length: Option<u32>) -> Self {
+        self.flags = flags;
+        self.encryption = encryption;
+        self.data_length = data_length;
+        self.raw = None;
+        self
+    }
+
+    pub fn set_group(&mut self, group: Option<u8>) {
+        self.group = group;
+        self.raw = None;
+        self.size = ((self.data.len() + group.iter().len()) as u32).to_be_bytes();
+    }
+
+    pub fn parse_text(&self) -> String {
+        match self.data.split_first() {
+            Some((encoding, text)) => text_from_bytes(*encoding, text),
+            None => String::new(),
+        }
     }
 
-    fn id(&self) -> String {
-        string_from_bytes(&self.id).unwrap()
+    pub fn try_parse_text(&self) -> Result<String, TextError> {
+        match self.data.split_first() {
+            Some((encoding, text)) => text_from_bytes_strict(*encoding, text),
+            None => Ok(String::new()),
+        }
+    }
+}
+
+// Frames are equal when they hold the same content, however they were read or written
+impl PartialEq for Frame {
+    fn eq(&self, other: &Self) -> bool {
+        (self.id, self.flags, self.group, self.encryption, self.data_length, &self.data)
+            == (other.id, other.flags, other.group, other.encryption, other.data_length, &other.data)
+    }
+}
+
+impl Eq for Frame {}
+
+// Called for every frame, returning None vetoes the frame and Some replaces it
+pub type FrameHook = Box<dyn Fn(&Frame) -> Option<Frame> + Send + Sync>;
+
+// Whether a frame body of this size ends where another frame, the padding or the end of the tag
+// starts. None when that far can't be seen without reading it
+fn lands_on_frame(reader: &mut Reader, size: u64, left: u64) -> io::Result<Option<bool>> {
+    if size >= left {
+        return Ok(Some(size == left));
+    }
+    let Some(next) = reader.peek_at(size as usize, 4)? else {
+        return Ok(None);
+    };
+    let is_id = next.len() == 4 && next.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit());
+    Ok(Some(next.first() == Some(&0) || is_id))
+}
+
+// Where the fields at the front of a frame end, past its declared size if need be. Content after
+// them runs to the end of the frame and says nothing about its size. None when a terminator is
+// missing altogether
+fn fields_len(id: &[u8; 4], body: &[u8], major_ver: u8) -> Option<usize> {
+    let after_terminator = |encoding: u8, from: usize| {
+        let bytes = body.get(from..)?;
+        let end = match encoding {
+            1 | 2 => bytes.chunks_exact(2).position(|pair| pair == [0, 0])? * 2 + 2,
+            _ => bytes.iter().position(|x| *x == 0)? + 1,
+        };
+        Some(from + end)
+    };
+    let encoding = *body.first()?;
+    match id {
+        b"TXXX" | b"WXXX" => after_terminator(encoding, 1),
+        b"COMM" | b"USLT" => after_terminator(encoding, 4),
+        // v2.2 pictures have a three letter format instead of a MIME type
+        b"APIC" if major_ver == 2 => after_terminator(encoding, 5),
+        b"APIC" => after_terminator(encoding, after_terminator(0, 1)? + 1),
+        b"GEOB" => after_terminator(encoding, after_terminator(encoding, after_terminator(0, 1)?)?),
+        b"PRIV" | b"UFID" => after_terminator(0, 0),
+        _ => Some(1),
+    }
+}
+
+// The size a frame's content really has when its declared size doesn't lead to another frame,
+// padding or the end of the tag, or cuts into the fields at its front. Only a position where a
+// known frame starts, one that leads on to another, or padding counts as the real end. Sizes
+// that lead on are doubted from the first kilobyte alone so large frames aren't read twice
+fn implied_size(reader: &mut Reader, frame_header: &[u8], size: u64, left: u64, major_ver: u8) -> io::Result<Option<u64>> {
+    if size == 0 {
+        return Ok(None);
+    }
+    // Flags like compression put more before the fields, so only the declared size is checked
+    let id = wire::id(frame_header, major_ver).filter(|_| major_ver == 2 || frame_header[9] == 0);
+    let fields_len = |body: &[u8]| id.map_or(Some(1), |id| fields_len(&id, body, major_ver));
+    // Four zero bytes are taken as padding, one could be the start of a frame size
+    let next = match size < left {
+        true => reader.peek_at(size as usize, (left - size).min(4) as usize)?,
+        false => Some(Vec::new()),
+    };
+    let lands = next.map(|next| size == left || next.iter().all(|x| *x == 0) || next.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit()));
+    if lands != Some(false) {
+        let Some(front) = reader.peek_at(0, size.min(1024) as usize)? else {
+            return Ok(None);
+        };
+        if size > 1024 || fields_len(&front).is_some() {
+            return Ok(None);
+        }
+    }
+
+    let Some(rest) = reader.peek_at(0, left as usize)?.filter(|rest| rest.len() as u64 == left) else {
+        return Ok(None);
+    };
+    let Some(fields) = fields_len(&rest) else {
+        return Ok(None);
+    };
+    let header_len = wire::header_len(major_ver);
+    let id_len = if major_ver == 2 { 3 } else { 4 };
+    let is_id = |p: usize| rest.get(p..p + id_len).is_some_and(|id| id.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit()));
+    let padding = rest.iter().rposition(|x| *x != 0).map_or(0, |last| last + 1);
+    let leads_on = |p: usize| {
+        if p >= padding {
+            return true;
+        }
+        let Some(next) = rest.get(p..p + header_len).filter(|_| is_id(p)) else {
+            return false;
+        };
+        let end = p + header_len + wire::body_size(next, major_ver) as usize;
+        let known = wire::id(next, major_ver).and_then(|id| string_from_bytes(&id)).is_some_and(|id| is_known(&id, major_ver.max(3)));
+        end > p + header_len && end <= rest.len() && (known || end >= padding || is_id(end))
+    };
+    Ok((fields..=rest.len()).find(|p| *p as u64 != size && leads_on(*p)).map(|p| p as u64))
+}
+
+pub(crate) fn run_hooks(hooks: &[FrameHook], frame: Frame) -> Option<Frame> {
+    hooks.iter().try_fold(frame, |frame, hook| hook(&frame))
+}
+
+// What reading does with a frame whose content ends somewhere other than its declared size says,
+// found when the declared size doesn't lead to the next frame or cuts into the frame's own fields
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeMismatch {
+    // Read the declared size anyway, into the next frame if that's where it goes
+    Declared,
+    // Read the frame up to where its content ends
+    Structure,
+    // Leave the frame out and carry on where its content ends
+    Skip,
+}
+
+pub struct ReadOptions {
+    lenient: bool,
+    size_mismatch: SizeMismatch,
+    hooks: Vec<FrameHook>,
+    diagnostics: Option<Diagnostics>,
+    lazy_over: Option<u64>,
+    verify_checksums: bool,
+    sidecar: bool,
+    updates: bool,
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self {
+            lenient: false,
+            size_mismatch: SizeMismatch::Declared,
+            hooks: Vec::new(),
+            diagnostics: None,
+            lazy_over: None,
+            verify_checksums: false,
+            sidecar: false,
+            updates: false,
+        }
+    }
+
+    // Frames bigger than this are left in the file and only read when needed, see Tag::lazy_frames.
+    // Tags read from a stream can't go back for them so they are always read in full
+    pub fn lazy_frames_over(mut self, size: u64) -> Self {
+        self.lazy_over = Some(size);
+        self
+    }
+
+    // Collects findings that don't stop the tag from being read
+    pub fn diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    // Check the CRCs written with WriteOptions::frame_checksums. A corrupt frame fails the read,
+    // or with lenient reading is reported to the diagnostics and kept
+    pub fn verify_checksums(mut self, verify: bool) -> Self {
+        self.verify_checksums = verify;
+        self
     }
 
-    fn size(&self) -> u64 {
-        (0..4).map(|x| {(self.size[x] as u64) << 8*(3-x)}).sum()
+    // Read the tag from the file's sidecar when it has one, see WriteOptions::sidecar
+    pub fn sidecar(mut self, sidecar: bool) -> Self {
+        self.sidecar = sidecar;
+        self
     }
 
-    fn parse_text(&self) -> String {
-        let text_type = self.data[0];
-        if text_type == 0 {
-            return ascii_from_bytes(&self.data[1..]);
-        } else if text_type == 1 {
-            return utf16_from_bytes(&self.data[1..]);
+    // Overlay the v2.4 update tags found later in the file onto the first, see crate::update.
+    // A tag read from a sidecar already holds everything
+    pub fn apply_updates(mut self, apply: bool) -> Self {
+        self.updates = apply;
+        self
+    }
+
+    // Skip over spec violations that can be worked around instead of failing. This includes v2.4
+    // frame sizes iTunes writes as plain integers, used when only they lead to the next frame
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    // Declared by default. A mismatch is reported to the diagnostics whatever the choice
+    pub fn size_mismatch(mut self, resolution: SizeMismatch) -> Self {
+        self.size_mismatch = resolution;
+        self
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    // Hooks run in the order they were added
+    pub fn on_frame_parsed(mut self, hook: impl Fn(&Frame) -> Option<Frame> + Send + Sync + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Tag {
+    header: Header,
+    extended_header: Option<ExtendedHeader>,
+    frames: Vec<Frame>,
+    lazy: Vec<LazyFrame>,
+    padding: u64,
+}
+
+// Tags are equal when they have the same version and frames, padding and sizes aside
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.header.major_ver == other.header.major_ver && self.frames == other.frames && self.lazy == other.lazy
+    }
+}
+
+impl Eq for Tag {}
+
+impl Tag {
+    pub fn from_reader(reader: &mut Reader) -> io::Result<Self> {
+        Self::from_reader_with(reader, &ReadOptions::new())
+    }
+
+    pub fn from_reader_with(reader: &mut Reader, options: &ReadOptions) -> io::Result<Self> {
+        let header = Header::from_reader(reader)?;
+        let mut remaining = header.size();
+        if header.major_ver == 2 && header.flags & 0b_01000000 != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Compressed ID3v2.2 tags can't be read"));
+        }
+
+        let extended_header = if header.extended_header() {
+            let extended_header = match header.major_ver {
+                4 => ExtendedHeader::from_reader_v24(reader, remaining)?,
+                _ => ExtendedHeader::from_reader(reader, remaining)?,
+            };
+            remaining = remaining.saturating_sub(extended_header.size() + 4);
+            Some(extended_header)
         } else {
-            return String::new();
+            None
+        };
+
+        let mut frames = Vec::new();
+        let mut lazy = Vec::new();
+        let mut padding = remaining;
+        let header_len = wire::header_len(header.major_ver) as u64;
+        while remaining >= header_len {
+            let offset = reader.position();
+            let frame_header = reader.read_n_bytes(header_len as usize)?;
+
+            // A zero byte where a frame id should be marks the start of padding
+            if frame_header[0] == 0 {
+                padding = remaining;
+                break;
+            }
+
+            let mut size = wire::body_size(&frame_header, header.major_ver) as u64;
+            if options.lenient && header.major_ver == 4 {
+                let plain = u32::from_be_bytes(frame_header[4..8].try_into().unwrap()) as u64;
+                let left = remaining - 10;
+                if plain != size && lands_on_frame(reader, size, left)? == Some(false) && lands_on_frame(reader, plain, left)? == Some(true) {
+                    if let Some(diagnostics) = &options.diagnostics {
+                        let id = string_from_bytes(&frame_header[..4]).unwrap_or_default();
+                        diagnostics.report(Finding::NonSyncSafeSize { id });
+                    }
+                    size = plain;
+                }
+            }
+            let check_size = options.size_mismatch != SizeMismatch::Declared || options.diagnostics.is_some();
+            let unsynchronised = header.major_ver != 4 && header.unsynchronisation();
+            if check_size && !unsynchronised && let Some(actual) = implied_size(reader, &frame_header, size, remaining - header_len, header.major_ver)? {
+                if let Some(diagnostics) = &options.diagnostics {
+                    let id = wire::id(&frame_header, header.major_ver).and_then(|id| string_from_bytes(&id)).unwrap_or_default();
+                    diagnostics.report(Finding::SizeMismatch { id, declared: size, actual });
+                }
+                match options.size_mismatch {
+                    SizeMismatch::Declared => {}
+                    SizeMismatch::Structure => size = actual,
+                    SizeMismatch::Skip => {
+                        remaining -= actual + header_len;
+                        padding = remaining;
+                        reader.skip_n_bytes(actual as usize)?;
+                        continue;
+                    }
+                }
+            }
+            if size + header_len > remaining {
+                return Err(Error::new(ErrorKind::InvalidData, "Frame exceeds tag size"));
+            }
+            remaining -= size + header_len;
+            padding = remaining;
+
+            // v2.2 frames without a counterpart in the later versions can't be kept, and neither
+            // can frames whose id has bytes other than capital letters and digits
+            let Some(id) = wire::id(&frame_header, header.major_ver) else {
+                if let Some(diagnostics) = &options.diagnostics {
+                    let short = header.major_ver == 2 && wire::valid_id(&frame_header[..3]);
+                    let id = String::from_utf8_lossy(&frame_header[..if header.major_ver == 2 { 3 } else { 4 }]).into_owned();
+                    let reason = if short { "no ID3v2.3 equivalent" } else { "invalid frame id" };
+                    diagnostics.report(Finding::SkippedFrame { id, reason: reason.to_string() });
+                }
+                reader.skip_n_bytes(size as usize)?;
+                continue;
+            };
+            if let Some(path) = reader.path().filter(|_| options.lazy_over.is_some_and(|limit| size > limit)) {
+                let id = string_from_bytes(&id).unwrap_or_default();
+                lazy.push(LazyFrame::new(&id, path, offset, size, header.major_ver, frames.len()));
+                reader.skip_n_bytes(size as usize)?;
+                continue;
+            }
+            let frame = Frame::from_header(reader, frame_header, size as u32, header.major_ver)?;
+
+            // Frames must hold at least one byte, lenient reading drops empty ones
+            if frame.size() == 0 {
+                if options.lenient {
+                    if let Some(diagnostics) = &options.diagnostics {
+                        diagnostics.report(Finding::SkippedFrame { id: frame.id(), reason: "zero size".to_string() });
+                    }
+                    continue;
+                }
+                return Err(Error::new(ErrorKind::InvalidData, format!("Frame {} has zero size", frame.id())));
+            }
+            frames.extend(run_hooks(&options.hooks, frame));
+        }
+
+        let tag = Self {
+            header,
+            extended_header,
+            frames,
+            lazy,
+            padding,
+        };
+        if options.verify_checksums {
+            let corrupt = tag.verify_checksums().map(|report| report.corrupt).unwrap_or_default();
+            if let Some(key) = corrupt.first().filter(|_| !options.lenient) {
+                return Err(Error::new(ErrorKind::InvalidData, format!("Frame {key} doesn't match its checksum")));
+            }
+            if let Some(diagnostics) = &options.diagnostics {
+                for key in corrupt {
+                    diagnostics.report(Finding::CorruptFrame { key });
+                }
+            }
+        }
+        if let Some(diagnostics) = &options.diagnostics {
+            diagnostics.inspect(&tag);
+        }
+        Ok(tag)
+    }
+
+    pub fn from_file(filename: impl AsRef<Path>) -> io::Result<Self> {
+        let mut reader = Reader::from_file(filename)?;
+        Self::from_reader(&mut reader)
+    }
+
+    pub fn from_file_with(filename: impl AsRef<Path>, options: &ReadOptions) -> io::Result<Self> {
+        let filename = filename.as_ref();
+        let from_sidecar = options.sidecar && has_sidecar(filename);
+        let mut reader = match from_sidecar {
+            true => Reader::from_file(sidecar_path(filename))?,
+            false => Reader::from_file(filename)?,
+        };
+        let mut tag = Self::from_reader_with(&mut reader, options)?;
+        if options.updates && !from_sidecar {
+            crate::update::apply_updates(filename, &mut tag, options)?;
+        }
+        Ok(tag)
+    }
+
+    pub fn new(major_ver: u8) -> Self {
+        Self {
+            header: Header {
+                major_ver,
+                minor_ver: 0,
+                flags: 0,
+                size: [0; 4],
+            },
+            extended_header: None,
+            frames: Vec::new(),
+            lazy: Vec::new(),
+            padding: 0,
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.header.major_ver
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn extended_header(&self) -> Option<&ExtendedHeader> {
+        self.extended_header.as_ref()
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    // See ExtendedHeader::is_update and crate::update
+    pub fn is_update(&self) -> bool {
+        self.extended_header.as_ref().is_some_and(|extended_header| extended_header.is_update())
+    }
+
+    // Only v2.4 tags can be updates. Clearing the flag keeps the rest of the extended header
+    pub fn set_update(&mut self, update: bool) -> io::Result<()> {
+        if update && self.version() != 4 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Only ID3v2.4 tags can be updates"));
+        }
+        match &mut self.extended_header {
+            Some(extended_header) if extended_header.major_ver == 4 => {
+                extended_header.flags[0] = (extended_header.flags[0] & !0b_01000000) | if update { 0b_01000000 } else { 0 };
+            }
+            _ if update => self.extended_header = Some(ExtendedHeader::update()),
+            _ => {}
+        }
+        // A v2.4 extended header left with nothing in it is dropped
+        if self.extended_header.as_ref().is_some_and(|x| x.major_ver == 4 && !x.is_update() && x.crc.is_none() && x.restrictions.is_none()) {
+            self.extended_header = None;
+        }
+        self.header.flags = (self.header.flags & !0b_01000000) | if self.extended_header.is_some() { 0b_01000000 } else { 0 };
+        Ok(())
+    }
+
+    // Bytes of padding found after the last frame
+    pub fn padding(&self) -> u64 {
+        self.padding
+    }
+
+    pub(crate) fn set_padding(&mut self, padding: u64) {
+        self.padding = padding;
+    }
+
+    pub fn frame(&self, id: &str) -> Option<&Frame> {
+        self.frames.iter().find(|frame| frame.id == id.as_bytes())
+    }
+
+    pub fn text(&self, id: &str) -> Option<String> {
+        self.frame(id).map(|frame| frame.parse_text())
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.text("TIT2")
+    }
+
+    pub fn artist(&self) -> Option<String> {
+        self.text("TPE1")
+    }
+
+    pub fn album(&self) -> Option<String> {
+        self.text("TALB")
+    }
+
+    pub fn frames_mut(&mut self) -> &mut Vec<Frame> {
+        &mut self.frames
+    }
+
+    pub fn add_frame(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    // Replaces the first frame with the id in place, or adds one at the end
+    pub fn set_text(&mut self, id: &str, text: &str) {
+        let Some(frame) = crate::convert::text_frame(id, &[text.to_string()], self.version()) else {
+            return;
+        };
+        match self.frames.iter().position(|existing| existing.id == id.as_bytes()) {
+            Some(index) => self.frames[index] = frame,
+            None => self.frames.push(frame),
+        }
+    }
+
+    // URL frames hold a Latin-1 URL without an encoding byte
+    pub fn url(&self, id: &str) -> Option<String> {
+        let frame = self.frame(id)?;
+        Some(frame.data().iter().take_while(|x| **x != 0).map(|x| *x as char).collect())
+    }
+
+    pub fn set_url(&mut self, id: &str, url: &str) -> io::Result<()> {
+        if !id.starts_with('W') || !crate::validate::is_url(url) || !url.is_ascii() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("invalid URL: {url}")));
+        }
+        let Some(frame) = Frame::new(id, url.as_bytes().to_vec()) else {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("invalid frame id: {id}")));
+        };
+        match self.frames.iter().position(|existing| existing.id == id.as_bytes()) {
+            Some(index) => self.frames[index] = frame,
+            None => self.frames.push(frame),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.frames.retain(|frame| frame.id != id.as_bytes());
+    }
+
+    // Copy of the tag with every frame passed through the hooks
+    pub(crate) fn with_hooks(&self, hooks: &[FrameHook]) -> Tag {
+        Self {
+            frames: self.frames.iter().filter_map(|frame| run_hooks(hooks, frame.clone())).collect(),
+            ..self.clone()
+        }
+    }
+
+    // Frames left in the file because they were bigger than ReadOptions::lazy_frames_over.
+    // They aren't in frames() but are read back in when the tag is written
+    pub fn lazy_frames(&self) -> &[LazyFrame] {
+        &self.lazy
+    }
+
+    // Reads every lazy frame back into the tag where it was in the file
+    pub fn load_lazy_frames(&mut self) -> io::Result<()> {
+        // Each index was taken without the lazy frames before it
+        for (loaded, lazy) in std::mem::take(&mut self.lazy).into_iter().enumerate() {
+            let index = (lazy.index() + loaded).min(self.frames.len());
+            self.frames.insert(index, lazy.load()?);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self, padding: usize) -> Vec<u8> {
+        let mut body: Vec<u8> = self.frames.iter().flat_map(|frame| frame.to_bytes(self.version())).collect();
+        body.extend(std::iter::repeat_n(0, padding));
+
+        // Of the extended header only the update flag is kept, a CRC would no longer match
+        let mut flags = 0x00;
+        if self.is_update() {
+            body.splice(0..0, ExtendedHeader::update().to_bytes());
+            flags |= 0b_01000000;
+        }
+        let mut bytes = vec![0x49, 0x44, 0x33, self.version(), 0x00, flags];
+        bytes.extend_from_slice(&sync_safe_from_u32(body.len() as u32));
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    // SHA-1 over the version and the frames, equal for tags that would be written the same
+    // whatever their padding. Lazy frames are covered by their id and size only
+    pub fn fingerprint(&self) -> [u8; 20] {
+        let mut bytes = vec![self.version()];
+        for frame in &self.frames {
+            bytes.extend(frame.to_bytes(self.version()));
         }
+        for lazy in &self.lazy {
+            bytes.extend(lazy.id().bytes());
+            bytes.extend(lazy.size().to_be_bytes());
+        }
+        sha1(&bytes)
+    }
+
+    // Keeps the original bytes of untouched frames, the extended header and the padding
+    pub fn to_bytes_preserving(&self) -> Vec<u8> {
+        let mut frames = Vec::new();
+        for frame in &self.frames {
+            match frame.raw_bytes(self.version()) {
+                Some(raw) => frames.extend_from_slice(raw),
+                None => frames.extend(frame.to_bytes(self.version())),
+            }
+        }
+        let padding = vec![0; self.padding as usize];
+        // Frames written again aren't unsynchronised, so the flag only holds while none are
+        let unsynchronised = self.header.unsynchronisation() && self.frames.iter().all(|frame| frame.raw_bytes(self.version()).is_some());
+
+        let mut body = Vec::new();
+        if let Some(extended_header) = &self.extended_header {
+            // The CRC and the v2.3 padding size are of the tag as it is written now. v2.3 takes
+            // the CRC before unsynchronisation over the frames, v2.4 over frames and padding
+            let mut extended_header = extended_header.clone();
+            if extended_header.crc.is_some() {
+                let crc = match self.version() {
+                    4 => crc32(&[&frames[..], &padding].concat()),
+                    _ if unsynchronised => crc32(&resynchronise(&frames)),
+                    _ => crc32(&frames),
+                };
+                extended_header.crc = Some(crc.to_be_bytes());
+            }
+            if extended_header.major_ver != 4 {
+                extended_header.padding_size = (self.padding as u32).to_be_bytes();
+            }
+            body.extend(extended_header.to_bytes());
+        }
+        body.extend(frames);
+        body.extend(padding);
+
+        // The footer isn't written so its flag is cleared
+        let mut flags = self.header.flags & !0b_00010000;
+        if !unsynchronised {
+            flags &= !0b_10000000;
+        }
+        let mut bytes = vec![0x49, 0x44, 0x33, self.version(), self.header.minor_ver, flags];
+        bytes.extend_from_slice(&sync_safe_from_u32(body.len() as u32));
+        bytes.extend_from_slice(&body);
+        bytes
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::write_temp;
 
     #[test]
     fn has_header() {
@@ -288,6 +1426,24 @@ mod tests {
         assert_eq!(bytes, vec![0x03, 0x00, 0x00]);
     }
 
+    #[test]
+    fn skip_bytes_in_stream() {
+        let file = File::open("test/Polygondwanaland.mp3").unwrap();
+        let mut reader = Reader::from_stream(file);
+        reader.skip_n_bytes(3).unwrap();
+        assert_eq!(reader.read_n_bytes(3).unwrap(), vec![0x03, 0x00, 0x00]);
+
+        let mut reader = Reader::from_stream(io::Cursor::new(vec![0; 4]));
+        assert_eq!(reader.skip_n_bytes(5).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn tag_from_stream() {
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        let tag = Tag::from_reader(&mut Reader::from_stream(io::Cursor::new(bytes))).unwrap();
+        assert_eq!(tag.frames().len(), 9);
+    }
+
     #[test]
     fn construct_header() {
         let mut reader = Reader::from_file("test/Polygondwanaland.mp3").unwrap();
@@ -311,6 +1467,7 @@ mod tests {
     #[test]
     fn construct_extended_header() {
         let header = ExtendedHeader::from_bytes(&[0x00, 0x00, 0x00, 0x0A, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xDE, 0xAD, 0xBE, 0xEF ]).unwrap();
+        assert_eq!(header.crc(), Some([0xDE, 0xAD, 0xBE, 0xEF]));
     }
 
     #[test]
@@ -328,7 +1485,43 @@ mod tests {
     #[test]
     fn padding_exists() {
         let header = ExtendedHeader::from_bytes(&[0x00, 0x00, 0x00, 0x0A, 0x80, 0x00, 0x00, 0x00, 0x00, 0x80, 0xDE, 0xAD, 0xBE, 0xEF ]).unwrap();
-        assert_eq!(header.has_padding(), true);
+        assert!(header.has_padding());
+    }
+
+    #[test]
+    fn extended_header_v24() {
+        let bytes = [0x00, 0x00, 0x00, 0x0F, 0x01, 0x70, 0x00, 0x05, 0x0D, 0x75, 0x36, 0x7D, 0x6F, 0x01, 0x42];
+        let header = ExtendedHeader::from_bytes_v24(&bytes).unwrap();
+        assert_eq!((header.is_update(), header.crc(), header.restrictions()), (true, Some([0xDE, 0xAD, 0xBE, 0xEF]), Some(0x42)));
+        assert_eq!(header.size(), 11);
+        assert_eq!(header.to_bytes(), bytes);
+        assert!(ExtendedHeader::from_bytes_v24(&bytes[..12]).is_none());
+    }
+
+    #[test]
+    fn hostile_v23_extended_header() {
+        let read = |size: [u8; 4]| {
+            let bytes = [&b"ID3\x03\x00\x40\x00\x00\x00\x10"[..], &size, &[0; 12]].concat();
+            Tag::from_reader(&mut Reader::from_stream(io::Cursor::new(bytes))).err().unwrap().kind()
+        };
+        assert_eq!(read([0, 0, 0, 2]), ErrorKind::InvalidData);
+        assert_eq!(read([0xFF, 0xFF, 0xFF, 0xF0]), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn update_flag_written() {
+        let mut tag = Tag::new(3);
+        assert_eq!(tag.set_update(true).unwrap_err().kind(), ErrorKind::InvalidInput);
+        let mut tag = Tag::new(4);
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.set_update(true).unwrap();
+        let read = Tag::from_reader(&mut Reader::from_stream(io::Cursor::new(tag.to_bytes(16)))).unwrap();
+        assert!(read.is_update() && read.header().extended_header());
+        assert_eq!((read.title().as_deref(), read.padding()), (Some("Crumbling Castle"), 16));
+        assert_eq!(read.to_bytes_preserving(), tag.to_bytes(16));
+
+        tag.set_update(false).unwrap();
+        assert!(tag.extended_header().is_none() && !tag.header().extended_header());
     }
 
     #[test]
@@ -343,9 +1536,352 @@ mod tests {
         assert_eq!(utf16_from_bytes(&bytes), "Libby DeCamp".to_string());
     }
 
+    #[test]
+    fn lossy_utf16_pairs_and_odd_bytes() {
+        assert_eq!(utf16_from_bytes(&[0xFF, 0xFE, 0x3D, 0xD8, 0x38, 0xDE, 0x41, 0x00, 0x42]), "😸A");
+        assert_eq!(utf16_from_bytes(&[0xFE, 0xFF, 0xD8, 0x3D, 0x00, 0x41]), "\u{FFFD}A");
+        assert_eq!(utf16_from_bytes(&[0x41, 0x00, 0x00, 0x00, 0x42, 0x00]), "A");
+        assert_eq!(spec_text_from_bytes(2, &[0xDE, 0xB8, 0x00]), "\u{FFFD}");
+    }
+
+    #[test]
+    fn terminated_round_trip() {
+        for encoding in 0..4 {
+            let mut data = Vec::new();
+            write_terminated(&mut data, encoding, "Āb");
+            write_terminated(&mut data, encoding, "");
+            data.extend_from_slice(b"tail");
+
+            let (first, rest) = read_terminated(encoding, &data);
+            let (second, rest) = read_terminated(encoding, rest);
+            let expected = if encoding == 0 { "?b" } else { "Āb" };
+            assert_eq!((first.as_str(), second.as_str(), rest), (expected, "", &b"tail"[..]));
+        }
+    }
+
+    #[test]
+    fn strict_utf16_errors() {
+        assert_eq!(text_from_bytes_strict(1, &[0xFE, 0xFF, 0x00, 0x41]), Ok("A".to_string()));
+        assert_eq!(text_from_bytes_strict(1, &[0x41, 0x00]), Err(TextError::MissingBom));
+        assert_eq!(text_from_bytes_strict(1, &[0xFF, 0xFE, 0x41, 0x00, 0x42]), Err(TextError::OddLength));
+        assert_eq!(text_from_bytes_strict(1, &[0xFF, 0xFE, 0x3D, 0xD8, 0x41, 0x00]), Err(TextError::UnpairedSurrogate(0xD83D)));
+        assert_eq!(text_from_bytes_strict(2, &[0xD8, 0x3D, 0xDE, 0x00]), Ok("😀".to_string()));
+        assert_eq!(text_from_bytes_strict(3, &[0xC3]), Err(TextError::InvalidUtf8));
+    }
+
+    #[test]
+    fn lossy_utf16_keeps_text() {
+        assert_eq!(text_from_bytes(1, &[0x41, 0x00, 0x42, 0x00]), "AB");
+        assert_eq!(text_from_bytes(1, &[0xFF, 0xFE, 0x3D, 0xD8, 0x41, 0x00, 0x42]), "\u{FFFD}A");
+        assert_eq!(text_from_bytes(2, &[0x00, 0x41, 0x00, 0x00, 0x00, 0x42]), "A");
+    }
+
     #[test]
     fn bytes_to_ascii() {
         let bytes = [0x43, 0x61, 0x73, 0x74, 0x6C, 0x65, 0x20, 0x52, 0x61, 0x74, 0x00];
         assert_eq!(ascii_from_bytes(&bytes), "Castle Rat".to_string());
     }
-} 
+
+    #[test]
+    fn v24_header() {
+        assert!(header_exists(&[0x49, 0x44, 0x33, 0x04, 0x00, 0x10, 0x00, 0x08, 0x2e, 0x37]))
+    }
+
+    #[test]
+    fn sync_safe_round_trip() {
+        assert_eq!(sync_safe_from_u32(187207), [0x00, 0x0b, 0x36, 0x47]);
+        assert_eq!(u32_from_sync_safe(&[0x00, 0x0b, 0x36, 0x47]), 187207);
+    }
+
+    #[test]
+    fn frame_to_bytes() {
+        let frame = Frame::new("TRCK", vec![0x00, 0x32]).unwrap();
+        assert_eq!(frame.to_bytes(3), vec![0x54, 0x52, 0x43, 0x4B, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x32]);
+    }
+
+    #[test]
+    fn grouped_frame_round_trip() {
+        let mut frame = Frame::new("TRCK", vec![0x00, 0x32]).unwrap();
+        frame.set_group(Some(0x80));
+        let bytes = frame.to_bytes(4);
+        assert_eq!(bytes[4..], [0x00, 0x00, 0x00, 0x03, 0x00, 0b_01000000, 0x80, 0x00, 0x32]);
+        assert_eq!(frame.size(), 3);
+    }
+
+    #[test]
+    fn data_length_indicator() {
+        // Grouped, unsynchronised TIT2 whose text contains 0xFF
+        let bytes = [b"TIT2".as_slice(), &[0x00, 0x00, 0x00, 0x09, 0x00, 0b_01000011, 0x05, 0x00, 0x00, 0x00, 0x03, 0x00, 0xFF, 0x00, b'A']].concat();
+        let mut reader = Reader::from_stream(io::Cursor::new(bytes));
+        let frame = Frame::from_reader(&mut reader, 4).unwrap();
+        assert_eq!((frame.group(), frame.data_length(), frame.data()), (Some(0x05), Some(3), &[0x00, 0xFF, b'A'][..]));
+        assert_eq!(frame.to_bytes(4)[4..], [0x00, 0x00, 0x00, 0x08, 0x00, 0b_01000001, 0x05, 0x00, 0x00, 0x00, 0x03, 0x00, 0xFF, b'A']);
+    }
+
+    #[test]
+    fn compressed_frame_keeps_data_length() {
+        let bytes = [b"TXXX".as_slice(), &[0x00, 0x00, 0x00, 0x07, 0x00, 0b_00001001, 0x00, 0x00, 0x01, 0x00, 0x78, 0x9C, 0x03]].concat();
+        let frame = Frame::from_reader(&mut Reader::from_stream(io::Cursor::new(bytes)), 4).unwrap();
+        assert_eq!((frame.data_length(), frame.data()), (Some(128), &[0x78, 0x9C, 0x03][..]));
+        assert_eq!(frame.to_bytes(4)[10..14], [0x00, 0x00, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn invalid_frame_id() {
+        assert!(Frame::new("TIT", vec![]).is_none());
+        assert!(Frame::new("tit2", vec![]).is_none());
+    }
+
+    #[test]
+    fn split_utf16_terminated() {
+        let bytes = [0xFF, 0xFE, 0x00, 0x01, 0x00, 0x00, 0x41, 0x00];
+        assert_eq!(split_terminated(1, &bytes), (&bytes[..4], &bytes[6..]));
+    }
+
+    #[test]
+    fn split_unterminated() {
+        assert_eq!(split_terminated(0, b"eng"), (&b"eng"[..], &b""[..]));
+    }
+
+    #[test]
+    fn text_round_trip() {
+        for encoding in [0, 1, 3] {
+            assert_eq!(text_from_bytes(encoding, &bytes_from_text(encoding, "Castle Rat")), "Castle Rat");
+        }
+    }
+
+    #[test]
+    fn clone_and_equality() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let mut copy = tag.clone();
+        assert!(copy == tag);
+
+        // Rebuilt frames equal the ones read from the file even without their raw bytes
+        let rebuilt = Frame::new("TIT2", tag.frame("TIT2").unwrap().data().to_vec()).unwrap();
+        copy.frames_mut()[0] = rebuilt;
+        assert!(copy == tag);
+
+        copy.set_text("TIT2", "Crumbling Castle");
+        assert!(copy != tag);
+        assert!(std::thread::spawn(move || copy.title()).join().unwrap().is_some());
+    }
+
+    #[test]
+    fn read_tag_frames() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let ids: Vec<String> = tag.frames().iter().map(|frame| frame.id()).collect();
+        assert_eq!(ids, vec!["TIT2", "TPE1", "TRCK", "TALB", "TYER", "TSRC", "TPE2", "COMM", "APIC"]);
+    }
+
+    #[test]
+    fn tag_to_bytes() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(Frame::new("TRCK", vec![0x00, 0x32]).unwrap());
+        let bytes = tag.to_bytes(4);
+        assert_eq!(bytes[..10], [0x49, 0x44, 0x33, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10]);
+        assert_eq!(bytes.len(), 10 + 12 + 4);
+    }
+
+    #[test]
+    fn tag_size_with_footer() {
+        let header = Header::from_bytes(&[0x49, 0x44, 0x33, 0x04, 0x00, 0x10, 0x00, 0x00, 0x01, 0x00]).unwrap();
+        assert_eq!(header.tag_size(), 10 + 128 + 10);
+    }
+
+    #[test]
+    fn preserving_round_trip() {
+        let original = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let size = tag.header().tag_size() as usize;
+        assert_eq!(tag.to_bytes_preserving(), original[..size]);
+    }
+
+    #[test]
+    fn preserving_edited_tag_with_crc_and_unsync() {
+        let frames = [Frame::new("TIT2", b"\x00Crumbling Castle".to_vec()).unwrap(), Frame::new("TALB", b"\x00Polygondwanaland".to_vec()).unwrap()];
+        let frames: Vec<u8> = frames.iter().flat_map(|frame| frame.to_bytes(3)).collect();
+        let extended_header = [0, 0, 0, 10, 0x80, 0, 0, 0, 0, 8, 0xDE, 0xAD, 0xBE, 0xEF];
+        let body = [&extended_header[..], &frames, &[0; 8]].concat();
+        let bytes = [&[0x49, 0x44, 0x33, 3, 0, 0b_11000000][..], &sync_safe_from_u32(body.len() as u32), &body].concat();
+        let read = |bytes: Vec<u8>| Tag::from_reader(&mut Reader::from_stream(io::Cursor::new(bytes))).unwrap();
+
+        // Untouched frames are still unsynchronised, only the CRC is put right
+        let tag = read(bytes.clone());
+        let written = tag.to_bytes_preserving();
+        assert_eq!(written[5], 0b_11000000);
+        assert_eq!(read(written).extended_header().unwrap().crc(), Some(crc32(&frames).to_be_bytes()));
+
+        let mut tag = read(bytes);
+        tag.set_text("TIT2", "The Castle in Ruins");
+        tag.set_padding(4);
+        let written = tag.to_bytes_preserving();
+        assert_eq!(written[5], 0b_01000000);
+        let edited = read(written.clone());
+        let frames = &written[24..written.len() - 4];
+        assert_eq!(edited.extended_header().unwrap().crc(), Some(crc32(frames).to_be_bytes()));
+        assert_eq!((edited.extended_header().unwrap().padding_size(), edited.padding()), (4, 4));
+        assert_eq!(edited.title().as_deref(), Some("The Castle in Ruins"));
+    }
+
+    #[test]
+    fn modified_frame_marked() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert!(!tag.frames()[0].is_modified());
+        tag.frames_mut()[0].set_group(Some(1));
+        assert!(tag.frames()[0].is_modified());
+    }
+
+    #[test]
+    fn frame_provenance() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let offsets: Vec<Option<u64>> = tag.frames().iter().map(|frame| frame.provenance().offset).collect();
+        assert_eq!(offsets[..2], [Some(10), Some(20 + tag.frames()[0].size())]);
+        assert_eq!(tag.frames()[0].provenance(), Provenance { offset: Some(10), version: Some(3), modified: false });
+
+        // Lazy frames know where they were once loaded, frames made since come from nowhere
+        let mut lazy = Tag::from_file_with("test/Polygondwanaland.mp3", &ReadOptions::new().lazy_frames_over(1024)).unwrap();
+        lazy.load_lazy_frames().unwrap();
+        let apic = lazy.frames().iter().find(|frame| frame.id() == "APIC").unwrap();
+        assert_eq!(apic.provenance().offset, tag.frame("APIC").unwrap().provenance().offset);
+        let (converted, _) = tag.convert(4);
+        assert_eq!(converted.frames()[0].provenance().version, Some(3));
+        assert_eq!(Frame::new("TIT2", b"\x00Title".to_vec()).unwrap().provenance(), Provenance { offset: None, version: None, modified: true });
+    }
+
+
+    fn tag_with_empty_frame() -> Vec<u8> {
+        let mut tag = Tag::new(4);
+        tag.add_frame(Frame::new("TIT2", b"\x00Title".to_vec()).unwrap());
+        tag.add_frame(Frame::new("TALB", vec![]).unwrap());
+        tag.add_frame(Frame::new("TPE1", b"\x00Artist".to_vec()).unwrap());
+        tag.to_bytes(32)
+    }
+
+    #[test]
+    fn tag_padding_size() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(tag.padding(), 9545);
+    }
+
+    #[test]
+    fn zero_size_frame_rejected() {
+        let path = write_temp("zero-size-strict", &tag_with_empty_frame());
+        let error = Tag::from_file(&path).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn zero_size_frame_skipped_when_lenient() {
+        let path = write_temp("zero-size-lenient", &tag_with_empty_frame());
+        let tag = Tag::from_file_with(&path, &ReadOptions::new().lenient(true)).unwrap();
+        let ids: Vec<String> = tag.frames().iter().map(|frame| frame.id()).collect();
+        assert_eq!(ids, vec!["TIT2", "TPE1"]);
+        assert_eq!(tag.padding(), 32);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // TIT2 with its size written as a plain integer the way iTunes does, then TALB
+    fn itunes_sizes() -> Vec<u8> {
+        let title = [b"\x00".as_slice(), &[b'x'; 199]].concat();
+        let mut body = [b"TIT2".as_slice(), &200u32.to_be_bytes(), &[0, 0], &title].concat();
+        body.extend(Frame::new("TALB", b"\x00Album".to_vec()).unwrap().to_bytes(4));
+        body.extend([0; 16]);
+        [b"ID3\x04\x00\x00".as_slice(), &sync_safe_from_u32(body.len() as u32), &body].concat()
+    }
+
+    #[test]
+    fn itunes_sizes_read_when_lenient() {
+        let path = write_temp("itunes", &itunes_sizes());
+        assert_eq!(Tag::from_file(&path).ok().and_then(|tag| tag.album()), None);
+
+        let diagnostics = Diagnostics::new();
+        let options = ReadOptions::new().lenient(true).diagnostics(diagnostics.clone());
+        let tag = Tag::from_file_with(&path, &options).unwrap();
+        assert_eq!((tag.title().unwrap().len(), tag.album().as_deref()), (199, Some("Album")));
+        assert_eq!(diagnostics.findings(), [Finding::NonSyncSafeSize { id: "TIT2".into() }]);
+        let tag = Tag::from_reader_with(&mut Reader::from_stream(io::Cursor::new(itunes_sizes())), &options).unwrap();
+        assert_eq!(tag.album().as_deref(), Some("Album"));
+
+        // Written back with a proper size
+        tag.write_to_file(&path, &crate::WriteOptions::new().preserve(true)).unwrap();
+        assert_eq!(Tag::from_file(&path).unwrap().title().unwrap().len(), 199);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // A v2.3 tag whose COMM claims five bytes of the TALB after it and whose TXXX ends before its
+    // description does, then padding
+    fn mismatched_sizes() -> Vec<u8> {
+        let frame = |id: &[u8], body: &[u8], size: u32| [id, &size.to_be_bytes(), &[0, 0], body].concat();
+        let comment = b"\x00engnote\x00Recorded live";
+        let mut body = frame(b"COMM", comment, comment.len() as u32 + 5);
+        body.extend(frame(b"TXXX", b"\x00Mood\x00Dark", 3));
+        body.extend(frame(b"TALB", b"\x00Album", 6));
+        body.extend([0; 16]);
+        [b"ID3\x03\x00\x00".as_slice(), &sync_safe_from_u32(body.len() as u32), &body].concat()
+    }
+
+    #[test]
+    fn size_mismatch_resolved() {
+        let path = write_temp("mismatch", &mismatched_sizes());
+        let diagnostics = Diagnostics::new();
+        let declared = Tag::from_file_with(&path, &ReadOptions::new().diagnostics(diagnostics.clone())).unwrap();
+        assert!(declared.album().is_none());
+        assert_eq!(diagnostics.findings()[0], Finding::SizeMismatch { id: "COMM".into(), declared: 27, actual: 22 });
+        assert_eq!(diagnostics.findings()[0].to_string(), "Frame COMM declares 27 bytes but its content takes 22");
+
+        let diagnostics = Diagnostics::new();
+        let options = ReadOptions::new().size_mismatch(SizeMismatch::Structure).diagnostics(diagnostics.clone());
+        let tag = Tag::from_file_with(&path, &options).unwrap();
+        assert_eq!(tag.comments()[0].text(), "Recorded live");
+        assert_eq!((tag.user_text("Mood").as_deref(), tag.album().as_deref()), (Some("Dark"), Some("Album")));
+        assert_eq!(diagnostics.findings()[1], Finding::SizeMismatch { id: "TXXX".into(), declared: 3, actual: 10 });
+
+        let tag = Tag::from_file_with(&path, &ReadOptions::new().size_mismatch(SizeMismatch::Skip)).unwrap();
+        let ids: Vec<String> = tag.frames().iter().map(|frame| frame.id()).collect();
+        assert_eq!((ids, tag.padding()), (vec!["TALB".to_string()], 16));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_tag_text() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(tag.frame("TIT2").unwrap().parse_text(), "Polygondwanaland".to_string());
+        assert_eq!(tag.frame("TIT2").unwrap().try_parse_text(), Ok("Polygondwanaland".to_string()));
+    }
+
+    #[test]
+    fn set_text_replaces_in_place() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.set_text("TCON", "Rock");
+        tag.remove("COMM");
+        assert_eq!(tag.frames()[0].parse_text(), "Crumbling Castle");
+        assert_eq!(tag.frames().last().unwrap().id(), "TCON");
+        assert!(tag.frame("COMM").is_none());
+    }
+
+    #[test]
+    fn parse_hooks_transform_and_veto() {
+        let options = ReadOptions::new()
+            .on_frame_parsed(|frame| (frame.id() != "APIC").then(|| frame.clone()))
+            .on_frame_parsed(|frame| match frame.id().as_str() {
+                "COMM" => None,
+                _ => Some(frame.clone()),
+            });
+        let tag = Tag::from_file_with("test/Polygondwanaland.mp3", &options).unwrap();
+        let ids: Vec<String> = tag.frames().iter().map(|frame| frame.id()).collect();
+        assert_eq!(ids, vec!["TIT2", "TPE1", "TRCK", "TALB", "TYER", "TSRC", "TPE2"]);
+    }
+
+    #[test]
+    fn fingerprint_ignores_padding() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let padded = Tag::from_reader(&mut Reader::from_stream(io::Cursor::new(tag.to_bytes(512)))).unwrap();
+        assert_eq!(padded.fingerprint(), tag.fingerprint());
+
+        let mut edited = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        edited.set_text("TRCK", "3");
+        assert_ne!(edited.fingerprint(), tag.fingerprint());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::frames::{Comment, Picture};
+use crate::{CompatibilityReport, ExtendedHeader, Frame, Header, ReadOptions, Tag, WriteOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A tag opened only to be read. It hands out a TagView, which has no way of writing back,
+// so code given a TagReader can't rewrite the file by accident
+///
+/// ```compile_fail
+/// use mp3_tool::{TagReader, WriteOptions};
+///
+/// let reader = TagReader::open("test/Polygondwanaland.mp3").unwrap();
+/// reader.tag().write_to_file(reader.filename(), &WriteOptions::new()).unwrap();
+/// ```
+pub struct TagReader {
+    filename: PathBuf,
+    tag: Tag,
+}
+
+impl TagReader {
+    pub fn open(filename: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with(filename, &ReadOptions::new())
+    }
+
+    pub fn open_with(filename: impl AsRef<Path>, options: &ReadOptions) -> io::Result<Self> {
+        let filename = filename.as_ref();
+        Ok(Self {
+            filename: filename.to_path_buf(),
+            tag: Tag::from_file_with(filename, options)?,
+        })
+    }
+
+    pub fn filename(&self) -> &Path {
+        &self.filename
+    }
+
+    pub fn tag(&self) -> TagView<'_> {
+        TagView { tag: &self.tag }
+    }
+}
+
+// The reading half of Tag, without anything that writes or saves it
+#[derive(Clone, Copy)]
+pub struct TagView<'a> {
+    tag: &'a Tag,
+}
+
+impl<'a> TagView<'a> {
+    pub fn version(&self) -> u8 {
+        self.tag.version()
+    }
+
+    pub fn header(&self) -> &'a Header {
+        self.tag.header()
+    }
+
+    pub fn extended_header(&self) -> Option<&'a ExtendedHeader> {
+        self.tag.extended_header()
+    }
+
+    pub fn frames(&self) -> &'a [Frame] {
+        self.tag.frames()
+    }
+
+    pub fn frame(&self, id: &str) -> Option<&'a Frame> {
+        self.tag.frame(id)
+    }
+
+    pub fn text(&self, id: &str) -> Option<String> {
+        self.tag.text(id)
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.tag.title()
+    }
+
+    pub fn artist(&self) -> Option<String> {
+        self.tag.artist()
+    }
+
+    pub fn album(&self) -> Option<String> {
+        self.tag.album()
+    }
+
+    pub fn url(&self, id: &str) -> Option<String> {
+        self.tag.url(id)
+    }
+
+    pub fn comments(&self) -> Vec<Comment> {
+        self.tag.comments()
+    }
+
+    pub fn pictures(&self) -> Vec<Picture> {
+        self.tag.pictures()
+    }
+
+    pub fn padding(&self) -> u64 {
+        self.tag.padding()
+    }
+
+    pub fn fingerprint(&self) -> [u8; 20] {
+        self.tag.fingerprint()
+    }
+}
+
+// A tag opened to be changed and saved back to the file it came from
+pub struct TagEditor {
+    filename: PathBuf,
+    tag: Tag,
+    fingerprint: [u8; 20],
+}
+
+impl TagEditor {
+    pub fn open_for_edit(filename: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_for_edit_with(filename, &ReadOptions::new())
+    }
+
+    pub fn open_for_edit_with(filename: impl AsRef<Path>, options: &ReadOptions) -> io::Result<Self> {
+        let filename = filename.as_ref();
+        let tag = Tag::from_file_with(filename, options)?;
+        Ok(Self {
+            filename: filename.to_path_buf(),
+            fingerprint: tag.fingerprint(),
+            tag,
+        })
+    }
+
+    pub fn filename(&self) -> &Path {
+        &self.filename
+    }
+
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    pub fn tag_mut(&mut self) -> &mut Tag {
+        &mut self.tag
+    }
+
+    // Whether the frames differ from when the file was opened
+    pub fn is_modified(&self) -> bool {
+        self.tag.fingerprint() != self.fingerprint
+    }
+
+    // Writes the tag back to the file it was opened from
+    pub fn save(self, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+        self.tag.write_to_file(&self.filename, options)
+    }
+
+    // Gives up write access, keeping the changes in memory
+    pub fn into_reader(self) -> TagReader {
+        TagReader {
+            filename: self.filename,
+            tag: self.tag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use std::fs;
+
+    #[test]
+    fn reader_and_editor() {
+        let path = temp_path("access.mp3");
+        let path = path.as_path();
+        fs::copy("test/Polygondwanaland.mp3", path).unwrap();
+
+        let reader = TagReader::open(path).unwrap();
+        assert_eq!(reader.tag().title().as_deref(), Some("Polygondwanaland"));
+
+        let mut editor = TagEditor::open_for_edit(reader.filename()).unwrap();
+        assert!(!editor.is_modified());
+        editor.tag_mut().set_text("TIT2", "Crumbling Castle");
+        assert!(editor.is_modified());
+        editor.save(&WriteOptions::new().preserve(true)).unwrap();
+
+        let reader = TagReader::open(path).unwrap();
+        assert_eq!(reader.tag().title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(reader.tag().frames().len(), 9);
+        fs::remove_file(path).unwrap();
+    }
+}
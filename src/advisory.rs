@@ -0,0 +1,62 @@
+use crate::Tag;
+
+// TXXX description iTunes and most storefronts read the content rating from
+pub const DESCRIPTION: &str = "ITUNESADVISORY";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Advisory {
+    None,
+    Explicit,
+    Clean,
+}
+
+impl Advisory {
+    // Stored as "0", "1" or "2"
+    pub fn from_value(value: &str) -> Option<Self> {
+        match value.trim() {
+            "0" => Some(Self::None),
+            "1" => Some(Self::Explicit),
+            "2" => Some(Self::Clean),
+            _ => None,
+        }
+    }
+
+    pub fn value(self) -> &'static str {
+        match self {
+            Self::None => "0",
+            Self::Explicit => "1",
+            Self::Clean => "2",
+        }
+    }
+}
+
+impl Tag {
+    // None when the tag has no rating or one that isn't understood
+    pub fn advisory(&self) -> Option<Advisory> {
+        Advisory::from_value(&self.user_text(DESCRIPTION)?)
+    }
+
+    pub fn set_advisory(&mut self, advisory: Advisory) {
+        self.set_user_text(DESCRIPTION, advisory.value());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advisory_round_trip() {
+        let mut tag = Tag::new(3);
+        assert_eq!(tag.advisory(), None);
+        for advisory in [Advisory::Explicit, Advisory::Clean, Advisory::None] {
+            tag.set_advisory(advisory);
+            assert_eq!(tag.advisory(), Some(advisory));
+        }
+        assert_eq!(tag.user_texts().len(), 1);
+        assert_eq!(tag.user_text("ITUNESADVISORY").as_deref(), Some("0"));
+
+        tag.set_user_text(DESCRIPTION, "explicit");
+        assert_eq!(tag.advisory(), None);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::paths::long_path;
+use crate::{Tag, mpeg};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Differences up to this are rounding in TLEN and encoder padding, not lost audio
+const TOLERANCE_MS: u64 = 1000;
+
+// How far the audio present is from the length the file claims, in order of concern
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    None,
+    // Under a tenth off, often a tag copied from another rip of the same track
+    Minor,
+    // Up to half of the audio missing or extra
+    Major,
+    // Most of the audio is gone
+    Critical,
+}
+
+// What the tag and the Xing header say about the length next to what walking the frames finds
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub audio_frames: u64,
+    pub duration_ms: u64,
+    // TLEN
+    pub declared_ms: Option<u64>,
+    // From a Xing, Info or VBRI header
+    pub xing_frames: Option<u32>,
+    pub xing_ms: Option<u64>,
+    // Bytes in the audio range after the last whole frame, a cut off frame or junk
+    pub trailing_bytes: u64,
+}
+
+impl Analysis {
+    // The Xing count is written by the encoder so it is trusted over TLEN
+    pub fn expected_ms(&self) -> Option<u64> {
+        self.xing_ms.or(self.declared_ms)
+    }
+
+    // How much shorter the audio is than expected, zero when it's as long or longer
+    pub fn missing_ms(&self) -> u64 {
+        self.expected_ms().map(|expected| expected.saturating_sub(self.duration_ms)).unwrap_or(0)
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.severity() > Severity::None && self.missing_ms() > 0
+    }
+
+    pub fn severity(&self) -> Severity {
+        let Some(expected) = self.expected_ms() else {
+            return Severity::None;
+        };
+        let difference = expected.abs_diff(self.duration_ms);
+        if difference <= TOLERANCE_MS {
+            Severity::None
+        } else if difference * 10 < expected {
+            Severity::Minor
+        } else if difference * 2 <= expected {
+            Severity::Major
+        } else {
+            Severity::Critical
+        }
+    }
+}
+
+// Reads the tag, the first frame's VBR header and walks every frame of the audio
+pub fn analyze(filename: impl AsRef<Path>) -> io::Result<Analysis> {
+    let filename = filename.as_ref();
+    let declared_ms = Tag::from_file(filename).ok().and_then(|tag| tag.text("TLEN")).and_then(|text| text.trim().parse().ok());
+
+    let mut file = File::open(long_path(filename))?;
+    let range = mpeg::audio_range(&mut file)?;
+    let mut frames = mpeg::scan(&mut file, range.clone())?;
+    let trailing_bytes = frames.last().map(|(offset, header)| range.end - offset - header.frame_length() as u64).unwrap_or(range.end - range.start);
+
+    let mut xing_frames = None;
+    if let Some((offset, header)) = frames.first().copied() {
+        let mut first = Vec::new();
+        file.seek(SeekFrom::Start(offset))?;
+        (&mut file).take(header.frame_length() as u64).read_to_end(&mut first)?;
+        xing_frames = mpeg::declared_frames(&first);
+    }
+    mpeg::drop_info_frame(&mut file, &mut frames)?;
+
+    let xing_ms = xing_frames.zip(frames.first()).map(|(count, (_, header))| {
+        count as u64 * header.samples() as u64 * 1000 / header.sample_rate() as u64
+    });
+    Ok(Analysis {
+        audio_frames: frames.len() as u64,
+        duration_ms: mpeg::duration_ms(&frames),
+        declared_ms,
+        xing_frames,
+        xing_ms,
+        trailing_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use crate::WriteOptions;
+    use std::fs;
+
+    #[test]
+    fn complete_file() {
+        let analysis = analyze("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!((analysis.audio_frames, analysis.xing_frames, analysis.declared_ms), (8154, Some(8154), None));
+        assert!(analysis.xing_ms.unwrap().abs_diff(analysis.duration_ms) < 10);
+        assert_eq!((analysis.severity(), analysis.is_truncated()), (Severity::None, false));
+    }
+
+    #[test]
+    fn truncated_download() {
+        let path = copy_of_test_file("truncated");
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let length = file.metadata().unwrap().len();
+        file.set_len(length - length / 3 - 100).unwrap();
+
+        let analysis = analyze(&path).unwrap();
+        assert!(analysis.is_truncated());
+        assert_eq!(analysis.severity(), Severity::Major);
+        assert!(analysis.trailing_bytes > 0);
+        assert!(analysis.missing_ms() > 60_000);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn tlen_without_xing() {
+        let analysis = Analysis { audio_frames: 100, duration_ms: 2612, declared_ms: Some(213024), xing_frames: None, xing_ms: None, trailing_bytes: 0 };
+        assert_eq!(analysis.severity(), Severity::Critical);
+
+        let path = copy_of_test_file("tlen");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.set_text("TLEN", "230000");
+        tag.write_to_file(&path, &WriteOptions::new().preserve(true)).unwrap();
+        let analysis = analyze(&path).unwrap();
+        assert_eq!((analysis.declared_ms, analysis.severity(), analysis.is_truncated()), (Some(230000), Severity::None, false));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use crate::paths::long_path;
+use crate::{Header, ReadOptions, Reader, Tag};
+use std::fs::File;
+use std::io::{self, Cursor, Error, ErrorKind, Read};
+use std::path::Path;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+// Tags of MP3s inside ZIP archives, read without extracting them. Entries are only read and
+// inflated as far as the tag goes. ZIP64 archives are supported, encrypted entries aren't
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZipEntry {
+    // Path inside the archive with / between directories
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+fn zip_error(error: ZipError) -> Error {
+    match error {
+        ZipError::Io(error) => error,
+        ZipError::FileNotFound => Error::new(ErrorKind::NotFound, "Not in the archive"),
+        ZipError::UnsupportedArchive(message) => Error::new(ErrorKind::Unsupported, message),
+        ZipError::CompressionMethodNotSupported(method) => Error::new(ErrorKind::Unsupported, format!("ZIP compression method {method} isn't supported")),
+        error => Error::new(ErrorKind::InvalidData, error),
+    }
+}
+
+fn open(zip_path: impl AsRef<Path>) -> io::Result<ZipArchive<File>> {
+    ZipArchive::new(File::open(long_path(zip_path))?).map_err(zip_error)
+}
+
+// Every entry in the archive in directory order, directories included
+pub fn entries(zip_path: impl AsRef<Path>) -> io::Result<Vec<ZipEntry>> {
+    let mut archive = open(zip_path)?;
+    (0..archive.len())
+        .map(|i| {
+            let entry = archive.by_index_raw(i).map_err(zip_error)?;
+            Ok(ZipEntry { name: entry.name().to_string(), size: entry.size(), compressed_size: entry.compressed_size() })
+        })
+        .collect()
+}
+
+// The MP3 entries, for indexing an archive without knowing its layout
+pub fn mp3_entries(zip_path: impl AsRef<Path>) -> io::Result<Vec<ZipEntry>> {
+    let entries = entries(zip_path)?;
+    Ok(entries.into_iter().filter(|entry| entry.name.to_ascii_lowercase().ends_with(".mp3")).collect())
+}
+
+pub fn read_tag(zip_path: impl AsRef<Path>, inner_path: impl AsRef<Path>) -> io::Result<Tag> {
+    read_tag_with(zip_path, inner_path, &ReadOptions::new())
+}
+
+// Lazy frames are read in full, there is no file to go back to for them. The inner path is
+// matched against entry names with either separator
+pub fn read_tag_with(zip_path: impl AsRef<Path>, inner_path: impl AsRef<Path>, options: &ReadOptions) -> io::Result<Tag> {
+    let mut archive = open(zip_path)?;
+    let inner_path = inner_path.as_ref().to_string_lossy().replace('\\', "/");
+    let mut entry = match archive.by_name(inner_path.trim_start_matches('/')) {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Err(Error::new(ErrorKind::NotFound, format!("{inner_path} is not in the archive"))),
+        Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+            return Err(Error::new(ErrorKind::Unsupported, format!("{inner_path} is encrypted")));
+        }
+        Err(error) => return Err(zip_error(error)),
+    };
+
+    let mut bytes = Vec::new();
+    (&mut entry).take(10).read_to_end(&mut bytes)?;
+    let tag_size = Header::from_bytes(&bytes).map_or(10, |header| header.tag_size());
+    entry.take(tag_size.saturating_sub(10)).read_to_end(&mut bytes)?;
+    Tag::from_reader_with(&mut Reader::from_stream(Cursor::new(bytes)), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use std::fs;
+
+    const END_OF_DIRECTORY: u32 = 0x06054b50;
+    const DIRECTORY_ENTRY: u32 = 0x02014b50;
+    const LOCAL_HEADER: u32 = 0x04034b50;
+    const STORED: u16 = 0;
+    const DEFLATED: u16 = 8;
+
+    // A directory, then a stored copy of the fixture and a deflated one made of stored blocks. The
+    // last entry's offset is in a ZIP64 extra field
+    fn archive() -> Vec<u8> {
+        let mp3 = fs::read("test/Polygondwanaland.mp3").unwrap();
+        let mut deflated = Vec::new();
+        let blocks: Vec<&[u8]> = mp3.chunks(65535).collect();
+        for (i, block) in blocks.iter().enumerate() {
+            deflated.push((i + 1 == blocks.len()) as u8);
+            deflated.extend((block.len() as u16).to_le_bytes());
+            deflated.extend((!(block.len() as u16)).to_le_bytes());
+            deflated.extend_from_slice(block);
+        }
+
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        let files: [(&str, u16, &[u8]); 3] = [("album/", STORED, b""), ("album/01 Crumbling Castle.mp3", STORED, &mp3), ("album/02 Polygondwanaland.mp3", DEFLATED, &deflated)];
+        for (i, (name, method, data)) in files.into_iter().enumerate() {
+            let offset = zip.len() as u32;
+            let sizes = [data.len() as u32, if method == STORED { data.len() } else { mp3.len() } as u32];
+            zip.extend(LOCAL_HEADER.to_le_bytes());
+            zip.extend([20, 0, 0, 0]);
+            zip.extend(method.to_le_bytes());
+            zip.extend([0; 8]);
+            zip.extend(sizes[0].to_le_bytes());
+            zip.extend(sizes[1].to_le_bytes());
+            zip.extend((name.len() as u16).to_le_bytes());
+            zip.extend([0, 0]);
+            zip.extend(name.as_bytes());
+            zip.extend_from_slice(data);
+
+            let zip64 = i == 2;
+            let extra: Vec<u8> = if zip64 { [[1, 0, 8, 0].as_slice(), &(offset as u64).to_le_bytes()].concat() } else { Vec::new() };
+            directory.extend(DIRECTORY_ENTRY.to_le_bytes());
+            directory.extend([45, 0, 20, 0, 0, 0]);
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 8]);
+            directory.extend(sizes[0].to_le_bytes());
+            directory.extend(sizes[1].to_le_bytes());
+            directory.extend((name.len() as u16).to_le_bytes());
+            directory.extend((extra.len() as u16).to_le_bytes());
+            directory.extend([0; 10]);
+            directory.extend((if zip64 { u32::MAX } else { offset }).to_le_bytes());
+            directory.extend(name.as_bytes());
+            directory.extend(extra);
+        }
+        let offset = zip.len() as u32;
+        zip.extend(&directory);
+        zip.extend(END_OF_DIRECTORY.to_le_bytes());
+        zip.extend([0, 0, 0, 0, 3, 0, 3, 0]);
+        zip.extend((directory.len() as u32).to_le_bytes());
+        zip.extend(offset.to_le_bytes());
+        zip.extend([0, 0]);
+        zip
+    }
+
+    #[test]
+    fn tags_read_from_archive() {
+        let path = temp_path("album.zip");
+        fs::write(&path, archive()).unwrap();
+        let expected = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+
+        let names: Vec<String> = mp3_entries(&path).unwrap().into_iter().map(|entry| entry.name).collect();
+        assert_eq!(names, ["album/01 Crumbling Castle.mp3", "album/02 Polygondwanaland.mp3"]);
+        assert!(entries(&path).unwrap()[0].is_dir());
+        for name in &names {
+            assert!(read_tag(&path, name).unwrap() == expected);
+        }
+        assert!(read_tag(&path, "album\\01 Crumbling Castle.mp3").is_ok());
+        assert!(read_tag(&path, Path::new("album").join("01 Crumbling Castle.mp3")).is_ok());
+        assert_eq!(read_tag(&path, "album/03.mp3").err().unwrap().kind(), ErrorKind::NotFound);
+        assert_eq!(entries("test/Polygondwanaland.mp3").err().unwrap().kind(), ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use crate::frames::{Picture, PictureType, mime_type};
+use crate::Tag;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArtQuery {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    // Folder the audio file lives in, used by providers that look next to the music
+    pub directory: Option<PathBuf>,
+}
+
+impl ArtQuery {
+    pub fn from_file(filename: impl AsRef<Path>, tag: &Tag) -> Self {
+        Self {
+            artist: tag.text("TPE2").or_else(|| tag.artist()),
+            album: tag.album(),
+            directory: filename.as_ref().parent().map(Path::to_path_buf),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArtCandidate {
+    source: String,
+    mime: String,
+    data: Vec<u8>,
+}
+
+impl ArtCandidate {
+    pub fn new(source: &str, mime: &str, data: Vec<u8>) -> Self {
+        Self {
+            source: source.to_string(),
+            mime: mime.to_string(),
+            data,
+        }
+    }
+
+    // Where the image came from, a path for local providers or a URL for web ones
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn mime(&self) -> &str {
+        &self.mime
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn to_picture(&self, picture_type: PictureType) -> Picture {
+        Picture::new(&self.mime, picture_type, "", self.data.clone())
+    }
+
+    pub fn embed(&self, tag: &mut Tag, picture_type: PictureType) {
+        tag.embed_picture(&self.to_picture(picture_type));
+    }
+}
+
+pub trait ArtProvider {
+    fn name(&self) -> &str;
+
+    // Candidates ordered from best to worst match
+    fn find(&self, query: &ArtQuery) -> io::Result<Vec<ArtCandidate>>;
+}
+
+// Looks for the usual cover image names in the album's folder
+pub struct FolderArt {
+    names: Vec<String>,
+}
+
+impl FolderArt {
+    pub fn new() -> Self {
+        Self {
+            names: ["cover", "folder", "front", "album", "albumart"].map(String::from).to_vec(),
+        }
+    }
+
+    pub fn names(mut self, names: &[&str]) -> Self {
+        self.names = names.iter().map(|x| x.to_string()).collect();
+        self
+    }
+}
+
+impl Default for FolderArt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArtProvider for FolderArt {
+    fn name(&self) -> &str {
+        "folder"
+    }
+
+    fn find(&self, query: &ArtQuery) -> io::Result<Vec<ArtCandidate>> {
+        let Some(directory) = &query.directory else {
+            return Ok(Vec::new());
+        };
+
+        // Images named after the album count as well as the generic names
+        let mut names = self.names.clone();
+        if let Some(album) = &query.album {
+            names.push(album.to_lowercase());
+        }
+
+        let mut entries: Vec<PathBuf> = fs::read_dir(directory)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        entries.sort();
+
+        let mut candidates = Vec::new();
+        for name in &names {
+            for path in &entries {
+                let Some(stem) = path.file_stem().and_then(|x| x.to_str()) else {
+                    continue;
+                };
+                if stem.to_lowercase() != *name {
+                    continue;
+                }
+                let data = fs::read(path)?;
+                if let Some(mime) = mime_type(&data) {
+                    candidates.push(ArtCandidate::new(&path.to_string_lossy(), mime, data));
+                }
+            }
+        }
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+
+    fn album_folder(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let directory = temp_path(name);
+        fs::create_dir_all(&directory).unwrap();
+        for (file, bytes) in files {
+            fs::write(directory.join(file), bytes).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn finds_folder_images() {
+        let directory = album_folder("found", &[
+            ("Folder.JPG", &[0xFF, 0xD8, 0xFF, 0xE0]),
+            ("cover.png", &[0x89, b'P', b'N', b'G']),
+            ("notes.txt", b"liner notes"),
+        ]);
+        let query = ArtQuery { directory: Some(directory.clone()), ..Default::default() };
+        let candidates = FolderArt::new().find(&query).unwrap();
+
+        let mimes: Vec<&str> = candidates.iter().map(|x| x.mime()).collect();
+        assert_eq!(mimes, ["image/png", "image/jpeg"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn skips_non_images() {
+        let directory = album_folder("not-image", &[("cover.jpg", b"not really a jpeg")]);
+        let query = ArtQuery { directory: Some(directory.clone()), ..Default::default() };
+        assert!(FolderArt::new().find(&query).unwrap().is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn embed_candidate() {
+        let mut tag = Tag::new(3);
+        ArtCandidate::new("cover.png", "image/png", vec![0x89, b'P', b'N', b'G']).embed(&mut tag, PictureType::FrontCover);
+        assert_eq!(tag.pictures()[0].picture_type(), PictureType::FrontCover);
+    }
+
+    #[test]
+    fn query_from_file() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let query = ArtQuery::from_file("test/Polygondwanaland.mp3", &tag);
+        assert_eq!(query.directory, Some(PathBuf::from("test")));
+        assert_eq!(query.album.as_deref(), Some("Polygondwanaland"));
+    }
+}
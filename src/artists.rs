@@ -0,0 +1,149 @@
+use crate::Tag;
+use crate::convert::{text_frame, text_values};
+
+fn starts_with_ignore_case(text: &str, pattern: &str) -> bool {
+    text.len() >= pattern.len() && text.is_char_boundary(pattern.len()) && text[..pattern.len()].eq_ignore_ascii_case(pattern)
+}
+
+// Splits combined artist credits like "A feat. B" into separate artists
+pub struct ArtistSplitter {
+    separators: Vec<String>,
+    exceptions: Vec<String>,
+    join: String,
+}
+
+impl ArtistSplitter {
+    pub fn new() -> Self {
+        Self {
+            separators: [" feat. ", " feat ", " ft. ", " featuring ", " with ", " & ", ";"].map(String::from).to_vec(),
+            exceptions: ["Simon & Garfunkel", "Earth, Wind & Fire", "King Gizzard & The Lizard Wizard", "Hall & Oates"]
+                .map(String::from)
+                .to_vec(),
+            join: "/".to_string(),
+        }
+    }
+
+    // Separators are matched without regard to ASCII case
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separators.push(separator.to_string());
+        self
+    }
+
+    pub fn separators(mut self, separators: &[&str]) -> Self {
+        self.separators = separators.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    // Names that contain a separator but are a single artist. An empty name would match everywhere
+    // and is ignored
+    pub fn exception(mut self, name: &str) -> Self {
+        if !name.is_empty() {
+            self.exceptions.push(name.to_string());
+        }
+        self
+    }
+
+    // What v2.3 tags put between artists, the spec uses "/"
+    pub fn join(mut self, join: &str) -> Self {
+        self.join = join.to_string();
+        self
+    }
+
+    pub fn split(&self, artist: &str) -> Vec<String> {
+        let mut artists: Vec<String> = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        'scan: while i < artist.len() {
+            let rest = &artist[i..];
+            if let Some(exception) = self.exceptions.iter().find(|exception| starts_with_ignore_case(rest, exception)) {
+                i += exception.len();
+                continue;
+            }
+            for separator in &self.separators {
+                if !separator.is_empty() && starts_with_ignore_case(rest, separator) {
+                    artists.push(artist[start..i].to_string());
+                    i += separator.len();
+                    start = i;
+                    continue 'scan;
+                }
+            }
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+        artists.push(artist[start..].to_string());
+
+        let mut unique: Vec<String> = Vec::new();
+        for artist in artists.iter().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            if !unique.iter().any(|x| x.eq_ignore_ascii_case(artist)) {
+                unique.push(artist.to_string());
+            }
+        }
+        unique
+    }
+
+    // v2.4 gets one value per artist, v2.3 a single value joined with the join string
+    pub fn apply(&self, tag: &mut Tag, id: &str) {
+        let Some(index) = tag.frames().iter().position(|frame| frame.id() == id) else {
+            return;
+        };
+        let frame = &tag.frames()[index];
+        let artists: Vec<String> = text_values(frame).iter().flat_map(|value| self.split(value)).collect();
+        let values = if tag.version() == 4 { artists } else { vec![artists.join(&self.join)] };
+
+        if let Some(mut split) = text_frame(id, &values, tag.version()) {
+            split.set_group(frame.group());
+            tag.frames_mut()[index] = split;
+        }
+    }
+}
+
+impl Default for ArtistSplitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_credits() {
+        let splitter = ArtistSplitter::new();
+        assert_eq!(splitter.split("Artist A feat. Artist B"), ["Artist A", "Artist B"]);
+        assert_eq!(splitter.split("A & B; C FT. D"), ["A", "B", "C", "D"]);
+        assert_eq!(splitter.split("Solo"), ["Solo"]);
+    }
+
+    #[test]
+    fn exceptions_kept_whole() {
+        let splitter = ArtistSplitter::new().exception("Crosby, Stills, Nash & Young");
+        assert_eq!(splitter.split("simon & garfunkel"), ["simon & garfunkel"]);
+        assert_eq!(splitter.split("Crosby, Stills, Nash & Young feat. Simon & Garfunkel"), ["Crosby, Stills, Nash & Young", "Simon & Garfunkel"]);
+    }
+
+    #[test]
+    fn empty_exception_ignored() {
+        assert_eq!(ArtistSplitter::new().exception("").split("A & B"), ["A", "B"]);
+    }
+
+    #[test]
+    fn custom_separators() {
+        let splitter = ArtistSplitter::new().separators(&[", "]);
+        assert_eq!(splitter.split("A, B & C"), ["A", "B & C"]);
+    }
+
+    #[test]
+    fn apply_by_version() {
+        let splitter = ArtistSplitter::new();
+
+        let mut tag = Tag::new(4);
+        tag.set_text("TPE1", "Ōkami feat. Wolf & Wolf");
+        splitter.apply(&mut tag, "TPE1");
+        assert_eq!(text_values(tag.frame("TPE1").unwrap()), ["Ōkami", "Wolf"]);
+
+        let mut tag = Tag::new(3);
+        tag.set_text("TPE1", "A feat. B");
+        splitter.join("; ").apply(&mut tag, "TPE1");
+        assert_eq!(tag.artist().unwrap(), "A; B");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::frames::Chapter;
+use crate::paths::long_path;
+use crate::{CompatibilityReport, Tag, WriteOptions, mpeg};
+use std::fs::File;
+use std::io::{self, BufWriter, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// Frames that only describe one part, everything else is taken from the first part's tag
+const PART_FRAMES: [&str; 7] = ["TIT2", "TRCK", "TLEN", "TSRC", "TCON", "CHAP", "CTOC"];
+
+pub const GENRE: &str = "Audiobook";
+
+struct Part {
+    title: String,
+    duration: u64,
+    frames: Vec<(u64, mpeg::FrameHeader)>,
+}
+
+fn read_part(filename: &Path, file: &mut File) -> io::Result<Part> {
+    let range = mpeg::audio_range(file)?;
+    let mut frames = mpeg::scan(file, range)?;
+    mpeg::drop_info_frame(file, &mut frames)?;
+    if frames.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} has no MPEG audio", filename.display())));
+    }
+
+    let stem = || filename.file_stem().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default();
+    let title = Tag::from_file(filename).ok().and_then(|tag| tag.title()).unwrap_or_else(stem);
+    Ok(Part { title, duration: mpeg::duration_ms(&frames), frames })
+}
+
+// Joins the parts in order into one file with a chapter per part named after its title.
+// Book level frames come from the first part and the genre is set to Audiobook
+pub fn merge(parts: &[impl AsRef<Path>], output: impl AsRef<Path>, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+    let output = output.as_ref();
+    let Some(first) = parts.first() else {
+        return Err(Error::new(ErrorKind::InvalidInput, "an audiobook needs at least one part"));
+    };
+    let source = Tag::from_file(first).unwrap_or_else(|_| Tag::new(4));
+    let mut out = BufWriter::new(File::create(long_path(output))?);
+    let mut chapters = Vec::new();
+    let mut sample_rate = None;
+    let mut elapsed = 0;
+
+    for (i, filename) in parts.iter().enumerate() {
+        let filename = filename.as_ref();
+        let mut file = File::open(long_path(filename))?;
+        let part = read_part(filename, &mut file)?;
+
+        // Players assume one sample rate for the whole stream
+        let rate = part.frames[0].1.sample_rate();
+        if *sample_rate.get_or_insert(rate) != rate {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("{} is {rate} Hz unlike the parts before it", filename.display())));
+        }
+
+        let start = part.frames[0].0;
+        let (last, header) = part.frames[part.frames.len() - 1];
+        file.seek(SeekFrom::Start(start))?;
+        io::copy(&mut file.take(last + header.frame_length() as u64 - start), &mut out)?;
+
+        let mut chapter = Chapter::new(&format!("chp{i}"), elapsed as u32, (elapsed + part.duration) as u32);
+        chapter.set_title(&part.title, source.version());
+        chapters.push(chapter);
+        elapsed += part.duration;
+    }
+    out.flush()?;
+    drop(out);
+
+    let mut tag = Tag::new(source.version());
+    for frame in source.frames() {
+        if !PART_FRAMES.contains(&frame.id().as_str()) {
+            tag.add_frame(frame.clone());
+        }
+    }
+    if let Some(album) = source.album() {
+        tag.set_text("TIT2", &album);
+    }
+    tag.set_text("TCON", GENRE);
+    tag.set_text("TLEN", &elapsed.to_string());
+    tag.set_chapters(&chapters);
+    tag.write_to_file(output, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use std::fs;
+
+
+    #[test]
+    fn merges_parts_into_chapters() {
+        let second = temp_path("part-2.mp3");
+        fs::copy("test/Polygondwanaland.mp3", &second).unwrap();
+        let mut tag = Tag::from_file(&second).unwrap();
+        tag.set_text("TIT2", "Deserted Dunes Welcome Weary Feet");
+        tag.write_to_file(&second, &WriteOptions::new()).unwrap();
+
+        let output = temp_path("book.mp3");
+        merge(&[Path::new("test/Polygondwanaland.mp3"), &second], &output, &WriteOptions::new()).unwrap();
+
+        let book = Tag::from_file(&output).unwrap();
+        assert_eq!(book.title().as_deref(), Some("Polygondwanaland"));
+        assert_eq!(book.text("TCON").as_deref(), Some(GENRE));
+        assert_eq!(book.pictures().len(), 1);
+
+        // Each part loses its Info frame
+        let part = 213_024 - 26;
+        let chapters = book.chapters();
+        let bounds: Vec<(u32, u32)> = chapters.iter().map(|chapter| (chapter.start(), chapter.end())).collect();
+        assert_eq!(bounds, [(0, part), (part, part * 2)]);
+        assert_eq!(chapters[1].title().as_deref(), Some("Deserted Dunes Welcome Weary Feet"));
+        assert_eq!(book.text("TLEN"), Some((part * 2).to_string()));
+
+        let mut file = File::open(&output).unwrap();
+        let range = mpeg::audio_range(&mut file).unwrap();
+        assert!(mpeg::duration_ms(&mpeg::scan(&mut file, range).unwrap()).abs_diff(part as u64 * 2) <= 1);
+        fs::remove_file(second).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn needs_parts() {
+        assert_eq!(merge(&[] as &[&str], temp_path("empty.mp3"), &WriteOptions::new()).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
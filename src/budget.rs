@@ -0,0 +1,190 @@
+use crate::device::{Ellipsis, graphemes, truncate};
+use crate::frames::{Lyrics, Picture};
+use crate::Tag;
+
+// Ways to make a tag smaller, each one only goes as far as it needs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shrink {
+    // Pictures that repeat the data of an earlier one or clash with it, see Tag::dedup_pictures
+    DuplicateArt,
+    // Cut the end off the longest lyrics first
+    TruncateLyrics,
+    // Drop PRIV frames, largest first
+    Private,
+}
+
+// A limit on the size of the tag, header included and padding left out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeBudget {
+    max_size: usize,
+    strategy: Vec<Shrink>,
+}
+
+impl SizeBudget {
+    pub fn new(max_size: usize) -> Self {
+        Self { max_size, strategy: vec![Shrink::DuplicateArt, Shrink::TruncateLyrics, Shrink::Private] }
+    }
+
+    // Tried in order until the tag fits, later steps are skipped once it does
+    pub fn strategy(mut self, strategy: &[Shrink]) -> Self {
+        self.strategy = strategy.to_vec();
+        self
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+}
+
+// One frame that was cut down or dropped
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trimmed {
+    pub id: String,
+    pub shrink: Shrink,
+    // Bytes the tag got smaller by
+    pub bytes: usize,
+    pub dropped: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BudgetReport {
+    pub max_size: usize,
+    pub before: usize,
+    pub after: usize,
+    pub trimmed: Vec<Trimmed>,
+}
+
+impl BudgetReport {
+    pub fn fits(&self) -> bool {
+        self.after <= self.max_size
+    }
+}
+
+fn size(tag: &Tag) -> usize {
+    tag.to_bytes(0).len()
+}
+
+// Index of the first picture repeating an earlier picture's data or clashing with it
+fn duplicate_picture(tag: &Tag) -> Option<usize> {
+    let mut kept: Vec<Picture> = Vec::new();
+    for (index, frame) in tag.frames().iter().enumerate() {
+        let Some(picture) = Picture::from_frame(frame) else {
+            continue;
+        };
+        if kept.iter().any(|existing| existing.data() == picture.data() || existing.clashes_with(&picture)) {
+            return Some(index);
+        }
+        kept.push(picture);
+    }
+    None
+}
+
+fn largest(tag: &Tag, id: &str) -> Option<usize> {
+    let frames = tag.frames().iter().enumerate().filter(|(_, frame)| frame.id() == id);
+    frames.max_by_key(|(_, frame)| frame.data().len()).map(|(index, _)| index)
+}
+
+// Cuts the longest lyrics by about as many bytes as the tag is over, None when there is nothing left to cut
+fn truncate_lyrics(tag: &mut Tag, over: usize) -> Option<()> {
+    let index = tag
+        .frames()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, frame)| Some((index, Lyrics::from_frame(frame)?)))
+        .filter(|(_, lyrics)| !lyrics.text().is_empty())
+        .max_by_key(|(_, lyrics)| lyrics.text().len())?
+        .0;
+    let lyrics = Lyrics::from_frame(&tag.frames()[index])?;
+    let text = lyrics.text();
+
+    // Scale by bytes per grapheme so UTF-16 text loses as much as Latin-1 would
+    let count = graphemes(text).len();
+    let per_grapheme = (lyrics.to_frame()?.data().len() / count.max(1)).max(1);
+    let keep = count.saturating_sub(over.div_ceil(per_grapheme));
+    let cut = Lyrics::new(lyrics.language(), lyrics.description(), truncate(text, keep, Ellipsis::None).trim_end());
+    tag.frames_mut()[index] = cut.to_frame()?;
+    Some(())
+}
+
+impl Tag {
+    // Shrinks the tag by the budget's strategy until it fits or the strategy runs out
+    pub fn fit_to_budget(&mut self, budget: &SizeBudget) -> BudgetReport {
+        let before = size(self);
+        let mut current = before;
+        let mut trimmed = Vec::new();
+        for shrink in &budget.strategy {
+            while current > budget.max_size {
+                let dropped = match shrink {
+                    Shrink::DuplicateArt => duplicate_picture(self),
+                    Shrink::Private => largest(self, "PRIV"),
+                    Shrink::TruncateLyrics => None,
+                };
+                let id = match dropped {
+                    Some(index) => self.frames_mut().remove(index).id(),
+                    None if *shrink == Shrink::TruncateLyrics && truncate_lyrics(self, current - budget.max_size).is_some() => {
+                        "USLT".to_string()
+                    }
+                    None => break,
+                };
+                let after = size(self);
+                if after >= current {
+                    break;
+                }
+                trimmed.push(Trimmed { id, shrink: *shrink, bytes: current - after, dropped: dropped.is_some() });
+                current = after;
+            }
+        }
+        BudgetReport { max_size: budget.max_size, before, after: current, trimmed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frames::PictureType;
+    use crate::{Frame, Language};
+
+    fn private(size: usize) -> Frame {
+        Frame::new("PRIV", [b"owner\0".to_vec(), vec![7; size]].concat()).unwrap()
+    }
+
+    #[test]
+    fn stops_once_it_fits() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let cover = tag.front_cover().unwrap();
+        tag.embed_picture(&Picture::new(cover.mime(), PictureType::Other, "copy", cover.data().to_vec()));
+        tag.add_frame(private(1000));
+        tag.add_frame(private(4000));
+        let size = size(&tag);
+
+        let report = tag.clone().fit_to_budget(&SizeBudget::new(size));
+        assert!(report.trimmed.is_empty() && report.fits());
+
+        let report = tag.fit_to_budget(&SizeBudget::new(size - cover.data().len() - 100));
+        assert_eq!(report.trimmed.iter().map(|x| (x.id.as_str(), x.shrink)).collect::<Vec<_>>(), [("APIC", Shrink::DuplicateArt), ("PRIV", Shrink::Private)]);
+        assert!(report.trimmed[1].bytes > 4000);
+        assert_eq!(tag.pictures().len(), 1);
+        assert_eq!(tag.frames().iter().filter(|frame| frame.id() == "PRIV").count(), 1);
+        assert_eq!((report.before, report.after), (size, self::size(&tag)));
+        assert!(report.fits());
+    }
+
+    #[test]
+    fn truncates_lyrics() {
+        let mut tag = Tag::new(4);
+        tag.set_text("TIT2", "Crumbling Castle");
+        let text = "Castle in the air ".repeat(100);
+        tag.add_frame(Lyrics::new(Language::new("eng").unwrap(), "", &text).to_frame().unwrap());
+        let budget = SizeBudget::new(size(&tag) - 500);
+
+        let report = tag.fit_to_budget(&budget);
+        assert!(report.fits());
+        assert_eq!((report.trimmed.len(), report.trimmed[0].dropped), (1, false));
+        let lyrics = tag.lyrics();
+        assert!(text.starts_with(lyrics[0].text()) && lyrics[0].text().len() < text.len() - 490);
+
+        // Nothing left to take away
+        let report = tag.fit_to_budget(&SizeBudget::new(10).strategy(&[Shrink::Private]));
+        assert!(!report.fits() && report.trimmed.is_empty());
+    }
+}
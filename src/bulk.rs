@@ -0,0 +1,542 @@
+pub use crate::write::CancelToken;
+use crate::genres::GenreMap;
+use crate::paths::long_path;
+use crate::resume::{Done, StateFile, plan_hash};
+use crate::spelling::Dictionary;
+use crate::{Tag, WriteOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+type EditCallback = Box<dyn Fn(&mut Tag) + Send + Sync>;
+
+// Whether the file was rewritten, or where and why it failed
+type RewriteResult = Result<bool, (Stage, io::Error)>;
+
+enum Operation {
+    SetText(String, String),
+    Remove(String),
+    Normalize(Dictionary),
+    MapGenres(GenreMap),
+    Custom(EditCallback),
+}
+
+// Changes applied to one file's tag, in the order they were added
+#[derive(Default)]
+pub struct TagEdit {
+    operations: Vec<Operation>,
+}
+
+impl TagEdit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_text(mut self, id: &str, text: &str) -> Self {
+        self.operations.push(Operation::SetText(id.to_string(), text.to_string()));
+        self
+    }
+
+    pub fn remove(mut self, id: &str) -> Self {
+        self.operations.push(Operation::Remove(id.to_string()));
+        self
+    }
+
+    // Clones of the dictionary share its counts so they add up over the whole run
+    pub fn normalize(mut self, dictionary: &Dictionary) -> Self {
+        self.operations.push(Operation::Normalize(dictionary.clone()));
+        self
+    }
+
+    // Clones of the map share its counts, so unmapped genres are reported for the whole run
+    pub fn map_genres(mut self, map: &GenreMap) -> Self {
+        self.operations.push(Operation::MapGenres(map.clone()));
+        self
+    }
+
+    pub fn custom(mut self, callback: impl Fn(&mut Tag) + Send + Sync + 'static) -> Self {
+        self.operations.push(Operation::Custom(Box::new(callback)));
+        self
+    }
+
+    pub fn apply(&self, tag: &mut Tag) {
+        for operation in &self.operations {
+            match operation {
+                Operation::SetText(id, text) => tag.set_text(id, text),
+                Operation::Remove(id) => tag.remove(id),
+                Operation::Normalize(dictionary) => {
+                    dictionary.apply(tag);
+                }
+                Operation::MapGenres(map) => {
+                    map.apply(tag);
+                }
+                Operation::Custom(callback) => callback(tag),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_written: u64,
+}
+
+// Where a file failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    // The tag couldn't be parsed or the file couldn't be opened
+    Read,
+    // The tag was read but writing it back failed, the original is left as it was
+    Write,
+}
+
+// What happens to files that fail, the run carries on either way
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Quarantine {
+    // Only list them in the report
+    #[default]
+    List,
+    // Move them into the directory, created when needed, so a rerun skips them
+    MoveTo(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub path: PathBuf,
+    pub stage: Stage,
+    pub error: io::Error,
+    // Where the file was moved, None when it was only listed or couldn't be moved
+    pub quarantined: Option<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct BulkReport {
+    succeeded: Vec<PathBuf>,
+    unchanged: Vec<PathBuf>,
+    failed: Vec<Failure>,
+    cancelled: Vec<PathBuf>,
+    resumed: Vec<PathBuf>,
+    state_error: Option<io::Error>,
+}
+
+impl BulkReport {
+    pub fn succeeded(&self) -> &[PathBuf] {
+        &self.succeeded
+    }
+
+    // Files left alone because the edit didn't change their tag
+    pub fn unchanged(&self) -> &[PathBuf] {
+        &self.unchanged
+    }
+
+    pub fn failed(&self) -> &[Failure] {
+        &self.failed
+    }
+
+    // Files that were never started because the run was cancelled
+    pub fn cancelled(&self) -> &[PathBuf] {
+        &self.cancelled
+    }
+
+    // Files an earlier, interrupted run already finished, see BulkWriter::resume
+    pub fn resumed(&self) -> &[PathBuf] {
+        &self.resumed
+    }
+
+    // Why the state file couldn't be read or written, the run itself went ahead
+    pub fn state_error(&self) -> Option<&io::Error> {
+        self.state_error.as_ref()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.cancelled.is_empty()
+    }
+}
+
+pub type ProgressCallback = Box<dyn Fn(Progress) + Send + Sync>;
+
+pub struct BulkWriter {
+    queue: Vec<(PathBuf, TagEdit)>,
+    options: WriteOptions,
+    quarantine: Quarantine,
+    // State file and the description of the plan it belongs to
+    state: Option<(PathBuf, String)>,
+    resume: bool,
+    concurrency: usize,
+    progress: Option<ProgressCallback>,
+    cancel: CancelToken,
+}
+
+impl BulkWriter {
+    pub fn new() -> Self {
+        Self {
+            queue: Vec::new(),
+            options: WriteOptions::new(),
+            quarantine: Quarantine::List,
+            state: None,
+            resume: false,
+            concurrency: 1,
+            progress: None,
+            cancel: CancelToken::new(),
+        }
+    }
+
+    pub fn push(&mut self, filename: impl AsRef<Path>, edit: TagEdit) {
+        self.queue.push((filename.as_ref().to_path_buf(), edit));
+    }
+
+    pub fn options(mut self, options: WriteOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn quarantine(mut self, quarantine: Quarantine) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    // Record each finished file so an interrupted run can be resumed. The plan describes the
+    // edit, together with the queued files it decides whether a state file matches this run.
+    // The file is removed once a run finishes without failures
+    pub fn state_file(mut self, path: impl AsRef<Path>, plan: &str) -> Self {
+        self.state = Some((path.as_ref().to_path_buf(), plan.to_string()));
+        self
+    }
+
+    // Skip the files a matching state file says were done, otherwise the state file starts over
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    // Number of files rewritten at the same time, at least one
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn on_progress(mut self, callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // Bytes written, or None when the tag came out the same and the file wasn't touched
+    fn rewrite(&self, filename: &Path, edit: &TagEdit) -> Result<Option<u64>, (Stage, io::Error)> {
+        let original = Tag::from_file(filename).map_err(|error| (Stage::Read, error))?;
+        let mut tag = original.clone();
+        edit.apply(&mut tag);
+        if self.options.is_no_op(&original, &tag) {
+            return Ok(None);
+        }
+        let write = || {
+            tag.write_to_file(filename, &self.options)?;
+            Ok(fs::metadata(filename)?.len())
+        };
+        write().map(Some).map_err(|error| (Stage::Write, error))
+    }
+
+    fn open_state(&self) -> io::Result<Option<(StateFile, HashMap<String, Done>)>> {
+        let Some((path, plan)) = &self.state else {
+            return Ok(None);
+        };
+        let hash = plan_hash(plan, self.queue.iter().map(|(filename, _)| filename.as_path()));
+        StateFile::open(path, &hash, self.resume).map(Some)
+    }
+
+    pub fn run(self) -> BulkReport {
+        let mut report = BulkReport::default();
+        let (state, done) = match self.open_state() {
+            Ok(Some((state, done))) => (Some(state), done),
+            Ok(None) => (None, HashMap::new()),
+            Err(error) => {
+                report.state_error = Some(error);
+                (None, HashMap::new())
+            }
+        };
+        let resumed: Vec<bool> = self.queue.iter().map(|(filename, _)| done.contains_key(filename.to_string_lossy().as_ref())).collect();
+        let files_total = resumed.iter().filter(|resumed| !**resumed).count();
+
+        let next = AtomicUsize::new(0);
+        let files_done = AtomicUsize::new(0);
+        let bytes_written = AtomicU64::new(0);
+        let results: Mutex<Vec<Option<RewriteResult>>> = Mutex::new((0..self.queue.len()).map(|_| None).collect());
+        let state_error = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(files_total) {
+                scope.spawn(|| {
+                    loop {
+                        if self.cancel.is_cancelled() {
+                            break;
+                        }
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((filename, edit)) = self.queue.get(index) else {
+                            break;
+                        };
+                        if resumed[index] {
+                            continue;
+                        }
+
+                        let result = self.rewrite(filename, edit);
+                        if let Ok(Some(bytes)) = result {
+                            bytes_written.fetch_add(bytes, Ordering::Relaxed);
+                        }
+                        if let (Some(state), Ok(bytes)) = (&state, &result) {
+                            let done = if bytes.is_some() { Done::Succeeded } else { Done::Unchanged };
+                            if let Err(error) = state.record(filename, done) {
+                                state_error.lock().unwrap().get_or_insert(error);
+                            }
+                        }
+                        results.lock().unwrap()[index] = Some(result.map(|bytes| bytes.is_some()));
+
+                        let files_done = files_done.fetch_add(1, Ordering::Relaxed) + 1;
+                        if let Some(callback) = &self.progress {
+                            callback(Progress {
+                                files_done,
+                                files_total,
+                                bytes_written: bytes_written.load(Ordering::Relaxed),
+                            });
+                        }
+                    }
+                });
+            }
+        });
+
+        // Results are reported in queue order whatever order the workers finished in
+        let results = results.into_inner().unwrap();
+        for (((filename, _), result), resumed) in self.queue.into_iter().zip(results).zip(resumed) {
+            match result {
+                _ if resumed => report.resumed.push(filename),
+                Some(Ok(true)) => report.succeeded.push(filename),
+                Some(Ok(false)) => report.unchanged.push(filename),
+                Some(Err((stage, error))) => {
+                    let quarantined = match &self.quarantine {
+                        Quarantine::List => None,
+                        Quarantine::MoveTo(dir) => move_into(&filename, dir).ok(),
+                    };
+                    report.failed.push(Failure { path: filename, stage, error, quarantined });
+                }
+                None => report.cancelled.push(filename),
+            }
+        }
+        if let Some(error) = state_error.into_inner().unwrap() {
+            report.state_error.get_or_insert(error);
+        }
+        if let Some(state) = state.filter(|_| report.is_success())
+            && let Err(error) = state.finish()
+        {
+            report.state_error.get_or_insert(error);
+        }
+        report
+    }
+}
+
+// Moves the file into dir, numbering the name when dir already has a file called that
+fn move_into(filename: &Path, dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(long_path(dir))?;
+    let name = filename.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut target = dir.join(name);
+    let stem = Path::new(name).file_stem().unwrap_or(name).to_string_lossy().into_owned();
+    let extension = Path::new(name).extension().map(|x| format!(".{}", x.to_string_lossy())).unwrap_or_default();
+    let mut number = 1;
+    while target.exists() {
+        target = dir.join(format!("{stem} ({number}){extension}"));
+        number += 1;
+    }
+    // A rename can't cross file systems, copy and remove instead
+    if fs::rename(long_path(filename), long_path(&target)).is_err() {
+        fs::copy(long_path(filename), long_path(&target))?;
+        fs::remove_file(long_path(filename))?;
+    }
+    Ok(target)
+}
+
+impl Default for BulkWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{copy_of_test_file, temp_path};
+    use std::sync::Arc;
+
+    #[test]
+    fn rewrites_queue_concurrently() {
+        let paths: Vec<PathBuf> = (0..4).map(|i| copy_of_test_file(&format!("queue-{i}"))).collect();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+
+        let mut writer = BulkWriter::new().concurrency(3).on_progress(move |_| {
+            counted.fetch_add(1, Ordering::Relaxed);
+        });
+        for (i, path) in paths.iter().enumerate() {
+            writer.push(path, TagEdit::new().set_text("TRCK", &(i + 1).to_string()).remove("COMM"));
+        }
+        let report = writer.run();
+
+        assert!(report.is_success());
+        assert_eq!(report.succeeded(), paths);
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+        for (i, path) in paths.iter().enumerate() {
+            let tag = Tag::from_file(path).unwrap();
+            assert_eq!(tag.text("TRCK"), Some((i + 1).to_string()));
+            assert!(tag.frame("COMM").is_none());
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn errors_are_collected() {
+        let path = copy_of_test_file("errors");
+        let mut writer = BulkWriter::new();
+        writer.push("test/does-not-exist.mp3", TagEdit::new());
+        writer.push(&path, TagEdit::new().custom(|tag| tag.set_text("TIT2", "Deserted Dunes")));
+        let report = writer.run();
+
+        assert_eq!(report.failed().len(), 1);
+        assert_eq!(report.failed()[0].error.kind(), io::ErrorKind::NotFound);
+        assert_eq!((report.failed()[0].stage, &report.failed()[0].quarantined), (Stage::Read, &None));
+        assert_eq!(report.succeeded(), std::slice::from_ref(&path));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failures_are_quarantined() {
+        let good = copy_of_test_file("quarantine-good");
+        let bad = temp_path("quarantine-bad.mp3");
+        fs::write(&bad, b"not an mp3").unwrap();
+        let dir = temp_path("quarantine");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(bad.file_name().unwrap()), b"earlier").unwrap();
+
+        let mut writer = BulkWriter::new().quarantine(Quarantine::MoveTo(dir.clone()));
+        writer.push(&bad, TagEdit::new().set_text("TIT2", "Loyalty"));
+        writer.push(&good, TagEdit::new().set_text("TIT2", "Loyalty"));
+        let report = writer.run();
+
+        assert_eq!(report.succeeded(), std::slice::from_ref(&good));
+        let failure = &report.failed()[0];
+        assert_eq!((&failure.path, failure.stage), (&bad, Stage::Read));
+        let moved = failure.quarantined.clone().unwrap();
+        assert_eq!(moved.file_name().unwrap().to_str().unwrap(), bad.file_stem().unwrap().to_str().unwrap().to_string() + " (1).mp3");
+        assert!(!bad.exists() && fs::read(&moved).unwrap() == b"not an mp3");
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_file(good).unwrap();
+    }
+
+    #[test]
+    fn cancel_stops_queue() {
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        let paths: Vec<PathBuf> = (0..3).map(|i| copy_of_test_file(&format!("cancel-{i}"))).collect();
+
+        let mut writer = BulkWriter::new().cancel_token(token).on_progress(move |_| cancel.cancel());
+        for path in &paths {
+            writer.push(path, TagEdit::new().set_text("TIT2", "Cancelled"));
+        }
+        let report = writer.run();
+
+        assert_eq!(report.succeeded().len(), 1);
+        assert_eq!(report.cancelled().len(), 2);
+        assert!(!report.is_success());
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn interrupted_run_resumes() {
+        let paths: Vec<PathBuf> = (0..3).map(|i| copy_of_test_file(&format!("resume-{i}"))).collect();
+        let state = temp_path("resume.state");
+        let writer = |token: CancelToken, plan: &str| {
+            let mut writer = BulkWriter::new().state_file(&state, plan).resume(true).cancel_token(token);
+            for path in &paths {
+                writer.push(path, TagEdit::new().set_text("TIT2", "Resumed"));
+            }
+            writer
+        };
+
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        let report = writer(token, "set TIT2").on_progress(move |_| cancel.cancel()).run();
+        assert_eq!((report.succeeded().len(), report.cancelled().len()), (1, 2));
+        assert!(state.exists());
+
+        // A different plan doesn't pick up the state of this one
+        let report = writer(CancelToken::new(), "set TPE1").run();
+        assert_eq!((report.resumed().len(), report.unchanged().len()), (0, 1));
+        assert!(!state.exists());
+
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        writer(token, "set TIT2").on_progress(move |_| cancel.cancel()).run();
+        let report = writer(CancelToken::new(), "set TIT2").run();
+        assert_eq!(report.resumed(), &paths[..1]);
+        assert_eq!(report.unchanged(), &paths[1..]);
+        assert!(report.is_success() && report.state_error().is_none());
+        assert!(!state.exists());
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn unchanged_files_are_skipped() {
+        let path = copy_of_test_file("unchanged");
+        let mut writer = BulkWriter::new().options(WriteOptions::new().preserve(true));
+        writer.push(&path, TagEdit::new().set_text("TRCK", "2/10"));
+        assert_eq!(writer.run().succeeded().len(), 1);
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let mut writer = BulkWriter::new().options(WriteOptions::new().preserve(true));
+        writer.push(&path, TagEdit::new().set_text("TRCK", "2/10"));
+        let report = writer.run();
+        assert!(report.is_success());
+        assert_eq!((report.succeeded().len(), report.unchanged()), (0, std::slice::from_ref(&path)));
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+
+        // Converting changes the file even without edits
+        let mut writer = BulkWriter::new().options(WriteOptions::new().version(4));
+        writer.push(&path, TagEdit::new());
+        assert_eq!(writer.run().succeeded(), std::slice::from_ref(&path));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn normalize_counts_whole_run() {
+        let paths: Vec<PathBuf> = (0..2).map(|i| copy_of_test_file(&format!("normalize-{i}"))).collect();
+        let dictionary = Dictionary::new().replace("POLYGONDWANALAND", "Polygondwanaland (2017)").fields(&["TALB"]);
+        let mut writer = BulkWriter::new().concurrency(2);
+        for path in &paths {
+            writer.push(path, TagEdit::new().normalize(&dictionary));
+        }
+        assert_eq!(writer.run().succeeded().len(), 2);
+        assert_eq!(dictionary.total(), 2);
+        for path in paths {
+            assert_eq!(Tag::from_file(&path).unwrap().album().as_deref(), Some("Polygondwanaland (2017)"));
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
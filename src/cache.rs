@@ -0,0 +1,194 @@
+use crate::paths::{changed_ns, long_path};
+use crate::Tag;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+struct Entry {
+    modified: SystemTime,
+    changed: u64,
+    size: u64,
+    last_used: u64,
+    tag: Arc<Tag>,
+}
+
+struct Entries {
+    map: HashMap<PathBuf, Entry>,
+    clock: u64,
+}
+
+pub struct TagCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl TagCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries {
+                map: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, filename: impl AsRef<Path>) -> io::Result<Arc<Tag>> {
+        let filename = filename.as_ref();
+        let metadata = fs::metadata(long_path(filename))?;
+        let modified = metadata.modified()?;
+        let changed = changed_ns(&metadata);
+        let size = metadata.len();
+
+        {
+            let mut entries = self.entries.lock().unwrap();
+            entries.clock += 1;
+            let clock = entries.clock;
+
+            // Only reuse the entry if the file has not changed since it was parsed. The change time
+            // catches edits written with preserve_mtime
+            if let Some(entry) = entries.map.get_mut(filename)
+                && entry.modified == modified
+                && entry.changed == changed
+                && entry.size == size
+            {
+                entry.last_used = clock;
+                return Ok(Arc::clone(&entry.tag));
+            }
+        }
+
+        // Parse without holding the lock so other threads aren't blocked on IO
+        let tag = Arc::new(Tag::from_file(filename)?);
+        self.insert(filename, modified, changed, size, Arc::clone(&tag));
+        Ok(tag)
+    }
+
+    pub fn invalidate(&self, filename: impl AsRef<Path>) {
+        self.entries.lock().unwrap().map.remove(filename.as_ref());
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().map.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn insert(&self, filename: &Path, modified: SystemTime, changed: u64, size: u64, tag: Arc<Tag>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let last_used = entries.clock;
+        entries.map.insert(filename.to_path_buf(), Entry { modified, changed, size, last_used, tag });
+
+        // Evict the least recently used entries until back within bounds
+        while entries.map.len() > self.capacity {
+            let oldest = entries.map.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(name, _)| name.clone())
+                .unwrap();
+            entries.map.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use crate::WriteOptions;
+    use std::thread;
+
+    #[test]
+    fn reuses_parsed_tag() {
+        let cache = TagCache::new(4);
+        let first = cache.get("test/Polygondwanaland.mp3").unwrap();
+        let second = cache.get("test/Polygondwanaland.mp3").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let a = copy_of_test_file("lru-a");
+        let b = copy_of_test_file("lru-b");
+        let c = copy_of_test_file("lru-c");
+        let cache = TagCache::new(2);
+
+        let first_a = cache.get(&a).unwrap();
+        cache.get(&b).unwrap();
+        cache.get(&a).unwrap();
+        cache.get(&c).unwrap();
+
+        // b was the least recently used so a is still cached
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&first_a, &cache.get(&a).unwrap()));
+
+        for path in [a, b, c] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn invalidates_changed_file() {
+        let path = copy_of_test_file("changed");
+        let cache = TagCache::new(4);
+        let before = cache.get(&path).unwrap();
+
+        // Growing the file changes its size even if mtime resolution is coarse
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.push(0);
+        fs::write(&path, bytes).unwrap();
+
+        let after = cache.get(&path).unwrap();
+        assert!(!Arc::ptr_eq(&before, &after));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalidates_edit_with_preserved_mtime() {
+        let path = copy_of_test_file("preserved-mtime");
+        let cache = TagCache::new(4);
+        let options = WriteOptions::new().preserve_mtime(true);
+        let mut tag = Tag::from_file(&path).unwrap();
+
+        // Titles of the same length so the second write leaves the size as it was too
+        tag.set_text("TIT2", "Crumbling Palace");
+        tag.write_to_file(&path, &options).unwrap();
+        cache.get(&path).unwrap();
+        tag.set_text("TIT2", "Crumbling Temple");
+        tag.write_to_file(&path, &options).unwrap();
+        assert_eq!(cache.get(&path).unwrap().text("TIT2").as_deref(), Some("Crumbling Temple"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let cache = Arc::new(TagCache::new(4));
+        let handles: Vec<_> = (0..4).map(|_| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || cache.get("test/Polygondwanaland.mp3").unwrap().frames().len())
+        }).collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 9);
+        }
+        assert_eq!(cache.len(), 1);
+    }
+}
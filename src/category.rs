@@ -0,0 +1,124 @@
+use crate::convert::text_values;
+use crate::{Frame, Tag};
+
+// Frames grouped by how their data is laid out, so an editor can show each group without
+// knowing every id. TXXX and WXXX start with a description and count as binary, see
+// Tag::user_texts and Tag::user_links for them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    Text,
+    Url,
+    Binary,
+}
+
+impl Frame {
+    pub fn category(&self) -> Category {
+        let id = self.id();
+        match id.as_bytes()[0] {
+            b'T' if id != "TXXX" => Category::Text,
+            b'W' if id != "WXXX" => Category::Url,
+            _ => Category::Binary,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TextFrame<'a>(&'a Frame);
+
+impl<'a> TextFrame<'a> {
+    pub fn id(&self) -> String {
+        self.0.id()
+    }
+
+    // Each value of a v2.4 multi-value frame
+    pub fn values(&self) -> Vec<String> {
+        text_values(self.0)
+    }
+
+    pub fn text(&self) -> String {
+        self.0.parse_text()
+    }
+
+    pub fn frame(&self) -> &'a Frame {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct UrlFrame<'a>(&'a Frame);
+
+impl<'a> UrlFrame<'a> {
+    pub fn id(&self) -> String {
+        self.0.id()
+    }
+
+    // Latin-1 up to any terminator
+    pub fn url(&self) -> String {
+        self.0.data().iter().take_while(|x| **x != 0).map(|x| *x as char).collect()
+    }
+
+    pub fn frame(&self) -> &'a Frame {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct BinaryFrame<'a>(&'a Frame);
+
+impl<'a> BinaryFrame<'a> {
+    pub fn id(&self) -> String {
+        self.0.id()
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.0.data()
+    }
+
+    pub fn frame(&self) -> &'a Frame {
+        self.0
+    }
+}
+
+// Frames left in the file by lazy reading aren't included, see Tag::load_lazy_frames
+impl Tag {
+    pub fn text_frames(&self) -> impl Iterator<Item = TextFrame<'_>> {
+        self.frames().iter().filter(|frame| frame.category() == Category::Text).map(TextFrame)
+    }
+
+    pub fn url_frames(&self) -> impl Iterator<Item = UrlFrame<'_>> {
+        self.frames().iter().filter(|frame| frame.category() == Category::Url).map(UrlFrame)
+    }
+
+    pub fn binary_frames(&self) -> impl Iterator<Item = BinaryFrame<'_>> {
+        self.frames().iter().filter(|frame| frame.category() == Category::Binary).map(BinaryFrame)
+    }
+
+    // Frames whose id passes the predicate, in tag order
+    pub fn frames_matching(&self, predicate: impl Fn(&str) -> bool) -> impl Iterator<Item = &Frame> {
+        self.frames().iter().filter(move |frame| predicate(&frame.id()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_by_category() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.set_url("WOAR", "https://kinggizzardandthelizardwizard.com").unwrap();
+        tag.set_user_text("Mood", "Dark");
+
+        let text: Vec<String> = tag.text_frames().map(|frame| frame.id()).collect();
+        assert!(text.contains(&"TALB".to_string()) && !text.contains(&"TXXX".to_string()));
+        assert_eq!(tag.text_frames().find(|frame| frame.id() == "TALB").unwrap().values(), ["Polygondwanaland"]);
+        let urls: Vec<String> = tag.url_frames().map(|frame| frame.url()).collect();
+        assert_eq!(urls, ["https://kinggizzardandthelizardwizard.com"]);
+        let binary: Vec<String> = tag.binary_frames().map(|frame| frame.id()).collect();
+        assert!(binary.contains(&"APIC".to_string()) && binary.contains(&"TXXX".to_string()));
+        assert_eq!(text.len() + urls.len() + binary.len(), tag.frames().len());
+
+        let matching: Vec<String> = tag.frames_matching(|id| id.starts_with('T')).map(|frame| frame.id()).collect();
+        assert_eq!(matching.len(), text.len() + 1);
+    }
+}
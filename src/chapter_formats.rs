@@ -0,0 +1,363 @@
+use crate::frames::{Chapter, Chapters, UserLink};
+use crate::json;
+use crate::paths::long_path;
+use crate::{CompatibilityReport, Tag, WriteOptions, mpeg};
+use std::fs::File;
+use std::io::{self, Error, ErrorKind};
+use std::path::Path;
+
+// Chapter lists kept outside the tag. Text is one "HH:MM:SS Title" line per chapter, Podlove is
+// the JSON array of Podlove Simple Chapters and FfMetadata is the ;FFMETADATA1 file ffmpeg reads
+// with -i metadata.txt -map_metadata 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChapterFormat {
+    Text,
+    Podlove,
+    FfMetadata,
+}
+
+impl ChapterFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChapterFormat::Text => "text",
+            ChapterFormat::Podlove => "podlove",
+            ChapterFormat::FfMetadata => "ffmetadata",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ChapterFormat::Text, ChapterFormat::Podlove, ChapterFormat::FfMetadata].into_iter().find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    // From the start of the file, anything not JSON or ffmpeg metadata is taken as text
+    pub fn detect(text: &str) -> Self {
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+        if text.starts_with(";FFMETADATA") {
+            ChapterFormat::FfMetadata
+        } else if text.starts_with(['[', '{']) {
+            ChapterFormat::Podlove
+        } else {
+            ChapterFormat::Text
+        }
+    }
+}
+
+// A chapter as the formats describe it, the end is often left to the next start
+struct Entry {
+    start: u32,
+    end: Option<u32>,
+    title: Option<String>,
+    url: Option<String>,
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("chapter line {line}: {message}"))
+}
+
+// HH:MM:SS or MM:SS with optional fractions of a second
+fn parse_time(text: &str) -> Option<u32> {
+    let (clock, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let parts: Vec<u32> = clock.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        [minutes, seconds] => (0, minutes, seconds),
+        _ => return None,
+    };
+    if (minutes >= 60 && parts.len() == 3) || seconds >= 60 || !fraction.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    let ms = format!("{fraction:0<3}")[..3].parse::<u32>().ok()?;
+    hours.checked_mul(3_600_000)?.checked_add(minutes * 60_000 + seconds * 1000 + ms)
+}
+
+// Milliseconds only when there are any
+fn format_time(ms: u32) -> String {
+    let seconds = ms / 1000;
+    let clock = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match ms % 1000 {
+        0 => clock,
+        fraction => format!("{clock}.{fraction:03}"),
+    }
+}
+
+fn url_of(chapter: &Chapter) -> Option<String> {
+    chapter.frames().iter().find_map(UserLink::from_frame).map(|link| link.url().to_string())
+}
+
+fn parse_text(text: &str) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim().trim_start_matches('\u{feff}'))) {
+        if line.is_empty() {
+            continue;
+        }
+        let (time, title) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let start = parse_time(time).ok_or_else(|| invalid(number, "expected a time like 01:02:03"))?;
+        let title = Some(title.trim().to_string()).filter(|title| !title.is_empty());
+        entries.push(Entry { start, end: None, title, url: None });
+    }
+    Ok(entries)
+}
+
+fn to_text(chapters: &[Chapter]) -> String {
+    chapters.iter().map(|chapter| format!("{} {}\n", format_time(chapter.start()), chapter.title().unwrap_or_default().replace('\n', " "))).collect()
+}
+
+// Either the array itself or an object with it under "chapters", times as text or seconds
+#[cfg(feature = "json")]
+fn parse_podlove(text: &str) -> io::Result<Vec<Entry>> {
+    use serde_json::Value;
+
+    let value: Value = serde_json::from_str(text).map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid chapter JSON"))?;
+    let list = value.get("chapters").unwrap_or(&value).as_array().map(Vec::as_slice).unwrap_or_default();
+    let time = |value: &Value| {
+        let seconds = value.as_f64().filter(|seconds| *seconds >= 0.0 && *seconds < u32::MAX as f64 / 1000.0);
+        seconds.map(|seconds| (seconds * 1000.0).round() as u32).or_else(|| parse_time(value.as_str()?.trim()))
+    };
+    let text = |chapter: &Value, key: &str| chapter.get(key).and_then(Value::as_str).filter(|text| !text.is_empty()).map(str::to_string);
+    list.iter().enumerate().map(|(i, chapter)| {
+        let start = chapter.get("start").or(chapter.get("startTime")).and_then(time).ok_or_else(|| invalid(i + 1, "missing or invalid start"))?;
+        let end = chapter.get("end").or(chapter.get("endTime")).and_then(time);
+        Ok(Entry { start, end, title: text(chapter, "title"), url: text(chapter, "href").or(text(chapter, "url")) })
+    }).collect()
+}
+
+#[cfg(not(feature = "json"))]
+fn parse_podlove(_text: &str) -> io::Result<Vec<Entry>> {
+    Err(Error::new(ErrorKind::Unsupported, "Reading Podlove chapters needs the json feature"))
+}
+
+fn to_podlove(chapters: &[Chapter]) -> String {
+    let entries: Vec<String> = chapters.iter().map(|chapter| {
+        let mut fields = vec![format!("\"start\":{}", json::quote(&format_time(chapter.start())))];
+        fields.push(format!("\"title\":{}", json::quote(&chapter.title().unwrap_or_default())));
+        fields.extend(url_of(chapter).map(|url| format!("\"href\":{}", json::quote(&url))));
+        format!("  {{{}}}", fields.join(","))
+    }).collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+// Values escape =, ;, #, \ and newlines with a backslash
+fn ff_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Logical lines split into key and value at the first unescaped =, escaped newlines stay in
+fn ff_lines(text: &str) -> Vec<(usize, String, Option<String>)> {
+    let mut lines = Vec::new();
+    let (mut key, mut value) = (String::new(), None::<String>);
+    let (mut line, mut start) = (1, 1);
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                // An escaped CRLF is a newline like in the rest of the file
+                Some('\r') => continue,
+                Some(escaped) => escaped,
+                None => break,
+            },
+            '=' if value.is_none() => {
+                value = Some(String::new());
+                continue;
+            }
+            '\n' => {
+                lines.push((start, std::mem::take(&mut key), value.take()));
+                start = line + 1;
+                line += 1;
+                continue;
+            }
+            '\r' => continue,
+            c => c,
+        };
+        line += (c == '\n') as usize;
+        value.as_mut().unwrap_or(&mut key).push(c);
+    }
+    lines.push((start, key, value));
+    lines
+}
+
+// TIMEBASE is the length of a tick in seconds as numerator and denominator
+fn ticks_to_ms(ticks: u32, (numerator, denominator): (u64, u64)) -> u32 {
+    (ticks as u128 * numerator as u128 * 1000 / denominator as u128).min(u32::MAX as u128) as u32
+}
+
+fn parse_ffmetadata(text: &str) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut chapter: Option<(Entry, (u64, u64))> = None;
+    let finish = |chapter: Option<(Entry, (u64, u64))>, entries: &mut Vec<Entry>| {
+        if let Some((mut entry, timebase)) = chapter {
+            entry.start = ticks_to_ms(entry.start, timebase);
+            entry.end = entry.end.map(|end| ticks_to_ms(end, timebase));
+            entries.push(entry);
+        }
+    };
+    for (number, key, value) in ff_lines(text) {
+        if key.starts_with([';', '#']) || (key.trim().is_empty() && value.is_none()) {
+            continue;
+        }
+        if key.starts_with('[') {
+            finish(chapter.take(), &mut entries);
+            if key.trim() == "[CHAPTER]" {
+                chapter = Some((Entry { start: 0, end: None, title: None, url: None }, (1, 1000)));
+            }
+            continue;
+        }
+        let (Some((entry, timebase)), Some(value)) = (chapter.as_mut(), value) else {
+            continue;
+        };
+        let ticks = || value.trim().parse::<u32>().map_err(|_| invalid(number, &format!("invalid {key}")));
+        match key.as_str() {
+            "TIMEBASE" => {
+                let fraction = value.trim().split_once('/').and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)));
+                *timebase = fraction.filter(|(x, y)| *x > 0 && *y > 0).ok_or_else(|| invalid(number, "invalid TIMEBASE"))?;
+            }
+            "START" => entry.start = ticks()?,
+            "END" => entry.end = Some(ticks()?),
+            "title" => entry.title = Some(value),
+            _ => {}
+        }
+    }
+    finish(chapter, &mut entries);
+    Ok(entries)
+}
+
+fn to_ffmetadata(chapters: &[Chapter]) -> String {
+    let mut text = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        text.push_str(&format!("\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\n", chapter.start(), chapter.end()));
+        if let Some(title) = chapter.title() {
+            text.push_str(&format!("title={}\n", ff_escape(&title)));
+        }
+    }
+    text
+}
+
+pub fn export(chapters: &[Chapter], format: ChapterFormat) -> String {
+    match format {
+        ChapterFormat::Text => to_text(chapters),
+        ChapterFormat::Podlove => to_podlove(chapters),
+        ChapterFormat::FfMetadata => to_ffmetadata(chapters),
+    }
+}
+
+// Chapters without an end run to the next one, or to the duration for the last. Ranges are
+// tidied with Chapters::fix_ranges and ids numbered chp0, chp1 and so on
+pub fn import(text: &str, format: ChapterFormat, duration: Option<u32>, major_ver: u8) -> io::Result<Vec<Chapter>> {
+    let entries = match format {
+        ChapterFormat::Text => parse_text(text)?,
+        ChapterFormat::Podlove => parse_podlove(text)?,
+        ChapterFormat::FfMetadata => parse_ffmetadata(text)?,
+    };
+    let mut chapters = Chapters::new();
+    if let Some(duration) = duration {
+        chapters = chapters.duration(duration);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let end = entry.end.or(entries.get(i + 1).map(|next| next.start)).or(duration).unwrap_or(entry.start);
+        let mut chapter = Chapter::new(&format!("chp{i}"), entry.start, end);
+        if let Some(title) = &entry.title {
+            chapter.set_title(title, major_ver);
+        }
+        if let Some(link) = entry.url.as_ref().and_then(|url| UserLink::new("", url).to_frame()) {
+            chapter.add_frame(link);
+        }
+        chapters.push(chapter);
+    }
+    chapters.fix_ranges();
+    chapters.renumber();
+    Ok(chapters.chapters().to_vec())
+}
+
+// The chapters of the file's tag in the order its table of contents gives
+pub fn export_file(filename: impl AsRef<Path>, format: ChapterFormat) -> io::Result<String> {
+    Ok(export(Chapters::from_tag(&Tag::from_file(filename)?).chapters(), format))
+}
+
+// Replaces the chapters of the file with the ones in text, timed against its audio
+pub fn import_file(filename: impl AsRef<Path>, text: &str, format: ChapterFormat, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+    let filename = filename.as_ref();
+    let mut file = File::open(long_path(filename))?;
+    let range = mpeg::audio_range(&mut file)?;
+    let duration = mpeg::duration_ms(&mpeg::scan(&mut file, range)?);
+
+    let mut tag = Tag::from_file(filename)?;
+    let chapters = import(text, format, Some(duration.min(u32::MAX as u64) as u32), tag.version())?;
+    tag.set_chapters(&chapters);
+    tag.write_to_file(filename, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use std::fs;
+
+    fn chapters() -> Vec<Chapter> {
+        let mut intro = Chapter::new("chp0", 0, 65_250);
+        intro.set_title("Crumbling Castle", 4);
+        intro.add_frame(UserLink::new("", "https://example.com/castle").to_frame().unwrap());
+        let mut outro = Chapter::new("chp1", 65_250, 3_725_000);
+        outro.set_title("Loyalty; #2 = Tetrachromacy", 4);
+        vec![intro, outro]
+    }
+
+    fn summary(chapters: &[Chapter]) -> Vec<(u32, u32, Option<String>, Option<String>)> {
+        chapters.iter().map(|chapter| (chapter.start(), chapter.end(), chapter.title(), url_of(chapter))).collect()
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let text = export(&chapters(), ChapterFormat::Text);
+        assert_eq!(text, "00:00:00 Crumbling Castle\n00:01:05.250 Loyalty; #2 = Tetrachromacy\n");
+        let podlove = export(&chapters(), ChapterFormat::Podlove);
+        assert!(podlove.contains("{\"start\":\"00:00:00\",\"title\":\"Crumbling Castle\",\"href\":\"https://example.com/castle\"}"));
+        let ffmetadata = export(&chapters(), ChapterFormat::FfMetadata);
+        assert!(ffmetadata.ends_with("START=65250\nEND=3725000\ntitle=Loyalty\\; \\#2 \\= Tetrachromacy\n"));
+
+        // Only Podlove keeps the link
+        let mut expected = summary(&chapters());
+        assert_eq!(ChapterFormat::detect(&podlove), ChapterFormat::Podlove);
+        if cfg!(feature = "json") {
+            assert_eq!(summary(&import(&podlove, ChapterFormat::Podlove, Some(3_725_000), 4).unwrap()), expected);
+        } else {
+            assert_eq!(import(&podlove, ChapterFormat::Podlove, None, 4).err().unwrap().kind(), ErrorKind::Unsupported);
+        }
+        expected[0].3 = None;
+        assert_eq!(ChapterFormat::detect(&ffmetadata), ChapterFormat::FfMetadata);
+        assert_eq!(summary(&import(&ffmetadata, ChapterFormat::FfMetadata, None, 4).unwrap()), expected);
+        assert_eq!(ChapterFormat::detect(&text), ChapterFormat::Text);
+        assert_eq!(summary(&import(&text, ChapterFormat::Text, Some(3_725_000), 4).unwrap()), expected);
+        assert_eq!(ChapterFormat::from_name("FFMetadata"), Some(ChapterFormat::FfMetadata));
+    }
+
+    #[test]
+    fn lenient_import() {
+        let text = "\u{feff}1:30 Second\n\n00:00 First\n";
+        let chapters = import(text, ChapterFormat::detect(text), Some(120_000), 3).unwrap();
+        let ids: Vec<&str> = chapters.iter().map(|chapter| chapter.element_id()).collect();
+        assert_eq!(ids, ["chp0", "chp1"]);
+        assert_eq!(summary(&chapters)[0], (0, 90_000, Some("First".to_string()), None));
+        assert_eq!(import("1:75 Bad", ChapterFormat::Text, None, 3).err().unwrap().to_string(), "chapter line 1: expected a time like 01:02:03");
+
+        let json = r#"{"version":"1.2.0","chapters":[{"startTime":12.5,"title":"Intro","url":"https://a.b"}]}"#;
+        if cfg!(feature = "json") {
+            assert_eq!(summary(&import(json, ChapterFormat::Podlove, Some(60_000), 4).unwrap()), [(12_500, 60_000, Some("Intro".to_string()), Some("https://a.b".to_string()))]);
+        }
+        let ffmetadata = ";FFMETADATA1\ntitle=Album\n[CHAPTER]\nTIMEBASE=1/44100\nSTART=44100\nEND=88200\ntitle=Two\\\nlines\n";
+        assert_eq!(summary(&import(ffmetadata, ChapterFormat::FfMetadata, None, 4).unwrap()), [(1000, 2000, Some("Two\nlines".to_string()), None)]);
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let path = copy_of_test_file("import");
+        import_file(&path, "00:00 Start\n00:01 Later\n", ChapterFormat::Text, &WriteOptions::new()).unwrap();
+        assert_eq!(export_file(&path, ChapterFormat::Text).unwrap(), "00:00:00 Start\n00:00:01 Later\n");
+        fs::remove_file(path).unwrap();
+    }
+}
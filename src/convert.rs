@@ -0,0 +1,526 @@
+use crate::ID3::{bytes_from_text, encoding_for, read_terminated, split_terminated, terminator, text_from_bytes};
+use crate::frames::{Comment, Equalisation, Link, Lyrics, Picture, UserLink, UserText, convert_embedded, image_format, mime_type};
+use crate::wire::{self, has_id};
+use crate::{Frame, Tag};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Converted { from: String, to: String },
+    Downgraded { id: String, reason: String },
+    Dropped { id: String, reason: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompatibilityReport {
+    target: u8,
+    changes: Vec<Change>,
+}
+
+impl CompatibilityReport {
+    pub fn new(target: u8) -> Self {
+        Self {
+            target,
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn target(&self) -> u8 {
+        self.target
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    // True when every frame made it into the target version unchanged
+    pub fn is_lossless(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn dropped(&self) -> Vec<&str> {
+        self.changes.iter().filter_map(|change| match change {
+            Change::Dropped { id, .. } => Some(id.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    fn convert(&mut self, from: &str, to: &str) {
+        self.changes.push(Change::Converted { from: from.to_string(), to: to.to_string() });
+    }
+
+    pub(crate) fn downgrade(&mut self, id: &str, reason: &str) {
+        self.changes.push(Change::Downgraded { id: id.to_string(), reason: reason.to_string() });
+    }
+
+    pub(crate) fn drop(&mut self, id: &str, reason: &str) {
+        self.changes.push(Change::Dropped { id: id.to_string(), reason: reason.to_string() });
+    }
+}
+
+// Frames that only exist in v2.4 and have no v2.3 counterpart
+const V24_ONLY: [&str; 14] = [
+    "ASPI", "RVA2", "SEEK", "SIGN", "TDEN", "TDRL", "TDTG", "TMCL", "TMOO", "TPRO", "TSOA", "TSOP", "TSOT", "TSST",
+];
+
+// Frames that v2.4 removed
+const V23_ONLY: [&str; 3] = ["RVAD", "TRDA", "TSIZ"];
+
+pub(crate) fn text_values(frame: &Frame) -> Vec<String> {
+    let Some((encoding, mut rest)) = frame.data().split_first() else {
+        return Vec::new();
+    };
+
+    let mut values = Vec::new();
+    while !rest.is_empty() {
+        let (value, remaining) = read_terminated(*encoding, rest);
+        values.push(value);
+        rest = remaining;
+    }
+    values
+}
+
+pub(crate) fn text_frame(id: &str, values: &[String], major_ver: u8) -> Option<Frame> {
+    let encoding = encoding_for(&values.concat(), major_ver);
+    let mut data = vec![encoding];
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            data.extend_from_slice(terminator(encoding));
+        }
+        data.extend(bytes_from_text(encoding, value));
+    }
+    Frame::new(id, data)
+}
+
+fn with_group(mut frame: Frame, group: Option<u8>) -> Frame {
+    frame.set_group(group);
+    frame
+}
+
+impl Tag {
+    pub fn convert(&self, target: u8) -> (Tag, CompatibilityReport) {
+        let mut report = CompatibilityReport::new(target);
+        let mut tag = Tag::new(target);
+        if target == self.version() {
+            for frame in self.frames() {
+                tag.add_frame(frame.clone());
+            }
+            return (tag, report);
+        }
+
+        let text = |id: &str| self.frame(id).map(|frame| frame.parse_text());
+        // TDAT and TIME are ddMM and HHmm, anything else can't be merged into TDRC
+        let date = text("TDAT").filter(|date| is_digits(date, 4));
+        let time = text("TIME").filter(|time| is_digits(time, 4));
+        for frame in self.frames() {
+            let id = frame.id();
+            let converted = match id.as_str() {
+                _ if !has_id(&id, target) => {
+                    report.drop(&id, &format!("frame does not exist in v2.{target}"));
+                    continue;
+                }
+                "APIC" if target == 2 || self.version() == 2 => {
+                    let Some(frame) = picture_format(frame, target, &mut report) else {
+                        continue;
+                    };
+                    reencode(&frame, target, &mut report)
+                }
+                "TYER" if target == 4 => {
+                    // Date and time only survive as part of the combined timestamp
+                    let mut timestamp = text("TYER").unwrap_or_default();
+                    if let Some(date) = &date {
+                        timestamp.push_str(&format!("-{}-{}", &date[2..], &date[..2]));
+                        if let Some(time) = &time {
+                            timestamp.push_str(&format!("T{}:{}", &time[..2], &time[2..]));
+                        }
+                    }
+                    report.convert("TYER", "TDRC");
+                    text_frame("TDRC", &[timestamp], target)
+                }
+                "TDAT" | "TIME" if target == 4 => {
+                    if self.frame("TYER").is_none() {
+                        report.drop(&id, "no TYER to merge into TDRC");
+                    } else if (if id == "TDAT" { &date } else { &time }).is_none() {
+                        report.drop(&id, "not a valid date or time");
+                    } else if date.is_none() {
+                        report.drop(&id, "no TDAT to merge into TDRC");
+                    } else {
+                        report.convert(&id, "TDRC");
+                    }
+                    continue;
+                }
+                "TORY" if target == 4 => {
+                    report.convert("TORY", "TDOR");
+                    text_frame("TDOR", &text_values(frame), target)
+                }
+                "IPLS" if target == 4 => {
+                    report.convert("IPLS", "TIPL");
+                    text_frame("TIPL", &text_values(frame), target)
+                }
+                "TDRC" if target == 3 => {
+                    let timestamp = frame.parse_text();
+                    report.convert("TDRC", "TYER");
+                    for (id, value) in split_timestamp(&timestamp) {
+                        if let Some(frame) = text_frame(id, &[value], target) {
+                            tag.add_frame(with_group(frame, frame_group(self, "TDRC")));
+                        }
+                    }
+                    continue;
+                }
+                "TDOR" if target == 3 => {
+                    report.convert("TDOR", "TORY");
+                    text_frame("TORY", &[frame.parse_text().chars().take(4).collect()], target)
+                }
+                "TIPL" if target == 3 => {
+                    report.convert("TIPL", "IPLS");
+                    text_frame("IPLS", &text_values(frame), target)
+                }
+                "EQUA" | "EQU2" => {
+                    let converted = Equalisation::from_frame(frame).and_then(|eq| eq.to_frame(target));
+                    if let Some(converted) = &converted {
+                        report.convert(&id, &converted.id());
+                    }
+                    converted
+                }
+                // The linked frame is named by its v2.2 id in v2.3 and earlier
+                "LINK" if (self.version() == 4) != (target == 4) => {
+                    let Some(link) = Link::from_frame(frame, self.version()) else {
+                        report.drop(&id, "frame could not be converted");
+                        continue;
+                    };
+                    let frame_id = match target {
+                        4 => wire::long_id(link.frame_id()),
+                        _ => wire::short_id(link.frame_id()).map(str::to_string),
+                    };
+                    let Some(frame_id) = frame_id else {
+                        report.drop(&id, &format!("linked frame {} has no v2.{target} id", link.frame_id()));
+                        continue;
+                    };
+                    Link::new(&frame_id, link.url(), link.additional_data().to_vec()).to_frame(target)
+                }
+                "CHAP" | "CTOC" => convert_embedded(frame, self.version(), target, |embedded| reencode(embedded, target, &mut report)),
+                _ if target == 3 && V24_ONLY.contains(&id.as_str()) => {
+                    report.drop(&id, "frame does not exist in v2.3");
+                    continue;
+                }
+                _ if target == 4 && V23_ONLY.contains(&id.as_str()) => {
+                    report.drop(&id, "frame was removed in v2.4");
+                    continue;
+                }
+                _ => reencode(frame, target, &mut report),
+            };
+
+            match converted {
+                Some(converted) => tag.add_frame(with_group(converted, frame.group())),
+                None => report.drop(&id, "frame could not be converted"),
+            }
+        }
+
+        (tag, report)
+    }
+}
+
+// v2.2 only names PNG, JPG, GIF and BMP images. A MIME type without a format, or one made up
+// from a format v2.2 doesn't know, is replaced by the one the image data shows. Pictures that
+// still have none can't be written as v2.2 and are kept as they are by the later versions
+fn picture_format(frame: &Frame, target: u8, report: &mut CompatibilityReport) -> Option<Frame> {
+    let picture = Picture::from_frame(frame)?;
+    if image_format(picture.mime()).is_some() {
+        return Some(frame.clone());
+    }
+    match mime_type(picture.data()).filter(|mime| image_format(mime).is_some()) {
+        Some(mime) => {
+            report.downgrade("APIC", &format!("MIME type {} replaced with {mime}", picture.mime()));
+            Picture::new(mime, picture.picture_type(), picture.description(), picture.data().to_vec()).to_frame()
+        }
+        None if target == 2 => {
+            report.drop("APIC", &format!("{} pictures have no v2.2 image format", picture.mime()));
+            None
+        }
+        None => {
+            report.downgrade("APIC", &format!("unknown image format kept as {}", picture.mime()));
+            Some(frame.clone())
+        }
+    }
+}
+
+fn frame_group(tag: &Tag, id: &str) -> Option<u8> {
+    tag.frame(id).and_then(|frame| frame.group())
+}
+
+fn is_digits(text: &str, len: usize) -> bool {
+    text.len() == len && text.bytes().all(|x| x.is_ascii_digit())
+}
+
+// TDRC is yyyy-MM-ddTHH:mm:ss, v2.3 splits it into TYER (yyyy), TDAT (ddMM) and TIME (HHmm).
+// Parts that aren't digits where they should be are left out
+pub(crate) fn split_timestamp(timestamp: &str) -> Vec<(&'static str, String)> {
+    let digits = |start: usize| timestamp.get(start..start + 2).filter(|part| is_digits(part, 2));
+    let mut frames = vec![("TYER", timestamp.chars().take(4).collect())];
+    if let (Some(month), Some(day)) = (digits(5), digits(8)) {
+        frames.push(("TDAT", format!("{day}{month}")));
+        if let (Some(hour), Some(minute)) = (digits(11), digits(14)) {
+            frames.push(("TIME", format!("{hour}{minute}")));
+        }
+    }
+    frames
+}
+
+// Rewrite frames whose text encoding or multiple values the target can't represent
+fn reencode(frame: &Frame, target: u8, report: &mut CompatibilityReport) -> Option<Frame> {
+    let id = frame.id();
+    let encoding = *frame.data().first().unwrap_or(&0);
+    let has_encoding = id.starts_with('T') || layout(&id).is_some() || matches!(id.as_str(), "WXXX" | "COMM" | "USLT" | "APIC");
+    if !has_encoding || target == 4 || encoding < 2 {
+        if target == 3 && id.starts_with('T') && id != "TXXX" && text_values(frame).len() > 1 {
+            report.downgrade(&id, "multiple values joined with '/'");
+            return text_frame(&id, &[text_values(frame).join("/")], target);
+        }
+        return Some(frame.clone());
+    }
+
+    report.downgrade(&id, "text re-encoded for v2.3");
+    match id.as_str() {
+        "COMM" => Comment::from_frame(frame)?.to_frame(),
+        "USLT" => Lyrics::from_frame(frame)?.to_frame(),
+        "TXXX" => UserText::from_frame(frame)?.to_frame(),
+        "WXXX" => UserLink::from_frame(frame)?.to_frame(),
+        "APIC" => Picture::from_frame(frame)?.to_frame(),
+        _ if !id.starts_with('T') => Frame::new(&id, reencoded_strings(frame, target)?),
+        _ => {
+            let values = text_values(frame);
+            if values.len() > 1 {
+                report.downgrade(&id, "multiple values joined with '/'");
+            }
+            text_frame(&id, &[values.join("/")], target)
+        }
+    }
+}
+
+// What follows the encoding byte of the other frames with text, up to the part that runs to the end
+#[derive(Clone, Copy)]
+enum Part {
+    // Bytes the encoding doesn't apply to, like a language, date or flag
+    Bytes(usize),
+    // A terminated string that is always ISO-8859-1, like a price or MIME type
+    Latin1,
+    // A terminated string in the frame's encoding
+    Text,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Rest {
+    Bytes,
+    Text,
+    // Terminated strings each followed by a four byte timestamp
+    Synced,
+}
+
+fn layout(id: &str) -> Option<(&'static [Part], Rest)> {
+    match id {
+        "SYLT" => Some((&[Part::Bytes(5), Part::Text], Rest::Synced)),
+        "GEOB" => Some((&[Part::Latin1, Part::Text, Part::Text], Rest::Bytes)),
+        "USER" => Some((&[Part::Bytes(3)], Rest::Text)),
+        "OWNE" => Some((&[Part::Latin1, Part::Bytes(8)], Rest::Text)),
+        "COMR" => Some((&[Part::Latin1, Part::Bytes(8), Part::Latin1, Part::Bytes(1), Part::Text, Part::Text, Part::Latin1], Rest::Bytes)),
+        _ => None,
+    }
+}
+
+// The frame's data with every string in its encoding written again in one the target allows.
+// None when the frame is too short for its layout
+fn reencoded_strings(frame: &Frame, target: u8) -> Option<Vec<u8>> {
+    let (parts, rest_layout) = layout(&frame.id())?;
+    let (&encoding, mut rest) = frame.data().split_first()?;
+    // Bytes kept as they are, or a string to write in the new encoding and whether it is terminated
+    let mut pieces: Vec<(Option<String>, Vec<u8>, bool)> = Vec::new();
+    let text = |bytes: &[u8], terminated| (Some(text_from_bytes(encoding, bytes)), Vec::new(), terminated);
+    let bytes = |bytes: &[u8]| (None, bytes.to_vec(), false);
+    for part in parts {
+        match part {
+            Part::Bytes(len) => {
+                pieces.push(bytes(rest.get(..*len)?));
+                rest = &rest[*len..];
+            }
+            Part::Latin1 => {
+                let (latin1, after) = split_terminated(0, rest);
+                pieces.push(bytes(&[latin1, &[0]].concat()));
+                rest = after;
+            }
+            Part::Text => {
+                let (string, after) = split_terminated(encoding, rest);
+                pieces.push(text(string, true));
+                rest = after;
+            }
+        }
+    }
+    match rest_layout {
+        Rest::Bytes => pieces.push(bytes(rest)),
+        Rest::Text => pieces.push(text(split_terminated(encoding, rest).0, false)),
+        Rest::Synced => while !rest.is_empty() {
+            let (string, after) = split_terminated(encoding, rest);
+            pieces.push(text(string, true));
+            pieces.push(bytes(after.get(..4)?));
+            rest = &after[4..];
+        },
+    }
+
+    let strings: String = pieces.iter().filter_map(|(string, _, _)| string.as_deref()).collect();
+    let new_encoding = encoding_for(&strings, target);
+    let mut data = vec![new_encoding];
+    for (string, bytes, terminated) in pieces {
+        match string {
+            Some(string) => data.extend(bytes_from_text(new_encoding, &string)),
+            None => data.extend(bytes),
+        }
+        if terminated {
+            data.extend_from_slice(terminator(new_encoding));
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Language;
+
+    fn frame(id: &str, data: &[u8]) -> Frame {
+        Frame::new(id, data.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn same_version_is_lossless() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let (converted, report) = tag.convert(3);
+        assert!(report.is_lossless());
+        assert_eq!(converted.frames().len(), tag.frames().len());
+    }
+
+    #[test]
+    fn utf8_reencoded_for_v23() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(frame("TIT2", "\x03日本".as_bytes()));
+        let (converted, report) = tag.convert(3);
+        assert_eq!(converted.frame("TIT2").unwrap().data()[0], 1);
+        assert_eq!(converted.frame("TIT2").unwrap().parse_text(), "日本");
+        assert_eq!(report.changes(), [Change::Downgraded { id: "TIT2".to_string(), reason: "text re-encoded for v2.3".to_string() }]);
+    }
+
+    #[test]
+    fn utf8_synced_lyrics_reencoded_for_v23() {
+        let mut tag = Tag::new(4);
+        let data = [&b"\x03eng\x02\x01"[..], "歌\0".as_bytes(), "日本\0".as_bytes(), &[0, 0, 0, 10], b"la\0", &[0, 0, 0, 20]].concat();
+        tag.add_frame(frame("SYLT", &data));
+        let (converted, report) = tag.convert(3);
+
+        let utf16 = |text: &str| [crate::ID3::utf16_bytes(text, false, true), vec![0, 0]].concat();
+        let expected = [&b"\x01eng\x02\x01"[..], &utf16("歌"), &utf16("日本"), &[0, 0, 0, 10], &utf16("la"), &[0, 0, 0, 20]].concat();
+        assert_eq!(converted.frame("SYLT").unwrap().data(), expected);
+        assert_eq!(report.changes(), [Change::Downgraded { id: "SYLT".to_string(), reason: "text re-encoded for v2.3".to_string() }]);
+    }
+
+    #[test]
+    fn multiple_values_joined_for_v23() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(frame("TPE1", b"\x00Simon\x00Garfunkel"));
+        let (converted, report) = tag.convert(3);
+        assert_eq!(converted.frame("TPE1").unwrap().parse_text(), "Simon/Garfunkel");
+        assert!(!report.is_lossless());
+    }
+
+    #[test]
+    fn v24_only_frames_dropped() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(frame("TMOO", b"\x00Calm"));
+        tag.add_frame(frame("TIT2", b"\x00Title"));
+        let (converted, report) = tag.convert(3);
+        assert_eq!(report.dropped(), ["TMOO"]);
+        assert_eq!(converted.frames().len(), 1);
+    }
+
+    #[test]
+    fn date_frames_merged_for_v24() {
+        let mut tag = Tag::new(3);
+        tag.add_frame(frame("TYER", b"\x002017"));
+        tag.add_frame(frame("TDAT", b"\x001711"));
+        tag.add_frame(frame("TIME", b"\x002030"));
+        let (converted, _) = tag.convert(4);
+        assert_eq!(converted.frames().len(), 1);
+        assert_eq!(converted.frame("TDRC").unwrap().parse_text(), "2017-11-17T20:30");
+    }
+
+    #[test]
+    fn timestamp_split_for_v23() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(frame("TDRC", b"\x002017-11-17T20:30"));
+        let (converted, _) = tag.convert(3);
+        assert_eq!(converted.frame("TYER").unwrap().parse_text(), "2017");
+        assert_eq!(converted.frame("TDAT").unwrap().parse_text(), "1711");
+        assert_eq!(converted.frame("TIME").unwrap().parse_text(), "2030");
+    }
+
+    #[test]
+    fn malformed_dates_not_merged() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(frame("TDRC", "\x032017-1é-17T2é:30".as_bytes()));
+        let (converted, _) = tag.convert(3);
+        assert_eq!(converted.frame("TYER").unwrap().parse_text(), "2017");
+        assert!(converted.frame("TDAT").is_none());
+
+        let mut tag = Tag::new(3);
+        tag.add_frame(frame("TYER", b"\x002017"));
+        tag.add_frame(frame("TDAT", "\x031é2".as_bytes()));
+        tag.add_frame(frame("TIME", b"\x002030"));
+        let (converted, report) = tag.convert(4);
+        assert_eq!(converted.frame("TDRC").unwrap().parse_text(), "2017");
+        assert_eq!(report.dropped(), ["TDAT", "TIME"]);
+
+        // Without a year there's nothing for a valid date to go in
+        tag.remove("TYER");
+        tag.set_text("TDAT", "1711");
+        let (converted, report) = tag.convert(4);
+        assert!(converted.frames().is_empty());
+        assert_eq!(report.dropped(), ["TDAT", "TIME"]);
+    }
+
+    #[test]
+    fn link_ids_remapped() {
+        let mut tag = Tag::new(3);
+        tag.add_frame(frame("LINK", b"COMhttp://example.com/a.mp3\0eng\0"));
+        tag.add_frame(frame("LINK", b"CRMhttp://example.com/b.mp3\0"));
+        let (converted, report) = tag.convert(4);
+        assert_eq!(converted.links()[0].frame_id(), "COMM");
+        assert_eq!(report.dropped(), ["LINK"]);
+        assert_eq!(converted.convert(3).0.frame("LINK").unwrap().data(), b"COMhttp://example.com/a.mp3\0eng\0");
+    }
+
+    #[test]
+    fn pictures_converted_for_v22() {
+        let mut tag = Tag::new(3);
+        tag.add_frame(frame("APIC", b"\x00image/jpg\x00\x03\x00\xFF\xD8\xFF"));
+        tag.add_frame(frame("APIC", b"\x00application/octet-stream\x00\x04\x00\x89PNG"));
+        tag.add_frame(frame("APIC", b"\x00image/webp\x00\x05\x00RIFF\x00\x00\x00\x00WEBP"));
+        tag.add_frame(frame("PRIV", b"owner\x00data"));
+        let (converted, report) = tag.convert(2);
+        assert_eq!(report.dropped(), ["APIC", "PRIV"]);
+        let mimes: Vec<String> = converted.pictures().iter().map(|picture| picture.mime().to_string()).collect();
+        assert_eq!(mimes, ["image/jpg", "image/png"]);
+
+        let bytes = converted.to_bytes(0);
+        assert_eq!(bytes[10..20], *b"PIC\x00\x00\x09\x00JPG");
+        let read = Tag::from_reader(&mut crate::Reader::from_stream(std::io::Cursor::new(bytes))).unwrap();
+        let mimes: Vec<String> = read.pictures().iter().map(|picture| picture.mime().to_string()).collect();
+        assert_eq!(mimes, ["image/jpeg", "image/png"]);
+        assert!(read.convert(3).1.is_lossless());
+    }
+
+    #[test]
+    fn utf8_comment_reencoded() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(frame("COMM", "\x03eng\x00Smörgåsbord".as_bytes()));
+        let (converted, _) = tag.convert(3);
+        let comment = &converted.comments_in(Language::ENG)[0];
+        assert_eq!(comment.text(), "Smörgåsbord");
+        assert_eq!(converted.frame("COMM").unwrap().data()[0], 0);
+    }
+}
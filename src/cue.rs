@@ -0,0 +1,311 @@
+use crate::frames::Chapter;
+use crate::paths::{long_path, sanitize_file_name};
+use crate::playlist::decode;
+use crate::{CompatibilityReport, Tag, WriteOptions, mpeg};
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// Frames that describe a single track, everything else in the source tag is copied to every split file
+const TRACK_FRAMES: [&str; 8] = ["TIT2", "TPE1", "TRCK", "TSRC", "TCOM", "TLEN", "CHAP", "CTOC"];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CueTrack {
+    pub number: u8,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub isrc: Option<String>,
+    // Milliseconds from the start of the file to INDEX 01
+    pub start: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub file: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("cue sheet line {line}: {message}"))
+}
+
+// A quoted string or the rest of the line
+fn value(rest: &str) -> String {
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+        None => rest.trim().to_string(),
+    }
+}
+
+// mm:ss:ff with 75 frames to the second, minutes can go past 99
+fn timestamp(text: &str) -> Option<u32> {
+    let mut parts = text.split(':').map(|part| part.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= 75 {
+        return None;
+    }
+    Some((minutes * 60 + seconds) * 1000 + frames * 1000 / 75)
+}
+
+// Back to mm:ss:ff, rounded to the nearest frame
+fn format_timestamp(ms: u32) -> String {
+    let frames = (ms as u64 * 75 + 500) / 1000;
+    format!("{:02}:{:02}:{:02}", frames / 75 / 60, frames / 75 % 60, frames % 75)
+}
+
+// Cue sheets have no way to escape a double quote
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+impl CueSheet {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut sheet = Self::default();
+        let mut current: Option<CueTrack> = None;
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match (command.to_ascii_uppercase().as_str(), current.as_mut()) {
+                ("TRACK", _) => {
+                    let track_number = rest.split_whitespace().next().and_then(|x| x.parse().ok())
+                        .ok_or_else(|| invalid(number, "invalid track number"))?;
+                    sheet.tracks.extend(current.take());
+                    current = Some(CueTrack { number: track_number, ..CueTrack::default() });
+                }
+                ("FILE", _) if sheet.file.is_some() => {
+                    return Err(Error::new(ErrorKind::Unsupported, "cue sheets with more than one FILE aren't supported"));
+                }
+                ("FILE", _) => sheet.file = Some(value(rest)),
+                ("TITLE", Some(track)) => track.title = Some(value(rest)),
+                ("TITLE", None) => sheet.title = Some(value(rest)),
+                ("PERFORMER", Some(track)) => track.performer = Some(value(rest)),
+                ("PERFORMER", None) => sheet.performer = Some(value(rest)),
+                ("SONGWRITER", Some(track)) => track.songwriter = Some(value(rest)),
+                ("ISRC", Some(track)) => track.isrc = Some(value(rest)),
+                ("INDEX", Some(track)) => {
+                    let mut fields = rest.split_whitespace();
+                    if fields.next() == Some("01") {
+                        track.start = fields.next().and_then(timestamp).ok_or_else(|| invalid(number, "invalid index time"))?;
+                    }
+                }
+                ("REM", None) => match rest.split_once(char::is_whitespace) {
+                    Some((key, text)) if key.eq_ignore_ascii_case("GENRE") => sheet.genre = Some(value(text.trim())),
+                    Some((key, text)) if key.eq_ignore_ascii_case("DATE") => sheet.date = Some(value(text.trim())),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        sheet.tracks.extend(current);
+
+        if sheet.tracks.windows(2).any(|pair| pair[1].start < pair[0].start) {
+            return Err(Error::new(ErrorKind::InvalidData, "cue sheet tracks are out of order"));
+        }
+        Ok(sheet)
+    }
+
+    pub fn read(filename: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&decode(&fs::read(long_path(filename))?))
+    }
+
+    // The sheet as a .cue file that parse reads back the same
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        lines.extend(self.genre.as_ref().map(|genre| format!("REM GENRE {}", quoted(genre))));
+        lines.extend(self.date.as_ref().map(|date| format!("REM DATE {date}")));
+        lines.extend(self.performer.as_ref().map(|performer| format!("PERFORMER {}", quoted(performer))));
+        lines.extend(self.title.as_ref().map(|title| format!("TITLE {}", quoted(title))));
+        lines.extend(self.file.as_ref().map(|file| format!("FILE {} MP3", quoted(file))));
+        for track in &self.tracks {
+            lines.push(format!("  TRACK {:02} AUDIO", track.number));
+            lines.extend(track.title.as_ref().map(|title| format!("    TITLE {}", quoted(title))));
+            lines.extend(track.performer.as_ref().map(|performer| format!("    PERFORMER {}", quoted(performer))));
+            lines.extend(track.songwriter.as_ref().map(|songwriter| format!("    SONGWRITER {}", quoted(songwriter))));
+            lines.extend(track.isrc.as_ref().map(|isrc| format!("    ISRC {isrc}")));
+            lines.push(format!("    INDEX 01 {}", format_timestamp(track.start)));
+        }
+        lines.iter().map(|line| format!("{line}\r\n")).collect()
+    }
+
+    // One chapter per track, each ending where the next one starts
+    pub fn chapters(&self, duration: u32, major_ver: u8) -> Vec<Chapter> {
+        self.tracks.iter().enumerate().map(|(i, track)| {
+            let end = self.tracks.get(i + 1).map(|next| next.start).unwrap_or(duration);
+            let mut chapter = Chapter::new(&format!("chp{i}"), track.start, end.max(track.start));
+            if let Some(title) = &track.title {
+                chapter.set_title(title, major_ver);
+            }
+            chapter
+        }).collect()
+    }
+
+    // The tag for one track, album level frames come from the source tag and the sheet
+    pub fn track_tag(&self, index: usize, source: &Tag) -> Option<Tag> {
+        let track = self.tracks.get(index)?;
+        let mut tag = Tag::new(source.version());
+        for frame in source.frames() {
+            if !TRACK_FRAMES.contains(&frame.id().as_str()) {
+                tag.add_frame(frame.clone());
+            }
+        }
+
+        let year = if tag.version() == 4 { "TDRC" } else { "TYER" };
+        let fields = [
+            ("TALB", self.title.as_ref()),
+            ("TCON", self.genre.as_ref()),
+            (year, self.date.as_ref()),
+            ("TIT2", track.title.as_ref()),
+            ("TPE1", track.performer.as_ref().or(self.performer.as_ref())),
+            ("TCOM", track.songwriter.as_ref()),
+            ("TSRC", track.isrc.as_ref()),
+        ];
+        for (id, value) in fields {
+            if let Some(value) = value {
+                tag.set_text(id, value);
+            }
+        }
+        tag.set_text("TRCK", &format!("{}/{}", track.number, self.tracks.len()));
+        Some(tag)
+    }
+}
+
+// Embeds the sheet's tracks as chapters of the single file they describe
+pub fn embed_chapters(filename: impl AsRef<Path>, sheet: &CueSheet, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+    let filename = filename.as_ref();
+    let mut file = File::open(long_path(filename))?;
+    let range = mpeg::audio_range(&mut file)?;
+    let duration = mpeg::duration_ms(&mpeg::scan(&mut file, range)?);
+
+    let mut tag = Tag::from_file(filename)?;
+    let chapters = sheet.chapters(duration as u32, tag.version());
+    tag.set_chapters(&chapters);
+    tag.write_to_file(filename, options)
+}
+
+fn file_name(track: &CueTrack) -> String {
+    let title = track.title.as_deref().unwrap_or("Track");
+    sanitize_file_name(&format!("{:02} {}.mp3", track.number, title.trim()))
+}
+
+// Cuts the file at the MPEG frame nearest each track's start and writes one tagged file per track
+pub fn split(filename: impl AsRef<Path>, sheet: &CueSheet, out_dir: impl AsRef<Path>, options: &WriteOptions) -> io::Result<Vec<PathBuf>> {
+    let filename = filename.as_ref();
+    let source = Tag::from_file(filename).unwrap_or_else(|_| Tag::new(4));
+    let mut file = File::open(long_path(filename))?;
+    let range = mpeg::audio_range(&mut file)?;
+    let mut frames = mpeg::scan(&mut file, range)?;
+    mpeg::drop_info_frame(&mut file, &mut frames)?;
+    let Some(end) = frames.last().map(|(offset, header)| offset + header.frame_length() as u64) else {
+        return Err(Error::new(ErrorKind::InvalidData, "no MPEG audio found"));
+    };
+
+    // Byte offset of the first frame at or after each track's start
+    let mut elapsed = 0;
+    let mut frame = frames.iter().peekable();
+    let mut starts = Vec::new();
+    for track in &sheet.tracks {
+        while let Some((_, header)) = frame.next_if(|_| elapsed < track.start as u64 * 1000) {
+            elapsed += header.samples() as u64 * 1_000_000 / header.sample_rate() as u64;
+        }
+        starts.push(frame.peek().map(|(offset, _)| *offset).unwrap_or(end));
+    }
+
+    let mut written = Vec::new();
+    for (i, track) in sheet.tracks.iter().enumerate() {
+        let start = starts[i];
+        let stop = starts.get(i + 1).copied().unwrap_or(end);
+        let path = out_dir.as_ref().join(file_name(track));
+
+        file.seek(SeekFrom::Start(start))?;
+        io::copy(&mut (&mut file).take(stop - start), &mut File::create(long_path(&path))?)?;
+        sheet.track_tag(i, &source).unwrap().write_to_file(&path, options)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+
+    const SHEET: &str = "REM GENRE Rock\r\nREM DATE 2017\r\nPERFORMER \"King Gizzard & The Lizard Wizard\"\r\nTITLE \"Polygondwanaland\"\r\nFILE \"Polygondwanaland.mp3\" MP3\r\n  TRACK 01 AUDIO\r\n    TITLE \"Crumbling Castle\"\r\n    INDEX 01 00:00:00\r\n  TRACK 02 AUDIO\r\n    TITLE \"Polygondwanaland\"\r\n    ISRC AUTZK1700076\r\n    INDEX 00 01:39:50\r\n    INDEX 01 01:40:00\r\n  TRACK 03 AUDIO\r\n    TITLE \"The Castle in the Air\"\r\n    PERFORMER \"Guest\"\r\n    INDEX 01 02:30:37\r\n";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = temp_path(name);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parse_sheet() {
+        let sheet = CueSheet::parse(SHEET).unwrap();
+        assert_eq!(sheet.title.as_deref(), Some("Polygondwanaland"));
+        assert_eq!((sheet.genre.as_deref(), sheet.date.as_deref()), (Some("Rock"), Some("2017")));
+        assert_eq!(sheet.file.as_deref(), Some("Polygondwanaland.mp3"));
+        let starts: Vec<u32> = sheet.tracks.iter().map(|track| track.start).collect();
+        assert_eq!(starts, [0, 100_000, 150_493]);
+        assert_eq!(sheet.tracks[1].isrc.as_deref(), Some("AUTZK1700076"));
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let sheet = CueSheet::parse(SHEET).unwrap();
+        assert_eq!(CueSheet::parse(&sheet.to_text()).unwrap(), sheet);
+        assert_eq!(format_timestamp(150_493), "02:30:37");
+        assert_eq!(format_timestamp(59_999), "01:00:00");
+    }
+
+    #[test]
+    fn rejects_bad_sheets() {
+        assert_eq!(CueSheet::parse("TRACK 01 AUDIO\nINDEX 01 00:61:00").unwrap_err().kind(), ErrorKind::InvalidData);
+        let reversed = "TRACK 01 AUDIO\nINDEX 01 01:00:00\nTRACK 02 AUDIO\nINDEX 01 00:30:00";
+        assert_eq!(CueSheet::parse(reversed).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(CueSheet::parse("FILE a.mp3 MP3\nFILE b.mp3 MP3").unwrap_err().kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn embeds_chapters() {
+        let dir = temp_dir("embed");
+        let path = dir.join("album.mp3");
+        fs::copy("test/Polygondwanaland.mp3", &path).unwrap();
+        let sheet = CueSheet::parse(SHEET).unwrap();
+        embed_chapters(&path, &sheet, &WriteOptions::new()).unwrap();
+
+        let chapters = Tag::from_file(&path).unwrap().chapters();
+        let bounds: Vec<(u32, u32)> = chapters.iter().map(|chapter| (chapter.start(), chapter.end())).collect();
+        assert_eq!(bounds, [(0, 100_000), (100_000, 150_493), (150_493, 213_024)]);
+        assert_eq!(chapters[2].title().as_deref(), Some("The Castle in the Air"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn splits_by_track() {
+        let dir = temp_dir("split");
+        let sheet = CueSheet::parse(SHEET).unwrap();
+        let written = split("test/Polygondwanaland.mp3", &sheet, &dir, &WriteOptions::new()).unwrap();
+        assert_eq!(written[0], dir.join("01 Crumbling Castle.mp3"));
+
+        let mut total = 0;
+        for (i, path) in written.iter().enumerate() {
+            let tag = Tag::from_file(path).unwrap();
+            assert_eq!(tag.text("TRCK"), Some(format!("{}/3", i + 1)));
+            assert_eq!(tag.album().as_deref(), Some("Polygondwanaland"));
+            assert_eq!(tag.pictures().len(), 1);
+
+            let mut file = File::open(path).unwrap();
+            let range = mpeg::audio_range(&mut file).unwrap();
+            total += mpeg::duration_ms(&mpeg::scan(&mut file, range).unwrap());
+        }
+        let third = Tag::from_file(&written[2]).unwrap();
+        assert_eq!(third.artist().as_deref(), Some("Guest"));
+        // All the audio except the Info frame, give or take rounding
+        assert!(total.abs_diff(213_024 - 26) < 5, "{total}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+use crate::mpeg;
+use std::io::{self, Read};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    Id3v2,
+    Mpeg,
+    Wav,
+    Aiff,
+    Mp4,
+    Aac,
+    Unknown,
+}
+
+impl FileKind {
+    // Kinds the parser can handle, everything else should be routed elsewhere
+    pub fn is_supported(&self) -> bool {
+        matches!(self, FileKind::Id3v2 | FileKind::Mpeg)
+    }
+}
+
+const PROBE_SIZE: u64 = 8192;
+
+pub fn sniff<R: Read>(reader: &mut R) -> io::Result<FileKind> {
+    let mut bytes = Vec::new();
+    reader.take(PROBE_SIZE).read_to_end(&mut bytes)?;
+    Ok(sniff_bytes(&bytes))
+}
+
+pub fn sniff_bytes(bytes: &[u8]) -> FileKind {
+    if bytes.starts_with(b"ID3") {
+        return FileKind::Id3v2;
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WAVE" {
+        return FileKind::Wav;
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"FORM" && (&bytes[8..12] == b"AIFF" || &bytes[8..12] == b"AIFC") {
+        return FileKind::Aiff;
+    }
+    if bytes.len() >= 8 && &bytes[4..8] == b"ftyp" {
+        return FileKind::Mp4;
+    }
+
+    // ADTS shares the sync word with MPEG audio but always has layer bits set to zero
+    if bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xF6 == 0xF0 {
+        return FileKind::Aac;
+    }
+    // Some encoders leave junk before the first frame so require two frames in a row
+    match mpeg::find_frame(bytes) {
+        Some(_) => FileKind::Mpeg,
+        None => FileKind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn sniff_id3() {
+        let mut file = File::open("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(sniff(&mut file).unwrap(), FileKind::Id3v2);
+    }
+
+    #[test]
+    fn sniff_bare_mpeg() {
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(sniff(&mut &bytes[187217..]).unwrap(), FileKind::Mpeg);
+    }
+
+    #[test]
+    fn sniff_containers() {
+        assert_eq!(sniff_bytes(b"RIFF\x24\x08\x00\x00WAVEfmt "), FileKind::Wav);
+        assert_eq!(sniff_bytes(b"FORM\x00\x00\x00\x00AIFFCOMM"), FileKind::Aiff);
+        assert_eq!(sniff_bytes(b"\x00\x00\x00\x20ftypM4A "), FileKind::Mp4);
+        assert_eq!(sniff_bytes(&[0xFF, 0xF1, 0x50, 0x80, 0x2E, 0x7F, 0xFC]), FileKind::Aac);
+    }
+
+    #[test]
+    fn sniff_unknown() {
+        assert_eq!(sniff_bytes(b"just some text"), FileKind::Unknown);
+        assert_eq!(sniff_bytes(&[]), FileKind::Unknown);
+        assert!(!FileKind::Unknown.is_supported());
+    }
+}
@@ -0,0 +1,415 @@
+use crate::convert::{CompatibilityReport, text_frame, text_values};
+use crate::frames::{Comment, Lyrics, Picture, PictureType, UserLink, UserText};
+use crate::{Frame, Tag, WriteOptions};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ellipsis {
+    None,
+    Dots,
+    Unicode,
+}
+
+impl Ellipsis {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Ellipsis::None => "",
+            Ellipsis::Dots => "...",
+            Ellipsis::Unicode => "…",
+        }
+    }
+}
+
+// Characters that attach to the one before them and must not be cut off from it
+fn is_extending(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+        | 0x200C | 0x200D | 0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F | 0xE0100..=0xE01EF
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+// Approximate grapheme clusters: combining marks, variation selectors, emoji modifiers,
+// zero width joiner sequences and flag pairs stay with their base character
+pub(crate) fn graphemes(text: &str) -> Vec<&str> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<char> = None;
+    let mut flag_half = false;
+    for (i, c) in text.char_indices() {
+        let joins = match previous {
+            None => false,
+            Some(previous) => {
+                is_extending(c) || previous == '\u{200D}' || (flag_half && is_regional_indicator(c))
+            }
+        };
+        if !joins && i > 0 {
+            clusters.push(&text[start..i]);
+            start = i;
+        }
+        flag_half = is_regional_indicator(c) && !(joins && flag_half);
+        previous = Some(c);
+    }
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+    clusters
+}
+
+// Shorten to at most limit graphemes, the ellipsis counts towards the limit
+pub fn truncate(text: &str, limit: usize, ellipsis: Ellipsis) -> String {
+    let clusters = graphemes(text);
+    if clusters.len() <= limit {
+        return text.to_string();
+    }
+    let marker = ellipsis.as_str();
+    let marker_len = graphemes(marker).len();
+    if limit <= marker_len {
+        return clusters[..limit].concat();
+    }
+    let kept = clusters[..limit - marker_len].concat();
+    format!("{}{marker}", kept.trim_end())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Truncation {
+    pub id: String,
+    pub original: String,
+    pub truncated: String,
+}
+
+// What a player or device can display, text limits are counted in graphemes
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceProfile {
+    name: String,
+    version: u8,
+    latin1_only: bool,
+    max_picture_size: Option<usize>,
+    picture_types: Vec<PictureType>,
+    id3v1: bool,
+    text_limit: Option<usize>,
+    frame_limits: Vec<(String, usize)>,
+    ellipsis: Ellipsis,
+}
+
+impl DeviceProfile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: 4,
+            latin1_only: false,
+            max_picture_size: None,
+            picture_types: Vec::new(),
+            id3v1: false,
+            text_limit: None,
+            frame_limits: Vec::new(),
+            ellipsis: Ellipsis::None,
+        }
+    }
+
+    // Head units that only know v2.3 with Latin-1, small art and read the v1 tag for the display
+    pub fn car_stereo() -> Self {
+        Self::new("old car stereo")
+            .version(3)
+            .latin1_only(true)
+            .max_picture_size(500 * 1024)
+            .picture_types(&[PictureType::FrontCover])
+            .id3v1(true)
+            .text_limit(60)
+            .ellipsis(Ellipsis::Dots)
+    }
+
+    // Cheap flash players, v2.3 with UTF-16 but small screens and little memory for art
+    pub fn portable_player() -> Self {
+        Self::new("portable player")
+            .version(3)
+            .max_picture_size(200 * 1024)
+            .picture_types(&[PictureType::FrontCover, PictureType::BackCover])
+            .text_limit(250)
+            .ellipsis(Ellipsis::Unicode)
+    }
+
+    pub fn modern() -> Self {
+        Self::new("modern player")
+    }
+
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn latin1_only(mut self, latin1_only: bool) -> Self {
+        self.latin1_only = latin1_only;
+        self
+    }
+
+    // Pictures larger than this in bytes are dropped
+    pub fn max_picture_size(mut self, size: usize) -> Self {
+        self.max_picture_size = Some(size);
+        self
+    }
+
+    // Picture types the device shows. Others are turned into the first one listed,
+    // or dropped when the tag already has that picture
+    pub fn picture_types(mut self, types: &[PictureType]) -> Self {
+        self.picture_types = types.to_vec();
+        self
+    }
+
+    // Whether an ID3v1 tag should be kept at the end of the file
+    pub fn id3v1(mut self, id3v1: bool) -> Self {
+        self.id3v1 = id3v1;
+        self
+    }
+
+    // Limit for every text frame without its own limit
+    pub fn text_limit(mut self, limit: usize) -> Self {
+        self.text_limit = Some(limit);
+        self
+    }
+
+    pub fn frame_limit(mut self, id: &str, limit: usize) -> Self {
+        self.frame_limits.retain(|(existing, _)| existing != id);
+        self.frame_limits.push((id.to_string(), limit));
+        self
+    }
+
+    pub fn ellipsis(mut self, ellipsis: Ellipsis) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn target_version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn wants_id3v1(&self) -> bool {
+        self.id3v1
+    }
+
+    // Options for writing a tag that went through export_for
+    pub fn write_options(&self) -> WriteOptions {
+        WriteOptions::new().version(self.version).write_id3v1(self.id3v1).remove_id3v1(!self.id3v1)
+    }
+
+    pub fn limit_for(&self, id: &str) -> Option<usize> {
+        self.frame_limits.iter().find(|(existing, _)| existing == id).map(|(_, limit)| *limit).or(self.text_limit)
+    }
+}
+
+fn latin1(text: &str) -> String {
+    text.chars().map(|c| if (c as u32) < 256 { c } else { '?' }).collect()
+}
+
+// Rebuild a frame with all of its text in Latin-1, None for frames without a known text layout
+fn to_latin1(frame: &Frame, version: u8) -> Option<Frame> {
+    let id = frame.id();
+    let mut converted = match id.as_str() {
+        "COMM" => {
+            let comment = Comment::from_frame(frame)?;
+            Comment::new(comment.language(), &latin1(comment.description()), &latin1(comment.text())).to_frame()
+        }
+        "USLT" => {
+            let lyrics = Lyrics::from_frame(frame)?;
+            Lyrics::new(lyrics.language(), &latin1(lyrics.description()), &latin1(lyrics.text())).to_frame()
+        }
+        "TXXX" => {
+            let text = UserText::from_frame(frame)?;
+            UserText::new(&latin1(text.description()), &latin1(text.value())).to_frame()
+        }
+        "WXXX" => {
+            let link = UserLink::from_frame(frame)?;
+            UserLink::new(&latin1(link.description()), link.url()).to_frame()
+        }
+        "APIC" => {
+            let picture = Picture::from_frame(frame)?;
+            Picture::new(picture.mime(), picture.picture_type(), &latin1(picture.description()), picture.data().to_vec()).to_frame()
+        }
+        _ if id.starts_with('T') => {
+            let values: Vec<String> = text_values(frame).iter().map(|value| latin1(value)).collect();
+            text_frame(&id, &values, version)
+        }
+        _ => None,
+    }?;
+    converted.set_group(frame.group());
+    Some(converted)
+}
+
+impl Tag {
+    // Converts to the profile's version and applies its encoding, art and length limits in one go
+    pub fn export_for(&self, profile: &DeviceProfile) -> (Tag, CompatibilityReport) {
+        let (mut tag, mut report) = self.convert(profile.version);
+
+        if let Some(max) = profile.max_picture_size {
+            tag.frames_mut().retain(|frame| {
+                let too_large = frame.id() == "APIC" && frame.data().len() > max;
+                if too_large {
+                    report.drop("APIC", &format!("picture larger than {max} bytes"));
+                }
+                !too_large
+            });
+        }
+
+        if let Some(fallback) = profile.picture_types.first() {
+            let supported = |picture: &Picture| profile.picture_types.contains(&picture.picture_type());
+            let mut kept: Vec<Picture> = tag.pictures().into_iter().filter(supported).collect();
+            tag.frames_mut().retain_mut(|frame| {
+                let Some(picture) = Picture::from_frame(frame).filter(|picture| !supported(picture)) else {
+                    return true;
+                };
+                let converted = Picture::new(picture.mime(), *fallback, picture.description(), picture.data().to_vec());
+                if kept.iter().any(|existing| existing.clashes_with(&converted)) {
+                    report.drop("APIC", &format!("{:?} picture not supported", picture.picture_type()));
+                    return false;
+                }
+                let Some(mut replacement) = converted.to_frame() else {
+                    return true;
+                };
+                report.downgrade("APIC", &format!("{:?} picture stored as {fallback:?}", picture.picture_type()));
+                replacement.set_group(frame.group());
+                *frame = replacement;
+                kept.push(converted);
+                true
+            });
+        }
+
+        if profile.latin1_only {
+            let version = tag.version();
+            for frame in tag.frames_mut() {
+                if frame.data().first().is_none_or(|encoding| *encoding == 0) {
+                    continue;
+                }
+                if let Some(converted) = to_latin1(frame, version) {
+                    let lossy = converted.parse_text() != frame.parse_text();
+                    report.downgrade(&frame.id(), if lossy { "characters outside Latin-1 replaced" } else { "text re-encoded as Latin-1" });
+                    *frame = converted;
+                }
+            }
+        }
+
+        for truncation in tag.truncate_for(profile) {
+            report.downgrade(&truncation.id, &format!("shortened to \"{}\"", truncation.truncated));
+        }
+        (tag, report)
+    }
+
+    // Shortens text frames to the profile's limits and reports every value that changed
+    pub fn truncate_for(&mut self, profile: &DeviceProfile) -> Vec<Truncation> {
+        let mut truncations = Vec::new();
+        let version = self.version();
+        for frame in self.frames_mut() {
+            let id = frame.id();
+            if !id.starts_with('T') || id == "TXXX" {
+                continue;
+            }
+            let Some(limit) = profile.limit_for(&id) else {
+                continue;
+            };
+
+            let values = text_values(frame);
+            let shortened: Vec<String> = values.iter().map(|value| truncate(value, limit, profile.ellipsis)).collect();
+            if shortened == values {
+                continue;
+            }
+            for (original, truncated) in values.iter().zip(&shortened).filter(|(a, b)| a != b) {
+                truncations.push(Truncation {
+                    id: id.clone(),
+                    original: original.clone(),
+                    truncated: truncated.clone(),
+                });
+            }
+            if let Some(mut replacement) = text_frame(&id, &shortened, version) {
+                replacement.set_group(frame.group());
+                *frame = replacement;
+            }
+        }
+        truncations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grapheme_clusters() {
+        assert_eq!(graphemes("e\u{301}a"), ["e\u{301}", "a"]);
+        assert_eq!(graphemes("👍🏽!"), ["👍🏽", "!"]);
+        assert_eq!(graphemes("👨‍👩‍👧x"), ["👨‍👩‍👧", "x"]);
+        assert_eq!(graphemes("🇸🇪🇳🇴"), ["🇸🇪", "🇳🇴"]);
+    }
+
+    #[test]
+    fn truncate_with_ellipsis() {
+        assert_eq!(truncate("Polygondwanaland", 30, Ellipsis::Dots), "Polygondwanaland");
+        assert_eq!(truncate("Polygondwanaland", 10, Ellipsis::Dots), "Polygon...");
+        assert_eq!(truncate("Polygondwanaland", 10, Ellipsis::Unicode), "Polygondw…");
+        assert_eq!(truncate("Crumbling Castle", 10, Ellipsis::Unicode), "Crumbling…");
+        assert_eq!(truncate("Café\u{301}s", 4, Ellipsis::None), "Café\u{301}");
+    }
+
+    #[test]
+    fn truncate_tag() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let profile = DeviceProfile::new("test").text_limit(12).frame_limit("TPE1", 30).ellipsis(Ellipsis::Unicode);
+        let truncations = tag.truncate_for(&profile);
+
+        let ids: Vec<&str> = truncations.iter().map(|x| x.id.as_str()).collect();
+        assert_eq!(ids, ["TIT2", "TPE1", "TALB", "TPE2"]);
+        assert_eq!(tag.title().unwrap(), "Polygondwan…");
+        assert_eq!(tag.artist().unwrap().chars().count(), 30);
+        assert_eq!(tag.text("TSRC").unwrap(), "AUTZK1700076");
+    }
+
+    #[test]
+    fn export_for_car_stereo() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let (exported, report) = tag.export_for(&DeviceProfile::car_stereo().text_limit(20).max_picture_size(100 * 1024));
+
+        assert_eq!(exported.version(), 3);
+        assert!(exported.pictures().is_empty());
+        assert_eq!(report.dropped(), ["APIC"]);
+        assert!(exported.frames().iter().all(|frame| frame.data()[0] == 0));
+        assert_eq!(exported.artist().unwrap(), "King Gizzard & Th...");
+        assert_eq!(exported.comments()[0].text(), tag.comments()[0].text());
+    }
+
+    #[test]
+    fn export_converts_picture_types() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.embed_picture(&Picture::new("image/jpeg", PictureType::BackCover, "cover", vec![1]));
+        tag.embed_picture(&Picture::new("image/jpeg", PictureType::Band, "", vec![2]));
+        tag.embed_picture(&Picture::new("image/png", PictureType::FileIcon, "", vec![3]));
+
+        let (exported, report) = tag.export_for(&DeviceProfile::new("test").picture_types(&[PictureType::FrontCover]));
+        // The back cover clashes with the real cover and the icon with the converted band photo
+        let pictures = exported.pictures_of(PictureType::FrontCover);
+        assert_eq!(exported.pictures().len(), 2);
+        assert_eq!((pictures[0].description(), pictures[1].data()), ("cover", &[2][..]));
+        assert_eq!(report.dropped(), ["APIC", "APIC"]);
+    }
+
+    #[test]
+    fn export_replaces_non_latin1() {
+        let mut tag = Tag::new(4);
+        tag.set_text("TIT2", "Ōkami");
+        let (exported, report) = tag.export_for(&DeviceProfile::new("test").latin1_only(true));
+        assert_eq!(exported.title().unwrap(), "?kami");
+        assert!(!report.is_lossless());
+    }
+
+    #[test]
+    fn export_for_modern_is_lossless() {
+        let mut tag = Tag::new(4);
+        tag.set_text("TIT2", "Ōkami");
+        let (exported, report) = tag.export_for(&DeviceProfile::modern());
+        assert_eq!(exported.title().unwrap(), "Ōkami");
+        assert!(report.is_lossless());
+    }
+}
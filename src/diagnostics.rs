@@ -0,0 +1,208 @@
+use crate::Tag;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+const V23_FRAMES: [&str; 74] = [
+    "AENC", "APIC", "COMM", "COMR", "ENCR", "EQUA", "ETCO", "GEOB", "GRID", "IPLS", "LINK", "MCDI", "MLLT", "OWNE", "PRIV",
+    "PCNT", "POPM", "POSS", "RBUF", "RVAD", "RVRB", "SYLT", "SYTC", "TALB", "TBPM", "TCOM", "TCON", "TCOP", "TDAT", "TDLY",
+    "TENC", "TEXT", "TFLT", "TIME", "TIT1", "TIT2", "TIT3", "TKEY", "TLAN", "TLEN", "TMED", "TOAL", "TOFN", "TOLY", "TOPE",
+    "TORY", "TOWN", "TPE1", "TPE2", "TPE3", "TPE4", "TPOS", "TPUB", "TRCK", "TRDA", "TRSN", "TRSO", "TSIZ", "TSRC", "TSSE",
+    "TYER", "TXXX", "UFID", "USER", "USLT", "WCOM", "WCOP", "WOAF", "WOAR", "WOAS", "WORS", "WPAY", "WPUB", "WXXX",
+];
+
+const V23_REMOVED: [&str; 9] = ["EQUA", "IPLS", "RVAD", "TDAT", "TIME", "TORY", "TRDA", "TSIZ", "TYER"];
+
+const V24_ADDED: [&str; 18] = [
+    "ASPI", "EQU2", "RVA2", "SEEK", "SIGN", "TDEN", "TDOR", "TDRC", "TDRL", "TDTG", "TIPL", "TMCL", "TMOO", "TPRO", "TSOA",
+    "TSOP", "TSOT", "TSST",
+];
+
+// Chapter frames come from an addendum that works with both versions
+const ADDENDUM: [&str; 2] = ["CHAP", "CTOC"];
+
+// Pictures above this size are reported as oversized
+pub const LARGE_PICTURE: usize = 1024 * 1024;
+
+pub(crate) fn is_known(id: &str, major_ver: u8) -> bool {
+    ADDENDUM.contains(&id)
+        || crate::podcast::FRAMES.contains(&id)
+        || match major_ver {
+            4 => (V23_FRAMES.contains(&id) && !V23_REMOVED.contains(&id)) || V24_ADDED.contains(&id),
+            _ => V23_FRAMES.contains(&id),
+        }
+}
+
+// Frames defined by the other version of the spec
+pub(crate) fn is_other_version(id: &str, major_ver: u8) -> bool {
+    match major_ver {
+        4 => V23_REMOVED.contains(&id),
+        _ => V24_ADDED.contains(&id),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Finding {
+    UnknownFrame { id: String },
+    DeprecatedFrame { id: String, version: u8 },
+    SkippedFrame { id: String, reason: String },
+    PaddingAnomaly { reason: String },
+    OversizedArt { size: usize },
+    DroppedOnWrite { id: String, reason: String },
+    // Cut down to fit a size budget, see WriteOptions::size_budget
+    TrimmedOnWrite { id: String, bytes: usize },
+    CorruptFrame { key: String },
+    // A v2.4 frame size written as a plain integer instead of sync-safe, as iTunes does
+    NonSyncSafeSize { id: String },
+    // The frame's content ends after actual bytes instead of the declared size, see ReadOptions::size_mismatch
+    SizeMismatch { id: String, declared: u64, actual: u64 },
+}
+
+impl Finding {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Finding::UnknownFrame { .. } | Finding::PaddingAnomaly { .. } => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::UnknownFrame { id } => write!(f, "Unknown frame {id}"),
+            Finding::DeprecatedFrame { id, version } => write!(f, "Frame {id} doesn't belong in ID3v2.{version}"),
+            Finding::SkippedFrame { id, reason } => write!(f, "Skipped frame {id}: {reason}"),
+            Finding::PaddingAnomaly { reason } => write!(f, "Padding anomaly: {reason}"),
+            Finding::OversizedArt { size } => write!(f, "Picture of {size} bytes is unusually large"),
+            Finding::DroppedOnWrite { id, reason } => write!(f, "Frame {id} dropped on write: {reason}"),
+            Finding::TrimmedOnWrite { id, bytes } => write!(f, "Frame {id} trimmed by {bytes} bytes on write"),
+            Finding::CorruptFrame { key } => write!(f, "Frame {key} doesn't match its checksum"),
+            Finding::NonSyncSafeSize { id } => write!(f, "Frame {id} size isn't sync-safe"),
+            Finding::SizeMismatch { id, declared, actual } => write!(f, "Frame {id} declares {declared} bytes but its content takes {actual}"),
+        }
+    }
+}
+
+// Non-fatal findings from reading or writing, clones share the same list
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    findings: Arc<Mutex<Vec<Finding>>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn report(&self, finding: Finding) {
+        self.findings.lock().unwrap().push(finding);
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        self.findings.lock().unwrap().clone()
+    }
+
+    pub fn at_least(&self, severity: Severity) -> Vec<Finding> {
+        self.findings().into_iter().filter(|finding| finding.severity() >= severity).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.lock().unwrap().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.findings.lock().unwrap().clear();
+    }
+
+    // Findings about the frames of a parsed tag
+    pub(crate) fn inspect(&self, tag: &Tag) {
+        for frame in tag.frames() {
+            let id = frame.id();
+            if is_other_version(&id, tag.version()) {
+                self.report(Finding::DeprecatedFrame { id: id.clone(), version: tag.version() });
+            } else if !is_known(&id, tag.version()) {
+                self.report(Finding::UnknownFrame { id: id.clone() });
+            }
+            if id == "APIC" && frame.data().len() > LARGE_PICTURE {
+                self.report(Finding::OversizedArt { size: frame.data().len() });
+            }
+        }
+
+        // A v2.4 footer makes padding forbidden
+        if tag.header().footer() && tag.padding() > 0 {
+            self.report(Finding::PaddingAnomaly { reason: format!("{} bytes of padding with a footer", tag.padding()) });
+        }
+        if tag.padding() > LARGE_PICTURE as u64 {
+            self.report(Finding::PaddingAnomaly { reason: format!("{} bytes of padding", tag.padding()) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use crate::{Frame, ReadOptions, Reader, WriteOptions};
+
+    #[test]
+    fn clean_file() {
+        let diagnostics = Diagnostics::new();
+        Tag::from_file_with("test/Polygondwanaland.mp3", &ReadOptions::new().diagnostics(diagnostics.clone())).unwrap();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn frame_findings() {
+        let mut tag = Tag::new(4);
+        tag.set_text("TYER", "2017");
+        tag.set_text("TCMP", "1");
+        tag.add_frame(Frame::new("APIC", vec![0; LARGE_PICTURE + 1]).unwrap());
+        let diagnostics = Diagnostics::new();
+        diagnostics.inspect(&tag);
+
+        assert_eq!(diagnostics.findings(), [
+            Finding::DeprecatedFrame { id: "TYER".to_string(), version: 4 },
+            Finding::UnknownFrame { id: "TCMP".to_string() },
+            Finding::OversizedArt { size: LARGE_PICTURE + 1 },
+        ]);
+        assert_eq!(diagnostics.at_least(Severity::Warning).len(), 2);
+    }
+
+    #[test]
+    fn non_ascii_frame_id_skipped() {
+        let mut tag = Tag::new(3);
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.set_text("TALB", "Polygondwanaland");
+        let mut bytes = tag.to_bytes(0);
+        let talb = bytes.windows(4).position(|id| id == b"TALB").unwrap();
+        bytes[talb] = 0xC4;
+
+        let read = |options: &ReadOptions| Tag::from_reader_with(&mut Reader::from_stream(std::io::Cursor::new(bytes.clone())), options).unwrap();
+        let diagnostics = Diagnostics::new();
+        let tag = read(&ReadOptions::new().diagnostics(diagnostics.clone()));
+        assert_eq!(tag.frames().iter().map(Frame::id).collect::<Vec<_>>(), ["TIT2"]);
+        assert!(diagnostics.findings().contains(&Finding::SkippedFrame { id: "\u{FFFD}ALB".to_string(), reason: "invalid frame id".to_string() }));
+        assert_eq!(read(&ReadOptions::new()).frames().len(), 1);
+    }
+
+    #[test]
+    fn write_findings() {
+        let path = copy_of_test_file("write");
+        let path = path.as_path();
+
+        let mut tag = Tag::new(4);
+        tag.set_text("TMOO", "Calm");
+        let diagnostics = Diagnostics::new();
+        tag.write_to_file(path, &WriteOptions::new().version(3).diagnostics(diagnostics.clone())).unwrap();
+        assert_eq!(diagnostics.findings().len(), 1);
+        assert!(matches!(&diagnostics.findings()[0], Finding::DroppedOnWrite { id, .. } if id == "TMOO"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
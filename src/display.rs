@@ -0,0 +1,51 @@
+use crate::{Frame, Tag};
+use std::fmt;
+
+// The summary mp3tool show prints, only fields the tag has are listed. Text that doesn't decode
+// is shown escaped so what is stored can be seen
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frames = self.frames().len() + self.lazy_frames().len();
+        write!(f, "ID3v2.{}, {} bytes, {frames} frame{}", self.version(), self.header().tag_size(), if frames == 1 { "" } else { "s" })?;
+
+        let fields = [
+            ("Title", self.frame("TIT2").map(Frame::escaped_text)),
+            ("Artist", self.frame("TPE1").map(Frame::escaped_text)),
+            ("Album", self.frame("TALB").map(Frame::escaped_text)),
+            ("Track", self.track().map(|track| track.to_string())),
+            ("Year", self.year().map(|year| year.to_string())),
+            ("Genre", self.frame("TCON").map(Frame::escaped_text)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                write!(f, "\n{:<8}{value}", format!("{name}:"))?;
+            }
+        }
+        for picture in self.pictures() {
+            write!(f, "\n{:<8}{}, {}, {} bytes", "Art:", picture.picture_type().name(), picture.mime(), picture.data().len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_summary() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let summary = tag.to_string();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "ID3v2.3, 187217 bytes, 9 frames");
+        assert!(lines[1].starts_with("Title:  "));
+        assert!(lines.contains(&"Track:  2"));
+        assert!(lines.contains(&"Year:   2017"));
+        assert!(lines.last().unwrap().starts_with("Art:    Front cover, image/jpeg, "));
+    }
+
+    #[test]
+    fn empty_tag() {
+        assert_eq!(Tag::new(4).to_string(), "ID3v2.4, 10 bytes, 0 frames");
+    }
+}
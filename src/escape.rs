@@ -0,0 +1,101 @@
+use crate::Frame;
+use crate::ID3::split_terminated;
+use std::fmt::Write;
+
+fn push_char(text: &mut String, c: char) {
+    match c {
+        '\\' => text.push_str("\\\\"),
+        c => text.push(c),
+    }
+}
+
+fn push_byte(text: &mut String, byte: u8) {
+    let _ = write!(text, "\\x{byte:02X}");
+}
+
+fn utf8(text: &mut String, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        let (valid, bad) = match std::str::from_utf8(bytes) {
+            Ok(valid) => (valid, 0),
+            Err(error) => (std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap(), error.error_len().unwrap_or(bytes.len() - error.valid_up_to())),
+        };
+        valid.chars().for_each(|c| push_char(text, c));
+        bytes[valid.len()..valid.len() + bad].iter().for_each(|byte| push_byte(text, *byte));
+        bytes = &bytes[valid.len() + bad..];
+    }
+}
+
+fn utf16(text: &mut String, bytes: &[u8], big_endian: bool) {
+    let units = bytes.chunks_exact(2).map(|pair| match big_endian {
+        true => u16::from_be_bytes([pair[0], pair[1]]),
+        false => u16::from_le_bytes([pair[0], pair[1]]),
+    });
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => push_char(text, c),
+            Err(error) => {
+                let _ = write!(text, "\\u{{{:04X}}}", error.unpaired_surrogate());
+            }
+        }
+    }
+    if let [.., last] = bytes.chunks_exact(2).remainder() {
+        push_byte(text, *last);
+    }
+}
+
+// The first string of a text frame body written out whatever it holds. Sequences the encoding
+// can't decode become \xNN for each byte, or \u{NNNN} for an unpaired UTF-16 surrogate. UTF-16
+// without a BOM is read as little endian and an unknown encoding keeps only printable ASCII
+pub(crate) fn escaped(encoding: u8, bytes: &[u8]) -> String {
+    let bytes = split_terminated(encoding, bytes).0;
+    let mut text = String::new();
+    match (encoding, bytes) {
+        (0, _) => bytes.iter().for_each(|byte| push_char(&mut text, *byte as char)),
+        (1, [0xFE, 0xFF, rest @ ..]) => utf16(&mut text, rest, true),
+        (1, [0xFF, 0xFE, rest @ ..]) => utf16(&mut text, rest, false),
+        (1, _) => utf16(&mut text, bytes, false),
+        (2, _) => utf16(&mut text, bytes, true),
+        (3, _) => utf8(&mut text, bytes),
+        _ => {
+            for byte in bytes {
+                match byte {
+                    0x20..0x7F => push_char(&mut text, *byte as char),
+                    _ => push_byte(&mut text, *byte),
+                }
+            }
+        }
+    }
+    text
+}
+
+impl Frame {
+    // The frame's text as parse_text gives it when it decodes cleanly. Otherwise what is
+    // stored, with escapes where it doesn't decode and backslashes doubled so those stand out,
+    // instead of replacement characters or nothing at all
+    pub fn escaped_text(&self) -> String {
+        match (self.try_parse_text(), self.data().split_first()) {
+            (Ok(text), _) => text,
+            (Err(_), Some((encoding, bytes))) => escaped(*encoding, bytes),
+            (Err(_), None) => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(data: &[u8]) -> String {
+        Frame::new("TIT2", data.to_vec()).unwrap().escaped_text()
+    }
+
+    #[test]
+    fn bad_sequences_escaped() {
+        assert_eq!(text(b"\x03Crumbling \\ Castle"), "Crumbling \\ Castle");
+        assert_eq!(text(b"\x03Caf\xE9 \\ \xF0\x9F\x90\xBA\xC3\x00more"), "Caf\\xE9 \\\\ \u{1F43A}\\xC3");
+        assert_eq!(text(&[1, 0xFF, 0xFE, b'A', 0, 0x3D, 0xD8, b'B', 0, b'C']), "A\\u{D83D}B\\x43");
+        assert_eq!(text(&[1, b'A', 0, b'B', 0]), "AB");
+        assert_eq!(text(b"\x07Cr\xFCmbling\n"), "Cr\\xFCmbling\\x0A");
+        assert_eq!(Frame::new("TIT2", vec![]).unwrap().escaped_text(), "");
+    }
+}
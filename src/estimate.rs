@@ -0,0 +1,143 @@
+use crate::report::mp3_files;
+use crate::transcode;
+use crate::write::reencoded;
+use crate::{Frame, Tag};
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// How the text frames of one file would change size, counting frame data only
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileEstimate {
+    pub path: PathBuf,
+    // Frames whose text would be re-encoded
+    pub frames: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl FileEstimate {
+    // Negative when the tag gets smaller
+    pub fn difference(&self) -> i64 {
+        self.bytes_after as i64 - self.bytes_before as i64
+    }
+}
+
+// Totals over a library, files without a readable tag are listed under failed
+#[derive(Debug, Default)]
+pub struct EncodingEstimate {
+    pub files: Vec<FileEstimate>,
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+impl EncodingEstimate {
+    pub fn frames(&self) -> usize {
+        self.files.iter().map(|file| file.frames).sum()
+    }
+
+    pub fn bytes_before(&self) -> u64 {
+        self.files.iter().map(|file| file.bytes_before).sum()
+    }
+
+    pub fn bytes_after(&self) -> u64 {
+        self.files.iter().map(|file| file.bytes_after).sum()
+    }
+
+    pub fn difference(&self) -> i64 {
+        self.bytes_after() as i64 - self.bytes_before() as i64
+    }
+}
+
+// Data size before and after, None when the frame has no text, already uses the encoding
+// or holds text ISO-8859-1 can't represent
+fn frame_sizes(frame: &Frame, encoding: u8) -> Option<(usize, usize)> {
+    if frame.data().first() == Some(&encoding) {
+        return None;
+    }
+    let mut representable = true;
+    let data = reencoded(frame, encoding, |text| {
+        representable &= encoding != 0 || text.chars().all(|c| (c as u32) < 0x100);
+        transcode::encode(encoding, text)
+    })?;
+    representable.then_some((frame.data().len(), data.len()))
+}
+
+// Sizes if every text frame of the tag were written in the encoding
+pub fn estimate_tag(tag: &Tag, encoding: u8) -> (usize, u64, u64) {
+    tag.frames().iter().filter_map(|frame| frame_sizes(frame, encoding)).fold((0, 0, 0), |(frames, before, after), (old, new)| {
+        (frames + 1, before + old as u64, after + new as u64)
+    })
+}
+
+fn check_encoding(encoding: u8) -> io::Result<()> {
+    match encoding {
+        0..=3 => Ok(()),
+        _ => Err(Error::new(ErrorKind::InvalidInput, format!("unknown text encoding {encoding}"))),
+    }
+}
+
+// Reads the tag and reports what re-encoding would do, nothing is written
+pub fn estimate_file(path: impl AsRef<Path>, encoding: u8) -> io::Result<FileEstimate> {
+    check_encoding(encoding)?;
+    let path = path.as_ref();
+    let (frames, bytes_before, bytes_after) = estimate_tag(&Tag::from_file(path)?, encoding);
+    Ok(FileEstimate { path: path.to_path_buf(), frames, bytes_before, bytes_after })
+}
+
+pub fn estimate_files(files: &[impl AsRef<Path>], encoding: u8) -> io::Result<EncodingEstimate> {
+    check_encoding(encoding)?;
+    let mut estimate = EncodingEstimate::default();
+    for file in files {
+        match estimate_file(file, encoding) {
+            Ok(file) => estimate.files.push(file),
+            Err(error) => estimate.failed.push((file.as_ref().to_path_buf(), error)),
+        }
+    }
+    Ok(estimate)
+}
+
+// A file, or every MP3 below a directory. UTF-8 (3) and UTF-16BE (2) only exist in v2.4
+// so for v2.3 files this is the text part of converting them
+pub fn estimate(path: impl AsRef<Path>, encoding: u8) -> io::Result<EncodingEstimate> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return estimate_files(&[path], encoding);
+    }
+    let mut files = Vec::new();
+    mp3_files(path, &mut files)?;
+    estimate_files(&files, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use std::fs;
+
+    #[test]
+    fn utf16_to_utf8() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(Frame::new("TIT2", b"\x01\xFF\xFEC\x00a\x00f\x00\xE9\x00".to_vec()).unwrap());
+        tag.add_frame(Frame::new("TPE1", b"\x03Caf\xC3\xA9".to_vec()).unwrap());
+        assert_eq!(estimate_tag(&tag, 3), (1, 11, 6));
+        assert_eq!(estimate_tag(&tag, 0), (2, 17, 10));
+
+        // ISO-8859-1 can't hold the album so it is left out
+        tag.set_text("TALB", "東京");
+        assert_eq!(estimate_tag(&tag, 0).0, 2);
+    }
+
+    #[test]
+    fn library_totals() {
+        let dir = temp_path("estimate");
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("a.mp3")).unwrap();
+        fs::write(dir.join("b.mp3"), b"not an mp3").unwrap();
+
+        let estimate = estimate(&dir, 3).unwrap();
+        assert_eq!((estimate.files.len(), estimate.failed.len()), (1, 1));
+        assert!(estimate.frames() > 0);
+        assert_eq!(estimate.difference(), estimate.files[0].difference());
+        assert_eq!(estimate_file(dir.join("a.mp3"), 4).unwrap_err().kind(), ErrorKind::InvalidInput);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
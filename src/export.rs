@@ -0,0 +1,282 @@
+use crate::paths::{changed_ns, long_path};
+use crate::report::mp3_files;
+use crate::{mpeg, Tag};
+use rusqlite::types::Value;
+use rusqlite::{Connection, ErrorCode, params_from_iter};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+const TABLE: &str = "files";
+
+// Text columns and the frames they come from, the year falls back to TDRC
+const FIELDS: [(&str, &str); 10] = [
+    ("title", "TIT2"),
+    ("artist", "TPE1"),
+    ("album_artist", "TPE2"),
+    ("album", "TALB"),
+    ("track", "TRCK"),
+    ("disc", "TPOS"),
+    ("year", "TYER"),
+    ("genre", "TCON"),
+    ("composer", "TCOM"),
+    ("isrc", "TSRC"),
+];
+
+// Columns after the text fields, the last three are what decide whether a row is stale. The change
+// time is there for edits written with preserve_mtime, which leave the modification time as it was
+const COLUMNS: [&str; 10] = ["comment", "id3_version", "pictures", "bitrate", "sample_rate", "channels", "duration_ms", "size", "modified_ns", "changed_ns"];
+
+fn create_table() -> String {
+    let mut columns = vec!["path TEXT NOT NULL".to_string()];
+    columns.extend(FIELDS.iter().map(|(name, _)| format!("{name} TEXT")));
+    columns.push("comment TEXT".to_string());
+    columns.extend(COLUMNS[1..].iter().map(|name| format!("{name} INTEGER")));
+    format!("CREATE TABLE {TABLE} ({})", columns.join(", "))
+}
+
+fn column_count() -> usize {
+    1 + FIELDS.len() + COLUMNS.len()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+fn sql_error(error: rusqlite::Error) -> Error {
+    match error.sqlite_error_code() {
+        Some(ErrorCode::NotADatabase) => Error::new(ErrorKind::InvalidData, "Not a SQLite database"),
+        _ => Error::other(error),
+    }
+}
+
+fn text(value: Option<String>) -> Value {
+    value.map(Value::Text).unwrap_or(Value::Null)
+}
+
+fn integer(value: Option<u64>) -> Value {
+    value.map(|x| Value::Integer(x as i64)).unwrap_or(Value::Null)
+}
+
+// The first MPEG frame after the tag, the duration assumes a constant bitrate
+fn audio_properties(filename: impl AsRef<Path>) -> io::Result<Option<(mpeg::FrameHeader, u64)>> {
+    let mut file = File::open(long_path(filename.as_ref()))?;
+    let range = mpeg::audio_range(&mut file)?;
+
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(range.start))?;
+    file.take(64 * 1024).read_to_end(&mut bytes)?;
+    let Some(header) = mpeg::find_frame(&bytes).and_then(|offset| mpeg::FrameHeader::from_bytes(&bytes[offset..])) else {
+        return Ok(None);
+    };
+    let duration = (range.end - range.start) * 8 * 1000 / header.bitrate().max(1) as u64;
+    Ok(Some((header, duration)))
+}
+
+fn row(path: impl AsRef<Path>, stamp: Vec<Value>) -> io::Result<Vec<Value>> {
+    let path = path.as_ref();
+    // Files without a tag still get a row, their tag columns are just empty
+    let tag = Tag::from_file(path).ok();
+    let frame_text = |id: &str| tag.as_ref().and_then(|tag| tag.text(id));
+
+    let mut values = vec![Value::Text(path.to_string_lossy().into_owned())];
+    for (name, id) in FIELDS {
+        let value = match name {
+            "year" => frame_text(id).or_else(|| frame_text("TDRC")),
+            _ => frame_text(id),
+        };
+        values.push(text(value));
+    }
+
+    let audio = audio_properties(path)?;
+    values.extend([
+        text(tag.as_ref().and_then(|tag| tag.comments().first().map(|comment| comment.text().to_string()))),
+        integer(tag.as_ref().map(|tag| tag.version() as u64)),
+        integer(tag.as_ref().map(|tag| tag.pictures().len() as u64)),
+        integer(audio.map(|(header, _)| header.bitrate() as u64)),
+        integer(audio.map(|(header, _)| header.sample_rate() as u64)),
+        integer(audio.map(|(header, _)| header.channels() as u64)),
+        integer(audio.map(|(_, duration)| duration)),
+    ]);
+    values.extend(stamp);
+    Ok(values)
+}
+
+fn read_rows(db: &Connection) -> rusqlite::Result<Vec<Vec<Value>>> {
+    let exists = db.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?.exists([TABLE])?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let mut select = db.prepare(&format!("SELECT * FROM {TABLE}"))?;
+    let columns = select.column_count();
+    let rows = select.query_map([], |row| (0..columns).map(|i| row.get(i)).collect())?;
+    rows.collect()
+}
+
+// Rows of an earlier export keyed by path, a different layout means starting over
+fn previous_rows(db: &Connection) -> io::Result<HashMap<String, Vec<Value>>> {
+    let rows = read_rows(db).map_err(sql_error)?;
+    Ok(rows.into_iter()
+        .filter(|row| row.len() == column_count())
+        .filter_map(|row| match &row[0] {
+            Value::Text(path) => Some((path.clone(), row)),
+            _ => None,
+        })
+        .collect())
+}
+
+// Replaces the table with the rows in one transaction, other tables in the database are kept
+fn write_rows(db: &mut Connection, rows: &[Vec<Value>]) -> rusqlite::Result<()> {
+    let transaction = db.transaction()?;
+    transaction.execute(&format!("DROP TABLE IF EXISTS {TABLE}"), [])?;
+    transaction.execute(&create_table(), [])?;
+    {
+        let mut insert = transaction.prepare(&format!("INSERT INTO {TABLE} VALUES ({})", vec!["?"; column_count()].join(", ")))?;
+        for row in rows {
+            insert.execute(params_from_iter(row))?;
+        }
+    }
+    transaction.commit()
+}
+
+// One row per mp3 below dir with its tag fields, audio properties and number of pictures.
+// Files whose size, modification and change times match the existing database aren't read again
+pub fn sqlite(dir: impl AsRef<Path>, db_path: impl AsRef<Path>) -> io::Result<ExportReport> {
+    let mut files = Vec::new();
+    mp3_files(dir.as_ref(), &mut files)?;
+    sqlite_files(&files, db_path)
+}
+
+// Like sqlite but for a chosen list of files, rows for any other file are removed
+pub fn sqlite_files(files: &[impl AsRef<Path>], db_path: impl AsRef<Path>) -> io::Result<ExportReport> {
+    let mut db = Connection::open(long_path(db_path.as_ref())).map_err(sql_error)?;
+    let mut previous = previous_rows(&db)?;
+    let mut report = ExportReport::default();
+    let mut rows = Vec::new();
+    for path in files {
+        let path = path.as_ref();
+        // Rows are found by the path as row() stores it, names that aren't UTF-8 lossily
+        let name = path.to_string_lossy();
+        let metadata = fs::metadata(long_path(path))?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or(0);
+        let stamp = vec![integer(Some(metadata.len())), integer(Some(modified)), integer(Some(changed_ns(&metadata)))];
+
+        match previous.remove(name.as_ref()) {
+            Some(row) if row[row.len() - stamp.len()..] == stamp => {
+                report.unchanged += 1;
+                rows.push(row);
+            }
+            old => {
+                if old.is_some() {
+                    report.updated += 1;
+                } else {
+                    report.added += 1;
+                }
+                rows.push(row(path, stamp)?);
+            }
+        }
+    }
+    report.removed = previous.len();
+
+    write_rows(&mut db, &rows).map_err(sql_error)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::WriteOptions;
+    use std::path::PathBuf;
+
+    fn library(name: &str) -> PathBuf {
+        let dir = temp_path(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("01.mp3")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("02.mp3")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn exports_one_row_per_file() {
+        let dir = library("rows");
+        let db = dir.join("library.db");
+        let report = sqlite(&dir, &db).unwrap();
+        assert_eq!(report, ExportReport { added: 2, ..Default::default() });
+
+        let rows = read_rows(&Connection::open(&db).unwrap()).unwrap();
+        assert_eq!(rows.len(), 2);
+        let column = |name: &str| {
+            let index = 1 + FIELDS.iter().position(|(field, _)| *field == name)
+                .unwrap_or_else(|| FIELDS.len() + COLUMNS.iter().position(|column| *column == name).unwrap());
+            rows[0][index].clone()
+        };
+        assert_eq!(column("album"), Value::Text("Polygondwanaland".to_string()));
+        assert_eq!(column("year"), Value::Text("2017".to_string()));
+        assert_eq!(column("id3_version"), Value::Integer(3));
+        assert_eq!(column("pictures"), Value::Integer(1));
+        assert_eq!(column("sample_rate"), Value::Integer(44100));
+        assert_eq!(column("disc"), Value::Null);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn updates_incrementally() {
+        let dir = library("incremental");
+        let db = dir.join("library.db");
+        sqlite(&dir, &db).unwrap();
+
+        let changed = dir.join("02.mp3");
+        let mut tag = Tag::from_file(&changed).unwrap();
+        tag.set_text("TIT2", "Deserted Dunes Welcome Weary Feet");
+        tag.write_to_file(&changed, &WriteOptions::new()).unwrap();
+        fs::remove_file(dir.join("01.mp3")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("03.mp3")).unwrap();
+
+        let report = sqlite(&dir, &db).unwrap();
+        assert_eq!(report, ExportReport { added: 1, updated: 1, unchanged: 0, removed: 1 });
+        let report = sqlite(&dir, &db).unwrap();
+        assert_eq!(report, ExportReport { unchanged: 2, ..Default::default() });
+
+        let rows = read_rows(&Connection::open(&db).unwrap()).unwrap();
+        assert_eq!(rows[0][1], Value::Text("Deserted Dunes Welcome Weary Feet".to_string()));
+
+        // Same size and modification time, only the change time gives the edit away
+        if cfg!(unix) {
+            tag.set_text("TIT2", "Deserted Dunes Welcome Tired Feet");
+            tag.write_to_file(&changed, &WriteOptions::new().preserve_mtime(true)).unwrap();
+            let report = sqlite(&dir, &db).unwrap();
+            assert_eq!(report, ExportReport { updated: 1, unchanged: 1, ..Default::default() });
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_replace_other_files() {
+        let dir = library("other");
+        let db = dir.join("notes.db");
+        fs::write(&db, "not a database").unwrap();
+        assert_eq!(sqlite(&dir, &db).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_names_that_arent_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = temp_path("latin1");
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join(std::ffi::OsStr::from_bytes(b"caf\xE9.mp3"))).unwrap();
+        let db = dir.join("library.db");
+        assert_eq!(sqlite(&dir, &db).unwrap(), ExportReport { added: 1, ..Default::default() });
+        assert_eq!(sqlite(&dir, &db).unwrap(), ExportReport { unchanged: 1, ..Default::default() });
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use crate::category::Category;
+use crate::convert::text_values;
+use crate::frames::PictureType;
+use crate::language::Language;
+use crate::ID3::{read_terminated, text_from_bytes};
+use crate::Frame;
+use std::fmt;
+
+// Labelled parts of a frame, for property grids and exports that shouldn't know every layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldName {
+    Encoding,
+    Language,
+    Description,
+    Text,
+    Url,
+    Mime,
+    PictureType,
+    Filename,
+    Owner,
+    Identifier,
+    Email,
+    Rating,
+    Counter,
+    Data,
+}
+
+impl FieldName {
+    // Snake case, stable for use as a JSON key
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldName::Encoding => "encoding",
+            FieldName::Language => "language",
+            FieldName::Description => "description",
+            FieldName::Text => "text",
+            FieldName::Url => "url",
+            FieldName::Mime => "mime",
+            FieldName::PictureType => "picture_type",
+            FieldName::Filename => "filename",
+            FieldName::Owner => "owner",
+            FieldName::Identifier => "identifier",
+            FieldName::Email => "email",
+            FieldName::Rating => "rating",
+            FieldName::Counter => "counter",
+            FieldName::Data => "data",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    // The encoding byte, see Display for its name
+    Encoding(u8),
+    Language(Language),
+    PictureType(PictureType),
+    Text(String),
+    Number(u64),
+    Binary(Vec<u8>),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Encoding(0) => write!(f, "ISO-8859-1"),
+            FieldValue::Encoding(1) => write!(f, "UTF-16"),
+            FieldValue::Encoding(2) => write!(f, "UTF-16BE"),
+            FieldValue::Encoding(3) => write!(f, "UTF-8"),
+            FieldValue::Encoding(encoding) => write!(f, "unknown ({encoding})"),
+            FieldValue::Language(language) => write!(f, "{language}"),
+            FieldValue::PictureType(picture_type) => write!(f, "{picture_type:?}"),
+            FieldValue::Text(text) => write!(f, "{text}"),
+            FieldValue::Number(number) => write!(f, "{number}"),
+            FieldValue::Binary(data) if data.len() == 1 => write!(f, "1 byte"),
+            FieldValue::Binary(data) => write!(f, "{} bytes", data.len()),
+        }
+    }
+}
+
+type Fields = Vec<(FieldName, FieldValue)>;
+
+fn text(value: String) -> FieldValue {
+    FieldValue::Text(value)
+}
+
+// Counters are big-endian and as long as they need to be, four bytes at least
+fn number(bytes: &[u8]) -> Option<FieldValue> {
+    (bytes.len() <= 8).then(|| FieldValue::Number(bytes.iter().fold(0, |n, byte| (n << 8) | *byte as u64)))
+}
+
+fn user_defined(data: &[u8], name: FieldName) -> Option<Fields> {
+    let (encoding, rest) = data.split_first()?;
+    let (description, value) = read_terminated(*encoding, rest);
+    let value = match name {
+        FieldName::Url => read_terminated(0, value).0,
+        _ => text_from_bytes(*encoding, value),
+    };
+    Some(vec![(FieldName::Encoding, FieldValue::Encoding(*encoding)), (FieldName::Description, text(description)), (name, text(value))])
+}
+
+fn language_text(data: &[u8]) -> Option<Fields> {
+    let (encoding, rest) = data.split_first()?;
+    let language = Language::from_bytes_lossy(rest.get(..3)?.try_into().ok()?);
+    let (description, value) = read_terminated(*encoding, &rest[3..]);
+    Some(vec![
+        (FieldName::Encoding, FieldValue::Encoding(*encoding)),
+        (FieldName::Language, FieldValue::Language(language)),
+        (FieldName::Description, text(description)),
+        (FieldName::Text, text(text_from_bytes(*encoding, value))),
+    ])
+}
+
+fn picture(data: &[u8]) -> Option<Fields> {
+    let (encoding, rest) = data.split_first()?;
+    let (mime, rest) = read_terminated(0, rest);
+    let (picture_type, rest) = rest.split_first()?;
+    let (description, data) = read_terminated(*encoding, rest);
+    Some(vec![
+        (FieldName::Encoding, FieldValue::Encoding(*encoding)),
+        (FieldName::Mime, text(mime)),
+        (FieldName::PictureType, FieldValue::PictureType(PictureType::from_byte(*picture_type))),
+        (FieldName::Description, text(description)),
+        (FieldName::Data, FieldValue::Binary(data.to_vec())),
+    ])
+}
+
+fn object(data: &[u8]) -> Option<Fields> {
+    let (encoding, rest) = data.split_first()?;
+    let (mime, rest) = read_terminated(0, rest);
+    let (filename, rest) = read_terminated(*encoding, rest);
+    let (description, data) = read_terminated(*encoding, rest);
+    Some(vec![
+        (FieldName::Encoding, FieldValue::Encoding(*encoding)),
+        (FieldName::Mime, text(mime)),
+        (FieldName::Filename, text(filename)),
+        (FieldName::Description, text(description)),
+        (FieldName::Data, FieldValue::Binary(data.to_vec())),
+    ])
+}
+
+fn owned(data: &[u8], name: FieldName) -> Fields {
+    let (owner, rest) = read_terminated(0, data);
+    vec![(FieldName::Owner, text(owner)), (name, FieldValue::Binary(rest.to_vec()))]
+}
+
+fn popularimeter(data: &[u8]) -> Option<Fields> {
+    let (email, rest) = read_terminated(0, data);
+    let (rating, counter) = rest.split_first()?;
+    let mut fields = vec![(FieldName::Email, text(email)), (FieldName::Rating, FieldValue::Number(*rating as u64))];
+    if !counter.is_empty() {
+        fields.push((FieldName::Counter, number(counter)?));
+    }
+    Some(fields)
+}
+
+impl Frame {
+    // Known layouts split into their parts in frame order. Unknown and malformed frames come back
+    // as their data alone
+    pub fn fields(&self) -> Vec<(FieldName, FieldValue)> {
+        let data = self.data();
+        let fields = match (self.id().as_str(), self.category()) {
+            ("TXXX", _) => user_defined(data, FieldName::Text),
+            ("WXXX", _) => user_defined(data, FieldName::Url),
+            ("COMM" | "USLT", _) => language_text(data),
+            ("APIC", _) => picture(data),
+            ("GEOB", _) => object(data),
+            ("PRIV", _) => Some(owned(data, FieldName::Data)),
+            ("UFID", _) => Some(owned(data, FieldName::Identifier)),
+            ("POPM", _) => popularimeter(data),
+            ("PCNT", _) => number(data).map(|counter| vec![(FieldName::Counter, counter)]),
+            (_, Category::Text) => data.first().map(|encoding| {
+                let values = text_values(self).into_iter().map(|value| (FieldName::Text, text(value)));
+                [(FieldName::Encoding, FieldValue::Encoding(*encoding))].into_iter().chain(values).collect()
+            }),
+            (_, Category::Url) => Some(vec![(FieldName::Url, text(read_terminated(0, data).0))]),
+            (_, Category::Binary) => None,
+        };
+        fields.unwrap_or_else(|| vec![(FieldName::Data, FieldValue::Binary(data.to_vec()))])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tag;
+
+    #[test]
+    fn known_frames_split() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let album = tag.frame("TALB").unwrap().fields();
+        assert_eq!(album, [(FieldName::Encoding, FieldValue::Encoding(1)), (FieldName::Text, text("Polygondwanaland".to_string()))]);
+        let names: Vec<&str> = tag.frame("APIC").unwrap().fields().iter().map(|(name, _)| name.name()).collect();
+        assert_eq!(names, ["encoding", "mime", "picture_type", "description", "data"]);
+        assert_eq!(tag.frame("COMM").unwrap().fields()[1].0, FieldName::Language);
+
+        let rating = Frame::new("POPM", b"me@example.com\0\xC4\0\0\x01\0".to_vec()).unwrap();
+        let fields = rating.fields();
+        assert_eq!(fields[1..], [(FieldName::Rating, FieldValue::Number(196)), (FieldName::Counter, FieldValue::Number(256))]);
+        let values = Frame::new("TPE1", b"\x03A\0B".to_vec()).unwrap().fields();
+        assert_eq!(values.iter().map(|(_, value)| value.to_string()).collect::<Vec<_>>(), ["UTF-8", "A", "B"]);
+        assert_eq!(Frame::new("WXXX", b"\0home\0https://a.b".to_vec()).unwrap().fields()[2], (FieldName::Url, text("https://a.b".to_string())));
+        assert_eq!(Frame::new("COMM", vec![0, b'e']).unwrap().fields(), [(FieldName::Data, FieldValue::Binary(vec![0, b'e']))]);
+        assert_eq!(Frame::new("XYZW", vec![7]).unwrap().fields()[0].1.to_string(), "1 byte");
+    }
+}
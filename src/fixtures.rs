@@ -0,0 +1,269 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const TITLE: &str = "Crumbling Castle";
+pub const ARTIST: &str = "King Gizzard & The Lizard Wizard";
+pub const ALBUM: &str = "Polygondwanaland";
+pub const YEAR: &str = "2017";
+
+const LATIN1: u8 = 0x00;
+const UTF16: u8 = 0x01;
+
+static TEMP_PATHS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug)]
+pub enum Tagger {
+    ITunes,
+    Mp3tag,
+    EasyTag,
+    Lame,
+    WindowsMediaPlayer,
+}
+
+impl Tagger {
+    pub const ALL: [Tagger; 5] = [
+        Tagger::ITunes,
+        Tagger::Mp3tag,
+        Tagger::EasyTag,
+        Tagger::Lame,
+        Tagger::WindowsMediaPlayer,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tagger::ITunes => "itunes",
+            Tagger::Mp3tag => "mp3tag",
+            Tagger::EasyTag => "easytag",
+            Tagger::Lame => "lame",
+            Tagger::WindowsMediaPlayer => "wmp",
+        }
+    }
+
+    // The value each tagger stores in TRCK, which is what the parser must hand back
+    pub fn expected_track(&self) -> &'static str {
+        match self {
+            Tagger::ITunes => "1/7",
+            Tagger::Mp3tag => "1/7",
+            Tagger::EasyTag => "01",
+            Tagger::Lame => "1",
+            Tagger::WindowsMediaPlayer => "1",
+        }
+    }
+
+    // Ids of every frame in the order the tagger writes them
+    pub fn expected_ids(&self) -> Vec<&'static str> {
+        match self {
+            Tagger::ITunes => vec!["TIT2", "TPE1", "TALB", "TRCK", "TYER", "TCMP", "COMM"],
+            Tagger::Mp3tag => vec!["TIT2", "TPE1", "TALB", "TRCK", "TYER", "TXXX"],
+            Tagger::EasyTag => vec!["TIT2", "TPE1", "TALB", "TYER", "TRCK"],
+            Tagger::Lame => vec!["TSSE", "TIT2", "TPE1", "TALB", "TYER", "TRCK"],
+            Tagger::WindowsMediaPlayer => vec!["PRIV", "TIT2", "TPE1", "TALB", "TRCK", "TYER"],
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let track = self.expected_track();
+        match self {
+            // UTF-16 LE with BOM and terminators, iTunes-only TCMP frame, iTunNORM comment and generous padding
+            Tagger::ITunes => tag(None, 2048, &[
+                text_frame("TIT2", UTF16, &utf16_le(TITLE, true)),
+                text_frame("TPE1", UTF16, &utf16_le(ARTIST, true)),
+                text_frame("TALB", UTF16, &utf16_le(ALBUM, true)),
+                text_frame("TRCK", UTF16, &utf16_le(track, true)),
+                text_frame("TYER", UTF16, &utf16_le(YEAR, true)),
+                text_frame("TCMP", UTF16, &utf16_le("1", true)),
+                frame("COMM", &[
+                    &[UTF16][..],
+                    b"eng",
+                    &utf16_le("iTunNORM", true),
+                    &utf16_le(" 00000A2C 00000A2C 00003C6F", true),
+                ].concat()),
+            ]),
+            // UTF-16 LE with BOM but no terminators, user defined TXXX frame
+            Tagger::Mp3tag => tag(None, 1024, &[
+                text_frame("TIT2", UTF16, &utf16_le(TITLE, false)),
+                text_frame("TPE1", UTF16, &utf16_le(ARTIST, false)),
+                text_frame("TALB", UTF16, &utf16_le(ALBUM, false)),
+                text_frame("TRCK", UTF16, &utf16_le(track, false)),
+                text_frame("TYER", UTF16, &utf16_le(YEAR, false)),
+                frame("TXXX", &[&[UTF16][..], &utf16_le("RELEASETYPE", true), &utf16_le("album", false)].concat()),
+            ]),
+            // ISO-8859-1 without terminators, zero padded track numbers
+            Tagger::EasyTag => tag(None, 0, &[
+                text_frame("TIT2", LATIN1, &latin1(TITLE, false)),
+                text_frame("TPE1", LATIN1, &latin1(ARTIST, false)),
+                text_frame("TALB", LATIN1, &latin1(ALBUM, false)),
+                text_frame("TYER", LATIN1, &latin1(YEAR, false)),
+                text_frame("TRCK", LATIN1, &latin1(track, false)),
+            ]),
+            // ISO-8859-1 with terminators, encoder settings frame first and a small fixed padding
+            Tagger::Lame => tag(None, 128, &[
+                text_frame("TSSE", LATIN1, &latin1("LAME 64bits version 3.100 (http://lame.sf.net)", true)),
+                text_frame("TIT2", LATIN1, &latin1(TITLE, true)),
+                text_frame("TPE1", LATIN1, &latin1(ARTIST, true)),
+                text_frame("TALB", LATIN1, &latin1(ALBUM, true)),
+                text_frame("TYER", LATIN1, &latin1(YEAR, true)),
+                text_frame("TRCK", LATIN1, &latin1(track, true)),
+            ]),
+            // Extended header, binary PRIV frame before any text and UTF-16 BE with BOM
+            Tagger::WindowsMediaPlayer => tag(Some(512), 512, &[
+                frame("PRIV", &[&b"WM/MediaClassPrimaryID\0"[..], &[0xBC, 0x7D, 0x60, 0xD1, 0x23, 0xE3, 0xE2, 0x4B]].concat()),
+                text_frame("TIT2", UTF16, &utf16_be(TITLE)),
+                text_frame("TPE1", UTF16, &utf16_be(ARTIST)),
+                text_frame("TALB", UTF16, &utf16_be(ALBUM)),
+                text_frame("TRCK", UTF16, &utf16_be(track)),
+                text_frame("TYER", UTF16, &utf16_be(YEAR)),
+            ]),
+        }
+    }
+
+    pub fn write(&self) -> String {
+        let path = temp_path(&format!("fixture-{}.mp3", self.name()));
+        let mut bytes = self.build();
+        // A single silent MPEG frame header so the file looks like audio follows the tag
+        bytes.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        fs::write(&path, bytes).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+}
+
+// A path in the temp directory ending in the name. Every call gets its own since tests run in parallel
+pub fn temp_path(name: &str) -> PathBuf {
+    let count = TEMP_PATHS.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("mp3-tool-{}-{count}-{name}", std::process::id()))
+}
+
+pub fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = temp_path(&format!("{name}.mp3"));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+// The test file copied somewhere it can be edited
+pub fn copy_of_test_file(name: &str) -> PathBuf {
+    let path = temp_path(&format!("{name}.mp3"));
+    fs::copy("test/Polygondwanaland.mp3", &path).unwrap();
+    path
+}
+
+fn latin1(text: &str, terminated: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(text.chars().map(|c| c as u8));
+    if terminated {
+        bytes.push(0);
+    }
+    bytes
+}
+
+fn utf16_le(text: &str, terminated: bool) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(text.encode_utf16().flat_map(|c| c.to_le_bytes()));
+    if terminated {
+        bytes.extend_from_slice(&[0, 0]);
+    }
+    bytes
+}
+
+fn utf16_be(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(|c| c.to_be_bytes()));
+    bytes.extend_from_slice(&[0, 0]);
+    bytes
+}
+
+fn text_frame(id: &str, encoding: u8, text: &[u8]) -> Vec<u8> {
+    frame(id, &[&[encoding][..], text].concat())
+}
+
+fn frame(id: &str, data: &[u8]) -> Vec<u8> {
+    let mut bytes = id.as_bytes().to_vec();
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&[0x00, 0x00]);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+fn tag(extended_padding: Option<u32>, padding: usize, frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = Vec::new();
+    if let Some(extended_padding) = extended_padding {
+        body.extend_from_slice(&[0x00, 0x00, 0x00, 0x06, 0x00, 0x00]);
+        body.extend_from_slice(&extended_padding.to_be_bytes());
+    }
+    for frame in frames {
+        body.extend_from_slice(frame);
+    }
+    body.extend(std::iter::repeat_n(0, padding));
+
+    let size = body.len() as u32;
+    let flags = if extended_padding.is_some() { 0b_01000000 } else { 0 };
+    let mut bytes = vec![0x49, 0x44, 0x33, 0x03, 0x00, flags];
+    bytes.extend((0..4).map(|x| ((size >> (7*(3-x))) & 0x7F) as u8));
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+// The test file's audio as an internet radio station sends it, with a metadata block after every
+// metaint bytes and the title changing at the given blocks. Returns the recording and the audio
+pub fn stream_recording(metaint: usize, titles: &[(usize, &str)]) -> (Vec<u8>, Vec<u8>) {
+    let audio = fs::read("test/Polygondwanaland.mp3").unwrap()[187217..].to_vec();
+    let mut data = Vec::new();
+    let mut current = "";
+    for (block, chunk) in audio.chunks(metaint).enumerate() {
+        data.extend_from_slice(chunk);
+        if chunk.len() < metaint {
+            break;
+        }
+        current = titles.iter().find(|(at, _)| *at == block).map(|(_, title)| *title).unwrap_or(current);
+        let mut metadata = if current.is_empty() { Vec::new() } else { format!("StreamTitle='{current}';StreamUrl='';").into_bytes() };
+        metadata.resize(metadata.len().div_ceil(16) * 16, 0);
+        data.push((metadata.len() / 16) as u8);
+        data.extend(metadata);
+    }
+    (data, audio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tag;
+
+    fn read(tagger: Tagger) -> Tag {
+        let path = tagger.write();
+        let tag = Tag::from_file(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        tag
+    }
+
+    #[test]
+    fn frame_order_matches_tagger() {
+        for tagger in Tagger::ALL {
+            let tag = read(tagger);
+            let ids: Vec<String> = tag.frames().iter().map(|frame| frame.id()).collect();
+            assert_eq!(ids, tagger.expected_ids(), "{tagger:?}");
+        }
+    }
+
+    #[test]
+    fn text_matches_tagger() {
+        for tagger in Tagger::ALL {
+            let tag = read(tagger);
+            let text = |id| tag.frame(id).unwrap().parse_text();
+            assert_eq!(text("TIT2"), TITLE, "{tagger:?}");
+            assert_eq!(text("TPE1"), ARTIST, "{tagger:?}");
+            assert_eq!(text("TALB"), ALBUM, "{tagger:?}");
+            assert_eq!(text("TYER"), YEAR, "{tagger:?}");
+            assert_eq!(text("TRCK"), tagger.expected_track(), "{tagger:?}");
+        }
+    }
+
+    #[test]
+    fn extended_header_only_from_wmp() {
+        for tagger in Tagger::ALL {
+            let tag = read(tagger);
+            let expected = matches!(tagger, Tagger::WindowsMediaPlayer);
+            assert_eq!(tag.extended_header().is_some(), expected, "{tagger:?}");
+        }
+    }
+}
+
@@ -0,0 +1,25 @@
+mod aenc;
+mod chapter;
+mod chapters;
+mod comment;
+mod equalisation;
+mod group;
+mod link;
+mod mcdi;
+mod picture;
+mod sign;
+mod user;
+
+pub use aenc::AudioEncryption;
+pub use chapter::{Chapter, ChapterOptions, TableOfContents};
+pub use chapters::Chapters;
+pub(crate) use chapter::convert_embedded;
+pub use comment::{Comment, Lyrics};
+pub use equalisation::{Equalisation, Interpolation};
+pub use group::GroupRegistration;
+pub use link::Link;
+pub use mcdi::CdToc;
+pub use picture::{Picture, PictureType, mime_type};
+pub(crate) use picture::{format_mime, image_format};
+pub use sign::Signature;
+pub use user::{DuplicatePolicy, UserLink, UserText};
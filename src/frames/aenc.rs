@@ -0,0 +1,126 @@
+use crate::{Frame, Tag};
+
+pub struct AudioEncryption {
+    owner: String,
+    preview_start: u16,
+    preview_length: u16,
+    encryption_info: Vec<u8>,
+}
+
+impl AudioEncryption {
+    pub fn new(owner: &str, preview_start: u16, preview_length: u16, encryption_info: Vec<u8>) -> Self {
+        Self {
+            owner: owner.to_string(),
+            preview_start,
+            preview_length,
+            encryption_info,
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "AENC" {
+            return None;
+        }
+
+        // Owner is a terminated Latin-1 string followed by two 16 bit preview fields
+        let data = frame.data();
+        let end = data.iter().position(|x| *x == 0)?;
+        if data.len() < end + 5 {
+            return None;
+        }
+
+        Some(Self {
+            owner: data[..end].iter().map(|x| *x as char).collect(),
+            preview_start: u16::from_be_bytes([data[end+1], data[end+2]]),
+            preview_length: u16::from_be_bytes([data[end+3], data[end+4]]),
+            encryption_info: data[end+5..].to_vec(),
+        })
+    }
+
+    // None when the owner has a NUL or characters Latin-1 can't hold
+    pub fn to_frame(&self) -> Option<Frame> {
+        if self.owner.chars().any(|c| c as u32 > 0xFF || c == '\0') {
+            return None;
+        }
+        let mut data: Vec<u8> = self.owner.chars().map(|c| c as u8).collect();
+        data.push(0);
+        data.extend_from_slice(&self.preview_start.to_be_bytes());
+        data.extend_from_slice(&self.preview_length.to_be_bytes());
+        data.extend_from_slice(&self.encryption_info);
+        Frame::new("AENC", data)
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    // Preview start and length are counted in MPEG frames
+    pub fn preview_start(&self) -> u16 {
+        self.preview_start
+    }
+
+    pub fn preview_length(&self) -> u16 {
+        self.preview_length
+    }
+
+    pub fn has_preview(&self) -> bool {
+        self.preview_length > 0
+    }
+
+    pub fn encryption_info(&self) -> &[u8] {
+        &self.encryption_info
+    }
+}
+
+impl Tag {
+    pub fn audio_encryption(&self) -> Vec<AudioEncryption> {
+        self.frames().iter().filter_map(AudioEncryption::from_frame).collect()
+    }
+
+    pub fn is_audio_encrypted(&self) -> bool {
+        self.frames().iter().any(|frame| frame.id() == "AENC")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_aenc() {
+        let frame = Frame::new("AENC", b"mailto:drm@example.com\0\x00\x10\x00\x20\xDE\xAD".to_vec()).unwrap();
+        let aenc = AudioEncryption::from_frame(&frame).unwrap();
+        assert_eq!(aenc.owner(), "mailto:drm@example.com");
+        assert_eq!((aenc.preview_start(), aenc.preview_length()), (16, 32));
+        assert_eq!(aenc.encryption_info(), [0xDE, 0xAD]);
+    }
+
+    #[test]
+    fn aenc_round_trip() {
+        let aenc = AudioEncryption::new("owner", 0, 0, vec![1, 2, 3]);
+        let parsed = AudioEncryption::from_frame(&aenc.to_frame().unwrap()).unwrap();
+        assert_eq!(parsed.owner(), "owner");
+        assert!(!parsed.has_preview());
+        assert_eq!(parsed.encryption_info(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn owner_must_be_latin1() {
+        assert!(AudioEncryption::new("café", 0, 0, Vec::new()).to_frame().is_some());
+        assert!(AudioEncryption::new("日本", 0, 0, Vec::new()).to_frame().is_none());
+        assert!(AudioEncryption::new("a\0b", 0, 0, Vec::new()).to_frame().is_none());
+    }
+
+    #[test]
+    fn truncated_aenc() {
+        let frame = Frame::new("AENC", b"owner\0\x00".to_vec()).unwrap();
+        assert!(AudioEncryption::from_frame(&frame).is_none());
+    }
+
+    #[test]
+    fn unencrypted_file() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert!(!tag.is_audio_encrypted());
+        assert!(tag.audio_encryption().is_empty());
+    }
+}
@@ -0,0 +1,491 @@
+use crate::convert::text_frame;
+use crate::diagnostics::{Diagnostics, Finding};
+use crate::wire;
+use crate::{Frame, Reader, Tag};
+use std::io::Cursor;
+
+// ID3v2 Chapter Frame Addendum: https://id3.org/id3v2-chapters-1.0
+
+// Bounds on the sub-frames read from CHAP and CTOC frames, which can hold further CHAP and
+// CTOC frames of their own. Whatever goes past a limit is left out and reported
+#[derive(Clone, Debug)]
+pub struct ChapterOptions {
+    max_depth: usize,
+    max_sub_frames: usize,
+    diagnostics: Option<Diagnostics>,
+}
+
+impl ChapterOptions {
+    pub fn new() -> Self {
+        Self {
+            max_depth: 4,
+            max_sub_frames: 256,
+            diagnostics: None,
+        }
+    }
+
+    // Levels of CHAP and CTOC frames inside one another, 0 leaves every embedded one out
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    // Sub-frames kept for each chapter or table of contents
+    pub fn max_sub_frames(mut self, count: usize) -> Self {
+        self.max_sub_frames = count;
+        self
+    }
+
+    pub fn diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    fn skip(&self, id: &str, reason: String) {
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.report(Finding::SkippedFrame { id: id.to_string(), reason });
+        }
+    }
+}
+
+impl Default for ChapterOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Element ids are terminated Latin-1 strings
+fn read_element_id(data: &[u8]) -> Option<(String, &[u8])> {
+    let end = data.iter().position(|x| *x == 0)?;
+    Some((data[..end].iter().map(|x| *x as char).collect(), &data[end + 1..]))
+}
+
+// None when the id has a NUL or characters Latin-1 can't hold
+fn write_element_id(data: &mut Vec<u8>, element_id: &str) -> Option<()> {
+    if element_id.chars().any(|c| c as u32 > 0xFF || c == '\0') {
+        return None;
+    }
+    data.extend(element_id.chars().map(|c| c as u8));
+    data.push(0);
+    Some(())
+}
+
+// Frames embedded after the fixed fields, laid out like the frames of the tag itself. Sizes are
+// checked against what is left before anything is read, so a bad one can't run past the frame
+fn read_sub_frames(data: &[u8], major_ver: u8, owner: &str, depth: usize, options: &ChapterOptions) -> Option<Vec<Frame>> {
+    let header_len = wire::header_len(major_ver);
+    let mut rest = data;
+    let mut frames = Vec::new();
+    while rest.len() >= header_len && rest[0] != 0 {
+        let size = wire::body_size(&rest[..header_len], major_ver) as usize;
+        let Some(bytes) = rest.get(..header_len + size) else {
+            options.skip(owner, format!("sub-frame runs {} bytes past the end", header_len + size - rest.len()));
+            return None;
+        };
+        rest = &rest[header_len + size..];
+        let frame = wire::id(bytes, major_ver)
+            .filter(|id| id.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit()))
+            .and_then(|_| Frame::from_reader(&mut Reader::from_stream(Cursor::new(bytes.to_vec())), major_ver).ok());
+        let Some(frame) = frame else {
+            options.skip(owner, format!("malformed sub-frame {}", String::from_utf8_lossy(&bytes[..header_len.min(4)])));
+            return None;
+        };
+
+        if frames.len() == options.max_sub_frames {
+            options.skip(&frame.id(), format!("{owner} has more than {} sub-frames", options.max_sub_frames));
+            continue;
+        }
+        // Embedded chapters are written again from what could be read of them within the limits
+        let frame = match frame.id().as_str() {
+            "CHAP" | "CTOC" if depth >= options.max_depth => {
+                options.skip(&frame.id(), format!("nested more than {} levels deep in {owner}", options.max_depth));
+                continue;
+            }
+            "CHAP" => Chapter::read(&frame, major_ver, depth + 1, options).and_then(|chapter| chapter.to_frame(major_ver)),
+            "CTOC" => TableOfContents::read(&frame, major_ver, depth + 1, options).and_then(|toc| toc.to_frame(major_ver)),
+            _ => Some(frame),
+        };
+        frames.extend(frame);
+    }
+    Some(frames)
+}
+
+fn title_of(frames: &[Frame]) -> Option<String> {
+    frames.iter().find(|frame| frame.id() == "TIT2").map(Frame::parse_text)
+}
+
+fn set_title(frames: &mut Vec<Frame>, title: &str, major_ver: u8) {
+    frames.retain(|frame| frame.id() != "TIT2");
+    frames.extend(text_frame("TIT2", &[title.to_string()], major_ver));
+}
+
+#[derive(Clone)]
+pub struct Chapter {
+    element_id: String,
+    start: u32,
+    end: u32,
+    start_offset: Option<u32>,
+    end_offset: Option<u32>,
+    frames: Vec<Frame>,
+}
+
+impl Chapter {
+    // Times are in milliseconds
+    pub fn new(element_id: &str, start: u32, end: u32) -> Self {
+        Self {
+            element_id: element_id.to_string(),
+            start,
+            end,
+            start_offset: None,
+            end_offset: None,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame, major_ver: u8) -> Option<Self> {
+        Self::from_frame_with(frame, major_ver, &ChapterOptions::new())
+    }
+
+    pub fn from_frame_with(frame: &Frame, major_ver: u8, options: &ChapterOptions) -> Option<Self> {
+        Self::read(frame, major_ver, 0, options)
+    }
+
+    fn read(frame: &Frame, major_ver: u8, depth: usize, options: &ChapterOptions) -> Option<Self> {
+        if frame.id() != "CHAP" {
+            return None;
+        }
+        let (element_id, data) = read_element_id(frame.data())?;
+        if data.len() < 16 {
+            return None;
+        }
+        let field = |i: usize| u32::from_be_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+
+        // All ones means the byte offsets aren't used
+        let offset = |i: usize| Some(field(i)).filter(|x| *x != u32::MAX);
+        let frames = read_sub_frames(&data[16..], major_ver, &element_id, depth, options)?;
+        Some(Self {
+            element_id,
+            start: field(0),
+            end: field(1),
+            start_offset: offset(2),
+            end_offset: offset(3),
+            frames,
+        })
+    }
+
+    pub fn to_frame(&self, major_ver: u8) -> Option<Frame> {
+        let mut data = Vec::new();
+        write_element_id(&mut data, &self.element_id)?;
+        for field in [self.start, self.end, self.start_offset.unwrap_or(u32::MAX), self.end_offset.unwrap_or(u32::MAX)] {
+            data.extend_from_slice(&field.to_be_bytes());
+        }
+        for frame in &self.frames {
+            data.extend(frame.to_bytes(major_ver));
+        }
+        Frame::new("CHAP", data)
+    }
+
+    pub fn element_id(&self) -> &str {
+        &self.element_id
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    pub fn offsets(&self) -> Option<(u32, u32)> {
+        self.start_offset.zip(self.end_offset)
+    }
+
+    // Byte offsets are dropped, they no longer point at the new times
+    pub fn set_range(&mut self, start: u32, end: u32) {
+        if (start, end) != (self.start, self.end) {
+            (self.start, self.end) = (start, end);
+            (self.start_offset, self.end_offset) = (None, None);
+        }
+    }
+
+    pub fn set_element_id(&mut self, element_id: &str) {
+        self.element_id = element_id.to_string();
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn add_frame(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    pub fn title(&self) -> Option<String> {
+        title_of(&self.frames)
+    }
+
+    // The TIT2 sub-frame is encoded for the version the tag will be written as
+    pub fn set_title(&mut self, title: &str, major_ver: u8) {
+        set_title(&mut self.frames, title, major_ver);
+    }
+}
+
+#[derive(Clone)]
+pub struct TableOfContents {
+    element_id: String,
+    top_level: bool,
+    ordered: bool,
+    children: Vec<String>,
+    frames: Vec<Frame>,
+}
+
+impl TableOfContents {
+    pub fn new(element_id: &str, children: Vec<String>) -> Self {
+        Self {
+            element_id: element_id.to_string(),
+            top_level: false,
+            ordered: true,
+            children,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn top_level(mut self, top_level: bool) -> Self {
+        self.top_level = top_level;
+        self
+    }
+
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    pub fn from_frame(frame: &Frame, major_ver: u8) -> Option<Self> {
+        Self::from_frame_with(frame, major_ver, &ChapterOptions::new())
+    }
+
+    pub fn from_frame_with(frame: &Frame, major_ver: u8, options: &ChapterOptions) -> Option<Self> {
+        Self::read(frame, major_ver, 0, options)
+    }
+
+    fn read(frame: &Frame, major_ver: u8, depth: usize, options: &ChapterOptions) -> Option<Self> {
+        if frame.id() != "CTOC" {
+            return None;
+        }
+        let (element_id, data) = read_element_id(frame.data())?;
+        let (flags, count) = (*data.first()?, *data.get(1)?);
+        let mut data = &data[2..];
+        let mut children = Vec::new();
+        for _ in 0..count {
+            let (child, rest) = read_element_id(data)?;
+            children.push(child);
+            data = rest;
+        }
+        let frames = read_sub_frames(data, major_ver, &element_id, depth, options)?;
+        Some(Self {
+            element_id,
+            top_level: flags & 0b10 != 0,
+            ordered: flags & 0b01 != 0,
+            children,
+            frames,
+        })
+    }
+
+    pub fn to_frame(&self, major_ver: u8) -> Option<Frame> {
+        if self.children.len() > 255 {
+            return None;
+        }
+        let mut data = Vec::new();
+        write_element_id(&mut data, &self.element_id)?;
+        data.push((self.top_level as u8) << 1 | self.ordered as u8);
+        data.push(self.children.len() as u8);
+        for child in &self.children {
+            write_element_id(&mut data, child)?;
+        }
+        for frame in &self.frames {
+            data.extend(frame.to_bytes(major_ver));
+        }
+        Frame::new("CTOC", data)
+    }
+
+    pub fn element_id(&self) -> &str {
+        &self.element_id
+    }
+
+    pub fn is_top_level(&self) -> bool {
+        self.top_level
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    pub fn children(&self) -> &[String] {
+        &self.children
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn set_children(&mut self, children: Vec<String>) {
+        self.children = children;
+    }
+
+    pub fn title(&self) -> Option<String> {
+        title_of(&self.frames)
+    }
+
+    pub fn set_title(&mut self, title: &str, major_ver: u8) {
+        set_title(&mut self.frames, title, major_ver);
+    }
+}
+
+// Re-lays out the embedded frames of a CHAP or CTOC frame for another version
+pub(crate) fn convert_embedded(frame: &Frame, major_ver: u8, target: u8, mut convert: impl FnMut(&Frame) -> Option<Frame>) -> Option<Frame> {
+    if let Some(mut chapter) = Chapter::from_frame(frame, major_ver) {
+        chapter.frames = chapter.frames.iter().filter_map(&mut convert).collect();
+        return chapter.to_frame(target);
+    }
+    let mut toc = TableOfContents::from_frame(frame, major_ver)?;
+    toc.frames = toc.frames.iter().filter_map(&mut convert).collect();
+    toc.to_frame(target)
+}
+
+impl Tag {
+    pub fn chapters(&self) -> Vec<Chapter> {
+        self.chapters_with(&ChapterOptions::new())
+    }
+
+    pub fn chapters_with(&self, options: &ChapterOptions) -> Vec<Chapter> {
+        self.frames().iter().filter_map(|frame| Chapter::from_frame_with(frame, self.version(), options)).collect()
+    }
+
+    pub fn tables_of_contents(&self) -> Vec<TableOfContents> {
+        self.tables_of_contents_with(&ChapterOptions::new())
+    }
+
+    pub fn tables_of_contents_with(&self, options: &ChapterOptions) -> Vec<TableOfContents> {
+        self.frames().iter().filter_map(|frame| TableOfContents::from_frame_with(frame, self.version(), options)).collect()
+    }
+
+    // Replaces every chapter and table of contents with the chapters in order under one top level table
+    pub fn set_chapters(&mut self, chapters: &[Chapter]) {
+        let version = self.version();
+        self.frames_mut().retain(|frame| frame.id() != "CHAP" && frame.id() != "CTOC");
+        if chapters.is_empty() {
+            return;
+        }
+        let children = chapters.iter().map(|chapter| chapter.element_id.clone()).collect();
+        self.frames_mut().extend(TableOfContents::new("toc", children).top_level(true).to_frame(version));
+        self.frames_mut().extend(chapters.iter().filter_map(|chapter| chapter.to_frame(version)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapter_round_trip() {
+        for version in [3, 4] {
+            let mut chapter = Chapter::new("chp0", 0, 213024);
+            chapter.set_title("Crumbling Castle", version);
+            let read = Chapter::from_frame(&chapter.to_frame(version).unwrap(), version).unwrap();
+            assert_eq!((read.start(), read.end(), read.offsets()), (0, 213024, None));
+            assert_eq!(read.title().as_deref(), Some("Crumbling Castle"));
+        }
+    }
+
+    #[test]
+    fn table_of_contents_round_trip() {
+        let toc = TableOfContents::new("toc", vec!["chp0".to_string(), "chp1".to_string()]).top_level(true);
+        let read = TableOfContents::from_frame(&toc.to_frame(4).unwrap(), 4).unwrap();
+        assert!(read.is_top_level() && read.is_ordered());
+        assert_eq!(read.children(), ["chp0", "chp1"]);
+    }
+
+    #[test]
+    fn element_ids_must_be_latin1() {
+        assert!(Chapter::new("chp\0", 0, 1000).to_frame(4).is_none());
+        assert!(Chapter::new("章", 0, 1000).to_frame(4).is_none());
+        assert!(TableOfContents::new("toc", vec!["章".to_string()]).to_frame(4).is_none());
+    }
+
+    #[test]
+    fn set_chapters_on_tag() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let chapters = vec![Chapter::new("chp0", 0, 1000), Chapter::new("chp1", 1000, 2000)];
+        tag.set_chapters(&chapters);
+        let tag = Tag::from_reader(&mut Reader::from_stream(Cursor::new(tag.to_bytes(0)))).unwrap();
+        let read: Vec<_> = tag.chapters().iter().map(|chapter| (chapter.element_id().to_string(), chapter.start(), chapter.end())).collect();
+        assert_eq!(read, [("chp0".to_string(), 0, 1000), ("chp1".to_string(), 1000, 2000)]);
+        assert_eq!(tag.tables_of_contents()[0].children(), ["chp0", "chp1"]);
+    }
+
+    #[test]
+    fn converts_embedded_frames() {
+        let mut tag = Tag::new(3);
+        let mut chapter = Chapter::new("chp0", 0, 1000);
+        chapter.set_title("Crumbling Castle", 3);
+        tag.set_chapters(&[chapter]);
+
+        let (converted, report) = tag.convert(4);
+        assert!(report.is_lossless());
+        assert_eq!(converted.chapters()[0].title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(converted.tables_of_contents()[0].children(), ["chp0"]);
+    }
+
+    #[test]
+    fn truncated_sub_frames() {
+        let mut chapter = Chapter::new("chp0", 0, 1000);
+        chapter.set_title("Crumbling Castle", 3);
+        let frame = chapter.to_frame(3).unwrap();
+        let cut = Frame::new("CHAP", frame.data()[..frame.data().len() - 4].to_vec()).unwrap();
+        assert!(Chapter::from_frame(&cut, 3).is_none());
+
+        // A sub-frame claiming nearly 2 GB fails before anything that size is read
+        let data = [b"chp0\x00".as_slice(), &[0; 16], b"TIT2\x7F\xFF\xFF\xFF\x00\x00\x00Castle"].concat();
+        let diagnostics = Diagnostics::new();
+        let options = ChapterOptions::new().diagnostics(diagnostics.clone());
+        assert!(Chapter::from_frame_with(&Frame::new("CHAP", data).unwrap(), 3, &options).is_none());
+        assert_eq!(diagnostics.findings()[0].to_string(), "Skipped frame chp0: sub-frame runs 2147483640 bytes past the end");
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        // Five chapters each inside the one before
+        let mut frame = Chapter::new("chp5", 0, 1000).to_frame(4).unwrap();
+        for level in (0..5).rev() {
+            let mut chapter = Chapter::new(&format!("chp{level}"), 0, 1000);
+            chapter.set_title("Crumbling Castle", 4);
+            chapter.add_frame(frame);
+            frame = chapter.to_frame(4).unwrap();
+        }
+
+        let depth = |chapter: &Chapter| {
+            let mut depth = 0;
+            let mut frames = chapter.frames().to_vec();
+            while let Some(nested) = frames.iter().find_map(|frame| Chapter::from_frame_with(frame, 4, &ChapterOptions::new().max_depth(usize::MAX))) {
+                depth += 1;
+                frames = nested.frames().to_vec();
+            }
+            depth
+        };
+        assert_eq!(depth(&Chapter::from_frame_with(&frame, 4, &ChapterOptions::new().max_depth(10)).unwrap()), 5);
+
+        let diagnostics = Diagnostics::new();
+        let options = ChapterOptions::new().max_depth(2).max_sub_frames(1).diagnostics(diagnostics.clone());
+        let chapter = Chapter::from_frame_with(&frame, 4, &options).unwrap();
+        assert_eq!(chapter.frames().len(), 1);
+        assert!(chapter.title().is_some());
+        let findings: Vec<String> = diagnostics.findings().iter().map(|finding| finding.to_string()).collect();
+        assert!(findings.contains(&"Skipped frame CHAP: chp0 has more than 1 sub-frames".to_string()));
+
+        let diagnostics = Diagnostics::new();
+        let options = ChapterOptions::new().max_depth(2).diagnostics(diagnostics.clone());
+        assert_eq!(depth(&Chapter::from_frame_with(&frame, 4, &options).unwrap()), 2);
+        assert_eq!(diagnostics.findings()[0].to_string(), "Skipped frame CHAP: nested more than 2 levels deep in chp2");
+    }
+}
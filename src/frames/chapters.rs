@@ -0,0 +1,176 @@
+use crate::frames::{Chapter, TableOfContents};
+use crate::Tag;
+
+// The chapters of a tag edited as one ordered list and written back under a single top level
+// table of contents that lists them in order. Times are in milliseconds
+#[derive(Clone, Default)]
+pub struct Chapters {
+    chapters: Vec<Chapter>,
+    // The top level table read from the tag, kept for its element id and sub-frames
+    toc: Option<TableOfContents>,
+    duration: Option<u32>,
+}
+
+impl Chapters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // In the order the top level table of contents lists them, then the rest by start time
+    pub fn from_tag(tag: &Tag) -> Self {
+        let toc = tag.tables_of_contents().into_iter().find(|toc| toc.is_top_level());
+        let mut chapters = tag.chapters();
+        let position = |chapter: &Chapter| {
+            let listed = toc.as_ref().and_then(|toc| toc.children().iter().position(|child| child == chapter.element_id()));
+            (listed.unwrap_or(usize::MAX), chapter.start())
+        };
+        chapters.sort_by_key(position);
+        Self { chapters, toc, duration: None }
+    }
+
+    // Length of the audio, see analyze::analyze. fix_ranges keeps every chapter inside it
+    pub fn duration(mut self, duration_ms: u32) -> Self {
+        self.duration = Some(duration_ms);
+        self
+    }
+
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Chapter> {
+        self.chapters.get_mut(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chapters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chapters.is_empty()
+    }
+
+    pub fn push(&mut self, chapter: Chapter) {
+        self.chapters.push(chapter);
+    }
+
+    // An index past the end adds the chapter at the end
+    pub fn insert(&mut self, index: usize, chapter: Chapter) {
+        self.chapters.insert(index.min(self.chapters.len()), chapter);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Chapter> {
+        (index < self.chapters.len()).then(|| self.chapters.remove(index))
+    }
+
+    // False when either index is out of range
+    pub fn move_chapter(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.chapters.len() || to >= self.chapters.len() {
+            return false;
+        }
+        let chapter = self.chapters.remove(from);
+        self.chapters.insert(to, chapter);
+        true
+    }
+
+    // By start time, chapters starting together keep their order
+    pub fn sort(&mut self) {
+        self.chapters.sort_by_key(|chapter| chapter.start());
+    }
+
+    // Sorts the chapters and ends each one where the next starts if they overlap. An end before
+    // the start is moved to the next start, or the end of the audio for the last chapter. With a
+    // duration, chapters starting after the audio are removed and the rest cut to fit it.
+    // Returns how many chapters were changed or removed
+    pub fn fix_ranges(&mut self) -> usize {
+        self.sort();
+        let duration = self.duration.unwrap_or(u32::MAX);
+        let before = self.chapters.len();
+        self.chapters.retain(|chapter| chapter.start() < duration || chapter.start() == 0);
+        let mut changed = before - self.chapters.len();
+
+        let starts: Vec<u32> = self.chapters.iter().map(|chapter| chapter.start()).collect();
+        for (i, chapter) in self.chapters.iter_mut().enumerate() {
+            let limit = starts.get(i + 1).copied().unwrap_or(duration);
+            let mut end = chapter.end().min(limit);
+            if end < chapter.start() {
+                end = if limit == u32::MAX { chapter.start() } else { limit };
+            }
+            if end != chapter.end() {
+                chapter.set_range(chapter.start(), end);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    // Element ids become chp0, chp1 and so on in list order
+    pub fn renumber(&mut self) {
+        for (i, chapter) in self.chapters.iter_mut().enumerate() {
+            chapter.set_element_id(&format!("chp{i}"));
+        }
+    }
+
+    // Replaces every chapter and table of contents in the tag, like Tag::set_chapters, keeping
+    // the id and sub-frames of the top level table the chapters were read with
+    pub fn apply(&self, tag: &mut Tag) {
+        let version = tag.version();
+        tag.frames_mut().retain(|frame| frame.id() != "CHAP" && frame.id() != "CTOC");
+        if self.chapters.is_empty() {
+            return;
+        }
+        let mut toc = self.toc.clone().unwrap_or_else(|| TableOfContents::new("toc", Vec::new())).top_level(true).ordered(true);
+        toc.set_children(self.chapters.iter().map(|chapter| chapter.element_id().to_string()).collect());
+        tag.frames_mut().extend(toc.to_frame(version));
+        tag.frames_mut().extend(self.chapters.iter().filter_map(|chapter| chapter.to_frame(version)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(chapters: &Chapters) -> Vec<(String, u32, u32)> {
+        chapters.chapters().iter().map(|chapter| (chapter.element_id().to_string(), chapter.start(), chapter.end())).collect()
+    }
+
+    #[test]
+    fn edit_and_apply() {
+        let mut tag = Tag::new(4);
+        let mut toc = TableOfContents::new("contents", vec!["b".to_string(), "a".to_string()]).top_level(true);
+        toc.set_title("Polygondwanaland", 4);
+        tag.add_frame(toc.to_frame(4).unwrap());
+        tag.add_frame(Chapter::new("a", 5000, 9000).to_frame(4).unwrap());
+        tag.add_frame(Chapter::new("b", 0, 5000).to_frame(4).unwrap());
+
+        let mut chapters = Chapters::from_tag(&tag);
+        assert_eq!(ranges(&chapters), [("b".to_string(), 0, 5000), ("a".to_string(), 5000, 9000)]);
+        chapters.insert(9, Chapter::new("c", 9000, 12000));
+        assert!(chapters.move_chapter(2, 0) && !chapters.move_chapter(3, 0));
+        assert_eq!(chapters.remove(1).unwrap().element_id(), "b");
+        chapters.renumber();
+        chapters.apply(&mut tag);
+
+        let toc = &tag.tables_of_contents()[0];
+        assert_eq!((toc.element_id(), toc.title().as_deref()), ("contents", Some("Polygondwanaland")));
+        assert_eq!(toc.children(), ["chp0", "chp1"]);
+        assert_eq!(ranges(&Chapters::from_tag(&tag)), [("chp0".to_string(), 9000, 12000), ("chp1".to_string(), 5000, 9000)]);
+    }
+
+    #[test]
+    fn ranges_fixed_to_the_duration() {
+        let mut chapters = Chapters::new().duration(10000);
+        chapters.push(Chapter::new("b", 4000, 20000));
+        chapters.push(Chapter::new("a", 0, 6000));
+        chapters.push(Chapter::new("c", 8000, 0));
+        chapters.push(Chapter::new("d", 12000, 13000));
+        assert_eq!(chapters.fix_ranges(), 4);
+        assert_eq!(ranges(&chapters), [("a".to_string(), 0, 4000), ("b".to_string(), 4000, 8000), ("c".to_string(), 8000, 10000)]);
+        assert_eq!(chapters.fix_ranges(), 0);
+
+        let mut open = Chapters::new();
+        open.push(Chapter::new("a", 3000, 1000));
+        assert_eq!(open.fix_ranges(), 1);
+        assert_eq!(ranges(&open), [("a".to_string(), 3000, 3000)]);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::ID3::{bytes_from_text, encoding_for, read_terminated, text_from_bytes, write_terminated};
+use crate::language::Language;
+use crate::{Frame, Tag};
+
+// COMM and USLT share a layout: encoding, language, terminated description then the text
+fn parse(frame: &Frame, id: &str) -> Option<(Language, String, String)> {
+    if frame.id() != id || frame.data().len() < 4 {
+        return None;
+    }
+
+    let data = frame.data();
+    let encoding = data[0];
+    let language = Language::from_bytes_lossy([data[1], data[2], data[3]]);
+    let (description, text) = read_terminated(encoding, &data[4..]);
+    Some((language, description, text_from_bytes(encoding, text)))
+}
+
+fn build(id: &str, language: Language, description: &str, text: &str) -> Option<Frame> {
+    // Latin-1 when possible, otherwise UTF-16 which every version can read
+    let encoding = encoding_for(&format!("{description}{text}"), 3);
+
+    let mut data = vec![encoding];
+    data.extend_from_slice(&language.bytes());
+    write_terminated(&mut data, encoding, description);
+    data.extend(bytes_from_text(encoding, text));
+    Frame::new(id, data)
+}
+
+pub struct Comment {
+    language: Language,
+    description: String,
+    text: String,
+}
+
+impl Comment {
+    pub fn new(language: Language, description: &str, text: &str) -> Self {
+        Self {
+            language,
+            description: description.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        let (language, description, text) = parse(frame, "COMM")?;
+        Some(Self { language, description, text })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        build("COMM", self.language, &self.description, &self.text)
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+pub struct Lyrics {
+    language: Language,
+    description: String,
+    text: String,
+}
+
+impl Lyrics {
+    pub fn new(language: Language, description: &str, text: &str) -> Self {
+        Self {
+            language,
+            description: description.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        let (language, description, text) = parse(frame, "USLT")?;
+        Some(Self { language, description, text })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        build("USLT", self.language, &self.description, &self.text)
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Frame {
+    // Language of frames that carry one, invalid codes come back as undetermined
+    pub fn language(&self) -> Option<Language> {
+        let data = self.data();
+        match self.id().as_str() {
+            "COMM" | "USLT" | "SYLT" | "USER" if data.len() >= 4 => {
+                Some(Language::from_bytes_lossy([data[1], data[2], data[3]]))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Tag {
+    pub fn comments(&self) -> Vec<Comment> {
+        self.frames().iter().filter_map(Comment::from_frame).collect()
+    }
+
+    pub fn comments_in(&self, language: Language) -> Vec<Comment> {
+        self.comments().into_iter().filter(|comment| comment.language == language).collect()
+    }
+
+    pub fn lyrics(&self) -> Vec<Lyrics> {
+        self.frames().iter().filter_map(Lyrics::from_frame).collect()
+    }
+
+    pub fn lyrics_in(&self, language: Language) -> Vec<Lyrics> {
+        self.lyrics().into_iter().filter(|lyrics| lyrics.language == language).collect()
+    }
+
+    pub fn frames_in(&self, language: Language) -> Vec<&Frame> {
+        self.frames().iter().filter(|frame| frame.language() == Some(language)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_comment() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let comments = tag.comments_in(Language::ENG);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].description(), "");
+        assert!(comments[0].text().starts_with("Visit https://"));
+        assert!(tag.comments_in(Language::DEU).is_empty());
+    }
+
+    #[test]
+    fn comment_round_trip() {
+        let comment = Comment::new(Language::SWE, "Recension", "Smörgåsbord");
+        let parsed = Comment::from_frame(&comment.to_frame().unwrap()).unwrap();
+        assert_eq!((parsed.language(), parsed.description(), parsed.text()), (Language::SWE, "Recension", "Smörgåsbord"));
+    }
+
+    #[test]
+    fn utf16_lyrics_round_trip() {
+        let lyrics = Lyrics::new(Language::JPN, "", "こんにちは");
+        let frame = lyrics.to_frame().unwrap();
+        assert_eq!(frame.data()[0], 1);
+        assert_eq!(Lyrics::from_frame(&frame).unwrap().text(), "こんにちは");
+    }
+
+    #[test]
+    fn invalid_language_is_und() {
+        let frame = Frame::new("COMM", b"\x00\x00\x00\x00\x00text".to_vec()).unwrap();
+        assert_eq!(Comment::from_frame(&frame).unwrap().language(), Language::UND);
+        assert_eq!(frame.language(), Some(Language::UND));
+    }
+}
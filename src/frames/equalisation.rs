@@ -0,0 +1,201 @@
+use crate::{Frame, Tag};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Band,
+    Linear,
+}
+
+pub struct Equalisation {
+    interpolation: Interpolation,
+    identification: String,
+    points: Vec<(f32, f32)>,
+}
+
+impl Equalisation {
+    pub fn new(interpolation: Interpolation, identification: &str, points: Vec<(f32, f32)>) -> Self {
+        let mut points = points;
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            interpolation,
+            identification: identification.to_string(),
+            points,
+        }
+    }
+
+    // Reads either layout so a tag can be converted by writing it back with another version
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        match frame.id().as_str() {
+            "EQUA" => Self::from_equa(frame.data()),
+            "EQU2" => Self::from_equ2(frame.data()),
+            _ => None,
+        }
+    }
+
+    fn from_equa(data: &[u8]) -> Option<Self> {
+        // https://id3.org/id3v2.3.0#Equalisation
+        let bits = *data.first()? as usize;
+        if bits == 0 {
+            return None;
+        }
+        let adjustment_len = bits.div_ceil(8);
+
+        let mut points = Vec::new();
+        for band in data[1..].chunks(2 + adjustment_len) {
+            if band.len() < 2 + adjustment_len {
+                return None;
+            }
+            let increment = band[0] & 0x80 != 0;
+            let frequency = u16::from_be_bytes([band[0] & 0x7F, band[1]]) as f32;
+            let magnitude = band[2..].iter().fold(0u64, |acc, x| (acc << 8) | *x as u64) as f32;
+
+            // EQUA leaves units open, adjustments are interpreted on the same 1/512 dB scale as EQU2
+            let adjustment = if increment { magnitude } else { -magnitude } / 512.0;
+            points.push((frequency, adjustment));
+        }
+
+        Some(Self {
+            interpolation: Interpolation::Band,
+            identification: String::new(),
+            points,
+        })
+    }
+
+    fn from_equ2(data: &[u8]) -> Option<Self> {
+        // https://id3.org/id3v2.4.0-frames#4.12
+        let interpolation = match data.first()? {
+            0 => Interpolation::Band,
+            1 => Interpolation::Linear,
+            _ => return None,
+        };
+        let end = data[1..].iter().position(|x| *x == 0)? + 1;
+        let identification = data[1..end].iter().map(|x| *x as char).collect();
+
+        let mut points = Vec::new();
+        for point in data[end+1..].chunks(4) {
+            if point.len() < 4 {
+                return None;
+            }
+            let frequency = u16::from_be_bytes([point[0], point[1]]) as f32 / 2.0;
+            let adjustment = i16::from_be_bytes([point[2], point[3]]) as f32 / 512.0;
+            points.push((frequency, adjustment));
+        }
+
+        Some(Self {
+            interpolation,
+            identification,
+            points,
+        })
+    }
+
+    pub fn to_frame(&self, major_ver: u8) -> Option<Frame> {
+        if major_ver == 4 {
+            Frame::new("EQU2", self.equ2_bytes()?)
+        } else {
+            Frame::new("EQUA", self.equa_bytes())
+        }
+    }
+
+    fn equa_bytes(&self) -> Vec<u8> {
+        let mut data = vec![16];
+        for (frequency, adjustment) in &self.points {
+            let frequency = (frequency.round() as u16).min(0x7FFF);
+            let increment = if *adjustment >= 0.0 { 0x80 } else { 0x00 };
+            let magnitude = (adjustment.abs() * 512.0).round().min(u16::MAX as f32) as u16;
+            data.push((frequency >> 8) as u8 | increment);
+            data.push(frequency as u8);
+            data.extend_from_slice(&magnitude.to_be_bytes());
+        }
+        data
+    }
+
+    // None when the identification has a NUL or characters Latin-1 can't hold
+    fn equ2_bytes(&self) -> Option<Vec<u8>> {
+        if self.identification.chars().any(|c| c as u32 > 0xFF || c == '\0') {
+            return None;
+        }
+        let mut data = vec![match self.interpolation {
+            Interpolation::Band => 0,
+            Interpolation::Linear => 1,
+        }];
+        data.extend(self.identification.chars().map(|c| c as u8));
+        data.push(0);
+        for (frequency, adjustment) in &self.points {
+            let frequency = (frequency * 2.0).round().min(u16::MAX as f32) as u16;
+            let adjustment = (adjustment * 512.0).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            data.extend_from_slice(&frequency.to_be_bytes());
+            data.extend_from_slice(&adjustment.to_be_bytes());
+        }
+        Some(data)
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn identification(&self) -> &str {
+        &self.identification
+    }
+
+    // Frequencies in Hz paired with volume adjustments in dB, ordered by frequency
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+}
+
+impl Tag {
+    pub fn equalisation(&self) -> Vec<Equalisation> {
+        self.frames().iter().filter_map(Equalisation::from_frame).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_equa() {
+        let frame = Frame::new("EQUA", vec![0x10, 0x80, 0x64, 0x04, 0x00, 0x03, 0xE8, 0x02, 0x00]).unwrap();
+        let eq = Equalisation::from_frame(&frame).unwrap();
+        assert_eq!(eq.points(), [(100.0, 2.0), (1000.0, -1.0)]);
+        assert_eq!(eq.interpolation(), Interpolation::Band);
+    }
+
+    #[test]
+    fn parse_equ2() {
+        let frame = Frame::new("EQU2", vec![0x01, b'l', b'i', b'v', b'e', 0x00, 0x00, 0xC8, 0xFE, 0x00]).unwrap();
+        let eq = Equalisation::from_frame(&frame).unwrap();
+        assert_eq!(eq.interpolation(), Interpolation::Linear);
+        assert_eq!(eq.identification(), "live");
+        assert_eq!(eq.points(), [(100.0, -1.0)]);
+    }
+
+    #[test]
+    fn convert_equ2_to_equa() {
+        let eq = Equalisation::new(Interpolation::Linear, "live", vec![(1000.0, -1.0), (100.0, 2.0)]);
+        let frame = eq.to_frame(3).unwrap();
+        assert_eq!(frame.id(), "EQUA");
+        assert_eq!(frame.data(), [0x10, 0x80, 0x64, 0x04, 0x00, 0x03, 0xE8, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn convert_equa_to_equ2() {
+        let frame = Frame::new("EQUA", vec![0x10, 0x80, 0x64, 0x04, 0x00]).unwrap();
+        let converted = Equalisation::from_frame(&frame).unwrap().to_frame(4).unwrap();
+        assert_eq!(converted.id(), "EQU2");
+        assert_eq!(Equalisation::from_frame(&converted).unwrap().points(), [(100.0, 2.0)]);
+    }
+
+    #[test]
+    fn identification_must_be_latin1() {
+        assert!(Equalisation::new(Interpolation::Band, "live\0", Vec::new()).to_frame(4).is_none());
+        assert!(Equalisation::new(Interpolation::Band, "ライブ", Vec::new()).to_frame(4).is_none());
+        assert!(Equalisation::new(Interpolation::Band, "ライブ", Vec::new()).to_frame(3).is_some());
+    }
+
+    #[test]
+    fn truncated_band() {
+        let frame = Frame::new("EQUA", vec![0x10, 0x80, 0x64, 0x04]).unwrap();
+        assert!(Equalisation::from_frame(&frame).is_none());
+    }
+}
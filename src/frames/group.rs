@@ -0,0 +1,149 @@
+use crate::{Frame, Tag};
+use std::io::{self, Error, ErrorKind};
+
+// Symbols below 0x80 are reserved and above 0xF0 aren't allowed
+const SYMBOLS: std::ops::RangeInclusive<u8> = 0x80..=0xF0;
+
+// GRID, ties a group symbol used in frame headers to whoever defined the group
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupRegistration {
+    owner: String,
+    symbol: u8,
+    data: Vec<u8>,
+}
+
+impl GroupRegistration {
+    pub fn new(owner: &str, symbol: u8, data: Vec<u8>) -> Self {
+        Self {
+            owner: owner.to_string(),
+            symbol,
+            data,
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "GRID" {
+            return None;
+        }
+
+        // Owner is a terminated Latin-1 string followed by the symbol and any group dependent data
+        let data = frame.data();
+        let end = data.iter().position(|x| *x == 0)?;
+        let symbol = *data.get(end + 1)?;
+        Some(Self {
+            owner: data[..end].iter().map(|x| *x as char).collect(),
+            symbol,
+            data: data[end + 2..].to_vec(),
+        })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        if self.owner.is_empty() || !SYMBOLS.contains(&self.symbol) || self.owner.chars().any(|c| c as u32 > 0xFF || c == '\0') {
+            return None;
+        }
+
+        let mut data: Vec<u8> = self.owner.chars().map(|c| c as u8).collect();
+        data.push(0);
+        data.push(self.symbol);
+        data.extend_from_slice(&self.data);
+        Frame::new("GRID", data)
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    pub fn symbol(&self) -> u8 {
+        self.symbol
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Tag {
+    pub fn group_registrations(&self) -> Vec<GroupRegistration> {
+        self.frames().iter().filter_map(GroupRegistration::from_frame).collect()
+    }
+
+    pub fn group_registration(&self, symbol: u8) -> Option<GroupRegistration> {
+        self.group_registrations().into_iter().find(|registration| registration.symbol == symbol)
+    }
+
+    pub fn frames_in_group(&self, symbol: u8) -> Vec<&Frame> {
+        self.frames().iter().filter(|frame| frame.group() == Some(symbol)).collect()
+    }
+
+    // Adds the frame as a member of the group, whether or not the group is registered
+    pub fn add_to_group(&mut self, mut frame: Frame, symbol: u8) {
+        frame.set_group(Some(symbol));
+        self.add_frame(frame);
+    }
+
+    // Groups every frame with one of the ids and registers the group, replacing any
+    // registration with the same symbol. Returns how many frames were grouped
+    pub fn assign_group(&mut self, ids: &[&str], registration: &GroupRegistration) -> io::Result<usize> {
+        let Some(grid) = registration.to_frame() else {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("invalid group registration: {:#04X}", registration.symbol)));
+        };
+        let mut grouped = 0;
+        for frame in self.frames_mut().iter_mut().filter(|frame| ids.contains(&frame.id().as_str())) {
+            frame.set_group(Some(registration.symbol));
+            grouped += 1;
+        }
+        self.frames_mut().retain(|frame| GroupRegistration::from_frame(frame).is_none_or(|existing| existing.symbol != registration.symbol));
+        self.add_frame(grid);
+        Ok(grouped)
+    }
+
+    // Removes every frame in the group along with its registration, returns how many frames went
+    pub fn remove_group(&mut self, symbol: u8) -> usize {
+        let before = self.frames().len();
+        self.frames_mut().retain(|frame| frame.group() != Some(symbol));
+        let removed = before - self.frames().len();
+        self.frames_mut().retain(|frame| GroupRegistration::from_frame(frame).is_none_or(|existing| existing.symbol != symbol));
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_frame(id: &str, text: &str) -> Frame {
+        let mut data = vec![0x03];
+        data.extend_from_slice(text.as_bytes());
+        Frame::new(id, data).unwrap()
+    }
+
+    #[test]
+    fn grid_round_trip() {
+        let frame = Frame::new("GRID", [b"https://example.com\0".as_slice(), &[0x81, 0x01, 0x02]].concat()).unwrap();
+        let registration = GroupRegistration::from_frame(&frame).unwrap();
+        assert_eq!((registration.owner(), registration.symbol(), registration.data()), ("https://example.com", 0x81, &[0x01, 0x02][..]));
+        assert_eq!(registration.to_frame().unwrap().data(), frame.data());
+        assert!(GroupRegistration::new("owner", 0x20, vec![]).to_frame().is_none());
+    }
+
+    #[test]
+    fn assign_select_and_remove() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(text_frame("TIT2", "Crumbling Castle"));
+        tag.add_frame(text_frame("TCOP", "2017 Flightless"));
+        tag.add_frame(text_frame("TPE1", "King Gizzard"));
+
+        let registration = GroupRegistration::new("signer", 0x80, vec![]);
+        assert_eq!(tag.assign_group(&["TIT2", "TCOP"], &registration).unwrap(), 2);
+        assert_eq!(tag.assign_group(&["TIT2", "TCOP"], &registration).unwrap(), 2);
+        assert_eq!(tag.group_registrations(), vec![registration]);
+        tag.add_to_group(text_frame("TALB", "Polygondwanaland"), 0x80);
+
+        let ids: Vec<String> = tag.frames_in_group(0x80).iter().map(|frame| frame.id()).collect();
+        assert_eq!(ids, ["TIT2", "TCOP", "TALB"]);
+
+        assert_eq!(tag.remove_group(0x80), 3);
+        assert!(tag.group_registration(0x80).is_none());
+        assert_eq!(tag.frames().len(), 1);
+    }
+}
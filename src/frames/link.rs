@@ -0,0 +1,149 @@
+use crate::wire;
+use crate::{Frame, Tag};
+
+pub struct Link {
+    frame_id: String,
+    url: String,
+    additional_data: Vec<String>,
+}
+
+impl Link {
+    pub fn new(frame_id: &str, url: &str, additional_data: Vec<String>) -> Self {
+        Self {
+            frame_id: frame_id.to_string(),
+            url: url.to_string(),
+            additional_data,
+        }
+    }
+
+    pub fn from_frame(frame: &Frame, major_ver: u8) -> Option<Self> {
+        if frame.id() != "LINK" {
+            return None;
+        }
+
+        // v2.3 only reserves three bytes for the linked frame id, v2.4 uses the full four
+        let id_len = if major_ver == 4 { 4 } else { 3 };
+        let data = frame.data();
+        if data.len() < id_len || !data[..id_len].iter().all(|x| x.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let frame_id: String = data[..id_len].iter().map(|x| *x as char).collect();
+
+        // URL is a terminated Latin-1 string followed by zero or more terminated strings of extra ID
+        // data, which may be empty themselves
+        let rest = &data[id_len..];
+        let rest = rest.strip_suffix(&[0]).unwrap_or(rest);
+        let mut parts = rest.split(|x| *x == 0).map(|part| part.iter().map(|x| *x as char).collect::<String>());
+        let url = parts.next()?;
+        let additional_data = parts.collect();
+
+        Some(Self {
+            frame_id,
+            url,
+            additional_data,
+        })
+    }
+
+    // None when the id doesn't fit the version or the strings aren't terminated Latin-1
+    pub fn to_frame(&self, major_ver: u8) -> Option<Frame> {
+        let id_len = if major_ver == 4 { 4 } else { 3 };
+        let latin1 = |text: &str| text.chars().all(|c| c != '\0' && (c as u32) <= 0xFF);
+        if self.frame_id.len() != id_len || !self.url.is_ascii() || !latin1(&self.url) || !self.additional_data.iter().all(|part| latin1(part)) {
+            return None;
+        }
+
+        let mut data = self.frame_id.as_bytes().to_vec();
+        data.extend_from_slice(self.url.as_bytes());
+        data.push(0);
+        for part in &self.additional_data {
+            data.extend(part.chars().map(|c| c as u8));
+            data.push(0);
+        }
+        Frame::new("LINK", data)
+    }
+
+    pub fn frame_id(&self) -> &str {
+        &self.frame_id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn additional_data(&self) -> &[String] {
+        &self.additional_data
+    }
+
+    // Find the linked frame among the other tags in the same file. The three character ids of
+    // v2.3 links are the v2.2 ones and are looked up by the frame they stand for
+    pub fn resolve<'a>(&self, tags: &'a [Tag]) -> Option<&'a Frame> {
+        let id = match self.frame_id.len() {
+            3 => wire::long_id(&self.frame_id)?,
+            _ => self.frame_id.clone(),
+        };
+        tags.iter().find_map(|tag| tag.frame(&id))
+    }
+}
+
+impl Tag {
+    pub fn links(&self) -> Vec<Link> {
+        self.frames().iter()
+            .filter_map(|frame| Link::from_frame(frame, self.version()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_v24_link() {
+        let frame = Frame::new("LINK", b"TIT2http://example.com/a.mp3\0".to_vec()).unwrap();
+        let link = Link::from_frame(&frame, 4).unwrap();
+        assert_eq!((link.frame_id(), link.url()), ("TIT2", "http://example.com/a.mp3"));
+        assert!(link.additional_data().is_empty());
+    }
+
+    #[test]
+    fn parse_v23_link() {
+        let frame = Frame::new("LINK", b"COMhttp://example.com/a.mp3\0eng\0".to_vec()).unwrap();
+        let link = Link::from_frame(&frame, 3).unwrap();
+        assert_eq!((link.frame_id(), link.url()), ("COM", "http://example.com/a.mp3"));
+        assert_eq!(link.additional_data(), ["eng".to_string()]);
+    }
+
+    #[test]
+    fn link_round_trip() {
+        let link = Link::new("TXXX", "http://example.com", vec!["MOOD".to_string()]);
+        let frame = link.to_frame(4).unwrap();
+        assert_eq!(frame.data(), b"TXXXhttp://example.com\0MOOD\0");
+        assert_eq!(Link::from_frame(&frame, 4).unwrap().additional_data(), ["MOOD".to_string()]);
+    }
+
+    #[test]
+    fn v23_link_rejects_long_id() {
+        assert!(Link::new("TIT2", "http://example.com", vec![]).to_frame(3).is_none());
+    }
+
+    #[test]
+    fn resolve_link() {
+        let tags = [Tag::from_file("test/Polygondwanaland.mp3").unwrap()];
+        let link = Link::new("TALB", "http://example.com", vec![]);
+        assert_eq!(link.resolve(&tags).unwrap().parse_text(), "Polygondwanaland".to_string());
+        assert!(Link::new("TIT3", "http://example.com", vec![]).resolve(&tags).is_none());
+        assert_eq!(Link::new("TAL", "http://example.com", vec![]).resolve(&tags).unwrap().id(), "TALB");
+    }
+
+    #[test]
+    fn additional_data_kept_as_written() {
+        let link = Link::new("COM", "http://example.com", vec!["".to_string(), "Smörgåsbord".to_string(), "".to_string()]);
+        let frame = link.to_frame(3).unwrap();
+        assert_eq!(frame.data(), b"COMhttp://example.com\0\0Sm\xF6rg\xE5sbord\0\0");
+        assert_eq!(Link::from_frame(&frame, 3).unwrap().additional_data(), link.additional_data());
+
+        // Characters Latin-1 doesn't have, and NUL, can't be written
+        assert!(Link::new("COM", "http://example.com", vec!["日本".to_string()]).to_frame(3).is_none());
+        assert!(Link::new("COM", "http://example.com", vec!["a\0b".to_string()]).to_frame(3).is_none());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::digest::{base64, sha1};
+use crate::{Frame, Tag};
+
+const LEAD_OUT: u8 = 0xAA;
+
+// Audio CDs address sectors from two seconds into the disc
+const PREGAP: u32 = 150;
+
+pub struct CdToc {
+    first_track: u8,
+    offsets: Vec<u32>,
+    lead_out: u32,
+}
+
+impl CdToc {
+    // Offsets and lead-out are logical block addresses as reported by the drive
+    pub fn new(first_track: u8, offsets: Vec<u32>, lead_out: u32) -> Self {
+        Self {
+            first_track,
+            offsets,
+            lead_out,
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "MCDI" {
+            return None;
+        }
+
+        // Body is the format 0 READ TOC response: 4 byte header then 8 byte track descriptors
+        let data = frame.data();
+        if data.len() < 4 {
+            return None;
+        }
+        let first_track = data[2];
+
+        let mut offsets = Vec::new();
+        let mut lead_out = None;
+        for descriptor in data[4..].chunks(8) {
+            if descriptor.len() < 8 {
+                return None;
+            }
+            let address = u32::from_be_bytes([descriptor[4], descriptor[5], descriptor[6], descriptor[7]]);
+            if descriptor[2] == LEAD_OUT {
+                lead_out = Some(address);
+            } else {
+                offsets.push(address);
+            }
+        }
+
+        let toc = Self {
+            first_track,
+            offsets,
+            lead_out: lead_out?,
+        };
+        toc.is_valid().then_some(toc)
+    }
+
+    // Tracks are numbered 1 to 99 and start in order before the lead-out, which leaves room for
+    // the pregap. The frame comes from the file so none of that can be taken for granted
+    fn is_valid(&self) -> bool {
+        self.first_track >= 1
+            && !self.offsets.is_empty()
+            && self.first_track as usize + self.offsets.len() <= 100
+            && self.offsets.windows(2).all(|pair| pair[0] < pair[1])
+            && self.offsets.last().is_some_and(|last| *last < self.lead_out)
+            && self.lead_out.checked_add(PREGAP).is_some()
+    }
+
+    // None for a TOC no disc could have
+    pub fn to_frame(&self) -> Option<Frame> {
+        if !self.is_valid() {
+            return None;
+        }
+        let length = (2 + 8 * (self.offsets.len() + 1)) as u16;
+        let mut data = length.to_be_bytes().to_vec();
+        data.push(self.first_track);
+        data.push(self.last_track());
+
+        let tracks = (self.first_track..).zip(&self.offsets).chain([(LEAD_OUT, &self.lead_out)]);
+        for (number, address) in tracks {
+            data.extend_from_slice(&[0x00, 0x10, number, 0x00]);
+            data.extend_from_slice(&address.to_be_bytes());
+        }
+        Frame::new("MCDI", data)
+    }
+
+    pub fn first_track(&self) -> u8 {
+        self.first_track
+    }
+
+    pub fn last_track(&self) -> u8 {
+        (self.first_track as usize + self.offsets.len()).saturating_sub(1).min(u8::MAX as usize) as u8
+    }
+
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    pub fn lead_out(&self) -> u32 {
+        self.lead_out
+    }
+
+    // https://en.wikipedia.org/wiki/CDDB#Example_calculation_of_a_CDDB1_(FreeDB)_disc_ID
+    pub fn freedb_id(&self) -> Option<u32> {
+        if !self.is_valid() {
+            return None;
+        }
+        let seconds = |address: u32| (address + PREGAP) / 75;
+        let digit_sum = |mut n: u32| {
+            let mut sum = 0;
+            while n > 0 {
+                sum += n % 10;
+                n /= 10;
+            }
+            sum
+        };
+
+        let checksum: u32 = self.offsets.iter().map(|x| digit_sum(seconds(*x))).sum();
+        let length = seconds(self.lead_out) - seconds(self.offsets[0]);
+        Some(((checksum % 0xFF) << 24) | (length << 8) | self.offsets.len() as u32)
+    }
+
+    // https://musicbrainz.org/doc/Disc_ID_Calculation
+    pub fn musicbrainz_id(&self) -> Option<String> {
+        if !self.is_valid() {
+            return None;
+        }
+        let mut toc = format!("{:02X}{:02X}{:08X}", self.first_track, self.last_track(), self.lead_out + PREGAP);
+        for i in 0..99 {
+            let offset = self.offsets.get(i).map(|x| x + PREGAP).unwrap_or(0);
+            toc.push_str(&format!("{offset:08X}"));
+        }
+
+        Some(base64(&sha1(toc.as_bytes()))
+            .replace('+', ".")
+            .replace('/', "_")
+            .replace('=', "-"))
+    }
+}
+
+impl Tag {
+    pub fn cd_toc(&self) -> Option<CdToc> {
+        self.frame("MCDI").and_then(CdToc::from_frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_toc() -> CdToc {
+        // Example disc from the MusicBrainz documentation, with the pregap removed
+        CdToc::new(1, vec![0, 15213, 32164, 46442, 63264, 80339], 95312)
+    }
+
+    #[test]
+    fn musicbrainz_disc_id() {
+        assert_eq!(example_toc().musicbrainz_id().as_deref(), Some("49HHV7Eb8UKF3aQiNmu1GR8vKTY-"));
+    }
+
+    #[test]
+    fn freedb_disc_id() {
+        assert_eq!(example_toc().freedb_id(), Some(0x3404F606));
+    }
+
+    #[test]
+    fn toc_round_trip() {
+        let frame = example_toc().to_frame().unwrap();
+        let toc = CdToc::from_frame(&frame).unwrap();
+        assert_eq!((toc.first_track(), toc.last_track(), toc.lead_out()), (1, 6, 95312));
+        assert_eq!(toc.offsets(), example_toc().offsets());
+    }
+
+    #[test]
+    fn missing_lead_out() {
+        let frame = Frame::new("MCDI", vec![0x00, 0x0A, 0x01, 0x01, 0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        assert!(CdToc::from_frame(&frame).is_none());
+    }
+
+    #[test]
+    fn hostile_tocs() {
+        let hostile = [
+            CdToc::new(250, vec![0; 10], 95312),
+            CdToc::new(1, vec![15213, 0], 95312),
+            CdToc::new(1, vec![0, 15213], 100),
+            CdToc::new(1, vec![0], u32::MAX),
+            CdToc::new(0, vec![0], 95312),
+        ];
+        for toc in hostile {
+            assert!(toc.to_frame().is_none() && toc.freedb_id().is_none() && toc.musicbrainz_id().is_none());
+        }
+        assert_eq!(CdToc::new(250, vec![0; 10], 95312).last_track(), 255);
+
+        // 200 tracks from track 255, numbers that would run into the lead-out and past 255
+        let mut data = vec![0x06, 0x42, 0xFF, 0xFF];
+        for i in 0..200u32 {
+            data.extend_from_slice(&[0x00, 0x10, 0x01, 0x00]);
+            data.extend_from_slice(&(i * 100).to_be_bytes());
+        }
+        data.extend_from_slice(&[0x00, 0x10, LEAD_OUT, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(CdToc::from_frame(&Frame::new("MCDI", data).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+use crate::ID3::{encoding_for, read_terminated, write_terminated};
+use crate::{Frame, Tag};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PictureType {
+    Other,
+    FileIcon,
+    OtherFileIcon,
+    FrontCover,
+    BackCover,
+    LeafletPage,
+    Media,
+    LeadArtist,
+    Artist,
+    Conductor,
+    Band,
+    Composer,
+    Lyricist,
+    RecordingLocation,
+    DuringRecording,
+    DuringPerformance,
+    ScreenCapture,
+    BrightFish,
+    Illustration,
+    BandLogo,
+    PublisherLogo,
+}
+
+impl PictureType {
+    pub const ALL: [PictureType; 21] = [
+        PictureType::Other,
+        PictureType::FileIcon,
+        PictureType::OtherFileIcon,
+        PictureType::FrontCover,
+        PictureType::BackCover,
+        PictureType::LeafletPage,
+        PictureType::Media,
+        PictureType::LeadArtist,
+        PictureType::Artist,
+        PictureType::Conductor,
+        PictureType::Band,
+        PictureType::Composer,
+        PictureType::Lyricist,
+        PictureType::RecordingLocation,
+        PictureType::DuringRecording,
+        PictureType::DuringPerformance,
+        PictureType::ScreenCapture,
+        PictureType::BrightFish,
+        PictureType::Illustration,
+        PictureType::BandLogo,
+        PictureType::PublisherLogo,
+    ];
+
+    // Unknown values are read as Other
+    pub fn from_byte(byte: u8) -> Self {
+        Self::ALL.get(byte as usize).copied().unwrap_or(PictureType::Other)
+    }
+
+    pub fn to_byte(&self) -> u8 {
+        Self::ALL.iter().position(|x| x == self).unwrap() as u8
+    }
+
+    // The spec allows only one of each file icon whatever its description
+    pub fn is_unique(&self) -> bool {
+        matches!(self, PictureType::FileIcon | PictureType::OtherFileIcon)
+    }
+}
+
+// v2.2 PIC frames name the image format with three characters instead of a MIME type. PNG and
+// JPG are the ones the spec names, GIF and BMP turn up too
+const FORMATS: [(&str, &str); 4] = [("PNG", "image/png"), ("JPG", "image/jpeg"), ("GIF", "image/gif"), ("BMP", "image/bmp")];
+
+// A picture whose data is a URL has --> in place of the MIME type or format in every version
+pub(crate) const LINKED: &str = "-->";
+
+// None for MIME types v2.2 has no format for
+pub(crate) fn image_format(mime: &str) -> Option<&'static str> {
+    let mime = match mime.to_ascii_lowercase().as_str() {
+        "image/jpg" => "image/jpeg".to_string(),
+        mime => mime.to_string(),
+    };
+    match mime.as_str() {
+        LINKED => Some(LINKED),
+        mime => FORMATS.iter().find(|(_, x)| *x == mime).map(|(format, _)| *format),
+    }
+}
+
+// Formats outside the table become image/ and the format in lower case
+pub(crate) fn format_mime(format: &str) -> String {
+    match FORMATS.iter().find(|(x, _)| x.eq_ignore_ascii_case(format)) {
+        Some((_, mime)) => mime.to_string(),
+        None if format == LINKED => LINKED.to_string(),
+        None => format!("image/{}", format.to_ascii_lowercase()),
+    }
+}
+
+// Guess the MIME type from the image's magic bytes
+pub fn mime_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Picture {
+    mime: String,
+    picture_type: PictureType,
+    description: String,
+    data: Vec<u8>,
+}
+
+impl Picture {
+    pub fn new(mime: &str, picture_type: PictureType, description: &str, data: Vec<u8>) -> Self {
+        Self {
+            mime: mime.to_string(),
+            picture_type,
+            description: description.to_string(),
+            data,
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "APIC" {
+            return None;
+        }
+
+        let (encoding, rest) = frame.data().split_first()?;
+        let (mime, rest) = read_terminated(0, rest);
+        let (picture_type, rest) = rest.split_first()?;
+        let (description, data) = read_terminated(*encoding, rest);
+
+        Some(Self {
+            mime,
+            picture_type: PictureType::from_byte(*picture_type),
+            description,
+            data: data.to_vec(),
+        })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        let encoding = encoding_for(&self.description, 3);
+        let mut data = vec![encoding];
+        write_terminated(&mut data, 0, &self.mime);
+        data.push(self.picture_type.to_byte());
+        write_terminated(&mut data, encoding, &self.description);
+        data.extend_from_slice(&self.data);
+        Frame::new("APIC", data)
+    }
+
+    pub fn mime(&self) -> &str {
+        &self.mime
+    }
+
+    pub fn picture_type(&self) -> PictureType {
+        self.picture_type
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Picture {
+    // Two pictures with the same key can't both be in a tag
+    pub(crate) fn clashes_with(&self, other: &Picture) -> bool {
+        self.picture_type == other.picture_type && (self.picture_type.is_unique() || self.description == other.description)
+    }
+}
+
+impl Tag {
+    pub fn pictures(&self) -> Vec<Picture> {
+        self.frames().iter().filter_map(Picture::from_frame).collect()
+    }
+
+    pub fn pictures_of(&self, picture_type: PictureType) -> Vec<Picture> {
+        self.pictures().into_iter().filter(|picture| picture.picture_type == picture_type).collect()
+    }
+
+    // Falls back to a picture of type Other since many taggers store the cover that way
+    pub fn front_cover(&self) -> Option<Picture> {
+        let pictures = self.pictures();
+        let of_type = |picture_type| pictures.iter().find(|picture| picture.picture_type == picture_type).cloned();
+        of_type(PictureType::FrontCover).or_else(|| of_type(PictureType::Other))
+    }
+
+    pub fn back_cover(&self) -> Option<Picture> {
+        self.pictures_of(PictureType::BackCover).into_iter().next()
+    }
+
+    // Replaces any picture with the same type and description, or the same type for file icons
+    pub fn embed_picture(&mut self, picture: &Picture) {
+        self.frames_mut().retain(|frame| Picture::from_frame(frame).is_none_or(|existing| !existing.clashes_with(picture)));
+        if let Some(frame) = picture.to_frame() {
+            self.add_frame(frame);
+        }
+    }
+
+    // Removes every picture of the type and returns how many there were
+    pub fn remove_pictures(&mut self, picture_type: PictureType) -> usize {
+        let before = self.frames().len();
+        self.frames_mut().retain(|frame| Picture::from_frame(frame).is_none_or(|picture| picture.picture_type != picture_type));
+        before - self.frames().len()
+    }
+
+    // Keeps the first of any pictures that clash, for tags written by software that ignored
+    // the spec, and returns the ones removed
+    pub fn dedup_pictures(&mut self) -> Vec<Picture> {
+        let mut kept: Vec<Picture> = Vec::new();
+        let mut removed = Vec::new();
+        self.frames_mut().retain(|frame| {
+            let Some(picture) = Picture::from_frame(frame) else {
+                return true;
+            };
+            if kept.iter().any(|existing| existing.clashes_with(&picture)) {
+                removed.push(picture);
+                return false;
+            }
+            kept.push(picture);
+            true
+        });
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_cover() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let pictures = tag.pictures();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!((pictures[0].mime(), pictures[0].picture_type(), pictures[0].description()), ("image/jpeg", PictureType::FrontCover, "cover"));
+        assert_eq!(mime_type(pictures[0].data()), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn picture_round_trip() {
+        let picture = Picture::new("image/png", PictureType::BandLogo, "Logo", vec![0x89, b'P', b'N', b'G']);
+        assert_eq!(Picture::from_frame(&picture.to_frame().unwrap()), Some(picture));
+    }
+
+    #[test]
+    fn embed_replaces_same_type() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.embed_picture(&Picture::new("image/png", PictureType::FrontCover, "cover", vec![1]));
+        tag.embed_picture(&Picture::new("image/png", PictureType::BackCover, "", vec![2]));
+        let pictures = tag.pictures();
+        assert_eq!(pictures.len(), 2);
+        assert_eq!(pictures[0].data(), [1]);
+    }
+
+    #[test]
+    fn select_and_dedup() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(tag.front_cover().unwrap().description(), "cover");
+        assert_eq!(tag.back_cover(), None);
+
+        tag.embed_picture(&Picture::new("image/jpeg", PictureType::BackCover, "", vec![1]));
+        tag.embed_picture(&Picture::new("image/png", PictureType::FileIcon, "small", vec![2]));
+        tag.embed_picture(&Picture::new("image/png", PictureType::FileIcon, "smaller", vec![3]));
+        assert_eq!(tag.pictures_of(PictureType::FileIcon).len(), 1);
+        assert_eq!(tag.back_cover().unwrap().data(), [1]);
+
+        // Frames added directly skip the checks embed_picture makes
+        tag.add_frame(Picture::new("image/jpeg", PictureType::BackCover, "", vec![4]).to_frame().unwrap());
+        tag.add_frame(Picture::new("image/png", PictureType::FileIcon, "", vec![5]).to_frame().unwrap());
+        let removed: Vec<Vec<u8>> = tag.dedup_pictures().iter().map(|picture| picture.data().to_vec()).collect();
+        assert_eq!(removed, [[4], [5]]);
+        assert_eq!(tag.pictures().len(), 3);
+
+        assert_eq!(tag.remove_pictures(PictureType::FrontCover), 1);
+        assert_eq!(tag.front_cover(), None);
+    }
+
+    #[test]
+    fn cover_stored_as_other() {
+        let mut tag = Tag::new(4);
+        tag.embed_picture(&Picture::new("image/png", PictureType::Other, "", vec![1]));
+        assert_eq!(tag.front_cover().unwrap().data(), [1]);
+    }
+
+    #[test]
+    fn unknown_picture_type() {
+        assert_eq!(PictureType::from_byte(0x40), PictureType::Other);
+        assert_eq!(PictureType::PublisherLogo.to_byte(), 0x14);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{Frame, Tag};
+
+pub struct Signature {
+    group: u8,
+    signature: Vec<u8>,
+}
+
+impl Signature {
+    pub fn new(group: u8, signature: Vec<u8>) -> Self {
+        Self { group, signature }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "SIGN" {
+            return None;
+        }
+
+        let (group, signature) = frame.data().split_first()?;
+        Some(Self {
+            group: *group,
+            signature: signature.to_vec(),
+        })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        let mut data = vec![self.group];
+        data.extend_from_slice(&self.signature);
+        Frame::new("SIGN", data)
+    }
+
+    // Symbol of the group of frames this signature covers
+    pub fn group(&self) -> u8 {
+        self.group
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+impl Tag {
+    pub fn signatures(&self) -> Vec<Signature> {
+        self.frames().iter().filter_map(Signature::from_frame).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sign() {
+        let frame = Frame::new("SIGN", vec![0x81, 0xDE, 0xAD]).unwrap();
+        let sign = Signature::from_frame(&frame).unwrap();
+        assert_eq!((sign.group(), sign.signature()), (0x81, &[0xDE, 0xAD][..]));
+    }
+
+    #[test]
+    fn empty_sign() {
+        let frame = Frame::new("SIGN", vec![]).unwrap();
+        assert!(Signature::from_frame(&frame).is_none());
+    }
+}
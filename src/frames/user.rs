@@ -0,0 +1,236 @@
+use crate::ID3::{bytes_from_text, encoding_for, read_terminated, text_from_bytes, write_terminated};
+use crate::{Frame, Tag};
+
+// TXXX, a description and a value so taggers can store fields the spec doesn't cover
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserText {
+    description: String,
+    value: String,
+}
+
+impl UserText {
+    pub fn new(description: &str, value: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "TXXX" {
+            return None;
+        }
+
+        let (encoding, rest) = frame.data().split_first()?;
+        let (description, value) = read_terminated(*encoding, rest);
+        Some(Self {
+            description,
+            value: read_terminated(*encoding, value).0,
+        })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        let encoding = encoding_for(&format!("{}{}", self.description, self.value), 3);
+        let mut data = vec![encoding];
+        write_terminated(&mut data, encoding, &self.description);
+        data.extend(bytes_from_text(encoding, &self.value));
+        Frame::new("TXXX", data)
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+// WXXX, the description follows the frame's encoding but the URL is always Latin-1
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserLink {
+    description: String,
+    url: String,
+}
+
+impl UserLink {
+    pub fn new(description: &str, url: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        if frame.id() != "WXXX" {
+            return None;
+        }
+
+        let (encoding, rest) = frame.data().split_first()?;
+        let (description, url) = read_terminated(*encoding, rest);
+        Some(Self {
+            description,
+            url: text_from_bytes(0, url),
+        })
+    }
+
+    pub fn to_frame(&self) -> Option<Frame> {
+        let encoding = encoding_for(&self.description, 3);
+        let mut data = vec![encoding];
+        write_terminated(&mut data, encoding, &self.description);
+        data.extend(bytes_from_text(0, &self.url));
+        Frame::new("WXXX", data)
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+// What collapse_user_texts does with TXXX frames that share a description
+#[derive(Clone, Debug, PartialEq)]
+pub enum DuplicatePolicy {
+    KeepFirst,
+    KeepLast,
+    // Every value in one frame, in the order they were found
+    Join(String),
+}
+
+impl Tag {
+    pub fn user_texts(&self) -> Vec<UserText> {
+        self.frames().iter().filter_map(UserText::from_frame).collect()
+    }
+
+    // The first value when several taggers have written the same description, see user_text_values
+    pub fn user_text(&self, description: &str) -> Option<String> {
+        self.user_texts().into_iter().find(|text| text.description == description).map(|text| text.value)
+    }
+
+    // Every value written under the description, in tag order
+    pub fn user_text_values(&self, description: &str) -> Vec<String> {
+        self.user_texts().into_iter().filter(|text| text.description == description).map(|text| text.value).collect()
+    }
+
+    // Descriptions used by more than one TXXX frame, which the spec doesn't allow
+    pub fn duplicate_user_texts(&self) -> Vec<String> {
+        let descriptions: Vec<String> = self.user_texts().into_iter().map(|text| text.description).collect();
+        let mut duplicates: Vec<String> = Vec::new();
+        for (i, description) in descriptions.iter().enumerate() {
+            if descriptions[..i].contains(description) && !duplicates.contains(description) {
+                duplicates.push(description.clone());
+            }
+        }
+        duplicates
+    }
+
+    // Leaves one TXXX frame per description where the first of them was, returns how many were removed
+    pub fn collapse_user_texts(&mut self, policy: &DuplicatePolicy) -> usize {
+        let mut removed = 0;
+        for description in self.duplicate_user_texts() {
+            let values = self.user_text_values(&description);
+            let value = match policy {
+                DuplicatePolicy::KeepFirst => values[0].clone(),
+                DuplicatePolicy::KeepLast => values[values.len() - 1].clone(),
+                DuplicatePolicy::Join(separator) => values.join(separator),
+            };
+            removed += values.len() - 1;
+            self.set_user_text(&description, &value);
+        }
+        removed
+    }
+
+    // Replaces the TXXX frame with the same description in place, or adds one at the end.
+    // Any duplicates of it are removed so the tag holds just this value
+    pub fn set_user_text(&mut self, description: &str, value: &str) {
+        let Some(frame) = UserText::new(description, value).to_frame() else {
+            return;
+        };
+        let matches = |frame: &Frame| UserText::from_frame(frame).is_some_and(|text| text.description == description);
+        match self.frames().iter().position(matches) {
+            Some(index) => {
+                self.frames_mut()[index] = frame;
+                let mut position = 0;
+                self.frames_mut().retain(|frame| {
+                    position += 1;
+                    position <= index + 1 || !matches(frame)
+                });
+            }
+            None => self.add_frame(frame),
+        }
+    }
+
+    pub fn remove_user_text(&mut self, description: &str) {
+        self.frames_mut().retain(|frame| UserText::from_frame(frame).is_none_or(|text| text.description != description));
+    }
+
+    pub fn user_links(&self) -> Vec<UserLink> {
+        self.frames().iter().filter_map(UserLink::from_frame).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_text_utf16() {
+        // Both halves carry their own BOM and the description's terminator is two aligned zeros
+        let data = [
+            &[0x01, 0xFF, 0xFE, 0x00, 0x01, 0x00, 0x00, 0xFF, 0xFE][..],
+            &[0x41, 0x00, 0x00, 0x00],
+        ].concat();
+        let text = UserText::from_frame(&Frame::new("TXXX", data).unwrap()).unwrap();
+        assert_eq!((text.description(), text.value()), ("\u{100}", "A"));
+    }
+
+    #[test]
+    fn user_text_round_trip() {
+        for text in [UserText::new("MOOD", "Calm"), UserText::new("Ēnergy", "High")] {
+            assert_eq!(UserText::from_frame(&text.to_frame().unwrap()), Some(text));
+        }
+    }
+
+    #[test]
+    fn user_link_round_trip() {
+        let link = UserLink::new("Bandcamp ☺", "https://kinggizzard.bandcamp.com");
+        let frame = link.to_frame().unwrap();
+        assert!(frame.data().ends_with(b"\x00\x00https://kinggizzard.bandcamp.com"));
+        assert_eq!(UserLink::from_frame(&frame), Some(link));
+    }
+
+    #[test]
+    fn duplicate_descriptions() {
+        let mut tag = Tag::new(4);
+        for (description, value) in [("MOOD", "Calm"), ("STYLE", "Prog"), ("MOOD", "Restless"), ("MOOD", "Calm")] {
+            tag.add_frame(UserText::new(description, value).to_frame().unwrap());
+        }
+        assert_eq!(tag.user_text_values("MOOD"), ["Calm", "Restless", "Calm"]);
+        assert_eq!(tag.duplicate_user_texts(), ["MOOD"]);
+
+        let mut last = tag.clone();
+        assert_eq!(last.collapse_user_texts(&DuplicatePolicy::KeepLast), 2);
+        assert_eq!(last.user_texts(), [UserText::new("MOOD", "Calm"), UserText::new("STYLE", "Prog")]);
+
+        assert_eq!(tag.collapse_user_texts(&DuplicatePolicy::Join("; ".to_string())), 2);
+        assert_eq!(tag.user_text("MOOD").as_deref(), Some("Calm; Restless; Calm"));
+        assert!(tag.duplicate_user_texts().is_empty());
+    }
+
+    #[test]
+    fn find_user_text() {
+        let mut tag = Tag::new(3);
+        tag.add_frame(UserText::new("MOOD", "Calm").to_frame().unwrap());
+        assert_eq!(tag.user_text("MOOD").as_deref(), Some("Calm"));
+        assert!(tag.user_text("STYLE").is_none());
+
+        tag.set_user_text("MOOD", "Restless");
+        tag.set_user_text("STYLE", "Prog");
+        assert_eq!(tag.user_texts(), [UserText::new("MOOD", "Restless"), UserText::new("STYLE", "Prog")]);
+        tag.remove_user_text("MOOD");
+        assert_eq!(tag.user_texts(), [UserText::new("STYLE", "Prog")]);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::Tag;
+use crate::convert::{text_frame, text_values};
+use crate::id3v1::GENRES;
+use crate::paths::long_path;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("genre map line {line}: {message}"))
+}
+
+// A genre found in a tag that no rule covers, and how many tags had it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnmappedGenre {
+    pub genre: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    mapped: usize,
+    // Keyed by the folded genre, with the spelling it was first seen in
+    unmapped: BTreeMap<String, (String, usize)>,
+}
+
+// A controlled vocabulary of genres, like the one a station or store files its music under.
+// Every rule names a genre of the vocabulary and the genres that collapse into it, matched
+// ignoring case and surrounding whitespace. ID3v1 numbers like (17) are matched by their name.
+// Clones share the counts so one map can be handed to every file of a bulk run
+#[derive(Clone, Debug, Default)]
+pub struct GenreMap {
+    rules: Vec<(String, String)>,
+    counts: Arc<Mutex<Counts>>,
+}
+
+fn fold(genre: &str) -> String {
+    genre.trim().to_lowercase()
+}
+
+// The genre a value names, with v2.3 references to ID3v1 genres replaced by their name. A
+// reference followed by text is refined by that text, which is what's used
+fn resolve(value: &str) -> &str {
+    let value = value.trim();
+    let (number, rest) = match value.strip_prefix('(').and_then(|value| value.split_once(')')) {
+        Some((number, rest)) => (number, rest),
+        None => (value, ""),
+    };
+    match number.parse::<usize>().ok().and_then(|number| GENRES.get(number)) {
+        Some(name) if rest.trim().is_empty() => name,
+        Some(_) => rest.trim(),
+        None => value,
+    }
+}
+
+impl GenreMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A genre of the vocabulary, kept as it is written here
+    pub fn genre(self, genre: &str) -> Self {
+        self.map(genre, genre)
+    }
+
+    // Earlier rules win when several match the same genre
+    pub fn map(mut self, from: &str, to: &str) -> Self {
+        self.rules.push((fold(from), to.trim().to_string()));
+        self
+    }
+
+    // One rule per line, the genre to collapse and the genre of the vocabulary it becomes
+    // separated by a tab. A line with no tab is a genre of the vocabulary. Blank lines and lines
+    // starting with # are skipped
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut map = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (from, to) = line.split_once('\t').unwrap_or((line, line));
+            if from.trim().is_empty() || to.trim().is_empty() {
+                return Err(invalid(i + 1, "empty genre"));
+            }
+            map = map.map(from, to);
+        }
+        Ok(map)
+    }
+
+    pub fn from_file(filename: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(long_path(filename.as_ref()))?)
+    }
+
+    // The vocabulary genre the value becomes, None when no rule covers it
+    pub fn lookup(&self, value: &str) -> Option<&str> {
+        let folded = fold(resolve(value));
+        self.rules.iter().find(|(from, _)| *from == folded).map(|(_, to)| to.as_str())
+    }
+
+    // Replaces each genre of the TCON frame with its vocabulary genre, sub-genres collapsing into
+    // the same one are written once. Genres no rule covers are kept and counted as unmapped.
+    // Returns how many values changed
+    pub fn apply(&self, tag: &mut Tag) -> usize {
+        let Some(index) = tag.frames().iter().position(|frame| frame.id() == "TCON") else {
+            return 0;
+        };
+        let frame = &tag.frames()[index];
+        let group = frame.group();
+        let values = text_values(frame);
+
+        let mut counts = self.counts.lock().unwrap();
+        let mut genres: Vec<String> = Vec::new();
+        let mut changed = 0;
+        for value in &values {
+            let genre = match self.lookup(value) {
+                Some(genre) => genre.to_string(),
+                None => {
+                    let value = value.trim();
+                    counts.unmapped.entry(fold(value)).or_insert_with(|| (value.to_string(), 0)).1 += 1;
+                    value.to_string()
+                }
+            };
+            changed += (genre != *value) as usize;
+            if !genres.contains(&genre) {
+                genres.push(genre);
+            }
+        }
+        counts.mapped += changed;
+        if genres != values && let Some(mut mapped) = text_frame("TCON", &genres, tag.version()) {
+            mapped.set_group(group);
+            tag.frames_mut()[index] = mapped;
+        }
+        changed
+    }
+
+    // Genres mapped by every clone so far
+    pub fn total(&self) -> usize {
+        self.counts.lock().unwrap().mapped
+    }
+
+    // Genres no rule covered, most common first
+    pub fn unmapped(&self) -> Vec<UnmappedGenre> {
+        let counts = self.counts.lock().unwrap();
+        let mut unmapped: Vec<UnmappedGenre> =
+            counts.unmapped.values().map(|(genre, count)| UnmappedGenre { genre: genre.clone(), count: *count }).collect();
+        unmapped.sort_by_key(|unmapped| std::cmp::Reverse(unmapped.count));
+        unmapped
+    }
+
+    pub fn clear_counts(&self) {
+        *self.counts.lock().unwrap() = Counts::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_genres_collapse() {
+        let map = GenreMap::parse("# vocabulary\nRock\nElectronic\nPsychedelic Rock\tRock\nGarage rock\tRock\nTechno\tElectronic\n").unwrap();
+        let mut tag = Tag::new(4);
+        tag.add_frame(text_frame("TCON", &["psychedelic rock ".to_string(), "Garage Rock".to_string(), "Zeuhl".to_string()], 4).unwrap());
+        assert_eq!(map.apply(&mut tag), 2);
+        assert_eq!(text_values(tag.frame("TCON").unwrap()), ["Rock", "Zeuhl"]);
+
+        // ID3v1 references are matched by name, genres already in the vocabulary aren't counted
+        let mut tag = Tag::new(3);
+        tag.set_text("TCON", "(52)");
+        assert_eq!(map.clone().apply(&mut tag), 1);
+        assert_eq!(tag.text("TCON").as_deref(), Some("Electronic"));
+        assert_eq!(map.apply(&mut tag), 0);
+        tag.set_text("TCON", "zeuhl");
+        map.apply(&mut tag);
+
+        assert_eq!(map.total(), 3);
+        assert_eq!(map.unmapped(), [UnmappedGenre { genre: "Zeuhl".into(), count: 2 }]);
+        assert_eq!(GenreMap::parse("Rock\n\tRock").unwrap_err().to_string(), "genre map line 2: empty genre");
+    }
+}
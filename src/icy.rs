@@ -0,0 +1,307 @@
+use crate::convert::text_frame;
+use crate::cue::{CueSheet, CueTrack};
+use crate::frames::Chapter;
+use crate::paths::long_path;
+use crate::{CompatibilityReport, Tag, WriteOptions, mpeg};
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::Path;
+
+// SHOUTcast/Icecast metadata: after every metaint bytes of audio comes a length byte, then
+// length * 16 bytes of text like StreamTitle='Artist - Title'; padded with zeros
+
+// Intervals servers commonly use, tried when the recording has no headers saying which
+const COMMON_METAINTS: [usize; 6] = [16000, 8192, 16384, 32768, 24576, 4096];
+
+// Metadata blocks that have to parse in a row before a guessed interval is believed
+const CONFIRM: usize = 3;
+
+// A title the station sent and where in the stripped audio it started
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamTitle {
+    pub start_ms: u64,
+    pub title: String,
+}
+
+impl StreamTitle {
+    // Stations mostly send "Artist - Title"
+    pub fn artist(&self) -> Option<&str> {
+        self.title.split_once(" - ").map(|(artist, _)| artist.trim())
+    }
+
+    pub fn song(&self) -> &str {
+        self.title.split_once(" - ").map(|(_, song)| song).unwrap_or(&self.title).trim()
+    }
+}
+
+// The HTTP or ICY response headers a recording starts with and where the stream begins
+fn split_headers(data: &[u8]) -> (Vec<(String, String)>, usize) {
+    if !(data.starts_with(b"ICY ") || data.starts_with(b"HTTP/")) {
+        return (Vec::new(), 0);
+    }
+    let Some(end) = data.windows(4).position(|x| x == b"\r\n\r\n") else {
+        return (Vec::new(), 0);
+    };
+    let headers = String::from_utf8_lossy(&data[..end])
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    (headers, end + 4)
+}
+
+// key='value' pairs, values may hold quotes so each one runs to the next "';"
+fn parse_metadata(block: &[u8]) -> Option<Vec<(String, String)>> {
+    let end = block.iter().position(|x| *x == 0).unwrap_or(block.len());
+    if block[end..].iter().any(|x| *x != 0) {
+        return None;
+    }
+    // Stations send Latin-1 as often as UTF-8
+    let text = match std::str::from_utf8(&block[..end]) {
+        Ok(text) => text.to_string(),
+        Err(_) => block[..end].iter().map(|x| *x as char).collect(),
+    };
+    if text.chars().any(|c| c.is_control()) {
+        return None;
+    }
+
+    let mut pairs = Vec::new();
+    let mut rest = text.as_str();
+    while let Some((key, value)) = rest.split_once("='") {
+        let (value, remaining) = value.split_once("';").unwrap_or((value.trim_end_matches('\''), ""));
+        pairs.push((key.trim().to_string(), value.to_string()));
+        rest = remaining;
+    }
+    (!pairs.is_empty() || text.is_empty()).then_some(pairs)
+}
+
+// Whether metadata blocks follow every metaint bytes, running into the end after a block counts
+fn fits(data: &[u8], metaint: usize) -> bool {
+    let mut position = metaint;
+    let mut titles = 0;
+    for _ in 0..CONFIRM {
+        let Some(length) = data.get(position).map(|x| *x as usize * 16) else {
+            return titles > 0;
+        };
+        let Some(block) = data.get(position + 1..position + 1 + length) else {
+            return titles > 0;
+        };
+        match parse_metadata(block) {
+            Some(pairs) => titles += pairs.iter().any(|(key, _)| key == "StreamTitle") as usize,
+            None => return false,
+        }
+        position += 1 + length + metaint;
+    }
+    // Zero length blocks fit anywhere there is a zero byte, so a title has to turn up
+    titles > 0
+}
+
+// The metadata interval of a recording without headers, from where the first title sits
+pub fn detect_metaint(data: &[u8]) -> Option<usize> {
+    let first = data.windows(13).position(|x| x == b"StreamTitle='")?.checked_sub(1)?;
+    // The title may be in the first block or come after empty ones, each a single zero byte
+    let guesses = (1..=8).filter(|blocks| (first + 1) % blocks == 0).map(|blocks| (first + 1) / blocks - 1);
+    let mut candidates: Vec<usize> = COMMON_METAINTS.into_iter().chain(guesses).collect();
+    candidates.dedup();
+    candidates.into_iter().find(|metaint| *metaint > 0 && fits(data, *metaint))
+}
+
+// A stream recording with its metadata taken out of the audio
+#[derive(Clone, Debug, PartialEq)]
+pub struct IcyStream {
+    headers: Vec<(String, String)>,
+    metaint: usize,
+    audio: Vec<u8>,
+    // Offset in the audio each title arrived at
+    titles: Vec<(usize, String)>,
+}
+
+impl IcyStream {
+    // Uses icy-metaint from the headers when the recording kept them, otherwise guesses it
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let (headers, start) = split_headers(data);
+        let data = &data[start..];
+        let metaint = headers.iter().find(|(name, _)| name == "icy-metaint").and_then(|(_, value)| value.parse().ok());
+        let metaint = metaint.filter(|metaint| *metaint > 0).or_else(|| detect_metaint(data))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No ICY metadata found"))?;
+
+        let mut audio = Vec::with_capacity(data.len());
+        let mut titles: Vec<(usize, String)> = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            let chunk = &data[position..(position + metaint).min(data.len())];
+            audio.extend_from_slice(chunk);
+            position += chunk.len();
+            let Some(length) = data.get(position).map(|x| *x as usize * 16) else {
+                break;
+            };
+            let block = data.get(position + 1..position + 1 + length).ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Recording ends inside ICY metadata"))?;
+            let pairs = parse_metadata(block).ok_or_else(|| Error::new(ErrorKind::InvalidData, "Malformed ICY metadata"))?;
+            // Stations repeat the current title in every block. The first one describes
+            // what was already playing when the recording started
+            if let Some((_, title)) = pairs.into_iter().find(|(key, _)| key == "StreamTitle")
+                && titles.last().is_none_or(|(_, last)| *last != title)
+            {
+                titles.push((if titles.is_empty() { 0 } else { audio.len() }, title));
+            }
+            position += 1 + length;
+        }
+        Ok(Self { headers, metaint, audio, titles })
+    }
+
+    pub fn from_file(filename: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read(long_path(filename))?)
+    }
+
+    pub fn metaint(&self) -> usize {
+        self.metaint
+    }
+
+    // A response header like icy-name or icy-genre, None when the recording has no headers
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(existing, _)| existing.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    // The MPEG audio with every metadata block removed
+    pub fn audio(&self) -> &[u8] {
+        &self.audio
+    }
+
+    // Each title change with the time of the first frame that started after it arrived
+    pub fn titles(&self) -> Vec<StreamTitle> {
+        let frames = mpeg::frames_in(&self.audio);
+        self.titles.iter().map(|(offset, title)| {
+            let before = frames.partition_point(|(start, _)| (*start as usize) < *offset);
+            StreamTitle { start_ms: mpeg::duration_ms(&frames[..before]), title: title.clone() }
+        }).collect()
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        mpeg::duration_ms(&mpeg::frames_in(&self.audio))
+    }
+
+    // Writes only the audio, ready to be tagged like any other mp3
+    pub fn save_audio(&self, filename: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(long_path(filename), &self.audio)
+    }
+
+    // One chapter per title running until the next, with the song as TIT2 and the artist as TPE1
+    pub fn chapters(&self, major_ver: u8) -> Vec<Chapter> {
+        let titles = self.titles();
+        let duration = self.duration_ms();
+        titles.iter().enumerate().map(|(i, title)| {
+            let end = titles.get(i + 1).map(|next| next.start_ms).unwrap_or(duration);
+            let mut chapter = Chapter::new(&format!("chp{i}"), title.start_ms as u32, end.max(title.start_ms) as u32);
+            chapter.set_title(title.song(), major_ver);
+            if let Some(frame) = title.artist().and_then(|artist| text_frame("TPE1", &[artist.to_string()], major_ver)) {
+                chapter.add_frame(frame);
+            }
+            chapter
+        }).collect()
+    }
+
+    // A sheet for the audio saved as file, the station is the album. Cue sheets stop at track 99
+    pub fn cue_sheet(&self, file: &str) -> CueSheet {
+        let tracks = self.titles().into_iter().take(99).enumerate().map(|(i, title)| CueTrack {
+            number: i as u8 + 1,
+            title: Some(title.song().to_string()),
+            performer: title.artist().map(str::to_string),
+            start: title.start_ms as u32,
+            ..CueTrack::default()
+        });
+        CueSheet {
+            title: self.header("icy-name").map(str::to_string),
+            genre: self.header("icy-genre").map(str::to_string),
+            file: Some(file.to_string()),
+            tracks: tracks.collect(),
+            ..CueSheet::default()
+        }
+    }
+
+    // Writes the audio with a tag holding a chapter for every title and the station's details
+    pub fn save_with_chapters(&self, filename: impl AsRef<Path>, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+        let filename = filename.as_ref();
+        self.save_audio(filename)?;
+        let mut tag = Tag::new(4);
+        if let Some(name) = self.header("icy-name") {
+            tag.set_station_name(name);
+        }
+        // A station url that isn't valid is left out rather than failing the save
+        if let Some(url) = self.header("icy-url") {
+            let _ = tag.set_station_url(url);
+        }
+        tag.set_chapters(&self.chapters(tag.version()));
+        tag.write_to_file(filename, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::fixtures::stream_recording;
+
+    #[test]
+    fn strips_metadata() {
+        let (data, audio) = stream_recording(16000, &[(0, "King Gizzard - Crumbling Castle"), (7, "King Gizzard - Polygondwanaland")]);
+        let stream = IcyStream::parse(&data).unwrap();
+        assert_eq!(stream.metaint(), 16000);
+        assert!(stream.audio() == audio);
+
+        let titles = stream.titles();
+        assert_eq!(titles.len(), 2);
+        assert_eq!((titles[0].start_ms, titles[0].artist(), titles[0].song()), (0, Some("King Gizzard"), "Crumbling Castle"));
+        // 8 blocks of 16000 bytes at 320 kbit/s
+        assert!(titles[1].start_ms.abs_diff(8 * 16000 * 8 / 320) < 30);
+    }
+
+    #[test]
+    fn detects_unusual_interval() {
+        let (data, _) = stream_recording(5000, &[(2, "Don't Stop - Me Now")]);
+        assert_eq!(detect_metaint(&data), Some(5000));
+        assert_eq!(IcyStream::parse(&data).unwrap().titles()[0].title, "Don't Stop - Me Now");
+    }
+
+    #[test]
+    fn headers_give_interval() {
+        let (data, _) = stream_recording(8192, &[(0, "Lizard Radio")]);
+        let mut with_headers = b"ICY 200 OK\r\nicy-name: Gizz FM\r\nicy-metaint: 8192\r\n\r\n".to_vec();
+        with_headers.extend(data);
+        let stream = IcyStream::parse(&with_headers).unwrap();
+        assert_eq!((stream.header("icy-name"), stream.metaint()), (Some("Gizz FM"), 8192));
+        assert_eq!(stream.titles()[0].artist(), None);
+    }
+
+    #[test]
+    fn titles_become_chapters() {
+        let (data, _) = stream_recording(16000, &[(0, "King Gizzard - Crumbling Castle"), (200, "Polygondwanaland")]);
+        let mut with_headers = b"ICY 200 OK\r\nicy-name: Gizz FM\r\nicy-url: https://gizz.example\r\n\r\n".to_vec();
+        with_headers.extend(data);
+        let stream = IcyStream::parse(&with_headers).unwrap();
+        let path = temp_path("icy.mp3");
+        let path = path.as_path();
+        stream.save_with_chapters(path, &WriteOptions::new()).unwrap();
+
+        let tag = Tag::from_file(path).unwrap();
+        let chapters = tag.chapters();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!((chapters[0].start(), chapters[0].title().as_deref()), (0, Some("Crumbling Castle")));
+        assert_eq!(chapters[0].frames().iter().find(|frame| frame.id() == "TPE1").unwrap().parse_text(), "King Gizzard");
+        assert_eq!((chapters[1].start(), chapters[1].end()), (chapters[0].end(), stream.duration_ms() as u32));
+        assert_eq!(tag.station_name().as_deref(), Some("Gizz FM"));
+        assert_eq!(tag.station_url().as_deref(), Some("https://gizz.example"));
+        fs::remove_file(path).unwrap();
+
+        let sheet = CueSheet::parse(&stream.cue_sheet("gizz.mp3").to_text()).unwrap();
+        assert_eq!(sheet.title.as_deref(), Some("Gizz FM"));
+        assert_eq!(sheet.tracks[1].title.as_deref(), Some("Polygondwanaland"));
+        assert_eq!(sheet.tracks[1].start / 10, chapters[1].start() / 10);
+    }
+
+    #[test]
+    fn plain_mp3_has_no_metadata() {
+        let data = fs::read("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(IcyStream::parse(&data).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
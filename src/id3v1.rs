@@ -0,0 +1,176 @@
+use crate::paths::long_path;
+use crate::Tag;
+use std::fs::File;
+use std::io::{self, SeekFrom};
+use std::io::prelude::*;
+use std::path::Path;
+
+pub const SIZE: u64 = 128;
+
+// The original ID3v1 genres, the index is the genre byte
+pub const GENRES: [&str; 80] = [
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop", "Jazz", "Metal",
+    "New Age", "Oldies", "Other", "Pop", "R&B", "Rap", "Reggae", "Rock", "Techno", "Industrial",
+    "Alternative", "Ska", "Death Metal", "Pranks", "Soundtrack", "Euro-Techno", "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk",
+    "Fusion", "Trance", "Classical", "Instrumental", "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise",
+    "AlternRock", "Bass", "Soul", "Punk", "Space", "Meditative", "Instrumental Pop", "Instrumental Rock", "Ethnic", "Gothic",
+    "Darkwave", "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream", "Southern Rock", "Comedy", "Cult", "Gangsta",
+    "Top 40", "Christian Rap", "Pop/Funk", "Jungle", "Native American", "Cabaret", "New Wave", "Psychadelic", "Rave", "Showtunes",
+    "Trailer", "Lo-Fi", "Tribal", "Acid Punk", "Acid Jazz", "Polka", "Retro", "Musical", "Rock & Roll", "Hard Rock",
+];
+
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+    bytes[..end].iter().map(|x| *x as char).collect::<String>().trim_end().to_string()
+}
+
+// Latin-1, cut to the field's length and zero padded
+fn put_field(bytes: &mut [u8], text: &str) {
+    let encoded = text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' });
+    for (slot, byte) in bytes.iter_mut().zip(encoded) {
+        *slot = byte;
+    }
+}
+
+// Genre byte for a TCON value, either "(17)", "17" or a genre name
+fn genre_index(genre: &str) -> Option<u8> {
+    let trimmed = genre.trim();
+    let number = trimmed.strip_prefix('(').and_then(|x| x.split(')').next()).unwrap_or(trimmed);
+    if let Ok(index) = number.parse::<u8>() {
+        return Some(index);
+    }
+    GENRES.iter().position(|name| name.eq_ignore_ascii_case(trimmed)).map(|index| index as u8)
+}
+
+// ID3v1.1, a fixed 128 byte block at the very end of the file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Id3v1 {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    pub track: Option<u8>,
+    pub genre: Option<u8>,
+}
+
+impl Id3v1 {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SIZE as usize || &bytes[..3] != b"TAG" {
+            return None;
+        }
+
+        // v1.1 steals the last two comment bytes for a zero and the track number
+        let (comment, track) = if bytes[125] == 0 && bytes[126] != 0 {
+            (&bytes[97..125], Some(bytes[126]))
+        } else {
+            (&bytes[97..127], None)
+        };
+        Some(Self {
+            title: field(&bytes[3..33]),
+            artist: field(&bytes[33..63]),
+            album: field(&bytes[63..93]),
+            year: field(&bytes[93..97]),
+            comment: field(comment),
+            track,
+            genre: (bytes[127] != 255).then_some(bytes[127]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; SIZE as usize] {
+        let mut bytes = [0; SIZE as usize];
+        bytes[..3].copy_from_slice(b"TAG");
+        put_field(&mut bytes[3..33], &self.title);
+        put_field(&mut bytes[33..63], &self.artist);
+        put_field(&mut bytes[63..93], &self.album);
+        put_field(&mut bytes[93..97], &self.year);
+        match self.track {
+            Some(track) => {
+                put_field(&mut bytes[97..125], &self.comment);
+                bytes[126] = track;
+            }
+            None => put_field(&mut bytes[97..127], &self.comment),
+        }
+        bytes[127] = self.genre.unwrap_or(255);
+        bytes
+    }
+
+    pub fn from_tag(tag: &Tag) -> Self {
+        let text = |id: &str| tag.text(id).unwrap_or_default();
+        let year = tag.text("TYER").or_else(|| tag.text("TDRC")).unwrap_or_default();
+        Self {
+            title: text("TIT2"),
+            artist: text("TPE1"),
+            album: text("TALB"),
+            year: year.chars().take(4).collect(),
+            comment: tag.comments().first().map(|comment| comment.text().to_string()).unwrap_or_default(),
+            track: text("TRCK").split('/').next().and_then(|track| track.trim().parse().ok()).filter(|track| *track != 0),
+            genre: tag.text("TCON").and_then(|genre| genre_index(&genre)),
+        }
+    }
+
+    pub fn genre_name(&self) -> Option<&'static str> {
+        self.genre.and_then(|genre| GENRES.get(genre as usize).copied())
+    }
+}
+
+// The v1 tag at the end of the file if there is one
+pub fn read(file: &mut File) -> io::Result<Option<Id3v1>> {
+    let length = file.metadata()?.len();
+    if length < SIZE {
+        return Ok(None);
+    }
+    let mut bytes = [0; SIZE as usize];
+    file.seek(SeekFrom::Start(length - SIZE))?;
+    file.read_exact(&mut bytes)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(Id3v1::from_bytes(&bytes))
+}
+
+pub fn read_file(filename: impl AsRef<Path>) -> io::Result<Option<Id3v1>> {
+    read(&mut File::open(long_path(filename))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_v11() {
+        let tag = Id3v1 {
+            title: "Crumbling Castle".to_string(),
+            artist: "King Gizzard & The Lizard Wizard".to_string(),
+            album: "Polygondwanaland".to_string(),
+            year: "2017".to_string(),
+            comment: "A comment far too long to fit in the field".to_string(),
+            track: Some(1),
+            genre: Some(17),
+        };
+        let read = Id3v1::from_bytes(&tag.to_bytes()).unwrap();
+        assert_eq!(read.artist, "King Gizzard & The Lizard Wiza");
+        assert_eq!(read.comment, "A comment far too long to fi");
+        assert_eq!((read.track, read.genre_name()), (Some(1), Some("Rock")));
+    }
+
+    #[test]
+    fn from_id3v2() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.set_text("TCON", "(52)");
+        let v1 = Id3v1::from_tag(&tag);
+        assert_eq!((v1.title.as_str(), v1.year.as_str(), v1.track), ("Polygondwanaland", "2017", Some(2)));
+        assert_eq!(v1.genre_name(), Some("Electronic"));
+        assert!(v1.comment.starts_with("Visit https://"));
+    }
+
+    #[test]
+    fn genre_lookup() {
+        assert_eq!(genre_index("Hip-Hop"), Some(7));
+        assert_eq!(genre_index("17"), Some(17));
+        assert_eq!(genre_index("Zeuhl"), None);
+    }
+
+    #[test]
+    fn no_v1_tag() {
+        assert_eq!(read_file("test/Polygondwanaland.mp3").unwrap(), None);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::Tag;
+use crate::frames::{Picture, mime_type};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{ImageError, ImageReader, RgbImage};
+use std::io::{self, Cursor, Error, ErrorKind};
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn io_error(error: ImageError) -> Error {
+    match error {
+        ImageError::IoError(error) => error,
+        ImageError::Unsupported(error) => Error::new(ErrorKind::Unsupported, error),
+        error => Error::new(ErrorKind::InvalidData, error),
+    }
+}
+
+// Decoded image as 8 bit RGB, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    image: RgbImage,
+}
+
+impl Image {
+    pub fn decode(data: &[u8]) -> io::Result<Self> {
+        if !matches!(mime_type(data), Some("image/jpeg" | "image/png")) {
+            return Err(Error::new(ErrorKind::Unsupported, "Only JPEG and PNG images can be decoded"));
+        }
+        Ok(Self { image: image::load_from_memory(data).map_err(io_error)?.to_rgb8() })
+    }
+
+    pub fn width(&self) -> usize {
+        self.image.width() as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.image.height() as usize
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        self.image.as_raw()
+    }
+
+    pub fn resize(&self, width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+        Self { image: imageops::resize(&self.image, width, height, FilterType::Triangle) }
+    }
+
+    pub fn to_jpeg(&self, quality: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        // Writing to memory only fails for images too large for JPEG, which decode can't give
+        JpegEncoder::new_with_quality(&mut out, quality).encode_image(&self.image).expect("image fits in a JPEG");
+        out
+    }
+}
+
+// Width and height of a JPEG or PNG read from its header
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    mime_type(data).filter(|mime| matches!(*mime, "image/jpeg" | "image/png"))?;
+    ImageReader::new(Cursor::new(data)).with_guessed_format().ok()?.into_dimensions().ok()
+}
+
+fn segment(data: &[u8], position: usize) -> io::Result<&[u8]> {
+    let length = data.get(position..position + 2).map(|x| u16::from_be_bytes([x[0], x[1]]) as usize).ok_or_else(|| invalid("Truncated JPEG"))?;
+    data.get(position + 2..position + length).filter(|_| length >= 2).ok_or_else(|| invalid("Truncated JPEG"))
+}
+
+// The JPEG without its EXIF, XMP, other application segments and comments. JFIF and Adobe
+// segments stay as they say how the image is to be decoded
+fn strip_metadata(data: &[u8]) -> io::Result<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(invalid("Not a JPEG image"));
+    }
+    let mut out = vec![0xFF, 0xD8];
+    let mut position = 2;
+    loop {
+        let marker = match data.get(position..position + 2) {
+            Some([0xFF, marker]) => *marker,
+            _ => return Err(invalid("Expected a JPEG marker")),
+        };
+        if marker == 0xDA || marker == 0xD9 {
+            out.extend_from_slice(&data[position..]);
+            return Ok(out);
+        }
+        let body = segment(data, position + 2)?;
+        let end = position + 4 + body.len();
+        if !matches!(marker, 0xE1 | 0xE3..=0xED | 0xEF | 0xFE) {
+            out.extend_from_slice(&data[position..end]);
+        }
+        position = end;
+    }
+}
+
+// How embed_optimized shrinks a picture before it goes in the tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArtOptions {
+    max_dimension: u32,
+    quality: u8,
+    strip_metadata: bool,
+}
+
+impl ArtOptions {
+    pub fn new() -> Self {
+        Self {
+            max_dimension: 1000,
+            quality: 85,
+            strip_metadata: true,
+        }
+    }
+
+    // Longest side allowed, larger pictures are scaled down keeping their aspect ratio
+    pub fn max_dimension(mut self, pixels: u32) -> Self {
+        self.max_dimension = pixels.max(1);
+        self
+    }
+
+    // JPEG quality from 1 to 100 for pictures that get re-encoded
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    // Drop EXIF, XMP and comments from JPEGs that are kept as they are
+    pub fn strip_metadata(mut self, strip: bool) -> Self {
+        self.strip_metadata = strip;
+        self
+    }
+}
+
+impl Default for ArtOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The picture scaled down and re-encoded as JPEG if it is too large, otherwise
+// only stripped of metadata. Anything else is returned unchanged
+pub fn optimize(picture: &Picture, options: &ArtOptions) -> io::Result<Picture> {
+    let data = picture.data();
+    let too_large = dimensions(data).is_some_and(|(width, height)| width.max(height) > options.max_dimension);
+    let rebuild = |mime: &str, data: Vec<u8>| Picture::new(mime, picture.picture_type(), picture.description(), data);
+
+    if too_large {
+        let image = Image::decode(data)?;
+        let longest = image.width().max(image.height());
+        let scale = |side: usize| (side * options.max_dimension as usize + longest / 2) / longest;
+        let resized = image.resize(scale(image.width()), scale(image.height()));
+        return Ok(rebuild("image/jpeg", resized.to_jpeg(options.quality)));
+    }
+    if options.strip_metadata && mime_type(data) == Some("image/jpeg") {
+        return Ok(rebuild(picture.mime(), strip_metadata(data)?));
+    }
+    Ok(picture.clone())
+}
+
+impl Tag {
+    // Embeds the picture after optimize, replacing one with the same type and description
+    pub fn embed_optimized(&mut self, picture: &Picture, options: &ArtOptions) -> io::Result<()> {
+        self.embed_picture(&optimize(picture, options)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frames::PictureType;
+
+    fn cover() -> Picture {
+        Tag::from_file("test/Polygondwanaland.mp3").unwrap().pictures().remove(0)
+    }
+
+    #[test]
+    fn downscales_large_art() {
+        let picture = cover();
+        let optimized = optimize(&picture, &ArtOptions::new().max_dimension(300).quality(80)).unwrap();
+        assert_eq!(optimized.mime(), "image/jpeg");
+        assert_eq!(optimized.picture_type(), picture.picture_type());
+        assert_eq!(dimensions(optimized.data()), Some((300, 300)));
+        assert!(optimized.data().len() < picture.data().len() / 4);
+        assert_eq!(Image::decode(optimized.data()).unwrap().width(), 300);
+    }
+
+    #[test]
+    fn small_art_is_only_stripped() {
+        let picture = cover();
+        let mut data = picture.data()[..2].to_vec();
+        data.extend([0xFF, 0xE1, 0x00, 0x08]);
+        data.extend(b"Exif\0\0");
+        data.extend_from_slice(&picture.data()[2..]);
+        let with_exif = Picture::new("image/jpeg", PictureType::FrontCover, "", data);
+
+        assert_eq!(optimize(&with_exif, &ArtOptions::new()).unwrap().data(), picture.data());
+        assert_eq!(optimize(&with_exif, &ArtOptions::new().strip_metadata(false)).unwrap(), with_exif);
+    }
+
+    #[test]
+    fn png_is_converted() {
+        let png = Picture::new("image/png", PictureType::BandLogo, "logo", include_bytes!("../test/gradient.png").to_vec());
+        let optimized = optimize(&png, &ArtOptions::new().max_dimension(12)).unwrap();
+        assert_eq!((optimized.mime(), optimized.description()), ("image/jpeg", "logo"));
+        assert_eq!(dimensions(optimized.data()), Some((12, 6)));
+    }
+
+    #[test]
+    fn resize_averages() {
+        let image = Image { image: RgbImage::from_raw(2, 2, vec![0, 0, 0, 255, 255, 255, 0, 0, 0, 255, 255, 255]).unwrap() };
+        assert_eq!(image.resize(1, 1).pixels(), [128, 128, 128]);
+    }
+
+    #[test]
+    fn embed_optimized() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.embed_optimized(&cover(), &ArtOptions::new().max_dimension(500)).unwrap();
+        let pictures = tag.pictures();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(dimensions(pictures[0].data()), Some((500, 500)));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::digest::crc32;
+use crate::merge::frame_key;
+use crate::{Frame, Tag};
+use std::collections::HashMap;
+
+// PRIV owner of the frame holding a CRC-32 of every other frame
+pub const CHECKSUM_OWNER: &str = "mp3-tool/frame-crc";
+
+// What checking the stored CRCs found, frames are named by their merge key
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub intact: usize,
+    // Frames whose content no longer matches their CRC
+    pub corrupt: Vec<String>,
+    // Checksummed frames that are gone from the tag
+    pub missing: Vec<String>,
+    // Frames added after the checksums were written
+    pub unchecked: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_intact(&self) -> bool {
+        self.corrupt.is_empty() && self.missing.is_empty()
+    }
+}
+
+fn is_checksum_frame(frame: &Frame) -> bool {
+    frame.id() == "PRIV" && frame.data().strip_prefix(CHECKSUM_OWNER.as_bytes()).is_some_and(|rest| rest.first() == Some(&0))
+}
+
+// Over the id and data so the flags a version conversion rewrites don't count
+fn checksum(frame: &Frame) -> u32 {
+    crc32(&[frame.id().as_bytes(), frame.data()].concat())
+}
+
+// Key and CRC of each frame in order: the key, a zero byte and the CRC big endian
+fn parse(data: &[u8]) -> Option<Vec<(String, u32)>> {
+    let mut rest = data.get(CHECKSUM_OWNER.len() + 1..)?;
+    let mut entries = Vec::new();
+    while !rest.is_empty() {
+        let end = rest.iter().position(|x| *x == 0)?;
+        let key = String::from_utf8(rest[..end].to_vec()).ok()?;
+        let crc = rest.get(end + 1..end + 5)?;
+        entries.push((key, u32::from_be_bytes(crc.try_into().ok()?)));
+        rest = &rest[end + 5..];
+    }
+    Some(entries)
+}
+
+impl Tag {
+    // Replaces any earlier checksum frame with one covering every frame the tag holds now
+    pub fn add_checksums(&mut self) {
+        self.remove_checksums();
+        let mut data = CHECKSUM_OWNER.as_bytes().to_vec();
+        data.push(0);
+        for frame in self.frames() {
+            data.extend_from_slice(frame_key(frame).as_bytes());
+            data.push(0);
+            data.extend_from_slice(&checksum(frame).to_be_bytes());
+        }
+        self.add_frame(Frame::new("PRIV", data).unwrap());
+    }
+
+    pub fn remove_checksums(&mut self) {
+        self.frames_mut().retain(|frame| !is_checksum_frame(frame));
+    }
+
+    // None when the tag has no readable checksum frame. Frames that share a key are matched in order
+    pub fn verify_checksums(&self) -> Option<IntegrityReport> {
+        let entries = parse(self.frames().iter().find(|frame| is_checksum_frame(frame))?.data())?;
+        let mut stored: HashMap<String, Vec<u32>> = HashMap::new();
+        for (key, crc) in entries.iter().rev() {
+            stored.entry(key.clone()).or_default().push(*crc);
+        }
+
+        let mut report = IntegrityReport::default();
+        for frame in self.frames().iter().filter(|frame| !is_checksum_frame(frame)) {
+            let key = frame_key(frame);
+            match stored.get_mut(&key).and_then(Vec::pop) {
+                Some(crc) if crc == checksum(frame) => report.intact += 1,
+                Some(_) => report.corrupt.push(key),
+                None => report.unchecked.push(key),
+            }
+        }
+        // Frames left in the file to be read later can't be checked but aren't missing
+        let lazy: Vec<&str> = self.lazy_frames().iter().map(|frame| frame.id()).collect();
+        for (key, crcs) in stored {
+            if !lazy.iter().any(|id| key.starts_with(id)) {
+                report.missing.extend(crcs.iter().map(|_| key.clone()));
+            }
+        }
+        report.missing.sort();
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use crate::diagnostics::{Diagnostics, Finding};
+    use crate::{ReadOptions, WriteOptions};
+
+    #[test]
+    fn localizes_corruption() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(tag.verify_checksums(), None);
+        tag.add_checksums();
+        tag.add_checksums();
+        assert_eq!(tag.frames().iter().filter(|frame| is_checksum_frame(frame)).count(), 1);
+        assert_eq!(tag.verify_checksums().unwrap(), IntegrityReport { intact: 9, ..Default::default() });
+
+        let frame = tag.frames_mut().iter_mut().find(|frame| frame.id() == "TALB").unwrap();
+        *frame = Frame::new("TALB", b"\x00Polygondwanalanc".to_vec()).unwrap();
+        tag.remove("TSRC");
+        tag.set_text("TBPM", "120");
+        let report = tag.verify_checksums().unwrap();
+        assert_eq!((report.corrupt, report.missing, report.unchecked), (vec!["TALB".into()], vec!["TSRC".into()], vec!["TBPM".into()]));
+        assert_eq!(report.intact, 7);
+    }
+
+    #[test]
+    fn verified_on_read() {
+        let path = copy_of_test_file("read");
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().frame_checksums(true)).unwrap();
+        let options = ReadOptions::new().verify_checksums(true);
+        assert!(Tag::from_file_with(&path, &options).is_ok());
+
+        // Flip a bit in the album title, past the frame header, encoding and BOM
+        let mut bytes = std::fs::read(&path).unwrap();
+        let album = bytes.windows(4).position(|window| window == b"TALB").unwrap();
+        bytes[album + 14] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+
+        let error = Tag::from_file_with(&path, &options).err().unwrap();
+        assert_eq!(error.to_string(), "Frame TALB doesn't match its checksum");
+        let diagnostics = Diagnostics::new();
+        Tag::from_file_with(&path, &options.lenient(true).diagnostics(diagnostics.clone())).unwrap();
+        assert!(diagnostics.findings().contains(&Finding::CorruptFrame { key: "TALB".into() }));
+        std::fs::remove_file(path).unwrap();
+    }
+}
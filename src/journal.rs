@@ -0,0 +1,233 @@
+use crate::frames::{Comment, UserText};
+use crate::merge::frame_key;
+use crate::paths::long_path;
+use crate::{Frame, Tag};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// TXXX description of the history kept in the tag itself
+pub const HISTORY: &str = "MP3TOOL_HISTORY";
+
+// Where WriteOptions::journal records each edit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Journal {
+    // A TXXX frame in the tag, travels with the file
+    Tag,
+    // A .history file next to the mp3, leaves the tag as it is
+    Sidecar,
+}
+
+// One changed field, old is None when it was added and new is None when it was removed
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalEntry {
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+impl JournalEntry {
+    // Tab separated with an empty column for a missing value
+    fn to_line(&self) -> String {
+        let value = |value: &Option<String>| value.as_deref().map(escape).unwrap_or_default();
+        format!("{}\t{}\t{}\t{}", self.timestamp, escape(&self.field), value(&self.old), value(&self.new))
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut columns = line.split('\t');
+        let value = |column: Option<&str>| column.filter(|x| !x.is_empty()).map(unescape);
+        Some(Self {
+            timestamp: columns.next()?.parse().ok()?,
+            field: unescape(columns.next()?),
+            old: value(columns.next()),
+            new: value(columns.next()),
+        })
+    }
+}
+
+fn parse(text: &str) -> Vec<JournalEntry> {
+    text.lines().filter_map(JournalEntry::from_line).collect()
+}
+
+// What a frame holds in a form worth reading back, binary frames only by size
+fn describe(frame: &Frame) -> String {
+    let id = frame.id();
+    if let Some(text) = UserText::from_frame(frame) {
+        return text.value().to_string();
+    }
+    if let Some(comment) = Comment::from_frame(frame) {
+        return comment.text().to_string();
+    }
+    match id.as_bytes()[0] {
+        b'T' => frame.parse_text(),
+        b'W' if id != "WXXX" => frame.data().iter().take_while(|x| **x != 0).map(|x| *x as char).collect(),
+        _ => format!("<{} bytes>", frame.data().len()),
+    }
+}
+
+fn is_history(frame: &Frame) -> bool {
+    UserText::from_frame(frame).is_some_and(|text| text.description() == HISTORY)
+}
+
+// Every field that was added, changed or removed going from before to after
+pub fn diff(before: &Tag, after: &Tag, timestamp: u64) -> Vec<JournalEntry> {
+    let keyed = |tag: &Tag| -> Vec<(String, Frame)> {
+        tag.frames().iter().filter(|frame| !is_history(frame)).map(|frame| (frame_key(frame), frame.clone())).collect()
+    };
+    let (before, after) = (keyed(before), keyed(after));
+    let find = |frames: &[(String, Frame)], key: &str| frames.iter().find(|(other, _)| other == key).map(|(_, frame)| frame.clone());
+    let entry = |field: &str, old: Option<&Frame>, new: Option<&Frame>| JournalEntry {
+        timestamp,
+        field: field.to_string(),
+        old: old.map(describe),
+        new: new.map(describe),
+    };
+
+    let mut entries = Vec::new();
+    for (key, frame) in &after {
+        match find(&before, key) {
+            Some(old) if old.data() == frame.data() => {}
+            old => entries.push(entry(key, old.as_ref(), Some(frame))),
+        }
+    }
+    for (key, frame) in &before {
+        if find(&after, key).is_none() {
+            entries.push(entry(key, Some(frame), None));
+        }
+    }
+    entries
+}
+
+// The changes writing the tag would make over what the file holds now
+pub(crate) fn pending(filename: &Path, tag: &Tag) -> Vec<JournalEntry> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    let current = Tag::from_file(filename).unwrap_or_else(|_| Tag::new(tag.version()));
+    diff(&current, tag, now)
+}
+
+// Copy of the tag with the entries added to its history frame
+pub(crate) fn with_history(tag: &Tag, entries: &[JournalEntry]) -> Tag {
+    let mut tag = tag.clone();
+    let mut history = tag.user_text(HISTORY).unwrap_or_default();
+    for entry in entries {
+        if !history.is_empty() {
+            history.push('\n');
+        }
+        history.push_str(&entry.to_line());
+    }
+    tag.set_user_text(HISTORY, &history);
+    tag
+}
+
+pub fn sidecar_path(filename: impl AsRef<Path>) -> PathBuf {
+    let mut path = filename.as_ref().as_os_str().to_owned();
+    path.push(".history");
+    PathBuf::from(path)
+}
+
+pub(crate) fn append_sidecar(filename: &Path, entries: &[JournalEntry]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(long_path(sidecar_path(filename)))?;
+    let lines: String = entries.iter().map(|entry| entry.to_line() + "\n").collect();
+    file.write_all(lines.as_bytes())
+}
+
+// Edits recorded in the file's tag and its sidecar, oldest first
+pub fn history(filename: impl AsRef<Path>) -> io::Result<Vec<JournalEntry>> {
+    let filename = filename.as_ref();
+    let mut entries = Tag::from_file(filename).map(|tag| tag.history()).unwrap_or_default();
+    match fs::read_to_string(long_path(sidecar_path(filename))) {
+        Ok(text) => entries.extend(parse(&text)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+    entries.sort_by_key(|entry| entry.timestamp);
+    Ok(entries)
+}
+
+impl Tag {
+    // Edits recorded in the tag's history frame, oldest first
+    pub fn history(&self) -> Vec<JournalEntry> {
+        self.user_text(HISTORY).map(|text| parse(&text)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use crate::WriteOptions;
+
+    #[test]
+    fn diff_of_tags() {
+        let before = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let mut after = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        after.set_text("TIT2", "Crumbling Castle");
+        after.remove("TSRC");
+        after.set_user_text("MOOD", "Restless\tbut calm");
+
+        let fields: Vec<(String, Option<String>, Option<String>)> =
+            diff(&before, &after, 0).into_iter().map(|entry| (entry.field, entry.old, entry.new)).collect();
+        assert_eq!(fields, [
+            ("TIT2".to_string(), Some("Polygondwanaland".to_string()), Some("Crumbling Castle".to_string())),
+            ("TXXX:MOOD".to_string(), None, Some("Restless\tbut calm".to_string())),
+            ("TSRC".to_string(), Some("AUTZK1700076".to_string()), None),
+        ]);
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let entry = JournalEntry { timestamp: 1510876800, field: "COMM:eng:".to_string(), old: None, new: Some("a\\b\nc".to_string()) };
+        assert_eq!(JournalEntry::from_line(&entry.to_line()), Some(entry));
+    }
+
+    #[test]
+    fn journal_in_tag_and_sidecar() {
+        let path = copy_of_test_file("tag");
+        let options = WriteOptions::new().preserve(true).journal(Journal::Tag);
+        for title in ["Crumbling Castle", "Deserted Dunes Welcome Weary Feet"] {
+            let mut tag = Tag::from_file(&path).unwrap();
+            tag.set_text("TIT2", title);
+            tag.write_to_file(&path, &options).unwrap();
+        }
+        let history = Tag::from_file(&path).unwrap().history();
+        let titles: Vec<Option<&str>> = history.iter().map(|entry| entry.new.as_deref()).collect();
+        assert_eq!(titles, [Some("Crumbling Castle"), Some("Deserted Dunes Welcome Weary Feet")]);
+        assert_eq!(history[1].old.as_deref(), Some("Crumbling Castle"));
+
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.set_text("TRCK", "3");
+        tag.write_to_file(&path, &WriteOptions::new().journal(Journal::Sidecar)).unwrap();
+        let all = super::history(&path).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!((all[2].field.as_str(), all[2].new.as_deref()), ("TRCK", Some("3")));
+        assert_eq!(Tag::from_file(&path).unwrap().history().len(), 2);
+
+        fs::remove_file(sidecar_path(&path)).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Language([u8; 3]);
+
+impl Language {
+    pub const UND: Language = Language(*b"und");
+    pub const MUL: Language = Language(*b"mul");
+    pub const ZXX: Language = Language(*b"zxx");
+    pub const ENG: Language = Language(*b"eng");
+    pub const DEU: Language = Language(*b"deu");
+    pub const FRA: Language = Language(*b"fra");
+    pub const SPA: Language = Language(*b"spa");
+    pub const ITA: Language = Language(*b"ita");
+    pub const POR: Language = Language(*b"por");
+    pub const NLD: Language = Language(*b"nld");
+    pub const SWE: Language = Language(*b"swe");
+    pub const JPN: Language = Language(*b"jpn");
+    pub const ZHO: Language = Language(*b"zho");
+    pub const KOR: Language = Language(*b"kor");
+    pub const RUS: Language = Language(*b"rus");
+
+    // Codes must be three ASCII letters, upper case input is normalised to lower case
+    pub fn new(code: &str) -> Option<Self> {
+        let bytes: [u8; 3] = code.as_bytes().try_into().ok()?;
+        Self::from_bytes(bytes)
+    }
+
+    pub fn from_bytes(bytes: [u8; 3]) -> Option<Self> {
+        if !bytes.iter().all(|x| x.is_ascii_alphabetic()) {
+            return None;
+        }
+        let bytes = bytes.map(|x| x.to_ascii_lowercase());
+
+        // Placeholder some taggers write instead of a real code
+        if &bytes == b"xxx" {
+            return None;
+        }
+        Some(Self(bytes))
+    }
+
+    // Reading never fails, anything invalid is treated as undetermined
+    pub fn from_bytes_lossy(bytes: [u8; 3]) -> Self {
+        Self::from_bytes(bytes).unwrap_or_default()
+    }
+
+    pub fn code(&self) -> &str {
+        std::str::from_utf8(&self.0).unwrap()
+    }
+
+    pub fn bytes(&self) -> [u8; 3] {
+        self.0
+    }
+
+    pub fn is_undetermined(&self) -> bool {
+        *self == Self::UND
+    }
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Self::UND
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_code() {
+        assert_eq!(Language::new("eng"), Some(Language::ENG));
+        assert_eq!(Language::new("ENG"), Some(Language::ENG));
+    }
+
+    #[test]
+    fn invalid_codes() {
+        assert_eq!(Language::new("en"), None);
+        assert_eq!(Language::new("e1g"), None);
+        assert_eq!(Language::from_bytes([0, 0, 0]), None);
+        assert_eq!(Language::new("XXX"), None);
+    }
+
+    #[test]
+    fn lossy_defaults_to_und() {
+        assert_eq!(Language::from_bytes_lossy([0, 0, 0]), Language::UND);
+        assert!(Language::default().is_undetermined());
+    }
+}
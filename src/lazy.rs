@@ -0,0 +1,127 @@
+use crate::paths::long_path;
+use crate::wire;
+use crate::{Frame, Reader};
+use std::fs::File;
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// A frame that was skipped while reading and is read from its file on demand
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LazyFrame {
+    id: String,
+    path: PathBuf,
+    offset: u64,
+    size: u64,
+    major_ver: u8,
+    index: usize,
+}
+
+impl LazyFrame {
+    pub(crate) fn new(id: &str, path: &Path, offset: u64, size: u64, major_ver: u8, index: usize) -> Self {
+        Self {
+            id: id.to_string(),
+            path: path.to_path_buf(),
+            offset,
+            size,
+            major_ver,
+            index,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    // Size of the body, not counting the frame header
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // Where the frame header starts in the file
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // Position among the tag's frames it was skipped at
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    fn open_at(&self, offset: u64) -> io::Result<File> {
+        let mut file = File::open(long_path(&self.path))?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(file)
+    }
+
+    // Reads the whole frame into memory
+    pub fn load(&self) -> io::Result<Frame> {
+        let header_len = wire::header_len(self.major_ver);
+        let mut reader = Reader::from_stream(self.open_at(self.offset)?.take(self.size + header_len as u64));
+        // The size found while reading, which lenient reading may have taken from a bad header
+        let header = reader.read_n_bytes(header_len)?;
+        let frame = Frame::from_header(&mut reader, header, self.size as u32, self.major_ver)?;
+        if frame.id() != self.id || frame.size() != self.size {
+            return Err(Error::new(ErrorKind::InvalidData, "File changed since the tag was read"));
+        }
+        Ok(frame.read_at_offset(self.offset))
+    }
+
+    // The body straight from the file, without holding it in memory
+    pub fn body(&self) -> io::Result<io::Take<File>> {
+        Ok(self.open_at(self.offset + wire::header_len(self.major_ver) as u64)?.take(self.size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::frames::Picture;
+    use crate::{ReadOptions, Tag, WriteOptions};
+    use std::fs;
+
+    fn lazy_options() -> ReadOptions {
+        ReadOptions::new().lazy_frames_over(64 * 1024)
+    }
+
+    #[test]
+    fn large_frames_stay_in_the_file() {
+        let tag = Tag::from_file_with("test/Polygondwanaland.mp3", &lazy_options()).unwrap();
+        assert_eq!(tag.frames().len(), 8);
+        let lazy = &tag.lazy_frames()[0];
+        assert_eq!((lazy.id(), lazy.size()), ("APIC", 177223));
+
+        let mut body = Vec::new();
+        lazy.body().unwrap().read_to_end(&mut body).unwrap();
+        assert_eq!(body.len(), 177223);
+        let picture = Picture::from_frame(&lazy.load().unwrap()).unwrap();
+        assert!(body.ends_with(picture.data()));
+        assert_eq!(picture.description(), "cover");
+    }
+
+    #[test]
+    fn written_back_in_place() {
+        let path = temp_path("write.mp3");
+        let path = path.as_path();
+        fs::copy("test/Polygondwanaland.mp3", path).unwrap();
+
+        let mut tag = Tag::from_file_with(path, &lazy_options()).unwrap();
+        tag.set_text("TRCK", "2/10");
+        tag.write_to_file(path, &WriteOptions::new().preserve(true)).unwrap();
+        let picture = Tag::from_file(path).unwrap().pictures()[0].data().to_vec();
+        assert_eq!(picture, Tag::from_file("test/Polygondwanaland.mp3").unwrap().pictures()[0].data());
+
+        let mut tag = Tag::from_file_with(path, &lazy_options()).unwrap();
+        tag.load_lazy_frames().unwrap();
+        assert!(tag.lazy_frames().is_empty());
+        assert_eq!(tag.frames().last().unwrap().id(), "APIC");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn streams_read_everything() {
+        let file = File::open("test/Polygondwanaland.mp3").unwrap();
+        let tag = Tag::from_reader_with(&mut Reader::from_stream(file), &lazy_options()).unwrap();
+        assert_eq!((tag.frames().len(), tag.lazy_frames().len()), (9, 0));
+    }
+}
@@ -1 +1,127 @@
+#[allow(non_snake_case)]
 mod ID3;
+pub mod access;
+pub mod advisory;
+pub mod analyze;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod art;
+pub mod artists;
+pub mod audiobook;
+pub mod budget;
+pub mod bulk;
+pub mod cache;
+pub mod category;
+pub mod chapter_formats;
+pub mod convert;
+pub mod cue;
+pub mod detect;
+pub mod device;
+pub mod diagnostics;
+mod display;
+mod digest;
+mod escape;
+pub mod estimate;
+#[cfg(feature = "sqlite")]
+pub mod export;
+pub mod fields;
+pub mod frames;
+pub mod genres;
+pub mod icy;
+pub mod id3v1;
+#[cfg(feature = "imaging")]
+pub mod imaging;
+pub mod integrity;
+mod json;
+pub mod journal;
+pub mod language;
+mod lazy;
+#[cfg(feature = "locking")]
+pub mod lock;
+pub mod lookup;
+pub mod merge;
+pub mod mpeg;
+pub mod names;
+mod numbers;
+pub mod order;
+mod original;
+pub mod paths;
+pub mod peek;
+pub mod playlist;
+pub mod podcast;
+pub mod probe;
+pub mod quality;
+pub mod quick;
+mod radio;
+pub mod range;
+pub mod repair;
+pub mod report;
+mod resume;
+mod rights;
+pub mod scrub;
+pub mod search;
+pub mod shared_art;
+pub mod sidecar;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod spelling;
+pub mod stats;
+pub mod strip;
+mod timestamps;
+mod text_format;
+pub mod transaction;
+pub mod transcode;
+pub mod update;
+pub mod validate;
+pub mod verify;
+mod wire;
+pub mod write;
+
+pub use ID3::{ExtendedHeader, Frame, FrameFlags, FrameHook, Header, Provenance, ReadOptions, Reader, SizeMismatch, Tag, TextError};
+pub use access::{TagEditor, TagReader, TagView};
+pub use advisory::Advisory;
+pub use artists::ArtistSplitter;
+pub use bulk::{BulkWriter, TagEdit};
+pub use cache::TagCache;
+pub use category::Category;
+pub use chapter_formats::ChapterFormat;
+pub use convert::CompatibilityReport;
+pub use device::DeviceProfile;
+pub use diagnostics::Diagnostics;
+pub use fields::{FieldName, FieldValue};
+pub use id3v1::Id3v1;
+pub use language::Language;
+pub use lazy::LazyFrame;
+pub use merge::MergeStrategy;
+pub use numbers::TrackNumber;
+pub use order::FrameOrder;
+pub use peek::TagSummary;
+pub use podcast::PodcastMetadata;
+pub use range::RangeReader;
+pub use timestamps::Timestamp;
+pub use transaction::{EditError, Transaction};
+pub use transcode::Transcoder;
+pub use write::{Utf16Policy, WriteOptions, retag_stream};
+
+// Applications keep these in shared state, they must stay Send + Sync. A Reader
+// only has to move to the thread that reads with it
+const _: () = {
+    const fn thread_safe<T: Send + Sync>() {}
+    const fn movable<T: Send>() {}
+    thread_safe::<Tag>();
+    thread_safe::<Frame>();
+    thread_safe::<Header>();
+    thread_safe::<ExtendedHeader>();
+    thread_safe::<LazyFrame>();
+    thread_safe::<ReadOptions>();
+    thread_safe::<WriteOptions>();
+    thread_safe::<Diagnostics>();
+    thread_safe::<TagCache>();
+    thread_safe::<TagSummary>();
+    thread_safe::<frames::Picture>();
+    thread_safe::<frames::Chapter>();
+    movable::<Reader>();
+};
+
+#[cfg(test)]
+mod fixtures;
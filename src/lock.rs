@@ -0,0 +1,105 @@
+use crate::paths::long_path;
+use crate::{CompatibilityReport, Tag, WriteOptions};
+use std::fs::{self, File, TryLockError};
+use std::io::{self, Error, ErrorKind};
+use std::path::Path;
+
+// Writes rename a new file over the old one, so a lock won on a file that has since been replaced is retried
+fn still_current(file: &File, filename: &Path) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let (locked, current) = (file.metadata()?, fs::metadata(filename)?);
+        Ok(locked.dev() == current.dev() && locked.ino() == current.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (file, filename);
+        Ok(true)
+    }
+}
+
+fn lock(filename: &Path, blocking: bool) -> io::Result<File> {
+    loop {
+        let file = File::open(long_path(filename))?;
+        if blocking {
+            file.lock()?;
+        } else {
+            file.try_lock().map_err(|error| match error {
+                TryLockError::WouldBlock => Error::new(ErrorKind::WouldBlock, "File is locked by another process"),
+                TryLockError::Error(error) => error,
+            })?;
+        }
+        if still_current(&file, filename)? {
+            return Ok(file);
+        }
+    }
+}
+
+// Read, change and write the tag while holding an exclusive advisory lock on the file
+pub fn edit_locked(filename: impl AsRef<Path>, options: &WriteOptions, edit: impl FnOnce(&mut Tag)) -> io::Result<CompatibilityReport> {
+    let filename = filename.as_ref();
+    let _lock = lock(filename, true)?;
+    let mut tag = Tag::from_file(filename)?;
+    edit(&mut tag);
+    tag.write_to_file(filename, options)
+}
+
+// Like edit_locked but fails with ErrorKind::WouldBlock instead of waiting for the lock
+pub fn try_edit(filename: impl AsRef<Path>, options: &WriteOptions, edit: impl FnOnce(&mut Tag)) -> io::Result<CompatibilityReport> {
+    let filename = filename.as_ref();
+    let _lock = lock(filename, false)?;
+    let mut tag = Tag::from_file(filename)?;
+    edit(&mut tag);
+    tag.write_to_file(filename, options)
+}
+
+impl Tag {
+    // Waits for any other process holding the lock before writing
+    pub fn save(&self, filename: impl AsRef<Path>, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+        let filename = filename.as_ref();
+        let _lock = lock(filename, true)?;
+        self.write_to_file(filename, options)
+    }
+
+    pub fn try_save(&self, filename: impl AsRef<Path>, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+        let filename = filename.as_ref();
+        let _lock = lock(filename, false)?;
+        self.write_to_file(filename, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+
+    #[test]
+    fn try_save_fails_while_locked() {
+        let path = copy_of_test_file("locked");
+        let tag = Tag::from_file(&path).unwrap();
+        let held = lock(&path, true).unwrap();
+        assert_eq!(tag.try_save(&path, &WriteOptions::new()).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        drop(held);
+        tag.try_save(&path, &WriteOptions::new()).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_edits_all_land() {
+        let path = copy_of_test_file("concurrent");
+        std::thread::scope(|scope| {
+            for id in ["TCON", "TBPM", "TKEY", "TLAN"] {
+                let path = &path;
+                scope.spawn(move || edit_locked(path, &WriteOptions::new(), |tag| tag.set_text(id, "1")).unwrap());
+            }
+        });
+
+        let tag = Tag::from_file(&path).unwrap();
+        for id in ["TCON", "TBPM", "TKEY", "TLAN"] {
+            assert_eq!(tag.text(id).as_deref(), Some("1"));
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
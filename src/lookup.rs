@@ -0,0 +1,287 @@
+use crate::convert::text_frame;
+use crate::merge::MergeStrategy;
+use crate::Tag;
+use std::future::Future;
+use std::io;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LookupQuery {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    // Milliseconds, as stored in TLEN
+    pub duration: Option<u64>,
+    // Acoustic fingerprint computed by the caller, e.g. Chromaprint
+    pub fingerprint: Option<String>,
+}
+
+impl LookupQuery {
+    pub fn from_tag(tag: &Tag) -> Self {
+        Self {
+            title: tag.title(),
+            artist: tag.artist(),
+            album: tag.album(),
+            duration: tag.text("TLEN").and_then(|length| length.trim().parse().ok()),
+            fingerprint: None,
+        }
+    }
+
+    pub fn fingerprint(mut self, fingerprint: &str) -> Self {
+        self.fingerprint = Some(fingerprint.to_string());
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagSuggestion {
+    source: String,
+    score: f32,
+    fields: Vec<(String, String)>,
+}
+
+impl TagSuggestion {
+    // Score runs from 0 (unlikely) to 1 (certain match)
+    pub fn new(source: &str, score: f32) -> Self {
+        Self {
+            source: source.to_string(),
+            score,
+            fields: Vec::new(),
+        }
+    }
+
+    // Fields are keyed by v2.3 text frame ids
+    pub fn field(mut self, id: &str, value: &str) -> Self {
+        self.fields.retain(|(field, _)| field != id);
+        self.fields.push((id.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.fields.iter().find(|(field, _)| field == id).map(|(_, value)| value.as_str())
+    }
+
+    pub fn to_tag(&self, major_ver: u8) -> Tag {
+        let mut tag = Tag::new(3);
+        for (id, value) in &self.fields {
+            if let Some(frame) = text_frame(id, std::slice::from_ref(value), 3) {
+                tag.add_frame(frame);
+            }
+        }
+        tag.convert(major_ver).0
+    }
+
+    pub fn apply(&self, tag: &mut Tag, strategy: MergeStrategy) {
+        let suggested = self.to_tag(tag.version());
+        tag.merge(&suggested, strategy);
+    }
+}
+
+pub trait MetadataProvider {
+    fn name(&self) -> &str;
+
+    // Candidates ordered from best to worst match
+    fn lookup(&self, query: &LookupQuery) -> io::Result<Vec<TagSuggestion>>;
+}
+
+pub trait AsyncMetadataProvider {
+    fn name(&self) -> &str;
+
+    fn lookup(&self, query: &LookupQuery) -> impl Future<Output = io::Result<Vec<TagSuggestion>>> + Send;
+}
+
+#[cfg(feature = "musicbrainz")]
+pub use musicbrainz::MusicBrainz;
+
+#[cfg(feature = "musicbrainz")]
+mod musicbrainz {
+    use super::*;
+    use serde_json::Value;
+    use std::io::{Error, ErrorKind};
+
+    // Performs a GET with the given user agent and returns the response body
+    pub type Transport = Box<dyn Fn(&str, &str) -> io::Result<String> + Send + Sync>;
+
+    // Reference provider for the MusicBrainz recording search, HTTP is left to the caller
+    pub struct MusicBrainz {
+        user_agent: String,
+        transport: Option<Transport>,
+    }
+
+    impl MusicBrainz {
+        pub fn new(user_agent: &str) -> Self {
+            Self {
+                user_agent: user_agent.to_string(),
+                transport: None,
+            }
+        }
+
+        pub fn with_transport(mut self, transport: Transport) -> Self {
+            self.transport = Some(transport);
+            self
+        }
+
+        pub fn query_url(&self, query: &LookupQuery) -> String {
+            let mut terms = Vec::new();
+            for (field, value) in [("recording", &query.title), ("artist", &query.artist), ("release", &query.album)] {
+                if let Some(value) = value {
+                    terms.push(format!("{field}:\"{}\"", value.replace('"', "")));
+                }
+            }
+            format!("https://musicbrainz.org/ws/2/recording?query={}&fmt=json&limit=5", percent_encode(&terms.join(" AND ")))
+        }
+
+        pub fn parse_response(body: &str) -> io::Result<Vec<TagSuggestion>> {
+            let value = serde_json::from_str::<Value>(body).map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid MusicBrainz response"))?;
+            let recordings = value.get("recordings").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+
+            Ok(recordings.iter().map(|recording| {
+                let score = recording.get("score").and_then(|x| x.as_f64()).unwrap_or(0.0) as f32 / 100.0;
+                let mut suggestion = TagSuggestion::new("musicbrainz", score);
+
+                if let Some(title) = recording.get("title").and_then(|x| x.as_str()) {
+                    suggestion = suggestion.field("TIT2", title);
+                }
+                if let Some(length) = recording.get("length").and_then(|x| x.as_f64()) {
+                    suggestion = suggestion.field("TLEN", &(length as u64).to_string());
+                }
+                let artist = recording.get("artist-credit").and_then(|x| x.as_array()?.first()).and_then(|x| x.get("name"));
+                if let Some(artist) = artist.and_then(|x| x.as_str()) {
+                    suggestion = suggestion.field("TPE1", artist);
+                }
+                if let Some(release) = recording.get("releases").and_then(|x| x.as_array()?.first()) {
+                    if let Some(album) = release.get("title").and_then(|x| x.as_str()) {
+                        suggestion = suggestion.field("TALB", album);
+                    }
+                    let year = release.get("date").and_then(|x| x.as_str()?.get(..4)).filter(|x| x.bytes().all(|x| x.is_ascii_digit()));
+                    if let Some(year) = year {
+                        suggestion = suggestion.field("TYER", year);
+                    }
+                }
+                suggestion
+            }).collect())
+        }
+    }
+
+    fn percent_encode(text: &str) -> String {
+        text.bytes().map(|x| match x {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (x as char).to_string(),
+            _ => format!("%{x:02X}"),
+        }).collect()
+    }
+
+    impl MetadataProvider for MusicBrainz {
+        fn name(&self) -> &str {
+            "musicbrainz"
+        }
+
+        fn lookup(&self, query: &LookupQuery) -> io::Result<Vec<TagSuggestion>> {
+            let Some(transport) = &self.transport else {
+                return Err(Error::new(ErrorKind::Unsupported, "MusicBrainz lookups need an HTTP transport"));
+            };
+            let body = transport(&self.query_url(query), &self.user_agent)?;
+            Self::parse_response(&body)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    struct Fixed;
+
+    impl MetadataProvider for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn lookup(&self, query: &LookupQuery) -> io::Result<Vec<TagSuggestion>> {
+            let title = query.title.clone().unwrap_or_default();
+            Ok(vec![TagSuggestion::new("fixed", 0.9).field("TIT2", &title).field("TYER", "2017")])
+        }
+    }
+
+    impl AsyncMetadataProvider for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        async fn lookup(&self, query: &LookupQuery) -> io::Result<Vec<TagSuggestion>> {
+            MetadataProvider::lookup(self, query)
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn query_from_tag() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let query = LookupQuery::from_tag(&tag).fingerprint("AQAA");
+        assert_eq!(query.title.as_deref(), Some("Polygondwanaland"));
+        assert_eq!(query.fingerprint.as_deref(), Some("AQAA"));
+    }
+
+    #[test]
+    fn sync_and_async_lookup() {
+        let query = LookupQuery { title: Some("Crumbling Castle".to_string()), ..Default::default() };
+        let sync = MetadataProvider::lookup(&Fixed, &query).unwrap();
+        let not_sync = block_on(AsyncMetadataProvider::lookup(&Fixed, &query)).unwrap();
+        assert_eq!(sync, not_sync);
+        assert_eq!(sync[0].get("TIT2"), Some("Crumbling Castle"));
+    }
+
+    #[test]
+    fn apply_suggestion() {
+        let mut tag = Tag::new(4);
+        let suggestion = TagSuggestion::new("fixed", 1.0).field("TIT2", "Crumbling Castle").field("TYER", "2017");
+        suggestion.apply(&mut tag, MergeStrategy::PreferSelf);
+        assert_eq!(tag.title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(tag.text("TDRC").as_deref(), Some("2017"));
+    }
+
+    #[cfg(feature = "musicbrainz")]
+    #[test]
+    fn musicbrainz_response() {
+        let body = r#"{"recordings": [{"id": "x", "score": 100, "title": "Crumbling Castle", "length": 643000,
+            "artist-credit": [{"name": "King Gizzard & The Lizard Wizard"}],
+            "releases": [{"title": "Polygondwanaland", "date": "2017-11-17"}]}]}"#;
+        let suggestions = MusicBrainz::parse_response(body).unwrap();
+        assert_eq!(suggestions[0].score(), 1.0);
+        assert_eq!(suggestions[0].get("TALB"), Some("Polygondwanaland"));
+        assert_eq!(suggestions[0].get("TYER"), Some("2017"));
+
+        let body = r#"{"recordings": [{"title": "x", "releases": [{"title": "y", "date": "20é7"}]}]}"#;
+        assert_eq!(MusicBrainz::parse_response(body).unwrap()[0].get("TYER"), None);
+    }
+
+    #[cfg(feature = "musicbrainz")]
+    #[test]
+    fn musicbrainz_query_url() {
+        let query = LookupQuery { title: Some("A B".to_string()), ..Default::default() };
+        let provider = MusicBrainz::new("mp3-tool-test/0.1");
+        assert!(provider.query_url(&query).contains("query=recording%3A%22A%20B%22"));
+        assert!(MetadataProvider::lookup(&provider, &query).is_err());
+    }
+}
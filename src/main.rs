@@ -0,0 +1,645 @@
+#[cfg(feature = "sqlite")]
+use mp3_tool::export;
+use mp3_tool::analyze::{self, Severity};
+use mp3_tool::chapter_formats::{self, ChapterFormat};
+use mp3_tool::estimate;
+use mp3_tool::frames::Chapters;
+use mp3_tool::genres::GenreMap;
+use mp3_tool::quality::{self, QualityWeights};
+use mp3_tool::repair::{self, StackedFix};
+use mp3_tool::report::{self, ReportFormat, ReportOptions};
+use mp3_tool::scrub::ScrubPolicy;
+use mp3_tool::search::{self, Query};
+use mp3_tool::shared_art::ArtDedup;
+use mp3_tool::sidecar;
+use mp3_tool::spelling::Dictionary;
+use mp3_tool::stats::{self, StatsOptions};
+use mp3_tool::verify::{self, VerifyOptions};
+use mp3_tool::{BulkWriter, Frame, Header, MergeStrategy, Reader, Tag, TagEdit, WriteOptions, playlist, retag_stream};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufWriter, Error, ErrorKind, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: mp3tool show [--frames] <file|playlist|->...
+       mp3tool dump <file|->
+       mp3tool apply <text> <file|->
+       mp3tool set [--resume] <id> <text> <file|playlist|->...
+       mp3tool convert [--resume] <3|4> <file|playlist|->...
+       mp3tool normalize [--resume] [--genres <map>] <dictionary> <file|playlist|->...
+       mp3tool estimate <latin1|utf16|utf16be|utf8> <file|dir>
+       mp3tool art <extract|embed> <dir>
+       mp3tool merge-sidecars <file|dir|playlist>...
+       mp3tool export <db> <dir|playlist>
+       mp3tool fix <file>
+       mp3tool analyze <file|playlist>...
+       mp3tool quality <file|dir>
+       mp3tool stats <file|dir>
+       mp3tool verify [--audio-hash] <file|dir|playlist>...
+       mp3tool hash-audio <file|playlist>...
+       mp3tool report [--html] [--art] <file|dir>
+       mp3tool scrub [--dry-run] <file|playlist|->...
+       mp3tool find <dir> <text> [--regex] [--field <id>]...
+       mp3tool chapters export <file|-> [text|podlove|ffmetadata]
+       mp3tool chapters import <file|-> <chapters|-> [text|podlove|ffmetadata]
+
+A file of - reads the MP3 from stdin, commands that change it write the changed file to stdout";
+
+// Playlists expand to their entries, anything else is taken as a file
+fn sources(paths: &[impl AsRef<Path>]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if playlist::is_playlist(path) {
+            files.extend(playlist::read(path)?);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+// Tag text and frame ids have to be text, unlike paths
+fn utf8(arg: &OsStr) -> io::Result<&str> {
+    arg.to_str().ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("{} isn't valid UTF-8", arg.display())))
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+// A path of - reads the tag from stdin
+fn read_tag(path: &Path) -> io::Result<Tag> {
+    if is_stdin(path) {
+        Tag::from_reader(&mut Reader::from_stream(io::stdin()))
+    } else {
+        Tag::from_file(path)
+    }
+}
+
+// Edit the tag of the MP3 coming in on stdin and write the file out on stdout, only the tag is
+// read in and the audio is streamed through. Input without a tag gets a new v2.4 one
+fn edit_stream(edit: impl FnOnce(&mut Tag) -> io::Result<()>) -> io::Result<()> {
+    let stdout = io::stdout().lock();
+    if stdout.is_terminal() {
+        return Err(Error::new(ErrorKind::InvalidInput, "won't write an MP3 to a terminal, redirect stdout"));
+    }
+    let mut stdin = io::stdin().lock();
+    let mut head = Vec::new();
+    (&mut stdin).take(10).read_to_end(&mut head)?;
+    let mut tag = match Header::from_bytes(&head) {
+        Some(header) => {
+            (&mut stdin).take(header.tag_size() - 10).read_to_end(&mut head)?;
+            Tag::from_reader(&mut Reader::from_stream(io::Cursor::new(head.clone())))?
+        }
+        None => Tag::new(4),
+    };
+    edit(&mut tag)?;
+    // A reader that stops early, like head, isn't an error
+    match retag_stream(io::Cursor::new(head).chain(stdin), BufWriter::new(stdout), &tag) {
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => Err(error),
+        _ => Ok(()),
+    }
+}
+
+fn describe(frame: &Frame) -> String {
+    let id = frame.id();
+    if id.starts_with('T') && id != "TXXX" {
+        frame.escaped_text()
+    } else {
+        format!("<{} bytes>", frame.size())
+    }
+}
+
+// A summary of each tag, or every frame with --frames
+fn show(paths: &[OsString], all_frames: bool) -> io::Result<()> {
+    let files = sources(paths)?;
+    for (i, path) in files.iter().enumerate() {
+        // Name each file once there is more than one
+        if files.len() > 1 {
+            println!("{}{}", if i > 0 { "\n" } else { "" }, path.display());
+        }
+        let tag = read_tag(path)?;
+        if !all_frames {
+            println!("{tag}");
+            continue;
+        }
+        println!("ID3v2.{}", tag.version());
+        for frame in tag.frames() {
+            println!("{}  {}", frame.id(), describe(frame));
+        }
+    }
+    Ok(())
+}
+
+// The tag as sorted plain text, for keeping tags under version control
+fn dump(path: &Path) -> io::Result<()> {
+    print!("{}", read_tag(path)?.to_text()?);
+    Ok(())
+}
+
+// Replace the file's tag with one written by dump
+fn apply(text: &Path, path: &Path) -> io::Result<()> {
+    if is_stdin(text) && is_stdin(path) {
+        return Err(Error::new(ErrorKind::InvalidInput, "the text and the file can't both come from stdin"));
+    }
+    let text = if is_stdin(text) { io::read_to_string(io::stdin())? } else { std::fs::read_to_string(text)? };
+    let tag = Tag::from_text(&text)?;
+    if is_stdin(path) {
+        return edit_stream(|existing| {
+            *existing = tag;
+            Ok(())
+        });
+    }
+    let options = WriteOptions::new();
+    if Tag::from_file(path).is_ok_and(|existing| existing.to_text().ok() == Some(text.clone())) {
+        println!("{} unchanged", path.display());
+        return Ok(());
+    }
+    tag.write_to_file(path, &options)?;
+    println!("{} updated", path.display());
+    Ok(())
+}
+
+// Where set and convert record their progress, --resume skips what an interrupted run finished
+const STATE_FILE: &str = ".mp3tool-batch";
+
+// Rewrite every file with the same edit, failures are listed and don't stop the others
+fn rewrite(plan: &str, resume: bool, paths: &[OsString], edit: impl Fn() -> TagEdit, options: WriteOptions) -> io::Result<()> {
+    let mut writer = BulkWriter::new().options(options).state_file(STATE_FILE, plan).resume(resume);
+    for file in sources(paths)? {
+        writer.push(&file, edit());
+    }
+    let report = writer.run();
+    for failure in report.failed() {
+        eprintln!("mp3tool: {}: {}", failure.path.display(), failure.error);
+    }
+    if let Some(error) = report.state_error() {
+        eprintln!("mp3tool: {STATE_FILE}: {error}");
+    }
+    if !report.resumed().is_empty() {
+        println!("Resumed after {} files", report.resumed().len());
+    }
+    println!("Updated {} files, {} unchanged", report.succeeded().len(), report.unchanged().len());
+    match report.failed().len() {
+        0 => Ok(()),
+        failed => Err(Error::other(format!("{failed} files failed"))),
+    }
+}
+
+fn set(id: &OsStr, text: &OsStr, paths: &[OsString], resume: bool) -> io::Result<()> {
+    let (id, text) = (utf8(id)?, utf8(text)?);
+    if paths == ["-"] {
+        return edit_stream(|tag| {
+            TagEdit::new().set_text(id, text).apply(tag);
+            Ok(())
+        });
+    }
+    let plan = format!("set {id} {text}");
+    rewrite(&plan, resume, paths, || TagEdit::new().set_text(id, text), WriteOptions::new().preserve(true))
+}
+
+fn convert(version: &str, paths: &[OsString], resume: bool) -> io::Result<()> {
+    let plan = format!("convert {version}");
+    let version = version.parse().map_err(|_| Error::new(ErrorKind::InvalidInput, format!("invalid version {version}")))?;
+    if paths == ["-"] {
+        if version != 3 && version != 4 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Only ID3v2.3 and ID3v2.4 can be written"));
+        }
+        return edit_stream(|tag| {
+            *tag = tag.convert(version).0;
+            Ok(())
+        });
+    }
+    rewrite(&plan, resume, paths, TagEdit::new, WriteOptions::new().version(version))
+}
+
+// Replace spellings from a tab separated dictionary and count how often each rule applied.
+// --genres also collapses genres into the vocabulary of a genre map and lists the ones it lacks
+fn normalize(args: &[OsString]) -> io::Result<()> {
+    let mut resume = false;
+    let mut genres = None;
+    let mut args = args;
+    loop {
+        match args {
+            [flag, rest @ ..] if flag == "--resume" => (resume, args) = (true, rest),
+            [flag, map, rest @ ..] if flag == "--genres" => (genres, args) = (Some(Path::new(map)), rest),
+            _ => break,
+        }
+    }
+    let Some((dictionary, paths)) = args.split_first().filter(|(_, paths)| !paths.is_empty()) else {
+        return Err(Error::new(ErrorKind::InvalidInput, "normalize needs a dictionary and files"));
+    };
+
+    let plan = format!("normalize {} {}", dictionary.display(), genres.map(Path::display).map(|map| map.to_string()).unwrap_or_default());
+    let dictionary = Dictionary::from_file(dictionary)?;
+    let genres = genres.map(GenreMap::from_file).transpose()?;
+    let edit = || match &genres {
+        Some(map) => TagEdit::new().normalize(&dictionary).map_genres(map),
+        None => TagEdit::new().normalize(&dictionary),
+    };
+    let streaming = paths == ["-"];
+    let result = match streaming {
+        true => edit_stream(|tag| {
+            edit().apply(tag);
+            Ok(())
+        }),
+        false => rewrite(&plan, resume, paths, edit, WriteOptions::new().preserve(true)),
+    };
+    // stdout carries the file when streaming
+    let print = |line: String| match streaming {
+        true => eprintln!("{line}"),
+        false => println!("{line}"),
+    };
+    for rule in dictionary.counts().iter().filter(|rule| rule.count > 0) {
+        print(format!("{} -> {}: {}", rule.from, rule.to, rule.count));
+    }
+    if let Some(map) = &genres {
+        print(format!("{} genres mapped", map.total()));
+        for unmapped in map.unmapped() {
+            print(format!("  unmapped {}: {}", unmapped.genre, unmapped.count));
+        }
+    }
+    result
+}
+
+// Move covers every track of an album shares into one image next to them, or embed that image everywhere
+fn art(operation: &str, dir: &Path) -> io::Result<()> {
+    let dedup = ArtDedup::new();
+    let options = WriteOptions::new().preserve(true);
+    let mut reclaimed = 0;
+    for album in dedup.scan(dir)? {
+        let change = match operation {
+            "extract" if album.shared.is_none() => continue,
+            "extract" => dedup.extract(&album, &options),
+            "embed" => dedup.embed(&album, &options),
+            other => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown art operation {other}"))),
+        };
+        match change {
+            Ok(change) if change.files_changed > 0 => {
+                println!("{}: {} files, {:+} bytes", change.directory.display(), change.files_changed, -change.bytes_reclaimed);
+                reclaimed += change.bytes_reclaimed;
+            }
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::NotFound && operation == "embed" => {}
+            Err(error) => eprintln!("mp3tool: {}: {error}", album.directory.display()),
+        }
+    }
+    println!("{reclaimed} bytes reclaimed");
+    Ok(())
+}
+
+// Write reviewed sidecar tags into their files and remove the sidecars
+fn merge_sidecars(paths: &[OsString]) -> io::Result<()> {
+    let options = WriteOptions::new().preserve(true);
+    let (dirs, files): (Vec<&OsString>, Vec<&OsString>) = paths.iter().partition(|path| Path::new(path).is_dir());
+    let mut results = vec![sidecar::merge_files(&sources(&files)?, &options)];
+    for dir in dirs {
+        results.push(sidecar::merge_dir(dir, &options)?);
+    }
+
+    let mut failed = 0;
+    for result in &results {
+        for file in &result.merged {
+            println!("{}", file.display());
+        }
+        for (file, error) in &result.failed {
+            eprintln!("mp3tool: {}: {error}", file.display());
+        }
+        failed += result.failed.len();
+    }
+    println!("Merged {} sidecars", results.iter().map(|result| result.merged.len()).sum::<usize>());
+    match failed {
+        0 => Ok(()),
+        failed => Err(Error::other(format!("{failed} sidecars failed"))),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn export(db: &Path, source: &Path) -> io::Result<()> {
+    let report = if playlist::is_playlist(source) {
+        export::sqlite_files(&playlist::read(source)?, db)?
+    } else {
+        export::sqlite(source, db)?
+    };
+    println!(
+        "{} added, {} updated, {} unchanged, {} removed",
+        report.added, report.updated, report.unchanged, report.removed,
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn export(_db: &Path, _source: &Path) -> io::Result<()> {
+    Err(Error::new(ErrorKind::Unsupported, "export needs mp3tool built with the sqlite feature"))
+}
+
+// Merge stacked tags into the outermost one so the audio starts where players expect
+fn fix(path: &Path) -> io::Result<()> {
+    let count = repair::read_stacked(path)?.len();
+    let options = WriteOptions::new().preserve(true);
+    match repair::fix_stacked(path, StackedFix::Merge(MergeStrategy::PreferSelf), &options)? {
+        Some(_) => println!("Merged {count} stacked tags"),
+        None => println!("Nothing to fix"),
+    }
+    Ok(())
+}
+
+fn seconds(ms: u64) -> String {
+    let seconds = (ms + 500) / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Compare the audio present with the length the tag and Xing header claim, fails when any file is cut short
+fn analyze(paths: &[OsString]) -> io::Result<()> {
+    let mut truncated = 0;
+    for path in sources(paths)? {
+        let analysis = analyze::analyze(&path)?;
+        let mut line = format!("{}: {} in {} frames", path.display(), seconds(analysis.duration_ms), analysis.audio_frames);
+        if let Some(xing) = analysis.xing_ms {
+            line += &format!(", Xing {}", seconds(xing));
+        }
+        if let Some(declared) = analysis.declared_ms {
+            line += &format!(", TLEN {}", seconds(declared));
+        }
+        if analysis.severity() != Severity::None {
+            line += &format!(" ({:?}, {} missing)", analysis.severity(), seconds(analysis.missing_ms()));
+        }
+        if analysis.audio_frames == 0 {
+            line += " (no audio)";
+        }
+        println!("{line}");
+        truncated += (analysis.is_truncated() || analysis.audio_frames == 0) as usize;
+    }
+    match truncated {
+        0 => Ok(()),
+        truncated => Err(Error::other(format!("{truncated} files are truncated"))),
+    }
+}
+
+// How much the text frames would grow or shrink in another encoding, nothing is written
+fn estimate(encoding: &str, path: &Path) -> io::Result<()> {
+    let encoding = match encoding {
+        "latin1" => 0,
+        "utf16" => 1,
+        "utf16be" => 2,
+        "utf8" => 3,
+        other => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown encoding {other}"))),
+    };
+    let estimate = estimate::estimate(path, encoding)?;
+    for file in estimate.files.iter().filter(|file| file.frames > 0) {
+        println!("{}: {} frames, {:+} bytes", file.path.display(), file.frames, file.difference());
+    }
+    for (file, error) in &estimate.failed {
+        eprintln!("mp3tool: {}: {error}", file.display());
+    }
+    println!(
+        "{} frames in {} files, {} bytes now, {} after ({:+})",
+        estimate.frames(),
+        estimate.files.len(),
+        estimate.bytes_before(),
+        estimate.bytes_after(),
+        estimate.difference(),
+    );
+    Ok(())
+}
+
+// Score every tag, worst first, and list what most files are missing
+fn quality(path: &Path) -> io::Result<()> {
+    let report = quality::score(path, &QualityWeights::new())?;
+    for file in &report.files {
+        let failed: Vec<&str> = file.score.failed.iter().map(|check| check.name()).collect();
+        match failed.is_empty() {
+            true => println!("{:3}%  {}", file.score.percent(), file.path.display()),
+            false => println!("{:3}%  {}  (failed {})", file.score.percent(), file.path.display(), failed.join(", ")),
+        }
+    }
+    for (file, error) in &report.failed {
+        eprintln!("mp3tool: {}: {error}", file.display());
+    }
+    if let Some(average) = report.average() {
+        println!("Average {average}% over {} files", report.files.len());
+    }
+    for (check, count) in report.failures() {
+        println!("  {count} failed {}", check.name());
+    }
+    Ok(())
+}
+
+// Versions, frame usage, art and padding over a library
+fn stats(path: &Path) -> io::Result<()> {
+    let stats = stats::collect(path, &StatsOptions::new().largest_art(5))?;
+    for (file, error) in &stats.failed {
+        eprintln!("mp3tool: {}: {error}", file.display());
+    }
+    let versions: Vec<String> = stats.versions.iter().map(|(version, count)| format!("ID3v2.{version} {count}")).collect();
+    println!("{} files, {} tagged ({}), {} untagged", stats.files, stats.tagged(), versions.join(", "), stats.untagged);
+    println!("{} bytes of tags, {} of them padding, {} tags without padding", stats.tag_bytes, stats.padding.total_bytes, stats.padding.tags_without);
+    println!(
+        "{} pictures in {} files, {} bytes, {} on average",
+        stats.art.pictures,
+        stats.art.files_with_art,
+        stats.art.total_bytes,
+        stats.art.average_bytes().unwrap_or(0),
+    );
+    for (file, bytes) in &stats.art.largest {
+        println!("  {bytes:>9}  {}", file.display());
+    }
+    for (id, usage) in stats.most_used() {
+        println!("{id}  {} files, {} frames, {} bytes", usage.files, usage.count, usage.bytes);
+    }
+    Ok(())
+}
+
+// One JSON line per file saying which checks passed, fails when any file does
+fn verify(args: &[OsString]) -> io::Result<()> {
+    let (options, paths) = match args {
+        [flag, paths @ ..] if flag == "--audio-hash" => (VerifyOptions::new().audio_hash(true), paths),
+        paths => (VerifyOptions::new(), paths),
+    };
+    let mut failed = 0;
+    for path in paths {
+        let verifications = match Path::new(path).is_dir() {
+            true => verify::verify(path, &options)?,
+            false => verify::verify_files(&sources(&[path])?, &options),
+        };
+        for verification in verifications {
+            println!("{}", verification.to_json());
+            failed += !verification.passed() as usize;
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        failed => Err(Error::other(format!("{failed} files failed verification"))),
+    }
+}
+
+// Store a hash of the audio in each tag for verify --audio-hash to check against
+fn hash_audio(paths: &[OsString]) -> io::Result<()> {
+    let files = sources(paths)?;
+    for path in &files {
+        verify::store_audio_hash(path, &WriteOptions::new().preserve(true))?;
+    }
+    println!("Hashed {} files", files.len());
+    Ok(())
+}
+
+// Markdown or HTML summary of a file or every file below a directory, with --art embedding the pictures
+fn report(args: &[OsString]) -> io::Result<()> {
+    let Some((path, flags)) = args.split_last() else {
+        return Err(Error::new(ErrorKind::InvalidInput, "report needs a file or directory"));
+    };
+    let mut options = ReportOptions::new();
+    for flag in flags {
+        options = match flag.to_str() {
+            Some("--html") => options.format(ReportFormat::Html),
+            Some("--art") => options.thumbnails(true),
+            _ => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown option {}", flag.display()))),
+        };
+    }
+    print!("{}", report::report(path, &options)?);
+    Ok(())
+}
+
+// Remove privacy sensitive frames and list what was taken out of each file
+fn scrub(args: &[OsString]) -> io::Result<()> {
+    let (dry_run, paths) = match args {
+        [flag, paths @ ..] if flag == "--dry-run" => (true, paths),
+        paths => (false, paths),
+    };
+    let policy = ScrubPolicy::new();
+    if paths == ["-"] && !dry_run {
+        return edit_stream(|tag| {
+            for removed in tag.scrub(&policy) {
+                eprintln!("  {}  {}", removed.id, removed.detail);
+            }
+            Ok(())
+        });
+    }
+    let options = WriteOptions::new().preserve(true);
+    let mut scrubbed = 0;
+    for path in sources(paths)? {
+        let mut tag = read_tag(&path)?;
+        let removed = tag.scrub(&policy);
+        if removed.is_empty() {
+            continue;
+        }
+        println!("{}", path.display());
+        for removed in &removed {
+            println!("  {}  {}", removed.id, removed.detail);
+        }
+        if !dry_run {
+            tag.write_to_file(&path, &options)?;
+        }
+        scrubbed += 1;
+    }
+    let verb = if dry_run { "Would scrub" } else { "Scrubbed" };
+    println!("{verb} {scrubbed} files");
+    Ok(())
+}
+
+// Print the path and matching field of every file below dir whose tag matches
+fn find(dir: &Path, text: &OsStr, flags: &[&str]) -> io::Result<()> {
+    let text = utf8(text)?;
+    let mut regex = false;
+    let mut fields = Vec::new();
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match *flag {
+            "--regex" => regex = true,
+            "--field" => fields.push(*flags.next().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "--field needs a frame id"))?),
+            other => return Err(Error::new(ErrorKind::InvalidInput, format!("unknown option {other}"))),
+        }
+    }
+
+    let mut query = if regex { Query::regex(text)? } else { Query::new(text) };
+    if !fields.is_empty() {
+        query = query.fields(&fields);
+    }
+    for found in search::find(dir, &query)? {
+        println!("{}  {}  {}", found.path.display(), found.field, found.value);
+    }
+    Ok(())
+}
+
+fn chapter_format(name: &str) -> io::Result<ChapterFormat> {
+    ChapterFormat::from_name(name).ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("unknown chapter format {name}")))
+}
+
+// Print the file's chapters in order, as text unless another format is given
+fn export_chapters(path: &Path, format: &str) -> io::Result<()> {
+    let format = chapter_format(format)?;
+    print!("{}", chapter_formats::export(Chapters::from_tag(&read_tag(path)?).chapters(), format));
+    Ok(())
+}
+
+// Replace the file's chapters with a chapter list, its format is detected unless given
+fn import_chapters(path: &Path, source: &Path, format: Option<&str>) -> io::Result<()> {
+    if is_stdin(source) && is_stdin(path) {
+        return Err(Error::new(ErrorKind::InvalidInput, "the chapters and the file can't both come from stdin"));
+    }
+    let text = if is_stdin(source) { io::read_to_string(io::stdin())? } else { std::fs::read_to_string(source)? };
+    let format = match format {
+        Some(format) => chapter_format(format)?,
+        None => ChapterFormat::detect(&text),
+    };
+    // Without the whole file to measure, the last chapter ends where the list says
+    if is_stdin(path) {
+        return edit_stream(|tag| {
+            tag.set_chapters(&chapter_formats::import(&text, format, None, tag.version())?);
+            Ok(())
+        });
+    }
+    chapter_formats::import_file(path, &text, format, &WriteOptions::new().preserve(true))?;
+    println!("{}: {} chapters", path.display(), Tag::from_file(path)?.chapters().len());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    // Paths are passed on as they are, whatever the platform allows in them. Commands and options
+    // are matched as text, so an argument that isn't UTF-8 can only ever be a path
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let words: Vec<&str> = args.iter().map(|arg| arg.to_str().unwrap_or("\u{FFFD}")).collect();
+    let path = |i: usize| Path::new(&args[i]);
+    let result = match words.as_slice() {
+        ["show", "--frames", paths @ ..] if !paths.is_empty() => show(&args[2..], true),
+        ["show", paths @ ..] if !paths.is_empty() => show(&args[1..], false),
+        ["dump", _] => dump(path(1)),
+        ["apply", _, _] => apply(path(1), path(2)),
+        ["set", "--resume", _, _, paths @ ..] if !paths.is_empty() => set(&args[2], &args[3], &args[4..], true),
+        ["set", _, _, paths @ ..] if !paths.is_empty() => set(&args[1], &args[2], &args[3..], false),
+        ["convert", "--resume", version, paths @ ..] if !paths.is_empty() => convert(version, &args[3..], true),
+        ["convert", version, paths @ ..] if !paths.is_empty() => convert(version, &args[2..], false),
+        ["normalize", rest @ ..] if rest.len() >= 2 => normalize(&args[1..]),
+        ["estimate", encoding, _] => estimate(encoding, path(2)),
+        ["art", operation, _] => art(operation, path(2)),
+        ["merge-sidecars", paths @ ..] if !paths.is_empty() => merge_sidecars(&args[1..]),
+        ["export", _, _] => export(path(1), path(2)),
+        ["fix", _] => fix(path(1)),
+        ["analyze", paths @ ..] if !paths.is_empty() => analyze(&args[1..]),
+        ["quality", _] => quality(path(1)),
+        ["stats", _] => stats(path(1)),
+        ["verify", rest @ ..] if rest.iter().any(|arg| *arg != "--audio-hash") => verify(&args[1..]),
+        ["hash-audio", paths @ ..] if !paths.is_empty() => hash_audio(&args[1..]),
+        ["report", rest @ ..] if !rest.is_empty() => report(&args[1..]),
+        ["scrub", rest @ ..] if rest.iter().any(|arg| *arg != "--dry-run") => scrub(&args[1..]),
+        ["find", _, _, flags @ ..] => find(path(1), &args[2], flags),
+        ["chapters", "export", _] => export_chapters(path(2), "text"),
+        ["chapters", "export", _, format] => export_chapters(path(2), format),
+        ["chapters", "import", _, _] => import_chapters(path(2), path(3), None),
+        ["chapters", "import", _, _, format] => import_chapters(path(2), path(3), Some(format)),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("mp3tool: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
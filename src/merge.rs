@@ -0,0 +1,143 @@
+use crate::ID3::{read_terminated, split_terminated};
+use crate::{Frame, Tag};
+
+// Called with (self, other) for every conflict, returning None removes the frame
+pub type MergeCallback = Box<dyn Fn(&Frame, &Frame) -> Option<Frame>>;
+
+pub enum MergeStrategy {
+    PreferSelf,
+    PreferOther,
+    PreferNonEmpty,
+    Custom(MergeCallback),
+}
+
+// Frames that can appear several times are told apart by their description, owner or language
+pub(crate) fn frame_key(frame: &Frame) -> String {
+    let id = frame.id();
+    let data = frame.data();
+    let described = |data: &[u8]| match data.split_first() {
+        Some((encoding, rest)) => read_terminated(*encoding, rest).0,
+        None => String::new(),
+    };
+
+    match id.as_str() {
+        "TXXX" | "WXXX" => format!("{id}:{}", described(data)),
+        "COMM" | "USLT" if data.len() >= 4 => {
+            let language: String = data[1..4].iter().map(|x| *x as char).collect();
+            format!("{id}:{language}:{}", described(&[&data[..1], &data[4..]].concat()))
+        }
+        "APIC" if !data.is_empty() => {
+            // Description comes after the MIME type and picture type
+            let (_, rest) = split_terminated(0, &data[1..]);
+            let description = rest.get(1..).map(|rest| described(&[&data[..1], rest].concat())).unwrap_or_default();
+            format!("{id}:{description}")
+        }
+        "PRIV" | "UFID" | "POPM" | "GEOB" => format!("{id}:{}", read_terminated(0, data).0),
+        _ => id,
+    }
+}
+
+fn is_empty(frame: &Frame) -> bool {
+    if frame.id().starts_with('T') {
+        frame.parse_text().trim().is_empty()
+    } else {
+        frame.data().is_empty()
+    }
+}
+
+impl Tag {
+    pub fn merge(&mut self, other: &Tag, strategy: MergeStrategy) {
+        for theirs in other.frames() {
+            let key = frame_key(theirs);
+            let Some(index) = self.frames().iter().position(|ours| frame_key(ours) == key) else {
+                self.add_frame(theirs.clone());
+                continue;
+            };
+
+            let ours = &self.frames()[index];
+            let merged = match &strategy {
+                MergeStrategy::PreferSelf => continue,
+                MergeStrategy::PreferOther => Some(theirs.clone()),
+                MergeStrategy::PreferNonEmpty if is_empty(ours) && !is_empty(theirs) => Some(theirs.clone()),
+                MergeStrategy::PreferNonEmpty => continue,
+                MergeStrategy::Custom(callback) => callback(ours, theirs),
+            };
+
+            match merged {
+                Some(frame) => self.frames_mut()[index] = frame,
+                None => {
+                    self.frames_mut().remove(index);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_tag(frames: &[(&str, &str)]) -> Tag {
+        let mut tag = Tag::new(4);
+        for (id, text) in frames {
+            let mut data = vec![0x03];
+            data.extend_from_slice(text.as_bytes());
+            tag.add_frame(Frame::new(id, data).unwrap());
+        }
+        tag
+    }
+
+    fn text(tag: &Tag, id: &str) -> String {
+        tag.frame(id).unwrap().parse_text()
+    }
+
+    #[test]
+    fn missing_frames_added() {
+        let mut tag = text_tag(&[("TIT2", "Title")]);
+        tag.merge(&text_tag(&[("TALB", "Album")]), MergeStrategy::PreferSelf);
+        assert_eq!((text(&tag, "TIT2"), text(&tag, "TALB")), ("Title".to_string(), "Album".to_string()));
+    }
+
+    #[test]
+    fn prefer_self_and_other() {
+        let other = text_tag(&[("TIT2", "Theirs")]);
+
+        let mut tag = text_tag(&[("TIT2", "Ours")]);
+        tag.merge(&other, MergeStrategy::PreferSelf);
+        assert_eq!(text(&tag, "TIT2"), "Ours");
+
+        tag.merge(&other, MergeStrategy::PreferOther);
+        assert_eq!(text(&tag, "TIT2"), "Theirs");
+        assert_eq!(tag.frames().len(), 1);
+    }
+
+    #[test]
+    fn prefer_non_empty() {
+        let mut tag = text_tag(&[("TIT2", ""), ("TALB", "Ours")]);
+        tag.merge(&text_tag(&[("TIT2", "Theirs"), ("TALB", "Theirs")]), MergeStrategy::PreferNonEmpty);
+        assert_eq!((text(&tag, "TIT2"), text(&tag, "TALB")), ("Theirs".to_string(), "Ours".to_string()));
+    }
+
+    #[test]
+    fn custom_callback() {
+        let mut tag = text_tag(&[("TIT2", "Ours"), ("TALB", "Ours")]);
+        tag.merge(&text_tag(&[("TIT2", "Theirs"), ("TALB", "Theirs")]), MergeStrategy::Custom(Box::new(|ours, theirs| {
+            match ours.id().as_str() {
+                "TIT2" => Some(theirs.clone()),
+                _ => None,
+            }
+        })));
+        assert_eq!(text(&tag, "TIT2"), "Theirs");
+        assert!(tag.frame("TALB").is_none());
+    }
+
+    #[test]
+    fn described_frames_kept_apart() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(Frame::new("TXXX", b"\x00MOOD\x00Calm".to_vec()).unwrap());
+        let mut other = Tag::new(4);
+        other.add_frame(Frame::new("TXXX", b"\x00STYLE\x00Prog".to_vec()).unwrap());
+        tag.merge(&other, MergeStrategy::PreferSelf);
+        assert_eq!(tag.frames().len(), 2);
+    }
+}
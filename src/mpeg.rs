@@ -0,0 +1,411 @@
+// MPEG audio frame headers: http://www.mp3-tech.org/programmer/frame_header.html
+use crate::id3v1;
+use crate::update::appended_tag_range;
+use crate::write::existing_tag_size;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Version {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Layer1,
+    Layer2,
+    Layer3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameHeader {
+    version: Version,
+    layer: Layer,
+    bitrate: u32,
+    sample_rate: u32,
+    padding: bool,
+    channels: u8,
+}
+
+const BITRATES_V1: [[u32; 15]; 3] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+];
+
+const BITRATES_V2: [[u32; 15]; 2] = [
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+impl FrameHeader {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = match (bytes[1] >> 3) & 0b11 {
+            0b00 => Version::Mpeg25,
+            0b10 => Version::Mpeg2,
+            0b11 => Version::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (bytes[1] >> 1) & 0b11 {
+            0b01 => Layer::Layer3,
+            0b10 => Layer::Layer2,
+            0b11 => Layer::Layer1,
+            _ => return None,
+        };
+
+        // Free format (0) and the invalid index (15) can't be used to find the next frame
+        let bitrate_index = (bytes[2] >> 4) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 {
+            return None;
+        }
+        let bitrate = match (version, layer) {
+            (Version::Mpeg1, Layer::Layer1) => BITRATES_V1[0][bitrate_index],
+            (Version::Mpeg1, Layer::Layer2) => BITRATES_V1[1][bitrate_index],
+            (Version::Mpeg1, Layer::Layer3) => BITRATES_V1[2][bitrate_index],
+            (_, Layer::Layer1) => BITRATES_V2[0][bitrate_index],
+            (_, _) => BITRATES_V2[1][bitrate_index],
+        } * 1000;
+
+        let sample_rate = match (bytes[2] >> 2) & 0b11 {
+            0 => 44100,
+            1 => 48000,
+            2 => 32000,
+            _ => return None,
+        } / match version {
+            Version::Mpeg1 => 1,
+            Version::Mpeg2 => 2,
+            Version::Mpeg25 => 4,
+        };
+
+        Some(Self {
+            version,
+            layer,
+            bitrate,
+            sample_rate,
+            padding: (bytes[2] >> 1) & 1 == 1,
+            channels: if bytes[3] >> 6 == 0b11 { 1 } else { 2 },
+        })
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    // Bits per second
+    pub fn bitrate(&self) -> u32 {
+        self.bitrate
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    pub fn samples(&self) -> u32 {
+        match (self.version, self.layer) {
+            (_, Layer::Layer1) => 384,
+            (Version::Mpeg1, _) | (_, Layer::Layer2) => 1152,
+            _ => 576,
+        }
+    }
+
+    // Length of the whole frame in bytes, header included
+    pub fn frame_length(&self) -> usize {
+        let padding = self.padding as u32;
+        let length = match self.layer {
+            Layer::Layer1 => (12 * self.bitrate / self.sample_rate + padding) * 4,
+            _ => self.samples() / 8 * self.bitrate / self.sample_rate + padding,
+        };
+        length as usize
+    }
+
+    // Version, layer and sample rate stay the same for a whole stream
+    pub fn is_consistent_with(&self, other: &FrameHeader) -> bool {
+        self.version == other.version && self.layer == other.layer && self.sample_rate == other.sample_rate
+    }
+}
+
+// Frames that have to follow one another before a sync word is believed, so bytes in
+// pictures or junk that happen to look like a header aren't taken for audio
+const CONFIRM: usize = 3;
+
+// Bytes searched at a time when looking for the audio again, the overlap keeps a run
+// of frames that starts near the end of one window whole in the next
+const WINDOW: usize = 64 * 1024;
+const OVERLAP: usize = 16 * 1024;
+
+// Whether CONFIRM consistent frames follow one another from position, running into the end
+// of the data after at least one frame counts
+fn starts_run(bytes: &[u8], mut position: usize, reference: Option<&FrameHeader>) -> bool {
+    let mut reference = reference.copied();
+    for count in 0..CONFIRM {
+        if position + 4 > bytes.len() {
+            return count > 0;
+        }
+        let Some(header) = FrameHeader::from_bytes(&bytes[position..]) else {
+            return false;
+        };
+        if reference.is_some_and(|reference| !reference.is_consistent_with(&header)) {
+            return false;
+        }
+        reference = Some(header);
+        position += header.frame_length();
+    }
+    true
+}
+
+// Position of the first frame that starts a run of consistent frames
+pub fn find_frame(bytes: &[u8]) -> Option<usize> {
+    (0..bytes.len()).find(|i| starts_run(bytes, *i, None))
+}
+
+// Where frames like the reference start again at or after from, None when they don't
+fn resync(reader: &mut BufReader<&mut File>, from: u64, end: u64, reference: &FrameHeader) -> io::Result<Option<u64>> {
+    let mut start = from;
+    while start + 4 <= end {
+        let mut window = Vec::new();
+        reader.seek(SeekFrom::Start(start))?;
+        (&mut *reader).take((end - start).min(WINDOW as u64)).read_to_end(&mut window)?;
+        let last = start + window.len() as u64 >= end;
+        let limit = if last { window.len() } else { window.len() - OVERLAP };
+        if let Some(i) = (0..limit).find(|i| starts_run(&window, *i, Some(reference))) {
+            return Ok(Some(start + i as u64));
+        }
+        if last {
+            break;
+        }
+        start += limit as u64;
+    }
+    Ok(None)
+}
+
+// Where the audio sits, between any ID3v2 tags at the start and an appended ID3v2 tag or an
+// ID3v1 tag at the end
+pub fn audio_range(file: &mut File) -> io::Result<Range<u64>> {
+    let start = existing_tag_size(file)?;
+    let mut end = file.metadata()?.len();
+    if id3v1::read(file)?.is_some() {
+        end -= id3v1::SIZE;
+    }
+    if let Some(appended) = appended_tag_range(file)? {
+        end = appended.start;
+    }
+    Ok(start..end.max(start))
+}
+
+// Offset and header of every whole frame in the range. Anything between frames that isn't one
+// like the first is skipped until a run of frames starts again
+pub fn scan(file: &mut File, range: Range<u64>) -> io::Result<Vec<(u64, FrameHeader)>> {
+    let mut head = Vec::new();
+    file.seek(SeekFrom::Start(range.start))?;
+    (&mut *file).take((range.end - range.start).min(64 * 1024)).read_to_end(&mut head)?;
+    let Some(first) = find_frame(&head) else {
+        return Ok(Vec::new());
+    };
+
+    let mut offset = range.start + first as u64;
+    let mut reader = BufReader::new(&mut *file);
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut frames: Vec<(u64, FrameHeader)> = Vec::new();
+    let mut bytes = [0; 4];
+    while offset + 4 <= range.end {
+        reader.read_exact(&mut bytes)?;
+        let reference = frames.first().map(|(_, header)| *header);
+        let header = FrameHeader::from_bytes(&bytes)
+            .filter(|header| reference.is_none_or(|reference| reference.is_consistent_with(header)))
+            .filter(|header| offset + header.frame_length() as u64 <= range.end);
+        let Some(header) = header else {
+            let Some(reference) = reference else {
+                break;
+            };
+            match resync(&mut reader, offset + 1, range.end, &reference)? {
+                Some(next) => {
+                    offset = next;
+                    reader.seek(SeekFrom::Start(offset))?;
+                    continue;
+                }
+                None => break,
+            }
+        };
+        let length = header.frame_length() as u64;
+        frames.push((offset, header));
+        offset += length;
+        reader.seek_relative(length as i64 - 4)?;
+    }
+    Ok(frames)
+}
+
+// Offset and header of every whole frame in bytes already in memory, skipping junk the way scan does
+pub fn frames_in(bytes: &[u8]) -> Vec<(u64, FrameHeader)> {
+    let mut frames: Vec<(u64, FrameHeader)> = Vec::new();
+    let Some(mut position) = find_frame(bytes) else {
+        return frames;
+    };
+    while position + 4 <= bytes.len() {
+        let reference = frames.first().map(|(_, header)| *header);
+        let header = FrameHeader::from_bytes(&bytes[position..])
+            .filter(|header| reference.is_none_or(|reference| reference.is_consistent_with(header)))
+            .filter(|header| position + header.frame_length() <= bytes.len());
+        match header {
+            Some(header) => {
+                frames.push((position as u64, header));
+                position += header.frame_length();
+            }
+            None => match (position + 1..bytes.len()).find(|i| starts_run(bytes, *i, reference.as_ref())) {
+                Some(next) => position = next,
+                None => break,
+            },
+        }
+    }
+    frames
+}
+
+// A Xing or Info frame at the start describes the whole file, so it's wrong once audio is cut or joined
+pub fn drop_info_frame(file: &mut File, frames: &mut Vec<(u64, FrameHeader)>) -> io::Result<()> {
+    let Some((offset, _)) = frames.first() else {
+        return Ok(());
+    };
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(*offset))?;
+    (&mut *file).take(64).read_to_end(&mut bytes)?;
+    if bytes.windows(4).any(|x| x == b"Xing" || x == b"Info") {
+        frames.remove(0);
+    }
+    Ok(())
+}
+
+// Number of audio frames a Xing, Info or VBRI header in the first frame says the file has,
+// not counting the header frame itself
+pub fn declared_frames(frame: &[u8]) -> Option<u32> {
+    let header = FrameHeader::from_bytes(frame)?;
+    let side_info = match (header.version, header.channels) {
+        (Version::Mpeg1, 1) => 17,
+        (Version::Mpeg1, _) => 32,
+        (_, 1) => 9,
+        _ => 17,
+    };
+    let number = |offset: usize| frame.get(offset..offset + 4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]));
+    let xing = 4 + side_info;
+    match frame.get(xing..xing + 4) {
+        Some(b"Xing" | b"Info") if number(xing + 4)? & 1 != 0 => number(xing + 8),
+        _ if frame.get(36..40) == Some(b"VBRI") => number(50),
+        _ => None,
+    }
+}
+
+// Playing time of the frames in milliseconds
+pub fn duration_ms(frames: &[(u64, FrameHeader)]) -> u64 {
+    let micros: u64 = frames.iter()
+        .map(|(_, header)| header.samples() as u64 * 1_000_000 / header.sample_rate() as u64)
+        .sum();
+    micros / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+
+    #[test]
+    fn parse_layer3_header() {
+        let header = FrameHeader::from_bytes(&[0xFF, 0xFB, 0xE0, 0x44]).unwrap();
+        assert_eq!((header.version(), header.layer()), (Version::Mpeg1, Layer::Layer3));
+        assert_eq!((header.bitrate(), header.sample_rate(), header.channels()), (320000, 44100, 2));
+        assert_eq!(header.frame_length(), 1044);
+    }
+
+    #[test]
+    fn parse_mpeg2_header() {
+        let header = FrameHeader::from_bytes(&[0xFF, 0xF3, 0x82, 0xC4]).unwrap();
+        assert_eq!(header.version(), Version::Mpeg2);
+        assert_eq!((header.bitrate(), header.sample_rate(), header.channels()), (64000, 22050, 1));
+        assert_eq!((header.samples(), header.frame_length()), (576, 209));
+    }
+
+    #[test]
+    fn reject_invalid_headers() {
+        assert!(FrameHeader::from_bytes(&[0xFF, 0xFB, 0x00, 0x44]).is_none());
+        assert!(FrameHeader::from_bytes(&[0xFF, 0xF9, 0x50, 0x80]).is_none());
+        assert!(FrameHeader::from_bytes(&[0xFF, 0xFB, 0xEC, 0x44]).is_none());
+    }
+
+    #[test]
+    fn find_first_frame() {
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(find_frame(&bytes[187217..]), Some(0));
+    }
+
+    #[test]
+    fn scan_file() {
+        let mut file = File::open("test/Polygondwanaland.mp3").unwrap();
+        let range = audio_range(&mut file).unwrap();
+        assert_eq!(range, 187217..file.metadata().unwrap().len());
+        let frames = scan(&mut file, range).unwrap();
+        assert_eq!(frames[0].0, 187217);
+        assert_eq!(frames[1].0, 187217 + frames[0].1.frame_length() as u64);
+        assert_eq!((frames.len(), duration_ms(&frames)), (8155, 213024));
+
+        let mut audio = frames.clone();
+        drop_info_frame(&mut file, &mut audio).unwrap();
+        assert_eq!(audio[0], frames[1]);
+    }
+
+    #[test]
+    fn resync_after_junk() {
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        let mut file = File::open("test/Polygondwanaland.mp3").unwrap();
+        let frames = scan(&mut file, 187217..bytes.len() as u64).unwrap();
+        let cut = frames[100].0 as usize;
+
+        // A header for another sample rate, then one like the real frames whose next frame is junk
+        let mut junk = vec![0x55; 4000];
+        junk[..4].copy_from_slice(&[0xFF, 0xFB, 0x94, 0x44]);
+        junk[1000..1004].copy_from_slice(&[0xFF, 0xFB, 0xE0, 0x44]);
+        let mut joined = bytes[..cut].to_vec();
+        joined.extend(&junk);
+        joined.extend(&bytes[cut..]);
+        let path = temp_path("mpeg.mp3");
+        std::fs::write(&path, &joined).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let range = audio_range(&mut file).unwrap();
+        let resynced = scan(&mut file, range).unwrap();
+        assert_eq!(resynced.len(), frames.len());
+        assert_eq!(resynced[100].0, frames[100].0 + junk.len() as u64);
+        assert_eq!(duration_ms(&resynced), duration_ms(&frames));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn false_sync_before_audio() {
+        // Two frames' worth of data that chain once but not a third time
+        let mut bytes = vec![0xFF, 0xFB, 0x90, 0x44];
+        bytes.resize(417, 0);
+        bytes.extend([0xFF, 0xFB, 0x90, 0x44]);
+        bytes.resize(417 + 418, 0);
+        let real = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        bytes.extend(&real[187217..187217 + 5000]);
+        assert_eq!(find_frame(&bytes), Some(835));
+    }
+
+    #[test]
+    fn info_frame_count() {
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(declared_frames(&bytes[187217..187217 + 1044]), Some(8154));
+        assert_eq!(declared_frames(&bytes[188261..189305]), None);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::frames::PictureType;
+
+// English names, mostly the descriptions from the v2.3 and v2.4 frame lists. Sorted by id
+const FRAME_NAMES: [(&str, &str); 101] = [
+    ("AENC", "Audio encryption"),
+    ("APIC", "Attached picture"),
+    ("ASPI", "Audio seek point index"),
+    ("CHAP", "Chapter"),
+    ("COMM", "Comment"),
+    ("COMR", "Commercial"),
+    ("CTOC", "Table of contents"),
+    ("ENCR", "Encryption method registration"),
+    ("EQU2", "Equalisation"),
+    ("EQUA", "Equalisation"),
+    ("ETCO", "Event timing codes"),
+    ("GEOB", "General encapsulated object"),
+    ("GRID", "Group identification registration"),
+    ("IPLS", "Involved people"),
+    ("LINK", "Linked information"),
+    ("MCDI", "Music CD identifier"),
+    ("MLLT", "MPEG location lookup table"),
+    ("OWNE", "Ownership"),
+    ("PCNT", "Play counter"),
+    ("PCST", "Podcast"),
+    ("POPM", "Popularimeter"),
+    ("POSS", "Position synchronisation"),
+    ("PRIV", "Private"),
+    ("RBUF", "Recommended buffer size"),
+    ("RVA2", "Relative volume adjustment"),
+    ("RVAD", "Relative volume adjustment"),
+    ("RVRB", "Reverb"),
+    ("SEEK", "Seek"),
+    ("SIGN", "Signature"),
+    ("SYLT", "Synchronised lyrics"),
+    ("SYTC", "Synchronised tempo codes"),
+    ("TALB", "Album"),
+    ("TBPM", "BPM"),
+    ("TCAT", "Podcast category"),
+    ("TCMP", "Compilation"),
+    ("TCOM", "Composer"),
+    ("TCON", "Genre"),
+    ("TCOP", "Copyright"),
+    ("TDAT", "Date"),
+    ("TDEN", "Encoding time"),
+    ("TDES", "Podcast description"),
+    ("TDLY", "Playlist delay"),
+    ("TDOR", "Original release time"),
+    ("TDRC", "Recording time"),
+    ("TDRL", "Release time"),
+    ("TDTG", "Tagging time"),
+    ("TENC", "Encoded by"),
+    ("TEXT", "Lyricist"),
+    ("TFLT", "File type"),
+    ("TGID", "Podcast ID"),
+    ("TIME", "Time"),
+    ("TIPL", "Involved people"),
+    ("TIT1", "Content group"),
+    ("TIT2", "Title"),
+    ("TIT3", "Subtitle"),
+    ("TKEY", "Initial key"),
+    ("TKWD", "Podcast keywords"),
+    ("TLAN", "Language"),
+    ("TLEN", "Length"),
+    ("TMCL", "Musician credits"),
+    ("TMED", "Media type"),
+    ("TMOO", "Mood"),
+    ("TOAL", "Original album"),
+    ("TOFN", "Original filename"),
+    ("TOLY", "Original lyricist"),
+    ("TOPE", "Original artist"),
+    ("TORY", "Original release year"),
+    ("TOWN", "File owner"),
+    ("TPE1", "Artist"),
+    ("TPE2", "Album artist"),
+    ("TPE3", "Conductor"),
+    ("TPE4", "Remixed by"),
+    ("TPOS", "Disc number"),
+    ("TPRO", "Produced notice"),
+    ("TPUB", "Publisher"),
+    ("TRCK", "Track number"),
+    ("TRDA", "Recording dates"),
+    ("TRSN", "Internet radio station"),
+    ("TRSO", "Internet radio station owner"),
+    ("TSIZ", "Size"),
+    ("TSO2", "Album artist sort order"),
+    ("TSOA", "Album sort order"),
+    ("TSOP", "Artist sort order"),
+    ("TSOT", "Title sort order"),
+    ("TSRC", "ISRC"),
+    ("TSSE", "Encoder settings"),
+    ("TSST", "Set subtitle"),
+    ("TXXX", "User defined text"),
+    ("TYER", "Year"),
+    ("UFID", "Unique file identifier"),
+    ("USER", "Terms of use"),
+    ("USLT", "Lyrics"),
+    ("WCOM", "Commercial information"),
+    ("WCOP", "Copyright information"),
+    ("WFED", "Podcast feed"),
+    ("WOAF", "Official audio file webpage"),
+    ("WOAR", "Official artist webpage"),
+    ("WOAS", "Official audio source webpage"),
+    ("WORS", "Official radio station webpage"),
+    ("WPAY", "Payment"),
+    ("WPUB", "Publisher webpage"),
+];
+
+// English name of a frame, None for ids the spec doesn't define
+pub fn frame_name(id: &str) -> Option<&'static str> {
+    FRAME_NAMES.binary_search_by_key(&id, |(known, _)| known).ok().map(|index| FRAME_NAMES[index].1)
+}
+
+impl PictureType {
+    // English name as the spec words it
+    pub fn name(&self) -> &'static str {
+        match self {
+            PictureType::Other => "Other",
+            PictureType::FileIcon => "32x32 file icon",
+            PictureType::OtherFileIcon => "Other file icon",
+            PictureType::FrontCover => "Front cover",
+            PictureType::BackCover => "Back cover",
+            PictureType::LeafletPage => "Leaflet page",
+            PictureType::Media => "Media",
+            PictureType::LeadArtist => "Lead artist",
+            PictureType::Artist => "Artist",
+            PictureType::Conductor => "Conductor",
+            PictureType::Band => "Band",
+            PictureType::Composer => "Composer",
+            PictureType::Lyricist => "Lyricist",
+            PictureType::RecordingLocation => "Recording location",
+            PictureType::DuringRecording => "During recording",
+            PictureType::DuringPerformance => "During performance",
+            PictureType::ScreenCapture => "Screen capture",
+            PictureType::BrightFish => "A bright coloured fish",
+            PictureType::Illustration => "Illustration",
+            PictureType::BandLogo => "Band logo",
+            PictureType::PublisherLogo => "Publisher logo",
+        }
+    }
+}
+
+// What a localization lookup is asked to name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Name<'a> {
+    Frame(&'a str),
+    Picture(PictureType),
+}
+
+// Returns the translated name, or None to fall back to English
+pub type NameLookup = Box<dyn Fn(Name) -> Option<String> + Send + Sync>;
+
+// Names for display, in English unless a lookup translates them
+pub struct Names {
+    lookup: Option<NameLookup>,
+}
+
+impl Names {
+    pub fn new() -> Self {
+        Self { lookup: None }
+    }
+
+    pub fn lookup(mut self, lookup: impl Fn(Name) -> Option<String> + Send + Sync + 'static) -> Self {
+        self.lookup = Some(Box::new(lookup));
+        self
+    }
+
+    fn translate(&self, name: Name) -> Option<String> {
+        self.lookup.as_ref().and_then(|lookup| lookup(name))
+    }
+
+    // Unknown frames are named by their id
+    pub fn frame(&self, id: &str) -> String {
+        self.translate(Name::Frame(id)).or_else(|| frame_name(id).map(str::to_string)).unwrap_or_else(|| id.to_string())
+    }
+
+    pub fn picture_type(&self, picture_type: PictureType) -> String {
+        self.translate(Name::Picture(picture_type)).unwrap_or_else(|| picture_type.name().to_string())
+    }
+}
+
+impl Default for Names {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_names() {
+        assert_eq!(frame_name("TPE2"), Some("Album artist"));
+        assert_eq!(frame_name("WPUB"), Some("Publisher webpage"));
+        assert_eq!(frame_name("ZZZZ"), None);
+        assert!(FRAME_NAMES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(PictureType::BrightFish.name(), "A bright coloured fish");
+    }
+
+    #[test]
+    fn lookup_with_fallback() {
+        let names = Names::new().lookup(|name| match name {
+            Name::Frame("TIT2") => Some("Titel".to_string()),
+            Name::Picture(PictureType::FrontCover) => Some("Framsida".to_string()),
+            _ => None,
+        });
+        assert_eq!((names.frame("TIT2"), names.frame("TALB"), names.frame("XYZ1")), ("Titel".into(), "Album".into(), "XYZ1".into()));
+        assert_eq!((names.picture_type(PictureType::FrontCover), names.picture_type(PictureType::Media)), ("Framsida".into(), "Media".into()));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::Tag;
+use std::fmt;
+
+// Number and optional total from TRCK or TPOS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackNumber {
+    pub number: u32,
+    pub total: Option<u32>,
+}
+
+impl fmt::Display for TrackNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.total {
+            Some(total) => write!(f, "{}/{total}", self.number),
+            None => write!(f, "{}", self.number),
+        }
+    }
+}
+
+// (start, end) of every run of ASCII digits
+fn digit_runs(text: &str) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, c) in text.char_indices() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, end)) if *end == i => *end = i + 1,
+            _ => runs.push((i, i + 1)),
+        }
+    }
+    runs
+}
+
+// The first four digit run, so "2007-06-01", "(2007)" and "12 March 2007" all give 2007
+fn parse_year(text: &str) -> Option<i32> {
+    let (start, end) = digit_runs(text).into_iter().find(|(start, end)| end - start == 4)?;
+    text[start..end].parse().ok()
+}
+
+// The first number, and a second one as the total when "/", "of" or "-" sits between them.
+// "03", "3/12", "3 of 12" and "Track 3" all work, zero or no digits at all give None
+fn parse_track(text: &str) -> Option<TrackNumber> {
+    let runs = digit_runs(text);
+    let (start, end) = *runs.first()?;
+    let number = text[start..end].parse().ok().filter(|number| *number > 0)?;
+    let total = runs.get(1).and_then(|(total_start, total_end)| {
+        let separator = text[end..*total_start].trim().to_lowercase();
+        ["/", "of", "-"].contains(&separator.as_str()).then(|| text[*total_start..*total_end].parse().ok())?
+    });
+    Some(TrackNumber { number, total: total.filter(|total| *total >= number) })
+}
+
+// The leading decimal number, with a comma accepted as the decimal point so "120", "120.5",
+// "120,5" and "120 BPM" all work. Zero and negative tempos give None
+fn parse_bpm(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let end = text.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',')).unwrap_or(text.len());
+    text[..end].replace(',', ".").parse().ok().filter(|bpm: &f64| bpm.is_finite() && *bpm > 0.0)
+}
+
+impl Tag {
+    // TDRC then TYER, falling back to the original release year in TDOR or TORY
+    pub fn year(&self) -> Option<i32> {
+        ["TDRC", "TYER", "TDOR", "TORY"].iter().find_map(|id| self.text(id).as_deref().and_then(parse_year))
+    }
+
+    // A total smaller than the number is dropped as junk
+    pub fn track(&self) -> Option<TrackNumber> {
+        self.text("TRCK").as_deref().and_then(parse_track)
+    }
+
+    pub fn disc(&self) -> Option<TrackNumber> {
+        self.text("TPOS").as_deref().and_then(parse_track)
+    }
+
+    pub fn bpm(&self) -> Option<f64> {
+        self.text("TBPM").as_deref().and_then(parse_bpm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lenient_years() {
+        for text in ["2017", "2017-11-17", "(2017)", "17 Nov 2017", " 2017 "] {
+            assert_eq!(parse_year(text), Some(2017), "{text}");
+        }
+        assert_eq!(parse_year("17"), None);
+        assert_eq!(parse_year("unknown"), None);
+    }
+
+    #[test]
+    fn lenient_tracks() {
+        let track = |number, total| Some(TrackNumber { number, total });
+        assert_eq!(parse_track("3"), track(3, None));
+        assert_eq!(parse_track("03/12"), track(3, Some(12)));
+        assert_eq!(parse_track("3 of 12"), track(3, Some(12)));
+        assert_eq!(parse_track("Track 3"), track(3, None));
+        assert_eq!(parse_track("3 (12)"), track(3, None));
+        assert_eq!(parse_track("12/3"), track(12, None));
+        assert_eq!(parse_track("0"), None);
+        assert_eq!(track(3, Some(12)).unwrap().to_string(), "3/12");
+    }
+
+    #[test]
+    fn lenient_bpm() {
+        assert_eq!(parse_bpm("120"), Some(120.0));
+        assert_eq!(parse_bpm("120,5"), Some(120.5));
+        assert_eq!(parse_bpm("96 BPM"), Some(96.0));
+        assert_eq!(parse_bpm("0"), None);
+        assert_eq!(parse_bpm("fast"), None);
+    }
+
+    #[test]
+    fn tag_accessors() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!((tag.year(), tag.track(), tag.bpm(), tag.disc()), (Some(2017), Some(TrackNumber { number: 2, total: None }), None, None));
+        tag.set_text("TDRC", "2017-11-17T00:00");
+        tag.set_text("TYER", "1999");
+        tag.set_text("TPOS", "1 of 2");
+        assert_eq!(tag.year(), Some(2017));
+        assert_eq!(tag.disc(), Some(TrackNumber { number: 1, total: Some(2) }));
+    }
+}
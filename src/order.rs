@@ -0,0 +1,92 @@
+use crate::Frame;
+
+// Presets list frame ids, "X*" for every other id starting with X and "*" for everything not listed
+
+// Identification first so players reading only the start of the tag find it, pictures last
+const RECOMMENDED: [&str; 18] = [
+    "UFID", "TIT2", "TPE1", "TPE2", "TALB", "TRCK", "TPOS", "TYER", "TDRC", "TCON", "TLEN", "T*", "W*", "COMM", "USLT",
+    "SYLT", "*", "APIC",
+];
+
+const ITUNES: [&str; 17] = [
+    "TIT2", "TPE1", "TPE2", "TALB", "TCON", "TCOM", "TRCK", "TPOS", "TYER", "TDRC", "TBPM", "TCMP", "T*", "COMM", "USLT",
+    "*", "APIC",
+];
+
+const MP3TAG: [&str; 13] = ["TIT2", "TPE1", "TALB", "TRCK", "TYER", "TDRC", "TCON", "COMM", "TPE2", "TPOS", "T*", "*", "APIC"];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum FrameOrder {
+    // Frames are written in the order they are in the tag
+    #[default]
+    Keep,
+    Recommended,
+    ITunes,
+    Mp3tag,
+    // Ids and patterns like the presets use, unlisted frames go last unless a "*" places them
+    Custom(Vec<String>),
+}
+
+impl FrameOrder {
+    fn patterns(&self) -> Vec<&str> {
+        match self {
+            FrameOrder::Keep => Vec::new(),
+            FrameOrder::Recommended => RECOMMENDED.to_vec(),
+            FrameOrder::ITunes => ITUNES.to_vec(),
+            FrameOrder::Mp3tag => MP3TAG.to_vec(),
+            FrameOrder::Custom(ids) => ids.iter().map(String::as_str).collect(),
+        }
+    }
+
+    // A listed id wins over any pattern that also matches it
+    fn rank(patterns: &[&str], id: &str) -> usize {
+        let wildcard = |pattern: &&str| pattern.strip_suffix('*').is_some_and(|prefix| id.starts_with(prefix));
+        patterns.iter().position(|pattern| *pattern == id)
+            .or_else(|| patterns.iter().position(wildcard))
+            .unwrap_or(patterns.len())
+    }
+
+    // Stable, so frames of the same rank keep their order
+    pub fn sort(&self, frames: &mut [Frame]) {
+        if *self == FrameOrder::Keep {
+            return;
+        }
+        let patterns = self.patterns();
+        frames.sort_by_cached_key(|frame| Self::rank(&patterns, &frame.id()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tag;
+
+    fn ids(frames: &[Frame]) -> Vec<String> {
+        frames.iter().map(Frame::id).collect()
+    }
+
+    #[test]
+    fn presets() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.add_frame(Frame::new("PRIV", b"owner\0".to_vec()).unwrap());
+        tag.add_frame(Frame::new("WOAR", b"https://kinggizzardandthelizardwizard.com".to_vec()).unwrap());
+
+        let mut frames = tag.frames().to_vec();
+        FrameOrder::Recommended.sort(&mut frames);
+        assert_eq!(ids(&frames), ["TIT2", "TPE1", "TPE2", "TALB", "TRCK", "TYER", "TSRC", "WOAR", "COMM", "PRIV", "APIC"]);
+
+        FrameOrder::Mp3tag.sort(&mut frames);
+        assert_eq!(ids(&frames), ["TIT2", "TPE1", "TALB", "TRCK", "TYER", "COMM", "TPE2", "TSRC", "WOAR", "PRIV", "APIC"]);
+
+        let mut kept = tag.frames().to_vec();
+        FrameOrder::Keep.sort(&mut kept);
+        assert_eq!(ids(&kept), ids(tag.frames()));
+    }
+
+    #[test]
+    fn custom_order() {
+        let mut frames = Tag::from_file("test/Polygondwanaland.mp3").unwrap().frames().to_vec();
+        FrameOrder::Custom(vec!["APIC".to_string(), "T*".to_string()]).sort(&mut frames);
+        assert_eq!(ids(&frames), ["APIC", "TIT2", "TPE1", "TRCK", "TALB", "TYER", "TSRC", "TPE2", "COMM"]);
+    }
+}
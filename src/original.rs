@@ -0,0 +1,92 @@
+use crate::Tag;
+
+// The original year is TORY in v2.3 and the TDOR timestamp in v2.4
+fn year_id(major_ver: u8) -> &'static str {
+    if major_ver == 4 { "TDOR" } else { "TORY" }
+}
+
+// Details of the recording a remix or cover is based on
+impl Tag {
+    pub fn original_filename(&self) -> Option<String> {
+        self.text("TOFN")
+    }
+
+    pub fn set_original_filename(&mut self, filename: &str) {
+        self.set_text("TOFN", filename);
+    }
+
+    pub fn original_album(&self) -> Option<String> {
+        self.text("TOAL")
+    }
+
+    pub fn set_original_album(&mut self, album: &str) {
+        self.set_text("TOAL", album);
+    }
+
+    pub fn original_artist(&self) -> Option<String> {
+        self.text("TOPE")
+    }
+
+    pub fn set_original_artist(&mut self, artist: &str) {
+        self.set_text("TOPE", artist);
+    }
+
+    pub fn original_lyricist(&self) -> Option<String> {
+        self.text("TOLY")
+    }
+
+    pub fn set_original_lyricist(&mut self, lyricist: &str) {
+        self.set_text("TOLY", lyricist);
+    }
+
+    // Read from either frame so tags mixing up the versions still give a year
+    pub fn original_year(&self) -> Option<u16> {
+        let text = self.text(year_id(self.version())).or_else(|| self.text(year_id(7 - self.version())))?;
+        text.get(..4)?.parse().ok()
+    }
+
+    // Writes the frame for the tag's version and removes the other one
+    pub fn set_original_year(&mut self, year: u16) {
+        let version = self.version();
+        self.remove(year_id(7 - version));
+        self.set_text(year_id(version), &format!("{year:04}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn original_frames() {
+        let mut tag = Tag::new(3);
+        tag.set_original_filename("polygondwanaland.wav");
+        tag.set_original_album("Polygondwanaland");
+        tag.set_original_artist("King Gizzard & The Lizard Wizard");
+        tag.set_original_lyricist("Stu Mackenzie");
+        assert_eq!(tag.original_filename().as_deref(), Some("polygondwanaland.wav"));
+        assert_eq!(tag.original_album().as_deref(), Some("Polygondwanaland"));
+        assert_eq!(tag.original_artist().as_deref(), Some("King Gizzard & The Lizard Wizard"));
+        assert_eq!(tag.original_lyricist().as_deref(), Some("Stu Mackenzie"));
+    }
+
+    #[test]
+    fn original_year_by_version() {
+        let mut tag = Tag::new(3);
+        tag.set_original_year(2017);
+        assert_eq!(tag.text("TORY").as_deref(), Some("2017"));
+
+        let (mut tag, _) = tag.convert(4);
+        assert_eq!(tag.original_year(), Some(2017));
+        tag.set_text("TDOR", "2017-11-17");
+        assert_eq!(tag.original_year(), Some(2017));
+
+        // A v2.3 frame in a v2.4 tag is still read, and replaced when set
+        tag.remove("TDOR");
+        tag.set_text("TORY", "2016");
+        assert_eq!(tag.original_year(), Some(2016));
+        tag.set_original_year(2017);
+        assert!(tag.frame("TORY").is_none());
+        assert_eq!(tag.text("TDOR").as_deref(), Some("2017"));
+    }
+}
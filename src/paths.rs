@@ -0,0 +1,113 @@
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+// Paths this long need the \\?\ prefix on Windows, a little under MAX_PATH so
+// the temporary file written next to the original fits too
+const LONG_PATH: usize = 244;
+
+// Windows won't create a file with one of these names, whatever its extension
+const RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4",
+    "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Longest file name most file systems allow, in bytes
+const MAX_NAME: usize = 255;
+
+// The \\?\ form of an absolute Windows path that is too long for the normal APIs
+fn verbatim(path: &str) -> Option<String> {
+    if path.len() < LONG_PATH || path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', r"\");
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{share}"));
+    }
+    let bytes = path.as_bytes();
+    let drive = bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    drive.then(|| format!(r"\\?\{path}"))
+}
+
+// The path to hand to the OS when opening or creating a file. On Windows long
+// paths get the \\?\ prefix so they aren't cut at MAX_PATH, elsewhere it's unchanged
+pub fn long_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+    // Verbatim paths skip normalisation so . and .. have to be resolved first
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match absolute.to_str().and_then(verbatim) {
+        Some(verbatim) => PathBuf::from(verbatim),
+        None => path.to_path_buf(),
+    }
+}
+
+// Nanoseconds since the epoch of the last change to the file. On Unix that is the inode change
+// time, which writing with preserve_mtime can't set back. Elsewhere only the modification time is there
+pub(crate) fn changed_ns(metadata: &Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.ctime() as u64).wrapping_mul(1_000_000_000).wrapping_add(metadata.ctime_nsec() as u64)
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.modified().ok().and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok()).map_or(0, |x| x.as_nanos() as u64)
+    }
+}
+
+// A file name every common file system accepts. Separators, characters Windows
+// forbids and control characters become _, trailing dots and spaces are dropped,
+// reserved device names get a leading _ and the result is cut to 255 bytes
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut name: String = name.chars().map(|c| if "/\\:*?\"<>|".contains(c) || c.is_control() { '_' } else { c }).collect();
+    name.truncate(name.trim_end_matches(['.', ' ']).len());
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        name.insert(0, '_');
+    }
+    if name.is_empty() {
+        name.push('_');
+    }
+    if name.len() > MAX_NAME {
+        // Keep the extension when there is one
+        let extension = name.rfind('.').map(|dot| name[dot..].to_string()).filter(|extension| extension.len() < 16).unwrap_or_default();
+        let mut end = MAX_NAME - extension.len();
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name = format!("{}{extension}", &name[..end]);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbatim_long_paths() {
+        let folder = "a".repeat(250);
+        assert_eq!(verbatim(&format!("C:/Music/{folder}/01.mp3")), Some(format!(r"\\?\C:\Music\{folder}\01.mp3")));
+        assert_eq!(verbatim(&format!(r"\\nas\music\{folder}")), Some(format!(r"\\?\UNC\nas\music\{folder}")));
+        assert_eq!(verbatim(&format!(r"\\?\C:\{folder}")), None);
+        assert_eq!(verbatim(r"C:\Music\01.mp3"), None);
+        assert_eq!(verbatim(&format!("Music/{folder}")), None);
+        assert!(long_path("test/Polygondwanaland.mp3").exists());
+    }
+
+    #[test]
+    fn sanitized_names() {
+        assert_eq!(sanitize_file_name("AC/DC: Back in Black?.mp3"), "AC_DC_ Back in Black_.mp3");
+        assert_eq!(sanitize_file_name("con.mp3"), "_con.mp3");
+        assert_eq!(sanitize_file_name("LPT1"), "_LPT1");
+        assert_eq!(sanitize_file_name("Console.mp3"), "Console.mp3");
+        assert_eq!(sanitize_file_name("Ending... "), "Ending");
+        assert_eq!(sanitize_file_name(""), "_");
+
+        let long = sanitize_file_name(&format!("{}.mp3", "é".repeat(200)));
+        assert!(long.len() <= MAX_NAME && long.ends_with("é.mp3"));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::paths::long_path;
+use crate::ID3::u32_from_sync_safe;
+use crate::wire;
+use crate::{Frame, Header, Tag, mpeg};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Frames a summary is made of
+const WANTED: [&str; 4] = ["TIT2", "TPE1", "TALB", "TLEN"];
+
+// Most bytes read from the tag, frames past this are never looked at
+const BUDGET: u64 = 4 * 1024;
+
+// Text frames bigger than this are skipped rather than read
+const MAX_TEXT: u64 = 1024;
+
+// What a file listing needs, read without parsing the whole tag
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TagSummary {
+    pub version: Option<u8>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    // Milliseconds, from TLEN or estimated from the first MPEG frame
+    pub duration: Option<u64>,
+    pub tag_size: u64,
+}
+
+fn read_bytes(file: &mut File, n: u64, spent: &mut u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    file.take(n).read_to_end(&mut bytes)?;
+    *spent += bytes.len() as u64;
+    Ok(bytes)
+}
+
+// Constant bitrate estimate from the first frame after the tag
+fn estimate_duration(file: &mut File, start: u64, spent: &mut u64) -> io::Result<Option<u64>> {
+    let length = file.metadata()?.len();
+    file.seek(SeekFrom::Start(start))?;
+    let bytes = read_bytes(file, 1024, spent)?;
+    let header = mpeg::find_frame(&bytes).and_then(|offset| mpeg::FrameHeader::from_bytes(&bytes[offset..]));
+    Ok(header.map(|header| length.saturating_sub(start) * 8 * 1000 / header.bitrate() as u64))
+}
+
+// Fills in the wanted frames, false when the tag's layout needs the full parser
+fn scan(file: &mut File, header_bytes: &[u8], summary: &mut TagSummary, spent: &mut u64) -> io::Result<bool> {
+    let Some(header) = Header::from_bytes(header_bytes) else {
+        return Ok(true);
+    };
+    let version = header.version().0;
+    if (version != 4 && header.unsynchronisation()) || (version == 2 && header_bytes[5] & 0b_01000000 != 0) {
+        return Ok(false);
+    }
+
+    let end = 10 + header.size();
+    let mut position = 10;
+    if header.extended_header() {
+        // The v2.4 size is sync-safe and counts its own four bytes, the v2.3 one doesn't
+        let size = read_bytes(file, 4, spent)?;
+        if size.len() < 4 {
+            return Ok(false);
+        }
+        position += match version {
+            4 => u32_from_sync_safe(&size) as u64,
+            _ => 4 + u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as u64,
+        };
+    }
+
+    let mut found = 0;
+    let header_len = wire::header_len(version);
+    while position + header_len as u64 <= end && *spent < BUDGET && found < WANTED.len() {
+        file.seek(SeekFrom::Start(position))?;
+        let frame_header = read_bytes(file, header_len as u64, spent)?;
+        if frame_header.len() < header_len || frame_header[0] == 0 {
+            break;
+        }
+        let size = wire::body_size(&frame_header, version) as u64;
+        position += header_len as u64 + size;
+
+        let id: String = wire::id(&frame_header, version).unwrap_or_default().iter().map(|x| *x as char).collect();
+        if !WANTED.contains(&id.as_str()) || size > MAX_TEXT {
+            continue;
+        }
+        // Unsynchronised, compressed, encrypted or grouped frames are left to the full parser
+        if version != 2 && frame_header[9] != 0 {
+            return Ok(false);
+        }
+        let data = read_bytes(file, size, spent)?;
+        let Some(frame) = Frame::new(&id, data) else {
+            continue;
+        };
+        let text = Some(frame.parse_text()).filter(|text| !text.is_empty());
+        match id.as_str() {
+            "TIT2" => summary.title = text,
+            "TPE1" => summary.artist = text,
+            "TALB" => summary.album = text,
+            _ => summary.duration = text.and_then(|text| text.trim().parse().ok()),
+        }
+        found += 1;
+    }
+    Ok(true)
+}
+
+impl Tag {
+    // Title, artist, album and duration from the first few KB of the file
+    pub fn peek(filename: impl AsRef<Path>) -> io::Result<TagSummary> {
+        let filename = filename.as_ref();
+        let mut file = File::open(long_path(filename))?;
+        let mut spent = 0;
+        let mut summary = TagSummary::default();
+
+        let header_bytes = read_bytes(&mut file, 10, &mut spent)?;
+        let Some(header) = Header::from_bytes(&header_bytes) else {
+            summary.duration = estimate_duration(&mut file, 0, &mut spent)?;
+            return Ok(summary);
+        };
+        summary.version = Some(header.version().0);
+        summary.tag_size = header.tag_size();
+
+        if !scan(&mut file, &header_bytes, &mut summary, &mut spent)? {
+            let tag = Tag::from_file(filename)?;
+            summary.title = tag.title().filter(|text| !text.is_empty());
+            summary.artist = tag.artist().filter(|text| !text.is_empty());
+            summary.album = tag.album().filter(|text| !text.is_empty());
+            summary.duration = tag.text("TLEN").and_then(|text| text.trim().parse().ok());
+        }
+        if summary.duration.is_none() {
+            summary.duration = estimate_duration(&mut file, summary.tag_size, &mut spent)?;
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::ID3::sync_safe_from_u32;
+    use crate::WriteOptions;
+    use std::fs;
+
+    #[test]
+    fn summary_of_test_file() {
+        let summary = Tag::peek("test/Polygondwanaland.mp3").unwrap();
+        assert_eq!(summary.version, Some(3));
+        assert_eq!(summary.title.as_deref(), Some("Polygondwanaland"));
+        assert_eq!(summary.artist.as_deref(), Some("King Gizzard & The Lizard Wizard"));
+        assert_eq!(summary.album.as_deref(), Some("Polygondwanaland"));
+        assert_eq!(summary.tag_size, 187217);
+
+        // No TLEN so the duration is estimated from the 320 kbps frames
+        assert_eq!(summary.duration, Some(213028));
+    }
+
+    #[test]
+    fn prefers_tlen() {
+        let path = temp_path("tlen.mp3");
+        let path = path.as_path();
+        fs::copy("test/Polygondwanaland.mp3", path).unwrap();
+        let mut tag = Tag::from_file(path).unwrap();
+        tag.set_text("TLEN", "213024");
+        tag.write_to_file(path, &WriteOptions::new().version(4)).unwrap();
+
+        let summary = Tag::peek(path).unwrap();
+        assert_eq!((summary.version, summary.duration), (Some(4), Some(213024)));
+        assert_eq!(summary.album.as_deref(), Some("Polygondwanaland"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn file_without_tag() {
+        let path = temp_path("untagged.mp3");
+        let bytes = fs::read("test/Polygondwanaland.mp3").unwrap();
+        fs::write(&path, &bytes[187217..]).unwrap();
+
+        let summary = Tag::peek(&path).unwrap();
+        assert_eq!((summary.version, summary.title), (None, None));
+        assert!(summary.duration.is_some());
+        fs::remove_file(path).unwrap();
+    }
+
+    // A v2.4 tag with a CRC in its extended header, TIT2 and a TPE1 with a data length indicator
+    fn v24_tag(extended: bool, data_length: bool) -> Vec<u8> {
+        let frame = |id: &[u8], format: u8, data: &[u8]| [id, &sync_safe_from_u32(data.len() as u32), &[0, format], data].concat();
+        let mut body = Vec::new();
+        if extended {
+            body.extend([0, 0, 0, 12, 1, 0x20, 5, 0, 0, 0, 0, 0]);
+        }
+        body.extend(frame(b"TIT2", 0, b"\x03Crumbling Castle"));
+        let artist = [&sync_safe_from_u32(11)[..], b"\x03Gizzards!!"].concat();
+        body.extend(if data_length { frame(b"TPE1", 1, &artist) } else { frame(b"TPE1", 0, &artist[4..]) });
+        let flags = if extended { 0x40 } else { 0 };
+        [&b"ID3\x04\x00"[..], &[flags], &sync_safe_from_u32(body.len() as u32), &body].concat()
+    }
+
+    #[test]
+    fn v24_extended_header_and_frame_flags() {
+        let path = temp_path("v24.mp3");
+        fs::write(&path, v24_tag(true, false)).unwrap();
+        let summary = Tag::peek(&path).unwrap();
+        assert_eq!((summary.title.as_deref(), summary.artist.as_deref()), (Some("Crumbling Castle"), Some("Gizzards!!")));
+
+        // The data length indicator sits before the text, only the full parse knows to skip it
+        fs::write(&path, v24_tag(false, true)).unwrap();
+        assert_eq!(Tag::peek(&path).unwrap().artist.as_deref(), Some("Gizzards!!"));
+        fs::remove_file(path).unwrap();
+    }
+}
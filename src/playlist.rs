@@ -0,0 +1,65 @@
+use crate::paths::long_path;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension().and_then(|x| x.to_str())
+        .is_some_and(|x| x.eq_ignore_ascii_case("m3u") || x.eq_ignore_ascii_case("m3u8"))
+}
+
+// M3U8 is UTF-8, plain M3U and cue sheets are usually Latin-1 but often UTF-8 anyway
+pub(crate) fn decode(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|x| *x as char).collect(),
+    }
+}
+
+// Entries of an M3U or M3U8 playlist in order, relative ones resolved against the playlist's folder.
+// Comments, #EXTINF lines and stream URLs are skipped
+pub fn parse(text: &str, base: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match line.strip_prefix("file://") {
+            Some(path) => Some(PathBuf::from(path)),
+            None if line.contains("://") => None,
+            None => Some(base.join(line)),
+        })
+        .collect()
+}
+
+pub fn read(filename: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let filename = filename.as_ref();
+    let base = filename.parent().unwrap_or(Path::new(""));
+    Ok(parse(&decode(&fs::read(long_path(filename))?), base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+
+    #[test]
+    fn resolves_entries() {
+        let text = "#EXTM3U\n#EXTINF:213,King Gizzard - Crumbling Castle\n01 Crumbling Castle.mp3\r\n\n/music/other.mp3\nhttp://radio.example/stream\nfile:///music/third.mp3\n";
+        let entries = parse(text, Path::new("/library/Polygondwanaland"));
+        assert_eq!(entries, [
+            PathBuf::from("/library/Polygondwanaland/01 Crumbling Castle.mp3"),
+            PathBuf::from("/music/other.mp3"),
+            PathBuf::from("/music/third.mp3"),
+        ]);
+    }
+
+    #[test]
+    fn reads_latin1_playlists() {
+        let path = temp_path("playlist.m3u");
+        fs::write(&path, b"\xC5ngest.mp3\n").unwrap();
+        let entries = read(&path).unwrap();
+        assert_eq!(entries, [std::env::temp_dir().join("Ångest.mp3")]);
+        assert!(is_playlist(&path));
+        fs::remove_file(path).unwrap();
+    }
+}
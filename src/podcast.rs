@@ -0,0 +1,123 @@
+use crate::frames::Chapter;
+use crate::{Frame, Tag};
+
+// Frames iTunes added for podcasts, not part of either spec but read by every podcast app
+pub(crate) const FRAMES: [&str; 6] = ["PCST", "TGID", "TDES", "WFED", "TCAT", "TKWD"];
+
+// Everything a podcast app reads from an episode. Missing fields are left alone when applied
+#[derive(Clone, Default)]
+pub struct PodcastMetadata {
+    // WFED, stored as a text frame like iTunes does rather than as a URL frame
+    pub feed_url: Option<String>,
+    // TGID, the episode's GUID from the feed
+    pub episode_id: Option<String>,
+    // TDES, the long description
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub keywords: Vec<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+impl PodcastMetadata {
+    pub fn from_tag(tag: &Tag) -> Self {
+        Self {
+            feed_url: tag.text("WFED"),
+            episode_id: tag.text("TGID"),
+            description: tag.text("TDES"),
+            category: tag.text("TCAT"),
+            keywords: tag.text("TKWD")
+                .map(|keywords| keywords.split(',').map(str::trim).filter(|x| !x.is_empty()).map(String::from).collect())
+                .unwrap_or_default(),
+            chapters: tag.chapters(),
+        }
+    }
+
+    // Marks the tag as a podcast episode and writes every field that is set
+    pub fn apply(&self, tag: &mut Tag) {
+        if tag.frame("PCST").is_none() {
+            tag.add_frame(Frame::new("PCST", vec![0; 4]).unwrap());
+        }
+        let fields = [
+            ("WFED", self.feed_url.clone()),
+            ("TGID", self.episode_id.clone()),
+            ("TDES", self.description.clone()),
+            ("TCAT", self.category.clone()),
+            ("TKWD", Some(self.keywords.join(",")).filter(|x| !x.is_empty())),
+        ];
+        for (id, text) in fields {
+            if let Some(text) = text {
+                tag.set_text(id, &text);
+            }
+        }
+        if !self.chapters.is_empty() {
+            tag.set_chapters(&self.chapters);
+        }
+    }
+}
+
+impl Tag {
+    // Players only treat a file as an episode when it has the PCST flag
+    pub fn is_podcast(&self) -> bool {
+        self.frame("PCST").is_some()
+    }
+
+    pub fn podcast(&self) -> Option<PodcastMetadata> {
+        self.is_podcast().then(|| PodcastMetadata::from_tag(self))
+    }
+
+    pub fn set_podcast(&mut self, metadata: &PodcastMetadata) {
+        metadata.apply(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Diagnostics, ReadOptions, Reader};
+    use std::io::Cursor;
+
+    fn episode() -> PodcastMetadata {
+        let mut chapter = Chapter::new("chp0", 0, 60_000);
+        chapter.set_title("Intro", 3);
+        PodcastMetadata {
+            feed_url: Some("https://podcast.example/feed.xml".to_string()),
+            episode_id: Some("urn:uuid:9f1c2a7e-41c6-4c55-a1b3-2c1d0e8f7a10".to_string()),
+            description: Some("Stu talks about recording Polygondwanaland".to_string()),
+            category: Some("Music".to_string()),
+            keywords: vec!["gizzard".to_string(), "interview".to_string()],
+            chapters: vec![chapter],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut tag = Tag::new(3);
+        assert!(tag.podcast().is_none());
+        tag.set_podcast(&episode());
+
+        let diagnostics = Diagnostics::new();
+        let bytes = tag.to_bytes(0);
+        let tag = Tag::from_reader_with(&mut Reader::from_stream(Cursor::new(bytes)), &ReadOptions::new().diagnostics(diagnostics.clone())).unwrap();
+        assert!(diagnostics.is_empty());
+
+        let read = tag.podcast().unwrap();
+        assert_eq!(read.feed_url, episode().feed_url);
+        assert_eq!(read.episode_id, episode().episode_id);
+        assert_eq!(read.description, episode().description);
+        assert_eq!(read.category.as_deref(), Some("Music"));
+        assert_eq!(read.keywords, ["gizzard", "interview"]);
+        assert_eq!(read.chapters[0].title().as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn apply_keeps_unset_fields() {
+        let mut tag = Tag::new(4);
+        tag.set_podcast(&episode());
+        tag.set_podcast(&PodcastMetadata { category: Some("Arts".to_string()), ..Default::default() });
+
+        let read = tag.podcast().unwrap();
+        assert_eq!((read.category.as_deref(), read.chapters.len()), (Some("Arts"), 1));
+        assert_eq!(read.feed_url, episode().feed_url);
+        assert_eq!(tag.frames().iter().filter(|frame| frame.id() == "PCST").count(), 1);
+    }
+}
@@ -0,0 +1,46 @@
+// Allocation free checks for format sniffers that only need to know how much to skip
+
+// Bytes taken by the ID3v2 tag starting with these ten, header and footer included.
+// Accepts v2.2 to v2.4 so anything a player would skip is skipped
+pub const fn id3v2_size(header: &[u8; 10]) -> Option<u32> {
+    if header[0] != b'I' || header[1] != b'D' || header[2] != b'3' {
+        return None;
+    }
+    if header[3] < 2 || header[3] > 4 || header[4] == 0xFF {
+        return None;
+    }
+    if header[6] & 0x80 != 0 || header[7] & 0x80 != 0 || header[8] & 0x80 != 0 || header[9] & 0x80 != 0 {
+        return None;
+    }
+    let size = (header[6] as u32) << 21 | (header[7] as u32) << 14 | (header[8] as u32) << 7 | header[9] as u32;
+    let footer = if header[3] == 4 && header[5] & 0b_00010000 != 0 { 10 } else { 0 };
+    Some(10 + size + footer)
+}
+
+// Whether the last 128 bytes of a file are an ID3v1 tag
+pub const fn has_id3v1(tail: &[u8; 128]) -> bool {
+    tail[0] == b'T' && tail[1] == b'A' && tail[2] == b'G'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, Id3v1, Tag};
+
+    #[test]
+    fn matches_header_parsing() {
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        let header: [u8; 10] = bytes[..10].try_into().unwrap();
+        assert_eq!(id3v2_size(&header).map(u64::from), Some(Header::from_bytes(&header).unwrap().tag_size()));
+        assert_eq!(id3v2_size(b"ID3\x04\x00\x10\x00\x00\x01\x00"), Some(10 + 128 + 10));
+        assert_eq!(id3v2_size(b"ID3\x03\x00\x00\x00\x00\x80\x00"), None);
+        assert_eq!(id3v2_size(b"\xFF\xFB\x90\x64\x00\x00\x00\x00\x00\x00"), None);
+    }
+
+    #[test]
+    fn id3v1_tail() {
+        const EMPTY: [u8; 128] = [0; 128];
+        const _: () = assert!(!has_id3v1(&EMPTY));
+        assert!(has_id3v1(&Id3v1::from_tag(&Tag::new(4)).to_bytes()));
+    }
+}
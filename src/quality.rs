@@ -0,0 +1,265 @@
+use crate::diagnostics::is_other_version;
+use crate::report::mp3_files;
+use crate::Tag;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Check {
+    // Completeness
+    Title,
+    Artist,
+    Album,
+    Track,
+    Year,
+    Art,
+    // Consistency, TRCK and TPOS numbers are no larger than their totals
+    Position,
+    // Cleanliness, no frames from the other version of the spec
+    NoDeprecated,
+    // Every text frame decodes and uses an encoding its version allows
+    ValidText,
+}
+
+impl Check {
+    pub const ALL: [Check; 9] = [
+        Check::Title,
+        Check::Artist,
+        Check::Album,
+        Check::Track,
+        Check::Year,
+        Check::Art,
+        Check::Position,
+        Check::NoDeprecated,
+        Check::ValidText,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Check::Title => "title",
+            Check::Artist => "artist",
+            Check::Album => "album",
+            Check::Track => "track number",
+            Check::Year => "year",
+            Check::Art => "cover art",
+            Check::Position => "track and disc totals",
+            Check::NoDeprecated => "no deprecated frames",
+            Check::ValidText => "valid text encodings",
+        }
+    }
+
+    fn index(self) -> usize {
+        Check::ALL.iter().position(|check| *check == self).unwrap()
+    }
+}
+
+// Points each check is worth, a check that fails gives none of its points
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QualityWeights {
+    weights: [u32; 9],
+}
+
+impl QualityWeights {
+    // Title and artist count the most, the rest are worth the same
+    pub fn new() -> Self {
+        Self { weights: [20, 20, 10, 10, 10, 10, 10, 5, 5] }
+    }
+
+    // A weight of zero leaves the check out
+    pub fn weight(mut self, check: Check, weight: u32) -> Self {
+        self.weights[check.index()] = weight;
+        self
+    }
+
+    pub fn total(&self) -> u32 {
+        self.weights.iter().sum()
+    }
+}
+
+impl Default for QualityWeights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QualityScore {
+    pub points: u32,
+    pub max: u32,
+    // Checks that failed and carried weight, heaviest first
+    pub failed: Vec<Check>,
+}
+
+impl QualityScore {
+    // 0 to 100, a tag scored with all weights at zero gets 100
+    pub fn percent(&self) -> u32 {
+        match self.max {
+            0 => 100,
+            max => self.points * 100 / max,
+        }
+    }
+}
+
+fn has_text(tag: &Tag, id: &str) -> bool {
+    tag.text(id).is_some_and(|text| !text.trim().is_empty())
+}
+
+// Missing positions pass, the completeness checks cover those
+fn consistent_position(tag: &Tag, id: &str) -> bool {
+    let Some(text) = tag.text(id) else {
+        return true;
+    };
+    let number = |text: &str| text.trim().parse::<u32>().ok().filter(|number| *number > 0);
+    match text.split_once('/') {
+        Some((position, total)) => number(position).zip(number(total)).is_some_and(|(position, total)| position <= total),
+        None => number(&text).is_some(),
+    }
+}
+
+fn passes(tag: &Tag, check: Check) -> bool {
+    match check {
+        Check::Title => has_text(tag, "TIT2"),
+        Check::Artist => has_text(tag, "TPE1"),
+        Check::Album => has_text(tag, "TALB"),
+        Check::Track => tag.track().is_some(),
+        Check::Year => tag.year().is_some(),
+        Check::Art => tag.frame("APIC").is_some(),
+        Check::Position => consistent_position(tag, "TRCK") && consistent_position(tag, "TPOS"),
+        Check::NoDeprecated => !tag.frames().iter().any(|frame| is_other_version(&frame.id(), tag.version())),
+        Check::ValidText => tag.frames().iter().filter(|frame| frame.id().starts_with('T')).all(|frame| {
+            let encoding = frame.data().first().copied().unwrap_or(0);
+            let allowed = if tag.version() == 4 { 3 } else { 1 };
+            encoding <= allowed && frame.try_parse_text().is_ok()
+        }),
+    }
+}
+
+impl Tag {
+    pub fn quality_score(&self) -> QualityScore {
+        self.quality_score_with(&QualityWeights::new())
+    }
+
+    pub fn quality_score_with(&self, weights: &QualityWeights) -> QualityScore {
+        let mut score = QualityScore { points: 0, max: weights.total(), failed: Vec::new() };
+        for check in Check::ALL {
+            let weight = weights.weights[check.index()];
+            if passes(self, check) {
+                score.points += weight;
+            } else if weight > 0 {
+                score.failed.push(check);
+            }
+        }
+        score.failed.sort_by_key(|check| std::cmp::Reverse(weights.weights[check.index()]));
+        score
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileQuality {
+    pub path: PathBuf,
+    pub score: QualityScore,
+}
+
+// Files sorted lowest score first so the ones needing the most work come up top
+#[derive(Debug, Default)]
+pub struct QualityReport {
+    pub files: Vec<FileQuality>,
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+impl QualityReport {
+    // Mean percentage over the files that could be read
+    pub fn average(&self) -> Option<u32> {
+        let total: u32 = self.files.iter().map(|file| file.score.percent()).sum();
+        (!self.files.is_empty()).then(|| total / self.files.len() as u32)
+    }
+
+    // How many files fail each check, most common first
+    pub fn failures(&self) -> Vec<(Check, usize)> {
+        let mut counts: Vec<(Check, usize)> = Check::ALL
+            .iter()
+            .map(|check| (*check, self.files.iter().filter(|file| file.score.failed.contains(check)).count()))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+    }
+}
+
+pub fn score_files(files: &[impl AsRef<Path>], weights: &QualityWeights) -> QualityReport {
+    let mut report = QualityReport::default();
+    for file in files {
+        let path = file.as_ref().to_path_buf();
+        match Tag::from_file(&path) {
+            Ok(tag) => report.files.push(FileQuality { path, score: tag.quality_score_with(weights) }),
+            Err(error) => report.failed.push((path, error)),
+        }
+    }
+    report.files.sort_by_key(|file| file.score.percent());
+    report
+}
+
+// A file, or every MP3 below a directory
+pub fn score(path: impl AsRef<Path>, weights: &QualityWeights) -> io::Result<QualityReport> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(score_files(&[path], weights));
+    }
+    let mut files = Vec::new();
+    mp3_files(path, &mut files)?;
+    Ok(score_files(&files, weights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::{Frame, WriteOptions};
+    use std::fs;
+
+    #[test]
+    fn complete_tag() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let score = tag.quality_score();
+        assert_eq!((score.points, score.max, score.percent()), (100, 100, 100));
+        assert!(score.failed.is_empty());
+    }
+
+    #[test]
+    fn failed_checks() {
+        let mut tag = Tag::new(3);
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.set_text("TRCK", "12/10");
+        tag.set_text("TDRC", "2017");
+        tag.add_frame(Frame::new("TALB", b"\x03Polygondwanaland".to_vec()).unwrap());
+
+        let score = tag.quality_score();
+        assert_eq!(score.failed, [Check::Artist, Check::Art, Check::Position, Check::NoDeprecated, Check::ValidText]);
+        assert_eq!(score.percent(), 50);
+
+        let weights = QualityWeights::new().weight(Check::Artist, 0).weight(Check::Art, 0);
+        let score = tag.quality_score_with(&weights);
+        assert_eq!((score.points, score.max), (50, 70));
+        assert!(!score.failed.contains(&Check::Artist));
+        assert_eq!(Tag::new(4).quality_score_with(&QualityWeights::new().weight(Check::Title, 0)).failed[0], Check::Artist);
+    }
+
+    #[test]
+    fn library_worst_first() {
+        let dir = temp_path("quality");
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("a.mp3")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("b.mp3")).unwrap();
+        let mut tag = Tag::from_file(dir.join("b.mp3")).unwrap();
+        tag.remove("APIC");
+        tag.remove("TALB");
+        tag.write_to_file(dir.join("b.mp3"), &WriteOptions::new()).unwrap();
+
+        let report = score(&dir, &QualityWeights::new()).unwrap();
+        assert_eq!(report.files.iter().map(|file| file.score.percent()).collect::<Vec<_>>(), [80, 100]);
+        assert_eq!(report.files[0].path, dir.join("b.mp3"));
+        assert_eq!(report.average(), Some(90));
+        assert_eq!(report.failures(), [(Check::Album, 1), (Check::Art, 1)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
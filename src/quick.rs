@@ -0,0 +1,89 @@
+use crate::paths::long_path;
+use crate::wire;
+use crate::{Header, Tag};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+// Title and artist for a now playing overlay or file picker, None unless the tag has both.
+// Only frame headers are read until TIT2 and TPE1 have been found or padding starts, other
+// frames like pictures are skipped over without reading them
+pub fn title_artist(path: impl AsRef<Path>) -> Option<(String, String)> {
+    let path = path.as_ref();
+    match scan(path) {
+        Ok(Some(found)) => found,
+        // Tags the scan can't walk frame by frame are parsed in full
+        Ok(None) => Tag::from_file(path).ok().and_then(|tag| Some((tag.title()?, tag.artist()?))),
+        Err(_) => None,
+    }
+}
+
+// None when the tag's layout needs the full parser, Some(None) when the frames aren't there
+fn scan(path: &Path) -> io::Result<Option<Option<(String, String)>>> {
+    let mut file = BufReader::with_capacity(4096, File::open(long_path(path))?);
+    let mut bytes = [0u8; 10];
+    file.read_exact(&mut bytes)?;
+    let Some(header) = Header::from_bytes(&bytes) else {
+        return Ok(Some(None));
+    };
+    let version = header.version().0;
+    if header.extended_header() || (version != 4 && header.unsynchronisation()) || (version == 2 && bytes[5] & 0b_01000000 != 0) {
+        return Ok(None);
+    }
+
+    let (mut title, mut artist) = (None, None);
+    let header_len = wire::header_len(version) as u64;
+    let mut remaining = header.size();
+    let mut frame_header = vec![0; header_len as usize];
+    while remaining >= header_len && (title.is_none() || artist.is_none()) {
+        file.read_exact(&mut frame_header)?;
+        if frame_header[0] == 0 {
+            break;
+        }
+        let size = wire::body_size(&frame_header, version) as u64;
+        remaining = remaining.saturating_sub(header_len + size);
+        let slot = match wire::id(&frame_header, version) {
+            Some(id) if &id == b"TIT2" => &mut title,
+            Some(id) if &id == b"TPE1" => &mut artist,
+            _ => {
+                file.seek_relative(size as i64)?;
+                continue;
+            }
+        };
+        let mut data = Vec::new();
+        (&mut file).take(size).read_to_end(&mut data)?;
+        *slot = wire::decode(&frame_header, data, version).map(|frame| frame.parse_text()).filter(|text| !text.is_empty());
+    }
+    Ok(Some(title.zip(artist)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::copy_of_test_file;
+    use crate::WriteOptions;
+    use std::fs;
+
+    #[test]
+    fn found_past_a_picture() {
+        let expected = Some(("Polygondwanaland".to_string(), "King Gizzard & The Lizard Wizard".to_string()));
+        assert_eq!(title_artist("test/Polygondwanaland.mp3"), expected);
+
+        // The picture first and the wanted frames last, for both versions that can be written
+        let path = copy_of_test_file("reversed");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.frames_mut().reverse();
+        for version in [3, 4] {
+            tag.write_to_file(&path, &WriteOptions::new().version(version)).unwrap();
+            assert_eq!(title_artist(&path), expected);
+        }
+        tag.remove("TPE1");
+        tag.write_to_file(&path, &WriteOptions::new()).unwrap();
+        assert_eq!(title_artist(&path), None);
+
+        let bytes = fs::read("test/Polygondwanaland.mp3").unwrap();
+        fs::write(&path, &bytes[187217..]).unwrap();
+        assert_eq!(title_artist(&path), None);
+        fs::remove_file(path).unwrap();
+    }
+}
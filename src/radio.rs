@@ -0,0 +1,48 @@
+use crate::Tag;
+use std::io;
+
+// Internet radio station details, stamped on streams and their recordings
+impl Tag {
+    pub fn station_name(&self) -> Option<String> {
+        self.text("TRSN")
+    }
+
+    pub fn set_station_name(&mut self, name: &str) {
+        self.set_text("TRSN", name);
+    }
+
+    pub fn station_owner(&self) -> Option<String> {
+        self.text("TRSO")
+    }
+
+    pub fn set_station_owner(&mut self, owner: &str) {
+        self.set_text("TRSO", owner);
+    }
+
+    // WORS, the station's homepage
+    pub fn station_url(&self) -> Option<String> {
+        self.url("WORS")
+    }
+
+    pub fn set_station_url(&mut self, url: &str) -> io::Result<()> {
+        self.set_url("WORS", url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn station_frames() {
+        let mut tag = Tag::new(4);
+        tag.set_station_name("Gizz Radio");
+        tag.set_station_owner("Flightless Records");
+        tag.set_station_url("https://radio.example").unwrap();
+        assert_eq!(tag.station_name().as_deref(), Some("Gizz Radio"));
+        assert_eq!(tag.station_owner().as_deref(), Some("Flightless Records"));
+        assert_eq!(tag.station_url().as_deref(), Some("https://radio.example"));
+        assert!(tag.set_station_url("radio.example").is_err());
+        assert!(tag.validate().is_empty());
+    }
+}
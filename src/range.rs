@@ -0,0 +1,89 @@
+use crate::{Header, ReadOptions, Reader, Tag};
+use std::io::{self, Cursor, Error, ErrorKind, Read, Seek, SeekFrom};
+
+// Bytes asked for first, most tags without art fit so they take a single request
+const FIRST_RANGE: usize = 16 * 1024;
+
+// Somewhere bytes can be fetched from by offset, like an object store answering HTTP Range
+// requests. See examples/http_range.rs for an adapter over plain HTTP
+pub trait RangeReader {
+    // Up to len bytes starting at offset, fewer only where the source ends
+    fn read_range(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>>;
+}
+
+// Files and anything else that can seek serve ranges directly
+impl<R: Read + Seek> RangeReader for R {
+    fn read_range(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        self.seek(SeekFrom::Start(offset))?;
+        let mut bytes = Vec::with_capacity(len);
+        self.take(len as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl Tag {
+    // The tag at the start of the source, fetched in at most two ranges: a first one big enough
+    // for most tags, then the rest of the tag when its header says there's more
+    pub fn from_ranges(source: &mut impl RangeReader) -> io::Result<Self> {
+        Self::from_ranges_with(source, &ReadOptions::new())
+    }
+
+    // Lazy frames need a file to be read from later, over ranges every frame is read in
+    pub fn from_ranges_with(source: &mut impl RangeReader, options: &ReadOptions) -> io::Result<Self> {
+        let mut bytes = source.read_range(0, FIRST_RANGE)?;
+        let Some(header) = Header::from_bytes(&bytes) else {
+            return Err(Error::new(ErrorKind::InvalidData, "File contains no ID3 header"));
+        };
+        let size = header.tag_size();
+        if (bytes.len() as u64) < size {
+            let rest = source.read_range(bytes.len() as u64, (size - bytes.len() as u64) as usize)?;
+            if (rest.len() as u64) < size - bytes.len() as u64 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "Source ends inside its ID3v2 tag"));
+            }
+            bytes.extend(rest);
+        }
+        bytes.truncate(size as usize);
+        Tag::from_reader_with(&mut Reader::from_stream(Cursor::new(bytes)), options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+
+    // Serves ranges of a buffer and remembers what was asked for
+    struct Ranges {
+        bytes: Vec<u8>,
+        requests: Vec<(u64, usize)>,
+    }
+
+    impl RangeReader for Ranges {
+        fn read_range(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+            self.requests.push((offset, len));
+            let start = (offset as usize).min(self.bytes.len());
+            Ok(self.bytes[start..(start + len).min(self.bytes.len())].to_vec())
+        }
+    }
+
+    #[test]
+    fn tag_over_ranges() {
+        let expected = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let tag = Tag::from_ranges(&mut File::open("test/Polygondwanaland.mp3").unwrap()).unwrap();
+        assert!(tag == expected);
+
+        let bytes = fs::read("test/Polygondwanaland.mp3").unwrap();
+        let mut ranges = Ranges { bytes: bytes.clone(), requests: Vec::new() };
+        assert!(Tag::from_ranges(&mut ranges).unwrap() == expected);
+        assert_eq!(ranges.requests, [(0, FIRST_RANGE), (FIRST_RANGE as u64, 187217 - FIRST_RANGE)]);
+
+        let mut small = Tag::new(4);
+        small.set_text("TIT2", "Crumbling Castle");
+        let mut ranges = Ranges { bytes: [small.to_bytes(64), bytes[187217..].to_vec()].concat(), requests: Vec::new() };
+        assert_eq!(Tag::from_ranges(&mut ranges).unwrap().title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(ranges.requests.len(), 1);
+
+        let mut truncated = Ranges { bytes: bytes[..100_000].to_vec(), requests: Vec::new() };
+        assert_eq!(Tag::from_ranges(&mut truncated).err().unwrap().kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::paths::long_path;
+use crate::write::tag_offsets;
+use crate::{CompatibilityReport, MergeStrategy, Reader, Tag, WriteOptions};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+pub enum StackedFix {
+    // The outermost tag is the one players read and usually the newest
+    KeepFirst,
+    KeepLast,
+    // Fill the first tag with frames only the stale tags have
+    Merge(MergeStrategy),
+}
+
+// Every ID3v2 tag stacked at the start of the file, in file order
+pub fn read_stacked(filename: impl AsRef<Path>) -> io::Result<Vec<Tag>> {
+    let filename = filename.as_ref();
+    let offsets = tag_offsets(&mut File::open(long_path(filename))?)?;
+    let mut tags = Vec::new();
+    for (offset, _) in offsets {
+        let mut reader = Reader::from_file(filename)?;
+        reader.skip_n_bytes(offset as usize)?;
+        tags.push(Tag::from_reader(&mut reader)?);
+    }
+    Ok(tags)
+}
+
+pub fn has_stacked_tags(filename: impl AsRef<Path>) -> io::Result<bool> {
+    let filename = filename.as_ref();
+    Ok(tag_offsets(&mut File::open(long_path(filename))?)?.len() > 1)
+}
+
+// Rewrites the file with a single tag, None when there was nothing to fix
+pub fn fix_stacked(filename: impl AsRef<Path>, fix: StackedFix, options: &WriteOptions) -> io::Result<Option<CompatibilityReport>> {
+    let filename = filename.as_ref();
+    let mut tags = read_stacked(filename)?;
+    if tags.len() < 2 {
+        return Ok(None);
+    }
+
+    let tag = match fix {
+        StackedFix::KeepFirst => tags.remove(0),
+        StackedFix::KeepLast => tags.pop().unwrap(),
+        StackedFix::Merge(strategy) => {
+            let mut first = tags.remove(0);
+            // Stale tags closer to the front win over older ones further in
+            let mut stale = tags.remove(0);
+            for older in &tags {
+                stale.merge(older, MergeStrategy::PreferSelf);
+            }
+            first.merge(&stale, strategy);
+            first
+        }
+    };
+    tag.write_to_file(filename, options).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // A small tag prepended to the test file like a broken tagger would
+    fn doubled_file(name: &str) -> PathBuf {
+        let path = temp_path(&format!("{name}.mp3"));
+        let mut tag = Tag::new(3);
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.set_text("TCON", "Prog");
+        let mut bytes = tag.to_bytes(16);
+        bytes.extend(fs::read("test/Polygondwanaland.mp3").unwrap());
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn audio(filename: &str) -> Vec<u8> {
+        let bytes = fs::read(filename).unwrap();
+        bytes[187217..].to_vec()
+    }
+
+    #[test]
+    fn detect_stacked() {
+        let path = doubled_file("detect");
+        let tags = read_stacked(&path).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].title().unwrap(), "Crumbling Castle");
+        assert_eq!(tags[1].title().unwrap(), "Polygondwanaland");
+        assert!(has_stacked_tags(&path).unwrap());
+        assert!(!has_stacked_tags("test/Polygondwanaland.mp3").unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn merge_stacked() {
+        let path = doubled_file("merge");
+        fix_stacked(&path, StackedFix::Merge(MergeStrategy::PreferSelf), &WriteOptions::new().padding(0)).unwrap().unwrap();
+
+        let tags = read_stacked(&path).unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].title().unwrap(), "Crumbling Castle");
+        assert_eq!(tags[0].text("TCON").unwrap(), "Prog");
+        assert_eq!(tags[0].album().unwrap(), "Polygondwanaland");
+
+        let bytes = fs::read(&path).unwrap();
+        let start = crate::Header::from_bytes(&bytes).unwrap().tag_size() as usize;
+        assert_eq!(bytes[start..], audio("test/Polygondwanaland.mp3"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn keep_last() {
+        let path = doubled_file("keep-last");
+        fix_stacked(&path, StackedFix::KeepLast, &WriteOptions::new()).unwrap();
+        assert!(fix_stacked(&path, StackedFix::KeepLast, &WriteOptions::new()).unwrap().is_none());
+        let tag = Tag::from_file(&path).unwrap();
+        assert_eq!(tag.title().unwrap(), "Polygondwanaland");
+        assert!(tag.frame("TCON").is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+use crate::convert::text_values;
+use crate::digest::base64;
+use crate::frames::{Comment, Picture, UserLink, UserText};
+use crate::names::Names;
+use crate::search::is_mp3;
+use crate::validate::validate_file;
+use crate::Tag;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportOptions {
+    format: ReportFormat,
+    thumbnails: bool,
+}
+
+impl ReportOptions {
+    pub fn new() -> Self {
+        Self {
+            format: ReportFormat::Markdown,
+            thumbnails: false,
+        }
+    }
+
+    pub fn format(mut self, format: ReportFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    // Embed the art as data URIs, scaled down first when built with the imaging feature
+    pub fn thumbnails(mut self, thumbnails: bool) -> Self {
+        self.thumbnails = thumbnails;
+        self
+    }
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// What the report says about one file
+struct FileReport {
+    path: String,
+    fields: Vec<(String, String)>,
+    // Description and the data URI when thumbnails are on
+    art: Vec<(String, Option<String>)>,
+    warnings: Vec<String>,
+}
+
+pub(crate) fn mp3_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.map(|entry| entry.map(|x| x.path())).collect::<io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            mp3_files(&path, files)?;
+        } else if is_mp3(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Text, URL, user defined and comment frames as name and value
+fn fields(tag: &Tag, names: &Names) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    for frame in tag.frames() {
+        let id = frame.id();
+        let field = match id.as_str() {
+            "TXXX" => UserText::from_frame(frame).map(|text| (text.description().to_string(), text.value().to_string())),
+            "WXXX" => UserLink::from_frame(frame).map(|link| (link.description().to_string(), link.url().to_string())),
+            "COMM" => Comment::from_frame(frame).map(|comment| (names.frame(&id), comment.text().to_string())),
+            _ if id.starts_with('T') => Some((names.frame(&id), text_values(frame).join("; "))),
+            _ if id.starts_with('W') => Some((names.frame(&id), frame.data().iter().take_while(|x| **x != 0).map(|x| *x as char).collect())),
+            _ => None,
+        };
+        fields.extend(field);
+    }
+    fields
+}
+
+#[cfg(feature = "imaging")]
+fn thumbnail(picture: &Picture) -> Picture {
+    use crate::imaging::{ArtOptions, optimize};
+    optimize(picture, &ArtOptions::new().max_dimension(160).quality(75)).unwrap_or_else(|_| picture.clone())
+}
+
+#[cfg(not(feature = "imaging"))]
+fn thumbnail(picture: &Picture) -> Picture {
+    picture.clone()
+}
+
+fn art(tag: &Tag, names: &Names, options: &ReportOptions) -> Vec<(String, Option<String>)> {
+    tag.pictures()
+        .iter()
+        .map(|picture| {
+            let mut line = format!("{}, {}, {} bytes", names.picture_type(picture.picture_type()), picture.mime(), picture.data().len());
+            if !picture.description().is_empty() {
+                line = format!("{line}, \"{}\"", picture.description());
+            }
+            let uri = options.thumbnails.then(|| {
+                let thumbnail = thumbnail(picture);
+                format!("data:{};base64,{}", thumbnail.mime(), base64(thumbnail.data()))
+            });
+            (line, uri)
+        })
+        .collect()
+}
+
+fn file_report(path: &Path, names: &Names, options: &ReportOptions) -> FileReport {
+    let mut report = FileReport { path: path.display().to_string(), fields: Vec::new(), art: Vec::new(), warnings: Vec::new() };
+    match Tag::from_file(path) {
+        Ok(tag) => {
+            report.fields = fields(&tag, names);
+            report.art = art(&tag, names, options);
+        }
+        Err(error) => report.warnings.push(format!("No readable ID3v2 tag: {error}")),
+    }
+    match validate_file(path) {
+        Ok(violations) => report.warnings.extend(violations.iter().map(|violation| violation.to_string())),
+        Err(error) => report.warnings.push(format!("Audio couldn't be analysed: {error}")),
+    }
+    report
+}
+
+fn summary(reports: &[FileReport]) -> String {
+    let warned = reports.iter().filter(|report| !report.warnings.is_empty()).count();
+    format!("{} file{}, {warned} with warnings", reports.len(), if reports.len() == 1 { "" } else { "s" })
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn markdown(reports: &[FileReport]) -> String {
+    let mut out = String::from("# Tag report\n");
+    out += &format!("\n{}\n", summary(reports));
+    for report in reports {
+        out += &format!("\n## {}\n", markdown_cell(&report.path));
+        if !report.fields.is_empty() {
+            out += "\n| Field | Value |\n| --- | --- |\n";
+            for (name, value) in &report.fields {
+                out += &format!("| {} | {} |\n", markdown_cell(name), markdown_cell(value));
+            }
+        }
+        if !report.art.is_empty() {
+            out += "\n**Art**\n\n";
+            for (line, uri) in &report.art {
+                out += &format!("- {}\n", markdown_cell(line));
+                if let Some(uri) = uri {
+                    out += &format!("\n  ![{}]({uri})\n", markdown_cell(line));
+                }
+            }
+        }
+        if !report.warnings.is_empty() {
+            out += "\n**Warnings**\n\n";
+            for warning in &report.warnings {
+                out += &format!("- {}\n", markdown_cell(warning));
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn html(reports: &[FileReport]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Tag report</title>\n</head>\n<body>\n<h1>Tag report</h1>\n");
+    out += &format!("<p>{}</p>\n", summary(reports));
+    for report in reports {
+        out += &format!("<h2>{}</h2>\n", escape_html(&report.path));
+        if !report.fields.is_empty() {
+            out += "<table>\n<tr><th>Field</th><th>Value</th></tr>\n";
+            for (name, value) in &report.fields {
+                out += &format!("<tr><td>{}</td><td>{}</td></tr>\n", escape_html(name), escape_html(value));
+            }
+            out += "</table>\n";
+        }
+        if !report.art.is_empty() {
+            out += "<h3>Art</h3>\n<ul>\n";
+            for (line, uri) in &report.art {
+                let image = uri.as_ref().map(|uri| format!("<br><img src=\"{uri}\" alt=\"{}\">", escape_html(line))).unwrap_or_default();
+                out += &format!("<li>{}{image}</li>\n", escape_html(line));
+            }
+            out += "</ul>\n";
+        }
+        if !report.warnings.is_empty() {
+            out += "<h3>Warnings</h3>\n<ul>\n";
+            for warning in &report.warnings {
+                out += &format!("<li>{}</li>\n", escape_html(warning));
+            }
+            out += "</ul>\n";
+        }
+    }
+    out + "</body>\n</html>\n"
+}
+
+// Report on the files given, unreadable files are listed with a warning rather than failing
+pub fn report_files(files: &[impl AsRef<Path>], options: &ReportOptions) -> String {
+    let names = Names::new();
+    let reports: Vec<FileReport> = files.iter().map(|file| file_report(file.as_ref(), &names, options)).collect();
+    match options.format {
+        ReportFormat::Markdown => markdown(&reports),
+        ReportFormat::Html => html(&reports),
+    }
+}
+
+// Report on a file, or every MP3 below a directory
+pub fn report(path: impl AsRef<Path>, options: &ReportOptions) -> io::Result<String> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(report_files(&[path], options));
+    }
+    let mut files = Vec::new();
+    mp3_files(path, &mut files)?;
+    Ok(report_files(&files, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+
+    #[test]
+    fn markdown_report() {
+        let report = report("test/Polygondwanaland.mp3", &ReportOptions::new()).unwrap();
+        assert!(report.starts_with("# Tag report\n\n1 file, 0 with warnings\n\n## test/Polygondwanaland.mp3\n"));
+        assert!(report.contains("| Track number | 2 |\n"));
+        assert!(report.contains("| Artist | King Gizzard & The Lizard Wizard |\n"));
+        assert!(report.contains("- Front cover, image/jpeg, "));
+        assert!(!report.contains("data:"));
+        assert!(!report.contains("Warnings"));
+    }
+
+    #[test]
+    fn html_report_with_thumbnails() {
+        let options = ReportOptions::new().format(ReportFormat::Html).thumbnails(true);
+        let report = report_files(&["test/Polygondwanaland.mp3", "test/missing.mp3"], &options);
+        assert!(report.contains("<p>2 files, 1 with warnings</p>"));
+        assert!(report.contains("<td>King Gizzard &amp; The Lizard Wizard</td>"));
+        assert!(report.contains("<img src=\"data:image/jpeg;base64,/9j/"));
+        assert!(report.contains("<li>No readable ID3v2 tag: "));
+    }
+
+    #[test]
+    fn directory_report() {
+        let dir = temp_path("report");
+        fs::create_dir_all(dir.join("disc 2")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("a.mp3")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("disc 2").join("b.mp3")).unwrap();
+        fs::write(dir.join("notes.txt"), "|").unwrap();
+
+        let report = report(&dir, &ReportOptions::new()).unwrap();
+        assert!(report.contains("2 files, 0 with warnings"));
+        assert!(report.find("a.mp3").unwrap() < report.find("b.mp3").unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use crate::digest::sha1;
+use crate::paths::long_path;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const MAGIC: &str = "mp3-tool batch";
+
+// How a file finished in an earlier run, failed files aren't recorded so they are tried again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Done {
+    Succeeded,
+    Unchanged,
+}
+
+// Hash of the plan description and every queued path, a state file only resumes the same run
+pub(crate) fn plan_hash<'a>(plan: &str, files: impl Iterator<Item = &'a Path>) -> String {
+    let mut bytes = plan.as_bytes().to_vec();
+    for file in files {
+        bytes.push(b'\n');
+        bytes.extend_from_slice(file.as_os_str().as_encoded_bytes());
+    }
+    sha1(&bytes).iter().map(|x| format!("{x:02x}")).collect()
+}
+
+// A header line with the plan hash, then one line per finished file appended as it finishes
+// so an interrupted run loses at most the files that were being written
+pub(crate) struct StateFile {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl StateFile {
+    // The files an earlier run with the same hash finished. A missing file, a different
+    // hash or resume being off starts a new state file
+    pub(crate) fn open(path: &Path, hash: &str, resume: bool) -> io::Result<(Self, HashMap<String, Done>)> {
+        let mut done = HashMap::new();
+        let previous = if resume { fs::read_to_string(long_path(path)).ok() } else { None };
+        let header = format!("{MAGIC} {hash}");
+        let file = match previous.filter(|text| text.lines().next() == Some(header.as_str())) {
+            Some(text) => {
+                for line in text.lines().skip(1) {
+                    match line.split_once('\t') {
+                        Some(("succeeded", path)) => done.insert(path.to_string(), Done::Succeeded),
+                        Some(("unchanged", path)) => done.insert(path.to_string(), Done::Unchanged),
+                        _ => None,
+                    };
+                }
+                OpenOptions::new().append(true).open(long_path(path))?
+            }
+            None => {
+                let mut file = File::create(long_path(path))?;
+                writeln!(file, "{header}")?;
+                file
+            }
+        };
+        Ok((Self { path: path.to_path_buf(), file: Mutex::new(file) }, done))
+    }
+
+    pub(crate) fn record(&self, filename: &Path, done: Done) -> io::Result<()> {
+        let status = match done {
+            Done::Succeeded => "succeeded",
+            Done::Unchanged => "unchanged",
+        };
+        let line = format!("{status}\t{}\n", filename.to_string_lossy());
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+
+    // Nothing is left to resume once every file went through
+    pub(crate) fn finish(self) -> io::Result<()> {
+        drop(self.file);
+        fs::remove_file(long_path(&self.path))
+    }
+}
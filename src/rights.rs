@@ -0,0 +1,74 @@
+use crate::Tag;
+use crate::validate::is_copyright;
+use std::io;
+
+// Copyright, licence URL and owner of the file
+impl Tag {
+    // The full TCOP text, "2017 Flightless Records"
+    pub fn copyright(&self) -> Option<String> {
+        self.text("TCOP")
+    }
+
+    // Only when the text starts with the year the spec requires
+    pub fn copyright_year(&self) -> Option<u16> {
+        self.copyright().filter(|text| is_copyright(text))?[..4].parse().ok()
+    }
+
+    pub fn copyright_holder(&self) -> Option<String> {
+        self.copyright().filter(|text| is_copyright(text)).map(|text| text[5..].to_string())
+    }
+
+    // Written as "YYYY holder" so it always has the required prefix
+    pub fn set_copyright(&mut self, year: u16, holder: &str) {
+        self.set_text("TCOP", &format!("{year:04} {holder}"));
+    }
+
+    // WCOP, where the terms of use or licence can be found
+    pub fn copyright_url(&self) -> Option<String> {
+        self.url("WCOP")
+    }
+
+    pub fn set_copyright_url(&mut self, url: &str) -> io::Result<()> {
+        self.set_url("WCOP", url)
+    }
+
+    // TOWN, the owner or licensee of the file
+    pub fn file_owner(&self) -> Option<String> {
+        self.text("TOWN")
+    }
+
+    pub fn set_file_owner(&mut self, owner: &str) {
+        self.set_text("TOWN", owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn copyright_message() {
+        let mut tag = Tag::new(4);
+        tag.set_copyright(2017, "Flightless Records");
+        assert_eq!(tag.copyright().as_deref(), Some("2017 Flightless Records"));
+        assert_eq!((tag.copyright_year(), tag.copyright_holder().as_deref()), (Some(2017), Some("Flightless Records")));
+
+        tag.set_text("TCOP", "(c) Flightless Records");
+        assert_eq!((tag.copyright_year(), tag.copyright_holder()), (None, None));
+    }
+
+    #[test]
+    fn copyright_url_and_owner() {
+        let mut tag = Tag::new(3);
+        tag.set_copyright_url("https://creativecommons.org/licenses/by-nc/4.0/").unwrap();
+        tag.set_copyright_url("https://kinggizzardandthelizardwizard.com/terms").unwrap();
+        assert_eq!(tag.copyright_url().as_deref(), Some("https://kinggizzardandthelizardwizard.com/terms"));
+        assert_eq!(tag.frames().len(), 1);
+        assert_eq!(tag.set_copyright_url("not a url").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        tag.set_file_owner("Lucas Axberg");
+        assert_eq!(tag.file_owner().as_deref(), Some("Lucas Axberg"));
+        assert!(tag.validate().is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::ID3::read_terminated;
+use crate::frames::{Comment, UserText};
+use crate::{Frame, Tag};
+
+// Words in comments that stores use for order and customer details
+const PURCHASE_WORDS: [&str; 7] = ["purchase", "bought", "order id", "order number", "customer", "account", "song id"];
+
+// Words in TXXX descriptions that point at a serial or the user it was issued to
+const SERIAL_WORDS: [&str; 6] = ["serial", "user id", "userid", "customer", "account", "licen"];
+
+fn contains_any(text: &str, words: &[&str]) -> bool {
+    let text = text.to_lowercase();
+    words.iter().any(|word| text.contains(word))
+}
+
+// Something shaped like name@domain.tld anywhere in the text
+fn has_email(text: &str) -> bool {
+    text.split(|c: char| c.is_whitespace() || "<>()[],;\"'".contains(c)).any(|word| {
+        word.split_once('@').is_some_and(|(name, domain)| !name.is_empty() && domain.contains('.') && !domain.ends_with('.'))
+    })
+}
+
+// Which kinds of frames scrub removes, all of them unless turned off
+#[derive(Clone, Debug)]
+pub struct ScrubPolicy {
+    private: bool,
+    identifiers: bool,
+    ratings: bool,
+    purchases: bool,
+    serials: bool,
+    extra: Vec<String>,
+}
+
+impl ScrubPolicy {
+    pub fn new() -> Self {
+        Self {
+            private: true,
+            identifiers: true,
+            ratings: true,
+            purchases: true,
+            serials: true,
+            extra: Vec::new(),
+        }
+    }
+
+    // PRIV frames, opaque data left by players and stores
+    pub fn private_frames(mut self, remove: bool) -> Self {
+        self.private = remove;
+        self
+    }
+
+    // UFID frames, ids that tie the file to a database entry
+    pub fn identifiers(mut self, remove: bool) -> Self {
+        self.identifiers = remove;
+        self
+    }
+
+    // POPM frames keyed by an email address
+    pub fn ratings(mut self, remove: bool) -> Self {
+        self.ratings = remove;
+        self
+    }
+
+    // OWNE, TOWN and comments with order details or email addresses
+    pub fn purchases(mut self, remove: bool) -> Self {
+        self.purchases = remove;
+        self
+    }
+
+    // TXXX frames holding serials, licences or account ids
+    pub fn serials(mut self, remove: bool) -> Self {
+        self.serials = remove;
+        self
+    }
+
+    // Any other frame id to remove outright
+    pub fn remove(mut self, id: &str) -> Self {
+        self.extra.push(id.to_string());
+        self
+    }
+
+    // Why the frame has to go, None to keep it
+    fn reason(&self, frame: &Frame) -> Option<String> {
+        let id = frame.id();
+        let owner = || read_terminated(0, frame.data()).0;
+        match id.as_str() {
+            _ if self.extra.contains(&id) => Some("removed by policy".to_string()),
+            "PRIV" if self.private => Some(format!("private data from {}", owner())),
+            "UFID" if self.identifiers => Some(format!("identifier from {}", owner())),
+            "POPM" if self.ratings && has_email(&owner()) => Some(format!("rating by {}", owner())),
+            "OWNE" if self.purchases => Some("ownership record".to_string()),
+            "TOWN" if self.purchases => Some(format!("file owner {}", frame.parse_text())),
+            "COMM" if self.purchases => {
+                let comment = Comment::from_frame(frame)?;
+                let text = format!("{} {}", comment.description(), comment.text());
+                (contains_any(&text, &PURCHASE_WORDS) || has_email(&text)).then(|| format!("comment \"{}\"", comment.text()))
+            }
+            "TXXX" if self.serials => {
+                let text = UserText::from_frame(frame)?;
+                (contains_any(text.description(), &SERIAL_WORDS) || has_email(text.value())).then(|| format!("user text {}", text.description()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for ScrubPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A frame scrub took out and what it held
+#[derive(Clone, Debug, PartialEq)]
+pub struct Removed {
+    pub id: String,
+    pub detail: String,
+}
+
+impl Tag {
+    // Removes frames that could identify the owner of the file, for sharing it publicly
+    pub fn scrub(&mut self, policy: &ScrubPolicy) -> Vec<Removed> {
+        let mut removed = Vec::new();
+        self.frames_mut().retain(|frame| match policy.reason(frame) {
+            Some(detail) => {
+                removed.push(Removed { id: frame.id(), detail });
+                false
+            }
+            None => true,
+        });
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Language;
+
+    fn tag_with_personal_frames() -> Tag {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let english = Language::new("eng").unwrap();
+        tag.add_frame(Frame::new("PRIV", b"WM/UniqueFileIdentifier\0AMGa_id=R 123".to_vec()).unwrap());
+        tag.add_frame(Frame::new("UFID", b"http://musicbrainz.org\0a1b2c3".to_vec()).unwrap());
+        tag.add_frame(Frame::new("POPM", b"lucas@example.com\0\xFF\0\0\0\x07".to_vec()).unwrap());
+        tag.add_frame(Frame::new("POPM", b"Windows Media Player 9 Series\0\xC4".to_vec()).unwrap());
+        tag.add_frame(Comment::new(english, "", "Purchased by lucas@example.com").to_frame().unwrap());
+        tag.set_user_text("Encoder Serial", "XK-2231-99");
+        tag.set_user_text("MOOD", "Restless");
+        tag
+    }
+
+    #[test]
+    fn removes_personal_frames() {
+        let mut tag = tag_with_personal_frames();
+        let removed = tag.scrub(&ScrubPolicy::new());
+        let ids: Vec<&str> = removed.iter().map(|removed| removed.id.as_str()).collect();
+        assert_eq!(ids, ["PRIV", "UFID", "POPM", "COMM", "TXXX"]);
+        assert_eq!(removed[2].detail, "rating by lucas@example.com");
+
+        // The band's own comment, the player rating and the mood are kept
+        assert!(tag.comments()[0].text().starts_with("Visit https://"));
+        assert_eq!(tag.frame("POPM").unwrap().data()[0], b'W');
+        assert_eq!(tag.user_text("MOOD").as_deref(), Some("Restless"));
+    }
+
+    #[test]
+    fn policy_keeps_what_is_turned_off() {
+        let mut tag = tag_with_personal_frames();
+        let policy = ScrubPolicy::new().private_frames(false).identifiers(false).serials(false).remove("TSRC");
+        let ids: Vec<String> = tag.scrub(&policy).into_iter().map(|removed| removed.id).collect();
+        assert_eq!(ids, ["TSRC", "POPM", "COMM"]);
+        assert!(tag.frame("PRIV").is_some() && tag.frame("UFID").is_some());
+    }
+
+    #[test]
+    fn emails() {
+        assert!(has_email("Purchased by <lucas@example.com>"));
+        assert!(!has_email("@kinggizzard on every platform"));
+        assert!(!has_email("user@localhost"));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::convert::text_values;
+use crate::frames::Comment;
+use crate::report::mp3_files;
+use crate::{Frame, Tag};
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// The fields searched when a query doesn't name any
+pub const DEFAULT_FIELDS: [&str; 7] = ["TIT2", "TPE1", "TPE2", "TALB", "TCOM", "TCON", "COMM"];
+
+enum Pattern {
+    Substring(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+// What to look for and where, matching ignores case
+pub struct Query {
+    pattern: Pattern,
+    fields: Vec<String>,
+}
+
+impl Query {
+    pub fn new(text: &str) -> Self {
+        Self {
+            pattern: Pattern::Substring(text.to_lowercase()),
+            fields: DEFAULT_FIELDS.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    // Syntax is the regex crate's, matching takes linear time whatever the pattern
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> io::Result<Self> {
+        let regex = regex::RegexBuilder::new(pattern).case_insensitive(true).build()
+            .map_err(|error| Error::new(ErrorKind::InvalidInput, format!("invalid pattern: {error}")))?;
+        Ok(Self { pattern: Pattern::Regex(regex), ..Self::new("") })
+    }
+
+    #[cfg(not(feature = "regex"))]
+    pub fn regex(_pattern: &str) -> io::Result<Self> {
+        Err(Error::new(ErrorKind::Unsupported, "Regex search needs the regex feature"))
+    }
+
+    pub fn fields(mut self, ids: &[&str]) -> Self {
+        self.fields = ids.iter().map(|id| id.to_string()).collect();
+        self
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.pattern {
+            Pattern::Substring(needle) => text.to_lowercase().contains(needle),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    // The field and value of every searched frame in the tag that matches
+    pub fn matches(&self, tag: &Tag) -> Vec<(String, String)> {
+        tag.frames().iter()
+            .filter(|frame| self.fields.contains(&frame.id()))
+            .flat_map(|frame| values(frame).into_iter().map(|value| (frame.id(), value)))
+            .filter(|(_, value)| self.is_match(value))
+            .collect()
+    }
+}
+
+fn values(frame: &Frame) -> Vec<String> {
+    match frame.id().as_str() {
+        "COMM" => Comment::from_frame(frame).map(|comment| comment.text().to_string()).into_iter().collect(),
+        id if id.starts_with('T') => text_values(frame),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub path: PathBuf,
+    pub field: String,
+    pub value: String,
+}
+
+pub(crate) fn is_mp3(path: &Path) -> bool {
+    path.extension().and_then(|x| x.to_str()).is_some_and(|x| x.eq_ignore_ascii_case("mp3"))
+}
+
+// Every matching field of every mp3 below the directory, in path order
+pub fn find(dir: impl AsRef<Path>, query: &Query) -> io::Result<Vec<Match>> {
+    let mut files = Vec::new();
+    mp3_files(dir.as_ref(), &mut files)?;
+    let mut found = Vec::new();
+    for path in files {
+        // Files without a readable tag simply don't match
+        let Ok(tag) = Tag::from_file(&path) else {
+            continue;
+        };
+        for (field, value) in query.matches(&tag) {
+            found.push(Match { path: path.clone(), field, value });
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::WriteOptions;
+    use std::fs;
+
+    fn library(name: &str) -> PathBuf {
+        let dir = temp_path(name);
+        fs::create_dir_all(dir.join("disc 2")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("01.mp3")).unwrap();
+        fs::copy("test/Polygondwanaland.mp3", dir.join("disc 2/02.MP3")).unwrap();
+        fs::write(dir.join("notes.txt"), "Deserted Dunes").unwrap();
+
+        let retitled = dir.join("disc 2/02.MP3");
+        let mut tag = Tag::from_file(&retitled).unwrap();
+        tag.set_text("TIT2", "Deserted Dunes Welcome Weary Feet");
+        tag.write_to_file(&retitled, &WriteOptions::new()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn substring_ignores_case() {
+        let dir = library("substring");
+        let found = find(&dir, &Query::new("DESERTED")).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, dir.join("disc 2/02.MP3"));
+        assert_eq!((found[0].field.as_str(), found[0].value.as_str()), ("TIT2", "Deserted Dunes Welcome Weary Feet"));
+
+        // Both files share the album artist
+        let found = find(&dir, &Query::new("lizard wizard").fields(&["TPE2"])).unwrap();
+        assert_eq!(found.len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_and_fields() {
+        let dir = library("regex");
+        let query = Query::regex("^polygon(dwana)?land$").unwrap().fields(&["TIT2", "TALB"]);
+        let found: Vec<_> = find(&dir, &query).unwrap().into_iter().map(|x| x.field).collect();
+        assert_eq!(found, ["TIT2", "TALB", "TALB"]);
+        // Nested quantifiers that would backtrack forever
+        assert!(!Query::regex("(a+)+b").unwrap().is_match(&"a".repeat(100_000)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn searches_comments() {
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let matches = Query::new("visit https://").matches(&tag);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, "COMM");
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let kind = if cfg!(feature = "regex") { ErrorKind::InvalidInput } else { ErrorKind::Unsupported };
+        assert_eq!(Query::regex("(unclosed").err().unwrap().kind(), kind);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::art::{ArtProvider, ArtQuery, FolderArt};
+use crate::frames::{Picture, PictureType};
+use crate::paths::long_path;
+use crate::report::mp3_files;
+use crate::{Tag, WriteOptions};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// The tracks of one directory and the front cover they embed
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlbumArt {
+    pub directory: PathBuf,
+    pub files: Vec<PathBuf>,
+    // The cover every track embeds byte for byte, None when they differ, one has none or it is small
+    pub shared: Option<Picture>,
+    // Front cover bytes embedded over all the tracks
+    pub embedded_bytes: u64,
+}
+
+impl AlbumArt {
+    // What storing the shared cover once next to the tracks would save
+    pub fn reclaimable(&self) -> u64 {
+        self.shared.as_ref().map_or(0, |picture| self.embedded_bytes - picture.data().len() as u64)
+    }
+}
+
+// What extracting or embedding did to one album
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArtChange {
+    pub directory: PathBuf,
+    // The image file next to the tracks
+    pub image: PathBuf,
+    pub files_changed: usize,
+    // Bytes freed on disk counting the image file, negative when embedding grew the album
+    pub bytes_reclaimed: i64,
+}
+
+fn extension(mime: &str) -> &str {
+    match mime.to_ascii_lowercase().as_str() {
+        "image/png" | "png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        _ => "jpg",
+    }
+}
+
+fn total_size(files: &[PathBuf]) -> io::Result<u64> {
+    files.iter().map(|file| Ok(fs::metadata(long_path(file))?.len())).sum()
+}
+
+fn same_picture(a: &Picture, b: &Picture) -> bool {
+    a.picture_type() == b.picture_type() && a.data() == b.data()
+}
+
+// Finds albums whose tracks all embed the same cover, and moves it between the tags and a
+// single image file in the album's directory
+pub struct ArtDedup {
+    min_size: usize,
+    name: String,
+}
+
+impl ArtDedup {
+    pub fn new() -> Self {
+        Self { min_size: 100 * 1024, name: "cover".to_string() }
+    }
+
+    // Smaller covers aren't worth moving out of the tags
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    // File name without extension for extracted covers, the extension follows the MIME type
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    // Every directory below dir holding at least one MP3. Unreadable tags count as having no cover
+    pub fn scan(&self, dir: impl AsRef<Path>) -> io::Result<Vec<AlbumArt>> {
+        let mut files = Vec::new();
+        mp3_files(dir.as_ref(), &mut files)?;
+        let mut albums: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for file in files {
+            albums.entry(file.parent().map(Path::to_path_buf).unwrap_or_default()).or_default().push(file);
+        }
+        Ok(albums.into_iter().map(|(directory, files)| self.album(directory, files)).collect())
+    }
+
+    fn album(&self, directory: PathBuf, files: Vec<PathBuf>) -> AlbumArt {
+        let covers: Vec<Option<Picture>> = files.iter().map(|file| Tag::from_file(file).ok().and_then(|tag| tag.front_cover())).collect();
+        let embedded_bytes = covers.iter().flatten().map(|picture| picture.data().len() as u64).sum();
+        let first = covers.first().cloned().flatten().filter(|picture| picture.data().len() >= self.min_size);
+        let shared = first.filter(|first| {
+            files.len() > 1 && covers.iter().all(|cover| cover.as_ref().is_some_and(|cover| same_picture(cover, first)))
+        });
+        AlbumArt { directory, files, shared, embedded_bytes }
+    }
+
+    // Writes the shared cover to the directory and strips it from every track. An image of the
+    // same name that holds something else is left alone and the album fails
+    pub fn extract(&self, album: &AlbumArt, options: &WriteOptions) -> io::Result<ArtChange> {
+        let Some(shared) = &album.shared else {
+            return Err(Error::new(ErrorKind::InvalidInput, "The tracks don't share a cover"));
+        };
+        let image = album.directory.join(format!("{}.{}", self.name, extension(shared.mime())));
+        match fs::read(long_path(&image)) {
+            Ok(existing) if existing != shared.data() => {
+                return Err(Error::new(ErrorKind::AlreadyExists, format!("{} already holds another image", image.display())));
+            }
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::NotFound => fs::write(long_path(&image), shared.data())?,
+            Err(error) => return Err(error),
+        }
+
+        let before = total_size(&album.files)?;
+        let mut files_changed = 0;
+        for file in &album.files {
+            let mut tag = Tag::from_file(file)?;
+            tag.frames_mut().retain(|frame| Picture::from_frame(frame).is_none_or(|picture| !same_picture(&picture, shared)));
+            tag.write_to_file(file, options)?;
+            files_changed += 1;
+        }
+        let after = total_size(&album.files)? + shared.data().len() as u64;
+        Ok(ArtChange { directory: album.directory.clone(), image, files_changed, bytes_reclaimed: before as i64 - after as i64 })
+    }
+
+    // Embeds the folder image as the front cover of every track that doesn't already have it.
+    // The image file is kept
+    pub fn embed(&self, album: &AlbumArt, options: &WriteOptions) -> io::Result<ArtChange> {
+        let query = ArtQuery { directory: Some(album.directory.clone()), ..Default::default() };
+        let mut names = vec![self.name.as_str()];
+        names.extend(["cover", "folder", "front"].into_iter().filter(|name| *name != self.name));
+        let provider = FolderArt::new().names(&names);
+        let Some(candidate) = provider.find(&query)?.into_iter().next() else {
+            return Err(Error::new(ErrorKind::NotFound, format!("No cover image in {}", album.directory.display())));
+        };
+        let picture = candidate.to_picture(PictureType::FrontCover);
+
+        let before = total_size(&album.files)?;
+        let mut files_changed = 0;
+        for file in &album.files {
+            let mut tag = Tag::from_file(file)?;
+            if tag.front_cover().is_some_and(|cover| cover.data() == picture.data()) {
+                continue;
+            }
+            tag.remove_pictures(PictureType::FrontCover);
+            tag.embed_picture(&picture);
+            tag.write_to_file(file, options)?;
+            files_changed += 1;
+        }
+        let after = total_size(&album.files)?;
+        let image = PathBuf::from(candidate.source());
+        Ok(ArtChange { directory: album.directory.clone(), image, files_changed, bytes_reclaimed: before as i64 - after as i64 })
+    }
+}
+
+impl Default for ArtDedup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+
+    fn album_dir(name: &str, tracks: usize) -> PathBuf {
+        let dir = temp_path(name);
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..tracks {
+            fs::copy("test/Polygondwanaland.mp3", dir.join(format!("{i}.mp3"))).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn finds_shared_covers() {
+        let dir = album_dir("scan", 2);
+        let dedup = ArtDedup::new();
+        let albums = dedup.scan(&dir).unwrap();
+        assert_eq!(albums.len(), 1);
+        let cover = albums[0].shared.as_ref().unwrap();
+        assert_eq!(albums[0].embedded_bytes, cover.data().len() as u64 * 2);
+        assert_eq!(albums[0].reclaimable(), cover.data().len() as u64);
+        assert!(ArtDedup::new().min_size(1 << 20).scan(&dir).unwrap()[0].shared.is_none());
+
+        // One track with another cover means nothing is shared
+        let path = dir.join("1.mp3");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.remove_pictures(PictureType::FrontCover);
+        tag.embed_picture(&Picture::new("image/png", PictureType::FrontCover, "", vec![0x89, b'P', b'N', b'G']));
+        tag.write_to_file(&path, &WriteOptions::new()).unwrap();
+        assert_eq!(dedup.scan(&dir).unwrap()[0].shared, None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn extract_then_embed() {
+        let dir = album_dir("extract", 3);
+        let dedup = ArtDedup::new();
+        let album = dedup.scan(&dir).unwrap().remove(0);
+        let cover = album.shared.clone().unwrap();
+
+        let change = dedup.extract(&album, &WriteOptions::new()).unwrap();
+        assert_eq!((change.image.clone(), change.files_changed), (dir.join("cover.jpg"), 3));
+        assert!(change.bytes_reclaimed >= 2 * cover.data().len() as i64);
+        assert_eq!(fs::read(&change.image).unwrap(), cover.data());
+        assert!(Tag::from_file(dir.join("0.mp3")).unwrap().pictures().is_empty());
+
+        let album = dedup.scan(&dir).unwrap().remove(0);
+        assert_eq!(album.shared, None);
+        let change = dedup.embed(&album, &WriteOptions::new()).unwrap();
+        assert_eq!(change.files_changed, 3);
+        assert!(change.bytes_reclaimed <= -3 * cover.data().len() as i64);
+        assert_eq!(Tag::from_file(dir.join("2.mp3")).unwrap().front_cover().unwrap().data(), cover.data());
+        assert_eq!(dedup.embed(&album, &WriteOptions::new()).unwrap().files_changed, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_other_images() {
+        let dir = album_dir("conflict", 2);
+        fs::write(dir.join("cover.jpg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        let dedup = ArtDedup::new();
+        let album = dedup.scan(&dir).unwrap().remove(0);
+        assert_eq!(dedup.extract(&album, &WriteOptions::new()).unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert!(Tag::from_file(dir.join("0.mp3")).unwrap().front_cover().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use crate::paths::long_path;
+use crate::report::mp3_files;
+use crate::{Tag, WriteOptions};
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// Tags kept next to the audio instead of in it, for read-only media or edits that should be
+// reviewed first. The sidecar holds the ID3v2 tag exactly as it would be written to the file.
+// See WriteOptions::sidecar and ReadOptions::sidecar
+pub fn sidecar_path(filename: impl AsRef<Path>) -> PathBuf {
+    let mut path = filename.as_ref().as_os_str().to_owned();
+    path.push(".id3");
+    PathBuf::from(path)
+}
+
+pub fn has_sidecar(filename: impl AsRef<Path>) -> bool {
+    long_path(sidecar_path(filename)).is_file()
+}
+
+// None when the file has no sidecar
+pub fn read(filename: impl AsRef<Path>) -> io::Result<Option<Tag>> {
+    let path = sidecar_path(filename);
+    match Tag::from_file(&path) {
+        Ok(tag) => Ok(Some(tag)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+// Replaces the sidecar in one step so a reader never sees half a tag
+pub(crate) fn write(filename: &Path, bytes: &[u8]) -> io::Result<()> {
+    let path = sidecar_path(filename);
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(long_path(&temp), bytes)?;
+    fs::rename(long_path(&temp), long_path(&path))
+}
+
+// Writes the sidecar's tag into the file and removes the sidecar. False when there was none
+pub fn merge(filename: impl AsRef<Path>, options: &WriteOptions) -> io::Result<bool> {
+    let filename = filename.as_ref();
+    if options.writes_sidecar() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Merging needs options that write to the file"));
+    }
+    let Some(tag) = read(filename)? else {
+        return Ok(false);
+    };
+    tag.write_to_file(filename, options)?;
+    fs::remove_file(long_path(sidecar_path(filename)))?;
+    Ok(true)
+}
+
+#[derive(Debug, Default)]
+pub struct SidecarMerge {
+    pub merged: Vec<PathBuf>,
+    // A failed file keeps its sidecar
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+pub fn merge_files(files: &[impl AsRef<Path>], options: &WriteOptions) -> SidecarMerge {
+    let mut result = SidecarMerge::default();
+    for file in files {
+        let file = file.as_ref();
+        match merge(file, options) {
+            Ok(true) => result.merged.push(file.to_path_buf()),
+            Ok(false) => {}
+            Err(error) => result.failed.push((file.to_path_buf(), error)),
+        }
+    }
+    result
+}
+
+// Every MP3 below the directory that has a sidecar
+pub fn merge_dir(dir: impl AsRef<Path>, options: &WriteOptions) -> io::Result<SidecarMerge> {
+    let mut files = Vec::new();
+    mp3_files(dir.as_ref(), &mut files)?;
+    files.retain(|file| has_sidecar(file));
+    Ok(merge_files(&files, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::ReadOptions;
+
+    #[test]
+    fn write_review_merge() {
+        let dir = temp_path("sidecar");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("track.mp3");
+        fs::copy("test/Polygondwanaland.mp3", &path).unwrap();
+        let original = fs::read(&path).unwrap();
+
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.write_to_file(&path, &WriteOptions::new().sidecar(true)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert_eq!(sidecar_path(&path), dir.join("track.mp3.id3"));
+        assert_eq!(read(&path).unwrap().unwrap().title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(Tag::from_file(&path).unwrap().title().as_deref(), Some("Polygondwanaland"));
+        let title = Tag::from_file_with(&path, &ReadOptions::new().sidecar(true)).unwrap().title();
+        assert_eq!(title.as_deref(), Some("Crumbling Castle"));
+
+        assert_eq!(merge(&path, &WriteOptions::new().sidecar(true)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        let result = merge_dir(&dir, &WriteOptions::new()).unwrap();
+        assert_eq!((result.merged, result.failed.len()), (vec![path.clone()], 0));
+        assert!(!has_sidecar(&path));
+        assert_eq!(Tag::from_file(&path).unwrap().title().as_deref(), Some("Crumbling Castle"));
+        assert!(!merge(&path, &WriteOptions::new()).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
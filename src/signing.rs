@@ -0,0 +1,93 @@
+use crate::frames::Signature;
+use crate::Tag;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// Frames in the group in tag order, excluding the signature itself. Each is its id, the length
+// of its body and the body, not the frame as a version writes it, so a signature made on a
+// v2.4 tag still verifies after the tag is written as v2.3 and the other way round
+fn signed_mac(tag: &Tag, group: u8, key: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    for frame in tag.frames().iter().filter(|frame| frame.group() == Some(group) && frame.id() != "SIGN") {
+        mac.update(frame.id().as_bytes());
+        mac.update(&(frame.data().len() as u32).to_be_bytes());
+        mac.update(frame.data());
+    }
+    mac
+}
+
+// Replaces any existing signature for the group with an HMAC-SHA256 over its frames
+pub fn sign(tag: &mut Tag, group: u8, key: &[u8]) {
+    let signature = signed_mac(tag, group, key).finalize().into_bytes();
+    tag.frames_mut().retain(|frame| Signature::from_frame(frame).is_none_or(|sign| sign.group() != group));
+    tag.add_frame(Signature::new(group, signature.to_vec()).to_frame().unwrap());
+}
+
+// Signatures are compared in constant time
+pub fn verify(tag: &Tag, group: u8, key: &[u8]) -> bool {
+    let mac = signed_mac(tag, group, key);
+    tag.signatures().iter().any(|sign| sign.group() == group && mac.clone().verify_slice(sign.signature()).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+
+    fn grouped_tag() -> Tag {
+        let mut tag = Tag::new(4);
+        for (id, text) in [("TIT2", "Crumbling Castle"), ("TCOP", "2017 Flightless")] {
+            let mut data = vec![0x03];
+            data.extend_from_slice(text.as_bytes());
+            let mut frame = Frame::new(id, data).unwrap();
+            frame.set_group(Some(0x80));
+            tag.add_frame(frame);
+        }
+        tag
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut tag = grouped_tag();
+        sign(&mut tag, 0x80, b"secret");
+        assert_eq!(tag.signatures().len(), 1);
+        assert!(verify(&tag, 0x80, b"secret"));
+        assert!(!verify(&tag, 0x80, b"other key"));
+    }
+
+    #[test]
+    fn resigning_replaces_signature() {
+        let mut tag = grouped_tag();
+        sign(&mut tag, 0x80, b"secret");
+        sign(&mut tag, 0x80, b"secret");
+        assert_eq!(tag.signatures().len(), 1);
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let mut tag = grouped_tag();
+        sign(&mut tag, 0x80, b"secret");
+        let mut frame = Frame::new("TIT2", b"\x03Tampered".to_vec()).unwrap();
+        frame.set_group(Some(0x80));
+        tag.frames_mut()[0] = frame;
+        assert!(!verify(&tag, 0x80, b"secret"));
+    }
+
+    #[test]
+    fn survives_version_change() {
+        let mut tag = grouped_tag();
+        sign(&mut tag, 0x80, b"secret");
+        let mut v23 = Tag::new(3);
+        tag.frames().iter().for_each(|frame| v23.add_frame(frame.clone()));
+        let read = Tag::from_reader(&mut crate::Reader::from_stream(std::io::Cursor::new(v23.to_bytes(0)))).unwrap();
+        assert!(verify(&read, 0x80, b"secret"));
+    }
+
+    #[test]
+    fn ungrouped_frames_not_covered() {
+        let mut tag = grouped_tag();
+        sign(&mut tag, 0x80, b"secret");
+        tag.add_frame(Frame::new("TALB", b"\x03Polygondwanaland".to_vec()).unwrap());
+        assert!(verify(&tag, 0x80, b"secret"));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::Tag;
+use crate::convert::{text_frame, text_values};
+use crate::paths::long_path;
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("dictionary line {line}: {message}"))
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    from: String,
+    folded: String,
+    to: String,
+}
+
+// How often one rule replaced a value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleCount {
+    pub from: String,
+    pub to: String,
+    pub count: usize,
+}
+
+// Canonical spellings for whole text values, like artist names or labels. Values match the
+// rule when they are equal ignoring case and surrounding whitespace. Clones share the counts
+// so one dictionary can be handed to every file of a bulk run
+#[derive(Clone, Debug, Default)]
+pub struct Dictionary {
+    rules: Vec<Rule>,
+    fields: Option<Vec<String>>,
+    counts: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Dictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Earlier rules win when several match the same value
+    pub fn replace(mut self, from: &str, to: &str) -> Self {
+        self.rules.push(Rule { from: from.to_string(), folded: from.trim().to_lowercase(), to: to.to_string() });
+        self.counts.lock().unwrap().push(0);
+        self
+    }
+
+    // Frames the rules apply to, every text frame but TXXX by default
+    pub fn fields(mut self, ids: &[&str]) -> Self {
+        self.fields = Some(ids.iter().map(|x| x.to_string()).collect());
+        self
+    }
+
+    // One rule per line, the spelling to replace and the canonical one separated by a tab.
+    // Blank lines and lines starting with # are skipped
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut dictionary = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((from, to)) = line.split_once('\t') else {
+                return Err(invalid(i + 1, "expected a tab between the two spellings"));
+            };
+            if from.trim().is_empty() || to.trim().is_empty() {
+                return Err(invalid(i + 1, "empty spelling"));
+            }
+            dictionary = dictionary.replace(from.trim(), to.trim());
+        }
+        Ok(dictionary)
+    }
+
+    pub fn from_file(filename: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(long_path(filename.as_ref()))?)
+    }
+
+    fn applies_to(&self, id: &str) -> bool {
+        match &self.fields {
+            Some(fields) => fields.iter().any(|field| field == id),
+            None => id.starts_with('T') && id != "TXXX",
+        }
+    }
+
+    // Index of the rule that replaces the value, None when none match or it is already canonical
+    fn rule_for(&self, value: &str) -> Option<usize> {
+        let folded = value.trim().to_lowercase();
+        let index = self.rules.iter().position(|rule| rule.folded == folded)?;
+        (self.rules[index].to != value).then_some(index)
+    }
+
+    // Replaces matching values of multi-value frames one by one. Returns how many values changed,
+    // frames without a match are left as they were
+    pub fn apply(&self, tag: &mut Tag) -> usize {
+        let mut replaced = 0;
+        for index in 0..tag.frames().len() {
+            let frame = &tag.frames()[index];
+            let (id, group) = (frame.id(), frame.group());
+            if !self.applies_to(&id) {
+                continue;
+            }
+            let mut values = text_values(frame);
+            let mut changed = false;
+            for value in values.iter_mut() {
+                if let Some(rule) = self.rule_for(value) {
+                    *value = self.rules[rule].to.clone();
+                    self.counts.lock().unwrap()[rule] += 1;
+                    replaced += 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                continue;
+            }
+            if let Some(mut normalized) = text_frame(&id, &values, tag.version()) {
+                normalized.set_group(group);
+                tag.frames_mut()[index] = normalized;
+            }
+        }
+        replaced
+    }
+
+    // Substitutions made by every clone so far, in the order the rules were added
+    pub fn counts(&self) -> Vec<RuleCount> {
+        let counts = self.counts.lock().unwrap();
+        self.rules
+            .iter()
+            .zip(counts.iter())
+            .map(|(rule, count)| RuleCount { from: rule.from.clone(), to: rule.to.clone(), count: *count })
+            .collect()
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.lock().unwrap().iter().sum()
+    }
+
+    pub fn clear_counts(&self) {
+        self.counts.lock().unwrap().iter_mut().for_each(|count| *count = 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_values_per_rule() {
+        let dictionary = Dictionary::new().replace("king gizzard", "King Gizzard & The Lizard Wizard").replace("Flightless Records", "Flightless");
+        let mut tag = Tag::new(4);
+        tag.add_frame(text_frame("TPE1", &["  KING GIZZARD ".to_string(), "Mild High Club".to_string()], 4).unwrap());
+        tag.set_text("TPE2", "King Gizzard");
+        tag.set_text("TPUB", "flightless records");
+        tag.set_text("TALB", "King Gizzard Live");
+        tag.set_text("TXXX", "king gizzard");
+
+        assert_eq!(dictionary.apply(&mut tag), 3);
+        assert_eq!(text_values(tag.frame("TPE1").unwrap()), ["King Gizzard & The Lizard Wizard", "Mild High Club"]);
+        assert_eq!(tag.text("TPUB").as_deref(), Some("Flightless"));
+        assert_eq!(tag.album().as_deref(), Some("King Gizzard Live"));
+
+        // Canonical values aren't counted again
+        assert_eq!(dictionary.clone().apply(&mut tag), 0);
+        assert_eq!(dictionary.counts().iter().map(|rule| rule.count).collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(dictionary.total(), 3);
+        dictionary.clear_counts();
+        assert_eq!(dictionary.total(), 0);
+    }
+
+    #[test]
+    fn restricted_fields() {
+        let dictionary = Dictionary::new().replace("ac-dc", "AC/DC").fields(&["TPE1"]);
+        let mut tag = Tag::new(3);
+        tag.set_text("TPE1", "AC-DC");
+        tag.set_text("TPE2", "AC-DC");
+        assert_eq!(dictionary.apply(&mut tag), 1);
+        assert_eq!((tag.artist().unwrap(), tag.text("TPE2").unwrap()), ("AC/DC".to_string(), "AC-DC".to_string()));
+    }
+
+    #[test]
+    fn parse_rules() {
+        let dictionary = Dictionary::parse("# artists\nBeatles\tThe Beatles\n\nSigur Ros\tSigur Rós\n").unwrap();
+        assert_eq!(dictionary.counts().len(), 2);
+        assert_eq!(dictionary.counts()[1], RuleCount { from: "Sigur Ros".into(), to: "Sigur Rós".into(), count: 0 });
+        assert_eq!(Dictionary::parse("a\tb\nno tab").unwrap_err().to_string(), "dictionary line 2: expected a tab between the two spellings");
+    }
+}
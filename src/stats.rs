@@ -0,0 +1,199 @@
+use crate::paths::long_path;
+use crate::report::mp3_files;
+use crate::{Header, ReadOptions, Tag};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+// Frames bigger than this are counted from their header without reading them in
+const LAZY_OVER: u64 = 16 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatsOptions {
+    lenient: bool,
+    largest_art: usize,
+}
+
+impl StatsOptions {
+    pub fn new() -> Self {
+        Self { lenient: false, largest_art: 10 }
+    }
+
+    // Read tags with ReadOptions::lenient so damaged ones are counted instead of failing
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    // How many of the biggest pictures ArtStats::largest lists, 10 by default
+    pub fn largest_art(mut self, count: usize) -> Self {
+        self.largest_art = count;
+        self
+    }
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// How often one frame id is used over the library
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameUsage {
+    // Files with at least one of the frame
+    pub files: usize,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+// Sizes are of whole APIC frames, the picture and the few bytes describing it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArtStats {
+    pub pictures: usize,
+    pub files_with_art: usize,
+    pub total_bytes: u64,
+    // The biggest pictures and the files they are in, biggest first
+    pub largest: Vec<(PathBuf, u64)>,
+}
+
+impl ArtStats {
+    pub fn average_bytes(&self) -> Option<u64> {
+        (self.pictures > 0).then(|| self.total_bytes / self.pictures as u64)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaddingStats {
+    pub total_bytes: u64,
+    pub largest: u64,
+    // Tags that have to be rewritten whole for any change that makes them grow
+    pub tags_without: usize,
+}
+
+// What a scan of a library found, for dashboards and the stats command
+#[derive(Debug, Default)]
+pub struct LibraryStats {
+    pub files: usize,
+    // Files without an ID3v2 tag
+    pub untagged: usize,
+    // Tagged files by major version
+    pub versions: BTreeMap<u8, usize>,
+    pub frames: BTreeMap<String, FrameUsage>,
+    pub art: ArtStats,
+    pub padding: PaddingStats,
+    // Bytes of every tag, header and padding included
+    pub tag_bytes: u64,
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+impl LibraryStats {
+    pub fn tagged(&self) -> usize {
+        self.versions.values().sum()
+    }
+
+    // Frame ids by how many files use them, most used first
+    pub fn most_used(&self) -> Vec<(&str, &FrameUsage)> {
+        let mut frames: Vec<(&str, &FrameUsage)> = self.frames.iter().map(|(id, usage)| (id.as_str(), usage)).collect();
+        frames.sort_by_key(|(_, usage)| std::cmp::Reverse(usage.files));
+        frames
+    }
+
+    fn add(&mut self, path: &Path, tag: &Tag, largest_art: usize) {
+        *self.versions.entry(tag.version()).or_default() += 1;
+        self.tag_bytes += tag.header().tag_size();
+        self.padding.total_bytes += tag.padding();
+        self.padding.largest = self.padding.largest.max(tag.padding());
+        self.padding.tags_without += (tag.padding() == 0) as usize;
+
+        let frames = tag.frames().iter().map(|frame| (frame.id(), frame.size()));
+        let lazy = tag.lazy_frames().iter().map(|frame| (frame.id().to_string(), frame.size()));
+        let mut seen: Vec<String> = Vec::new();
+        let mut pictures = 0;
+        for (id, size) in frames.chain(lazy) {
+            let usage = self.frames.entry(id.clone()).or_default();
+            usage.count += 1;
+            usage.bytes += size;
+            if !seen.contains(&id) {
+                usage.files += 1;
+                seen.push(id.clone());
+            }
+            if id == "APIC" {
+                pictures += 1;
+                self.art.total_bytes += size;
+                self.art.largest.push((path.to_path_buf(), size));
+            }
+        }
+        self.art.pictures += pictures;
+        self.art.files_with_art += (pictures > 0) as usize;
+        self.art.largest.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        self.art.largest.truncate(largest_art);
+    }
+}
+
+fn has_tag(path: &Path) -> io::Result<bool> {
+    let mut bytes = Vec::new();
+    File::open(long_path(path))?.take(10).read_to_end(&mut bytes)?;
+    Ok(Header::from_bytes(&bytes).is_some())
+}
+
+pub fn collect_files(files: &[impl AsRef<Path>], options: &StatsOptions) -> LibraryStats {
+    let read_options = ReadOptions::new().lenient(options.lenient).lazy_frames_over(LAZY_OVER);
+    let mut stats = LibraryStats::default();
+    for file in files {
+        let path = file.as_ref();
+        stats.files += 1;
+        match has_tag(path).and_then(|tagged| tagged.then(|| Tag::from_file_with(path, &read_options)).transpose()) {
+            Ok(Some(tag)) => stats.add(path, &tag, options.largest_art),
+            Ok(None) => stats.untagged += 1,
+            Err(error) => stats.failed.push((path.to_path_buf(), error)),
+        }
+    }
+    stats
+}
+
+// Statistics over a file, or every MP3 below a directory
+pub fn collect(path: impl AsRef<Path>, options: &StatsOptions) -> io::Result<LibraryStats> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(collect_files(&[path], options));
+    }
+    let mut files = Vec::new();
+    mp3_files(path, &mut files)?;
+    Ok(collect_files(&files, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::WriteOptions;
+    use std::fs;
+
+    #[test]
+    fn library_totals() {
+        let dir = temp_path("stats");
+        fs::create_dir_all(dir.join("album")).unwrap();
+        let original = fs::read("test/Polygondwanaland.mp3").unwrap();
+        fs::write(dir.join("album/1.mp3"), &original).unwrap();
+        fs::write(dir.join("album/2.mp3"), &original).unwrap();
+        fs::write(dir.join("untagged.mp3"), &original[187217..]).unwrap();
+        fs::write(dir.join("broken.mp3"), &original[..100]).unwrap();
+        let mut tag = Tag::from_file(dir.join("album/2.mp3")).unwrap();
+        tag.remove("APIC");
+        tag.write_to_file(dir.join("album/2.mp3"), &WriteOptions::new().version(4).padding(0)).unwrap();
+
+        let stats = collect(&dir, &StatsOptions::new()).unwrap();
+        assert_eq!((stats.files, stats.tagged(), stats.untagged, stats.failed.len()), (4, 2, 1, 1));
+        assert_eq!(stats.versions, BTreeMap::from([(3, 1), (4, 1)]));
+        assert_eq!(stats.frames["TIT2"], FrameUsage { files: 2, count: 2, bytes: 2 * tag.frame("TIT2").unwrap().size() });
+        assert_eq!(stats.most_used()[0].1.files, 2);
+
+        let apic = Tag::from_file("test/Polygondwanaland.mp3").unwrap().frame("APIC").unwrap().size();
+        assert_eq!((stats.art.pictures, stats.art.files_with_art, stats.art.average_bytes()), (1, 1, Some(apic)));
+        assert_eq!(stats.art.largest, [(dir.join("album/1.mp3"), apic)]);
+        assert_eq!((stats.padding.total_bytes, stats.padding.tags_without), (9545, 1));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
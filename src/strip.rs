@@ -0,0 +1,117 @@
+use crate::paths::long_path;
+use crate::{Tag, mpeg};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+// Frames a minimal tag keeps from the input, after it is converted to v2.4
+const MINIMAL_FRAMES: [&str; 8] = ["TIT2", "TPE1", "TPE2", "TALB", "TRCK", "TPOS", "TDRC", "TCON"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StripOptions {
+    minimal_tag: bool,
+}
+
+impl StripOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Start the output with a new v2.4 tag holding only title, artists, album, numbering, date
+    // and genre from the input, without padding
+    pub fn minimal_tag(mut self, keep: bool) -> Self {
+        self.minimal_tag = keep;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stripped {
+    pub audio_frames: usize,
+    // Everything in the input that wasn't an audio frame
+    pub bytes_removed: u64,
+}
+
+// Runs of frames that follow each other directly, copied in one go
+fn frame_runs(frames: &[(u64, mpeg::FrameHeader)]) -> Vec<Range<u64>> {
+    let mut runs: Vec<Range<u64>> = Vec::new();
+    for (offset, header) in frames {
+        let end = offset + header.frame_length() as u64;
+        match runs.last_mut() {
+            Some(run) if run.end == *offset => run.end = end,
+            _ => runs.push(*offset..end),
+        }
+    }
+    runs
+}
+
+// Writes only the MPEG audio frames of input to output. ID3v2 tags at either end and ID3v1 are
+// skipped by position, APE, Lyrics3 and any other junk because it doesn't parse as frames. The
+// Xing or Info frame is kept since it is a frame itself and players need it to seek
+pub fn strip_all(input: impl AsRef<Path>, output: impl AsRef<Path>, options: &StripOptions) -> io::Result<Stripped> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    if fs::canonicalize(long_path(output)).is_ok_and(|output| fs::canonicalize(long_path(input)).is_ok_and(|input| input == output)) {
+        return Err(Error::new(ErrorKind::InvalidInput, "strip_all can't write over its input"));
+    }
+    let mut file = File::open(long_path(input))?;
+    let range = mpeg::audio_range(&mut file)?;
+    let frames = mpeg::scan(&mut file, range)?;
+    if frames.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "No MPEG audio frames found"));
+    }
+
+    let mut writer = BufWriter::new(File::create(long_path(output))?);
+    if options.minimal_tag {
+        let (mut tag, _) = Tag::from_file(input).unwrap_or_else(|_| Tag::new(4)).convert(4);
+        tag.frames_mut().retain(|frame| MINIMAL_FRAMES.contains(&frame.id().as_str()));
+        if !tag.frames().is_empty() {
+            writer.write_all(&tag.to_bytes(0))?;
+        }
+    }
+    let mut audio = 0;
+    for run in frame_runs(&frames) {
+        file.seek(SeekFrom::Start(run.start))?;
+        audio += io::copy(&mut (&mut file).take(run.end - run.start), &mut writer)?;
+    }
+    writer.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+    Ok(Stripped { audio_frames: frames.len(), bytes_removed: file.metadata()?.len() - audio })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::id3v1::Id3v1;
+
+
+    #[test]
+    fn only_audio_frames_remain() {
+        let original = fs::read("test/Polygondwanaland.mp3").unwrap();
+        let mut file = File::open("test/Polygondwanaland.mp3").unwrap();
+        let range = mpeg::audio_range(&mut file).unwrap();
+        let frames = mpeg::scan(&mut file, range).unwrap();
+        let audio: Vec<u8> = frame_runs(&frames).into_iter().flat_map(|run| original[run.start as usize..run.end as usize].to_vec()).collect();
+
+        // Audio between the fixture's tag and an APE tag, Lyrics3 and ID3v1
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let ape = [b"APETAGEX".as_slice(), &[0xD0, 0x07, 0, 0, 32, 0, 0, 0], &[0; 16]].concat();
+        let lyrics = b"LYRICSBEGININD00003110000014LYRICS200".to_vec();
+        let input = temp_path("input.mp3");
+        fs::write(&input, [tag.to_bytes(1024), audio.clone(), ape, lyrics, Id3v1::from_tag(&tag).to_bytes().to_vec()].concat()).unwrap();
+
+        let output = temp_path("output.mp3");
+        let stripped = strip_all(&input, &output, &StripOptions::new()).unwrap();
+        assert!(fs::read(&output).unwrap() == audio);
+        assert_eq!(stripped.audio_frames, frames.len());
+        assert_eq!(stripped.bytes_removed, fs::metadata(&input).unwrap().len() - audio.len() as u64);
+
+        strip_all(&input, &output, &StripOptions::new().minimal_tag(true)).unwrap();
+        let minimal = Tag::from_file(&output).unwrap();
+        assert_eq!((minimal.version(), minimal.title(), minimal.frame("APIC").is_none()), (4, tag.title(), true));
+        assert!(fs::read(&output).unwrap().ends_with(&audio));
+        assert_eq!(strip_all(&input, &input, &StripOptions::new()).err().unwrap().kind(), ErrorKind::InvalidInput);
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+// A plain text form of a tag meant for version control: one frame per line, sorted so the
+// same tag always gives the same text, and read back to the same frames.
+//
+//     ID3v2.3
+//     TALB utf16 Polygondwanaland\0
+//     TPE1 latin1 King Gizzard & The Lizard Wizard
+//     TXXX base64 AGZvbwBiYXI=
+//     WOAR url https://kinggizzardandthelizardwizard.com
+//
+// Text frames whose bytes come back exactly from their values are written as text, with
+// values separated by \0 and a trailing \0 for a final terminator. Everything else is
+// written as base64. Flags, a group, an encryption method and a data length go between the id
+// and the kind as flags=read_only,compressed, group=N, encryption=N and length=N
+use crate::convert::text_values;
+use crate::digest::{base64, from_base64};
+use crate::ID3::{bytes_from_text, terminator};
+use crate::{Frame, FrameFlags, Tag};
+use std::io::{self, Error, ErrorKind};
+
+const ENCODINGS: [&str; 4] = ["latin1", "utf16", "utf16be", "utf8"];
+
+const FLAGS: [&str; 4] = ["discard_on_tag_change", "discard_on_file_change", "read_only", "compressed"];
+
+fn flag<'a>(flags: &'a mut FrameFlags, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "discard_on_tag_change" => Some(&mut flags.discard_on_tag_change),
+        "discard_on_file_change" => Some(&mut flags.discard_on_file_change),
+        "read_only" => Some(&mut flags.read_only),
+        "compressed" => Some(&mut flags.compressed),
+        _ => None,
+    }
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("tag text line {line}: {message}"))
+}
+
+// Backslash escapes for control characters, and for spaces at either end that editors strip
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    let last = value.chars().count().saturating_sub(1);
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ' ' if i == 0 || i == last => escaped.push_str("\\x20"),
+            _ if c.is_control() && (c as u32) < 0x100 => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// The values of a text payload, None for an unknown escape
+fn unescape(text: &str) -> Option<Vec<String>> {
+    let mut values = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let value = values.last_mut().unwrap();
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => value.push('\\'),
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => values.push(String::new()),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                value.push(u8::from_str_radix(&hex, 16).ok()? as char);
+            }
+            _ => return None,
+        }
+    }
+    Some(values)
+}
+
+// Empty values are written without a BOM so a final terminator comes back as it was
+fn text_data(encoding: u8, values: &[String]) -> Vec<u8> {
+    let mut data = vec![encoding];
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            data.extend_from_slice(terminator(encoding));
+        }
+        if !value.is_empty() {
+            data.extend(bytes_from_text(encoding, value));
+        }
+    }
+    data
+}
+
+// Kind and payload, text only when it gives back the same bytes
+fn payload(frame: &Frame) -> (&'static str, String) {
+    let id = frame.id();
+    let data = frame.data();
+    if id.starts_with('T') && id != "TXXX" {
+        let encoding = data.first().copied().unwrap_or(0xFF);
+        // Many taggers end the text with a terminator, kept as an empty last value
+        let mut values = text_values(frame);
+        if text_data(encoding, &values) != data {
+            values.push(String::new());
+        }
+        if let Some(kind) = ENCODINGS.get(encoding as usize).filter(|_| text_data(encoding, &values) == data) {
+            return (kind, values.iter().map(|value| escape(value)).collect::<Vec<_>>().join("\\0"));
+        }
+    }
+    if id.starts_with('W') && id != "WXXX" && !data.is_empty() && data.iter().all(|x| x.is_ascii_graphic()) {
+        return ("url", String::from_utf8_lossy(data).into_owned());
+    }
+    ("base64", base64(data))
+}
+
+fn line(frame: &Frame) -> String {
+    let mut line = frame.id();
+    let mut flags = frame.flags();
+    let set: Vec<&str> = FLAGS.into_iter().filter(|name| flag(&mut flags, name).is_some_and(|set| *set)).collect();
+    if !set.is_empty() {
+        line += &format!(" flags={}", set.join(","));
+    }
+    if let Some(group) = frame.group() {
+        line += &format!(" group={group}");
+    }
+    if let Some(method) = frame.encryption() {
+        line += &format!(" encryption={method}");
+    }
+    if let Some(length) = frame.data_length() {
+        line += &format!(" length={length}");
+    }
+    let (kind, payload) = payload(frame);
+    format!("{line} {kind} {payload}")
+}
+
+fn parse_line(number: usize, line: &str) -> io::Result<Frame> {
+    let (id, mut rest) = line.split_once(' ').ok_or_else(|| invalid(number, "expected a frame id and a value"))?;
+    let (mut flags, mut group, mut encryption, mut length) = (FrameFlags::default(), None, None, None);
+    loop {
+        let (word, remaining) = rest.split_once(' ').unwrap_or((rest, ""));
+        let Some((name, value)) = word.split_once('=') else {
+            break;
+        };
+        let bad = || invalid(number, &format!("invalid {name}"));
+        match name {
+            "flags" => {
+                for name in value.split(',') {
+                    *flag(&mut flags, name).ok_or_else(bad)? = true;
+                }
+            }
+            "group" => group = Some(value.parse().map_err(|_| bad())?),
+            "encryption" => encryption = Some(value.parse().map_err(|_| bad())?),
+            "length" => length = Some(value.parse().map_err(|_| bad())?),
+            _ => return Err(bad()),
+        }
+        rest = remaining;
+    }
+
+    let (kind, payload) = rest.split_once(' ').unwrap_or((rest, ""));
+    let data = match kind {
+        "base64" => from_base64(payload).ok_or_else(|| invalid(number, "invalid base64"))?,
+        "url" => payload.as_bytes().to_vec(),
+        _ => {
+            let encoding = ENCODINGS.iter().position(|x| *x == kind).ok_or_else(|| invalid(number, &format!("unknown kind {kind}")))?;
+            let values = unescape(payload).ok_or_else(|| invalid(number, "invalid escape"))?;
+            text_data(encoding as u8, &values)
+        }
+    };
+    let mut frame = Frame::new(id, data).ok_or_else(|| invalid(number, &format!("invalid frame id {id}")))?.with_flags(flags, encryption, length);
+    frame.set_group(group);
+    Ok(frame)
+}
+
+impl Tag {
+    // Frames left in the file by lazy reading are read in first
+    pub fn to_text(&self) -> io::Result<String> {
+        let mut tag = self.clone();
+        tag.load_lazy_frames()?;
+        let mut lines: Vec<String> = tag.frames().iter().map(line).collect();
+        lines.sort();
+        Ok(format!("ID3v2.{}\n", tag.version()) + &lines.iter().map(|line| line.clone() + "\n").collect::<String>())
+    }
+
+    // Blank lines and lines starting with # are skipped
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'));
+        let version = match lines.next() {
+            Some((_, "ID3v2.3")) => 3,
+            Some((_, "ID3v2.4")) => 4,
+            Some((i, _)) => return Err(invalid(i + 1, "expected ID3v2.3 or ID3v2.4")),
+            None => return Err(invalid(1, "empty")),
+        };
+        let mut tag = Tag::new(version);
+        for (i, line) in lines {
+            tag.add_frame(parse_line(i + 1, line)?);
+        }
+        Ok(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::text_frame;
+
+    #[test]
+    fn round_trip() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        tag.add_frame(text_frame("TPE2", &[" Ōkami\n".to_string(), "A\\B".to_string()], 3).unwrap());
+        let mut grouped = Frame::new("TIT3", b"\x00Live".to_vec()).unwrap();
+        grouped.set_group(Some(7));
+        tag.add_frame(grouped);
+        tag.add_frame(Frame::new("TCOM", b"\x00trailing\x00".to_vec()).unwrap());
+        tag.add_frame(Frame::new("TOPE", b"\x00two\x00\x00".to_vec()).unwrap());
+        tag.set_url("WOAR", "https://kinggizzardandthelizardwizard.com").unwrap();
+
+        let text = tag.to_text().unwrap();
+        assert!(text.starts_with("ID3v2.3\nAPIC base64 "));
+        assert!(text.contains("\nTALB utf16 Polygondwanaland\\0\n"));
+        assert!(text.contains("\nTCOM latin1 trailing\\0\n"));
+        assert!(text.contains("\nTIT3 group=7 latin1 Live\n"));
+        assert!(text.contains("\nTPE2 utf16 \\x20Ōkami\\n\\0A\\\\B\n"));
+        assert!(text.contains("\nTOPE latin1 two\\0\\0\n"));
+        assert!(text.contains("\nCOMM base64 "));
+        assert!(text.contains("\nWOAR url https://kinggizzardandthelizardwizard.com\n"));
+
+        let parsed = Tag::from_text(&text).unwrap();
+        assert_eq!(parsed.to_text().unwrap(), text);
+        for frame in tag.frames() {
+            assert!(parsed.frames().contains(frame), "{}", frame.id());
+        }
+        assert_eq!(parsed.frames().len(), tag.frames().len());
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |text| Tag::from_text(text).err().unwrap().to_string();
+        assert_eq!(error("ID3v2.2\n"), "tag text line 1: expected ID3v2.3 or ID3v2.4");
+        assert_eq!(error("# tag\nID3v2.4\nTIT2 latin1 ok\nTIT2 utf32 no\n"), "tag text line 4: unknown kind utf32");
+        assert_eq!(error("ID3v2.4\nTIT2 base64 !!\n"), "tag text line 2: invalid base64");
+        assert_eq!(error("ID3v2.4\nTIT2 latin1 a\\qb\n"), "tag text line 2: invalid escape");
+        assert_eq!(error("ID3v2.4\ntit2 latin1 a\n"), "tag text line 2: invalid frame id tit2");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::convert::split_timestamp;
+use crate::validate::is_timestamp;
+use crate::Tag;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A v2.4 timestamp, yyyy-MM-ddTHH:mm:ss cut off at any precision. Every field after the
+// first missing one is ignored
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub year: u16,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+    pub hour: Option<u8>,
+    pub minute: Option<u8>,
+    pub second: Option<u8>,
+}
+
+// Year, month and day of the days since 1970-01-01, from Howard Hinnant's civil_from_days
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u8;
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+impl Timestamp {
+    pub fn year(year: u16) -> Self {
+        Self { year, month: None, day: None, hour: None, minute: None, second: None }
+    }
+
+    // None unless the text is a valid timestamp
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if !is_timestamp(text) {
+            return None;
+        }
+        let mut parts = text.split(['-', 'T', ':']);
+        let year = parts.next()?.parse().ok()?;
+        let mut next = || parts.next().and_then(|part| part.parse().ok());
+        Some(Self { year, month: next(), day: next(), hour: next(), minute: next(), second: next() })
+    }
+
+    // Seconds since the Unix epoch as a UTC timestamp to the second
+    pub fn from_unix(seconds: u64) -> Self {
+        let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+        let time = seconds % 86_400;
+        Self {
+            year: year.clamp(0, 9999) as u16,
+            month: Some(month),
+            day: Some(day),
+            hour: Some((time / 3600) as u8),
+            minute: Some((time / 60 % 60) as u8),
+            second: Some((time % 60) as u8),
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::from_unix(SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        let rest = [("-", self.month), ("-", self.day), ("T", self.hour), (":", self.minute), (":", self.second)];
+        for (separator, value) in rest {
+            let Some(value) = value else {
+                break;
+            };
+            write!(f, "{separator}{value:02}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Tag {
+    fn timestamp(&self, id: &str) -> Option<Timestamp> {
+        self.text(id).as_deref().and_then(Timestamp::parse)
+    }
+
+    // TDRC, or on a v2.3 tag TYER with the date and time from TDAT and TIME
+    pub fn recording_time(&self) -> Option<Timestamp> {
+        if let Some(timestamp) = self.timestamp("TDRC") {
+            return Some(timestamp);
+        }
+        let mut timestamp = Timestamp::year(self.text("TYER")?.trim().parse().ok()?);
+        if let Some(date) = self.text("TDAT").filter(|date| date.len() == 4) {
+            (timestamp.day, timestamp.month) = (date[..2].parse().ok(), date[2..].parse().ok());
+            if let Some(time) = self.text("TIME").filter(|time| time.len() == 4) {
+                (timestamp.hour, timestamp.minute) = (time[..2].parse().ok(), time[2..].parse().ok());
+            }
+        }
+        Some(timestamp)
+    }
+
+    // A v2.3 tag gets TYER, TDAT and TIME, seconds have nowhere to go there
+    pub fn set_recording_time(&mut self, timestamp: &Timestamp) {
+        if self.version() == 4 {
+            self.set_text("TDRC", &timestamp.to_string());
+            return;
+        }
+        for id in ["TDRC", "TYER", "TDAT", "TIME"] {
+            self.remove(id);
+        }
+        for (id, value) in split_timestamp(&timestamp.to_string()) {
+            self.set_text(id, &value);
+        }
+    }
+
+    pub fn release_time(&self) -> Option<Timestamp> {
+        self.timestamp("TDRL")
+    }
+
+    // TDRL, TDEN and TDTG only exist in v2.4 and are dropped when the tag is written as v2.3
+    pub fn set_release_time(&mut self, timestamp: &Timestamp) {
+        self.set_text("TDRL", &timestamp.to_string());
+    }
+
+    // TDOR, or the year in TORY on a v2.3 tag
+    pub fn original_release_time(&self) -> Option<Timestamp> {
+        self.timestamp("TDOR").or_else(|| self.text("TORY")?.trim().parse().ok().map(Timestamp::year))
+    }
+
+    pub fn set_original_release_time(&mut self, timestamp: &Timestamp) {
+        match self.version() {
+            4 => self.set_text("TDOR", &timestamp.to_string()),
+            _ => self.set_text("TORY", &format!("{:04}", timestamp.year)),
+        }
+    }
+
+    pub fn encoding_time(&self) -> Option<Timestamp> {
+        self.timestamp("TDEN")
+    }
+
+    pub fn set_encoding_time(&mut self, timestamp: &Timestamp) {
+        self.set_text("TDEN", &timestamp.to_string());
+    }
+
+    // When the tag was last written, see WriteOptions::stamp_tagging_time
+    pub fn tagging_time(&self) -> Option<Timestamp> {
+        self.timestamp("TDTG")
+    }
+
+    pub fn set_tagging_time(&mut self, timestamp: &Timestamp) {
+        self.set_text("TDTG", &timestamp.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for text in ["2017", "2017-11", "2017-11-17", "2017-11-17T09", "2017-11-17T09:30", "2017-11-17T09:30:05"] {
+            assert_eq!(Timestamp::parse(text).unwrap().to_string(), text);
+        }
+        let timestamp = Timestamp::parse("2017-11-17T09:30").unwrap();
+        assert_eq!((timestamp.day, timestamp.minute, timestamp.second), (Some(17), Some(30), None));
+        assert_eq!(Timestamp::parse("2017-13"), None);
+        assert!(Timestamp::year(2017) < timestamp);
+    }
+
+    #[test]
+    fn unix_time() {
+        assert_eq!(Timestamp::from_unix(0).to_string(), "1970-01-01T00:00:00");
+        assert_eq!(Timestamp::from_unix(1_510_911_005).to_string(), "2017-11-17T09:30:05");
+        assert_eq!(Timestamp::from_unix(951_782_400).to_string(), "2000-02-29T00:00:00");
+    }
+
+    #[test]
+    fn time_frames() {
+        let timestamp = Timestamp::parse("2017-11-17T09:30").unwrap();
+        let mut tag = Tag::new(4);
+        tag.set_recording_time(&timestamp);
+        tag.set_release_time(&Timestamp::year(2018));
+        tag.set_original_release_time(&timestamp);
+        assert_eq!(tag.text("TDRC").as_deref(), Some("2017-11-17T09:30"));
+        assert_eq!(tag.release_time(), Some(Timestamp::year(2018)));
+        assert_eq!(tag.original_release_time(), Some(timestamp));
+        assert_eq!(tag.encoding_time(), None);
+
+        let mut tag = Tag::new(3);
+        tag.set_recording_time(&timestamp);
+        tag.set_original_release_time(&timestamp);
+        assert_eq!((tag.text("TYER"), tag.text("TDAT"), tag.text("TIME")), (Some("2017".into()), Some("1711".into()), Some("0930".into())));
+        assert_eq!(tag.recording_time(), Some(timestamp));
+        assert_eq!(tag.original_release_time(), Some(Timestamp::year(2017)));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::convert::text_frame;
+use crate::validate::Violation;
+use crate::{Frame, Tag};
+use std::fmt;
+
+enum Change {
+    SetText(String, String),
+    SetUrl(String, String),
+    SetFrame(Frame),
+    AddFrame(Frame),
+    Remove(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    // Not a frame id, or the id of a frame the change can't make
+    InvalidId { id: String },
+    InvalidUrl { id: String, url: String },
+    // Problems the changes would bring into the tag, ones it already had don't count
+    Violations(Vec<Violation>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::InvalidId { id } => write!(f, "invalid frame id: {id}"),
+            EditError::InvalidUrl { id, url } => write!(f, "{id} is not a URL: {url}"),
+            EditError::Violations(violations) => {
+                let violations: Vec<String> = violations.iter().map(|violation| violation.to_string()).collect();
+                write!(f, "{}", violations.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+// Changes to a tag gathered up and made together by commit, so a tag is never left with only
+// some of them. Dropping the transaction without committing leaves the tag as it was
+pub struct Transaction<'a> {
+    tag: &'a mut Tag,
+    changes: Vec<Change>,
+}
+
+impl Tag {
+    pub fn edit(&mut self) -> Transaction<'_> {
+        Transaction { tag: self, changes: Vec::new() }
+    }
+}
+
+impl Transaction<'_> {
+    pub fn set_text(mut self, id: &str, text: &str) -> Self {
+        self.changes.push(Change::SetText(id.to_string(), text.to_string()));
+        self
+    }
+
+    pub fn set_url(mut self, id: &str, url: &str) -> Self {
+        self.changes.push(Change::SetUrl(id.to_string(), url.to_string()));
+        self
+    }
+
+    // Replaces the first frame with the same id in place, or adds it at the end
+    pub fn set_frame(mut self, frame: Frame) -> Self {
+        self.changes.push(Change::SetFrame(frame));
+        self
+    }
+
+    pub fn add_frame(mut self, frame: Frame) -> Self {
+        self.changes.push(Change::AddFrame(frame));
+        self
+    }
+
+    pub fn remove(mut self, id: &str) -> Self {
+        self.changes.push(Change::Remove(id.to_string()));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // Makes the changes in order on a copy of the tag and checks it with Tag::validate, the tag
+    // only takes the copy's place when nothing failed
+    pub fn commit(self) -> Result<(), EditError> {
+        let mut edited = self.tag.clone();
+        for change in self.changes {
+            match change {
+                Change::SetText(id, _) if !id.starts_with('T') || id == "TXXX" => return Err(EditError::InvalidId { id }),
+                Change::SetText(id, text) => {
+                    let version = edited.version();
+                    match text_frame(&id, &[text], version) {
+                        Some(frame) => replace(&mut edited, frame),
+                        None => return Err(EditError::InvalidId { id }),
+                    }
+                }
+                Change::SetUrl(id, url) => {
+                    if edited.set_url(&id, &url).is_err() {
+                        return match Frame::new(&id, Vec::new()).filter(|_| id.starts_with('W')) {
+                            Some(_) => Err(EditError::InvalidUrl { id, url }),
+                            None => Err(EditError::InvalidId { id }),
+                        };
+                    }
+                }
+                Change::SetFrame(frame) => replace(&mut edited, frame),
+                Change::AddFrame(frame) => edited.add_frame(frame),
+                Change::Remove(id) => edited.remove(&id),
+            }
+        }
+
+        let before = self.tag.validate();
+        let introduced: Vec<Violation> = edited.validate().into_iter().filter(|violation| !before.contains(violation)).collect();
+        if !introduced.is_empty() {
+            return Err(EditError::Violations(introduced));
+        }
+        *self.tag = edited;
+        Ok(())
+    }
+}
+
+fn replace(tag: &mut Tag, frame: Frame) {
+    match tag.frames().iter().position(|existing| existing.id() == frame.id()) {
+        Some(index) => tag.frames_mut()[index] = frame,
+        None => tag.add_frame(frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_or_nothing() {
+        let mut tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let original = tag.clone();
+        let result = tag.edit().set_text("TIT2", "Crumbling Castle").remove("COMM").set_text("TRCK", "one").commit();
+        assert_eq!(result.unwrap_err(), EditError::Violations(vec![Violation::InvalidPosition { id: "TRCK".into(), value: "one".into() }]));
+        assert!(tag == original);
+        assert_eq!(tag.edit().set_text("TIT2", "x").set_url("WOAR", "not a url").commit().unwrap_err().to_string(), "WOAR is not a URL: not a url");
+        assert_eq!(tag.edit().set_text("COMM", "x").commit().unwrap_err(), EditError::InvalidId { id: "COMM".into() });
+        assert!(tag == original);
+
+        let edit = tag.edit().set_text("TIT2", "Crumbling Castle").remove("COMM").set_text("TRCK", "1/10").set_url("WOAR", "https://kinggizzard.com");
+        assert_eq!(edit.len(), 4);
+        edit.commit().unwrap();
+        assert_eq!((tag.title().as_deref(), tag.text("TRCK").as_deref()), (Some("Crumbling Castle"), Some("1/10")));
+        assert!(tag.frame("COMM").is_none() && tag.url("WOAR").is_some());
+    }
+}
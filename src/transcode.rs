@@ -0,0 +1,187 @@
+use crate::ID3::{spec_bytes_from_text, spec_text_from_bytes, spec_text_from_bytes_strict, split_terminated};
+use crate::TextError;
+use std::cell::RefCell;
+use std::sync::{Arc, RwLock};
+
+// Turns frame text into strings and back for the four ID3 encoding bytes. Every text
+// frame, comment, picture description and user frame goes through the one in use
+pub trait Transcoder: Send + Sync {
+    // The bytes are cut at their terminator before they get here
+    fn decode(&self, encoding: u8, bytes: &[u8]) -> Result<String, TextError>;
+
+    // Best effort decode for display, by default falls back to the spec decoding
+    fn decode_lossy(&self, encoding: u8, bytes: &[u8]) -> String {
+        self.decode(encoding, bytes).unwrap_or_else(|_| spec_text_from_bytes(encoding, bytes))
+    }
+
+    // Without a terminator
+    fn encode(&self, encoding: u8, text: &str) -> Vec<u8>;
+}
+
+// Latin-1, UTF-16 with and without BOM and UTF-8 as the spec defines them
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpecTranscoder;
+
+impl Transcoder for SpecTranscoder {
+    fn decode(&self, encoding: u8, bytes: &[u8]) -> Result<String, TextError> {
+        spec_text_from_bytes_strict(encoding, bytes)
+    }
+
+    fn decode_lossy(&self, encoding: u8, bytes: &[u8]) -> String {
+        spec_text_from_bytes(encoding, bytes)
+    }
+
+    fn encode(&self, encoding: u8, text: &str) -> Vec<u8> {
+        spec_bytes_from_text(encoding, text)
+    }
+}
+
+static DEFAULT: RwLock<Option<Arc<dyn Transcoder>>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Vec<Arc<dyn Transcoder>>> = const { RefCell::new(Vec::new()) };
+}
+
+// Used by every thread from now on, unless one has its own through with_transcoder
+pub fn set_transcoder(transcoder: impl Transcoder + 'static) {
+    *DEFAULT.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(transcoder));
+}
+
+// Back to SpecTranscoder
+pub fn reset_transcoder() {
+    *DEFAULT.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+// Runs f with the transcoder in use on this thread only
+pub fn with_transcoder<R>(transcoder: impl Transcoder + 'static, f: impl FnOnce() -> R) -> R {
+    struct Pop;
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| scoped.borrow_mut().pop());
+        }
+    }
+    SCOPED.with(|scoped| scoped.borrow_mut().push(Arc::new(transcoder)));
+    let _pop = Pop;
+    f()
+}
+
+fn current() -> Option<Arc<dyn Transcoder>> {
+    SCOPED.with(|scoped| scoped.borrow().last().cloned()).or_else(|| DEFAULT.read().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+pub(crate) fn decode(encoding: u8, bytes: &[u8]) -> Result<String, TextError> {
+    let bytes = split_terminated(encoding, bytes).0;
+    match current() {
+        Some(transcoder) => transcoder.decode(encoding, bytes),
+        None => SpecTranscoder.decode(encoding, bytes),
+    }
+}
+
+pub(crate) fn decode_lossy(encoding: u8, bytes: &[u8]) -> String {
+    let bytes = split_terminated(encoding, bytes).0;
+    match current() {
+        Some(transcoder) => transcoder.decode_lossy(encoding, bytes),
+        None => SpecTranscoder.decode_lossy(encoding, bytes),
+    }
+}
+
+pub(crate) fn encode(encoding: u8, text: &str) -> Vec<u8> {
+    match current() {
+        Some(transcoder) => transcoder.encode(encoding, text),
+        None => SpecTranscoder.encode(encoding, text),
+    }
+}
+
+#[cfg(feature = "encoding_rs")]
+pub use legacy::LegacyCodepage;
+
+#[cfg(feature = "encoding_rs")]
+mod legacy {
+    use super::*;
+    use encoding_rs::Encoding;
+
+    // Reads "Latin-1" frames as the codepage they were really written in, common with
+    // Cyrillic and East Asian rips. Text is still written the spec way
+    pub struct LegacyCodepage {
+        encoding: &'static Encoding,
+    }
+
+    impl LegacyCodepage {
+        pub fn new(encoding: &'static Encoding) -> Self {
+            Self { encoding }
+        }
+
+        // By WHATWG label, "windows-1251", "shift_jis", "gbk" and so on
+        pub fn for_label(label: &str) -> Option<Self> {
+            Encoding::for_label(label.as_bytes()).map(Self::new)
+        }
+    }
+
+    impl Transcoder for LegacyCodepage {
+        fn decode(&self, encoding: u8, bytes: &[u8]) -> Result<String, TextError> {
+            if encoding != 0 {
+                return SpecTranscoder.decode(encoding, bytes);
+            }
+            match self.encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+                Some(text) => Ok(text.into_owned()),
+                None => Err(TextError::UnknownEncoding(0)),
+            }
+        }
+
+        fn decode_lossy(&self, encoding: u8, bytes: &[u8]) -> String {
+            if encoding != 0 {
+                return SpecTranscoder.decode_lossy(encoding, bytes);
+            }
+            self.encoding.decode_without_bom_handling(bytes).0.into_owned()
+        }
+
+        fn encode(&self, encoding: u8, text: &str) -> Vec<u8> {
+            SpecTranscoder.encode(encoding, text)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::{Frame, Tag};
+
+        #[test]
+        fn cyrillic_latin1_frame() {
+            let mut tag = Tag::new(3);
+            tag.add_frame(Frame::new("TIT2", vec![0x00, 0xCA, 0xE8, 0xED, 0xEE]).unwrap());
+            let text = with_transcoder(LegacyCodepage::for_label("windows-1251").unwrap(), || tag.title());
+            assert_eq!(text.as_deref(), Some("Кино"));
+            assert_eq!(tag.title().as_deref(), Some("Êèíî"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, Tag};
+
+    // Reads Latin-1 frames back to front
+    struct Reversed;
+
+    impl Transcoder for Reversed {
+        fn decode(&self, encoding: u8, bytes: &[u8]) -> Result<String, TextError> {
+            SpecTranscoder.decode(encoding, bytes).map(|text| text.chars().rev().collect())
+        }
+
+        fn encode(&self, encoding: u8, text: &str) -> Vec<u8> {
+            SpecTranscoder.encode(encoding, &text.chars().rev().collect::<String>())
+        }
+    }
+
+    #[test]
+    fn scoped_transcoder() {
+        let mut tag = Tag::new(4);
+        tag.add_frame(Frame::new("TIT2", b"\0Castle\0".to_vec()).unwrap());
+        assert_eq!(with_transcoder(Reversed, || tag.title()).as_deref(), Some("eltsaC"));
+        assert_eq!(tag.title().as_deref(), Some("Castle"));
+
+        with_transcoder(Reversed, || tag.set_text("TALB", "Polygondwanaland"));
+        assert_eq!(tag.frame("TALB").unwrap().data()[1..4], *b"dna");
+    }
+}
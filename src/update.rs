@@ -0,0 +1,176 @@
+use crate::paths::long_path;
+use crate::write::{existing_tag_size, tag_offsets};
+use crate::{id3v1, Header, MergeStrategy, ReadOptions, Reader, Tag};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+// ID3v2.4 tags flagged as updates replace frames of a tag found earlier in the file. They are
+// stacked after it at the start, or appended at the end before any ID3v1 tag, which changes a
+// large file without moving its audio. See Tag::set_update and ReadOptions::apply_updates
+
+// The tag at the end of the file found through its footer, None when there is none
+pub(crate) fn appended_tag_range(file: &mut File) -> io::Result<Option<Range<u64>>> {
+    let mut end = file.metadata()?.len();
+    if id3v1::read(file)?.is_some() {
+        end -= id3v1::SIZE;
+    }
+    let front = existing_tag_size(file)?;
+    if end < front + 20 {
+        return Ok(None);
+    }
+    let mut footer = [0u8; 10];
+    file.seek(SeekFrom::Start(end - 10))?;
+    file.read_exact(&mut footer)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    // The footer is a copy of the header with the identifier reversed
+    if &footer[..3] != b"3DI" {
+        return Ok(None);
+    }
+    footer[..3].copy_from_slice(b"ID3");
+    let start = Header::from_bytes(&footer).filter(|header| header.footer()).and_then(|header| end.checked_sub(header.tag_size()));
+    Ok(start.filter(|start| *start >= front).map(|start| start..end))
+}
+
+pub fn read_appended(filename: impl AsRef<Path>) -> io::Result<Option<Tag>> {
+    let filename = filename.as_ref();
+    let Some(range) = appended_tag_range(&mut File::open(long_path(filename))?)? else {
+        return Ok(None);
+    };
+    let mut reader = Reader::from_file(filename)?;
+    reader.skip_n_bytes(range.start as usize)?;
+    Tag::from_reader(&mut reader).map(Some)
+}
+
+// Update tags in the order they apply: stacked ones after the first, then the appended one
+pub fn read_updates(filename: impl AsRef<Path>, options: &ReadOptions) -> io::Result<Vec<Tag>> {
+    let filename = filename.as_ref();
+    let mut file = File::open(long_path(filename))?;
+    let mut offsets: Vec<u64> = tag_offsets(&mut file)?.iter().skip(1).map(|(offset, _)| *offset).collect();
+    offsets.extend(appended_tag_range(&mut file)?.map(|range| range.start));
+
+    let mut updates = Vec::new();
+    for offset in offsets {
+        let mut reader = Reader::from_file(filename)?;
+        reader.skip_n_bytes(offset as usize)?;
+        let mut tag = Tag::from_reader_with(&mut reader, options)?;
+        if tag.is_update() {
+            tag.load_lazy_frames()?;
+            updates.push(tag);
+        }
+    }
+    Ok(updates)
+}
+
+// Frames of each update replace the frames they share a key with, see merge::frame_key
+pub(crate) fn apply_updates(filename: &Path, tag: &mut Tag, options: &ReadOptions) -> io::Result<()> {
+    for update in read_updates(filename, options)? {
+        tag.merge(&update, MergeStrategy::PreferOther);
+    }
+    Ok(())
+}
+
+// Appends the frames as an update tag. An earlier appended tag takes them in and is written
+// again in its place, since readers only find the last one
+pub fn append_update(filename: impl AsRef<Path>, update: &Tag) -> io::Result<()> {
+    if update.version() != 4 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Only ID3v2.4 tags can be appended"));
+    }
+    let filename = filename.as_ref();
+    let mut tag = update.clone();
+    tag.load_lazy_frames()?;
+    let mut file = OpenOptions::new().read(true).write(true).open(long_path(filename))?;
+    let length = file.metadata()?.len();
+
+    let mut trailer = Vec::new();
+    if id3v1::read(&mut file)?.is_some() {
+        file.seek(SeekFrom::Start(length - id3v1::SIZE))?;
+        file.read_to_end(&mut trailer)?;
+    }
+    let end = match appended_tag_range(&mut file)? {
+        Some(range) => {
+            let mut earlier = read_appended(filename)?.unwrap();
+            earlier.merge(&tag, MergeStrategy::PreferOther);
+            tag = earlier;
+            range.start
+        }
+        None => {
+            tag.set_update(true)?;
+            length - trailer.len() as u64
+        }
+    };
+
+    // Appended tags need a footer and can't have padding
+    let mut bytes = tag.to_bytes(0);
+    bytes[5] |= 0b_00010000;
+    let footer = [b"3DI".as_slice(), &bytes[3..10]].concat();
+    bytes.extend(footer);
+    file.set_len(end)?;
+    file.seek(SeekFrom::Start(end))?;
+    file.write_all(&bytes)?;
+    file.write_all(&trailer)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::mpeg::audio_range;
+    use std::fs;
+
+    fn temp_copy(name: &str) -> std::path::PathBuf {
+        let path = temp_path(name);
+        fs::copy("test/Polygondwanaland.mp3", &path).unwrap();
+        path
+    }
+
+    #[test]
+    fn appended_updates() {
+        let path = temp_copy("appended.mp3");
+        let audio = audio_range(&mut File::open(&path).unwrap()).unwrap();
+        let mut update = Tag::new(4);
+        update.set_text("TIT2", "Crumbling Castle");
+        append_update(&path, &update).unwrap();
+        let mut update = Tag::new(4);
+        update.set_text("TCOM", "Stu Mackenzie");
+        append_update(&path, &update).unwrap();
+
+        assert_eq!(audio_range(&mut File::open(&path).unwrap()).unwrap(), audio);
+        let appended = read_appended(&path).unwrap().unwrap();
+        assert!(appended.is_update());
+        assert_eq!(appended.frames().len(), 2);
+        assert_eq!(Tag::from_file(&path).unwrap().title().as_deref(), Some("Polygondwanaland"));
+
+        let tag = Tag::from_file_with(&path, &ReadOptions::new().apply_updates(true)).unwrap();
+        assert_eq!(tag.title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(tag.text("TCOM").as_deref(), Some("Stu Mackenzie"));
+        assert_eq!(tag.frames().len(), Tag::from_file(&path).unwrap().frames().len() + 1);
+        assert_eq!(append_update(&path, &Tag::new(3)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stacked_updates() {
+        let path = temp_copy("stacked.mp3");
+        let original = fs::read(&path).unwrap();
+        let mut primary = Tag::new(4);
+        primary.set_text("TIT2", "Polygondwanaland");
+        primary.set_text("TALB", "Polygondwanaland");
+        let mut update = Tag::new(4);
+        update.set_text("TIT2", "Crumbling Castle");
+        update.set_update(true).unwrap();
+        let mut stale = Tag::new(4);
+        stale.set_text("TALB", "Gumboot Soup");
+        let audio = &original[187217..];
+        fs::write(&path, [primary.to_bytes(0), update.to_bytes(0), stale.to_bytes(0), audio.to_vec()].concat()).unwrap();
+
+        let tag = Tag::from_file_with(&path, &ReadOptions::new().apply_updates(true)).unwrap();
+        assert_eq!(tag.title().as_deref(), Some("Crumbling Castle"));
+        assert_eq!(tag.album().as_deref(), Some("Polygondwanaland"));
+        assert_eq!(tag.frames().len(), 2);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+use crate::analyze::{self, Severity};
+use crate::convert::text_values;
+use crate::frames::{Picture, PictureType, UserLink};
+use crate::{Frame, Language, Tag, TextError};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    // TRCK and TPOS hold a number optionally followed by "/total"
+    InvalidPosition { id: String, value: String },
+    InvalidNumber { id: String, value: String },
+    InvalidTimestamp { id: String, value: String },
+    // TCOP and TPRO start with a year and a space
+    InvalidCopyright { id: String, value: String },
+    InvalidLanguage { id: String, code: String },
+    InvalidMime { mime: String },
+    // Only one picture per type and description, and one of each file icon
+    DuplicatePicture { picture_type: PictureType, description: String },
+    InvalidUrl { id: String, url: String },
+    // TXXX descriptions must be unique, see Tag::collapse_user_texts
+    DuplicateUserText { description: String },
+    Undecodable { id: String, error: TextError },
+    // Less audio than TLEN or the Xing header claim, usually a download that didn't finish
+    TruncatedAudio { expected_ms: u64, actual_ms: u64, severity: Severity },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::InvalidPosition { id, value } => write!(f, "{id} is not a number or number/total: {value}"),
+            Violation::InvalidNumber { id, value } => write!(f, "{id} is not a number: {value}"),
+            Violation::InvalidTimestamp { id, value } => write!(f, "{id} is not a timestamp: {value}"),
+            Violation::InvalidCopyright { id, value } => write!(f, "{id} doesn't start with a year: {value}"),
+            Violation::InvalidLanguage { id, code } => write!(f, "{id} has an invalid language code: {code}"),
+            Violation::InvalidMime { mime } => write!(f, "Picture has an invalid MIME type: {mime}"),
+            Violation::DuplicatePicture { picture_type, description } => {
+                write!(f, "More than one {} picture described \"{description}\"", picture_type.name())
+            }
+            Violation::InvalidUrl { id, url } => write!(f, "{id} is not a URL: {url}"),
+            Violation::DuplicateUserText { description } => write!(f, "More than one TXXX frame described \"{description}\""),
+            Violation::Undecodable { id, error } => write!(f, "{id} can't be decoded: {error}"),
+            Violation::TruncatedAudio { expected_ms, actual_ms, severity } => {
+                write!(f, "Audio is {actual_ms} ms long but should be {expected_ms} ms ({severity:?})")
+            }
+        }
+    }
+}
+
+fn is_number(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|x| x.is_ascii_digit())
+}
+
+fn is_position(value: &str) -> bool {
+    match value.split_once('/') {
+        Some((number, total)) => is_number(number) && is_number(total),
+        None => is_number(value),
+    }
+}
+
+// yyyy, yyyy-MM, yyyy-MM-dd, yyyy-MM-ddTHH, yyyy-MM-ddTHH:mm or yyyy-MM-ddTHH:mm:ss
+pub(crate) fn is_timestamp(value: &str) -> bool {
+    let parts: Vec<&str> = value.splitn(2, 'T').collect();
+    let date: Vec<&str> = parts[0].split('-').collect();
+    let time: Vec<&str> = parts.get(1).map(|time| time.split(':').collect()).unwrap_or_default();
+    if parts.len() == 2 && (date.len() != 3 || time.is_empty()) {
+        return false;
+    }
+
+    let field = |text: &str, len: usize, max: u32, min: u32| {
+        text.len() == len && is_number(text) && (min..=max).contains(&text.parse::<u32>().unwrap_or(u32::MAX))
+    };
+    let limits = [(4, 9999, 0), (2, 12, 1), (2, 31, 1)];
+    let time_limits = [(2, 23, 0), (2, 59, 0), (2, 59, 0)];
+    date.len() <= 3
+        && time.len() <= 3
+        && date.iter().zip(limits).all(|(part, (len, max, min))| field(part, len, max, min))
+        && time.iter().zip(time_limits).all(|(part, (len, max, min))| field(part, len, max, min))
+}
+
+// "YYYY " followed by the holder
+pub(crate) fn is_copyright(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() > 5 && bytes[..4].iter().all(u8::is_ascii_digit) && bytes[4] == b' '
+}
+
+// A scheme followed by a colon and no whitespace anywhere
+pub(crate) fn is_url(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+    !scheme.is_empty()
+        && scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        && !rest.is_empty()
+        && !url.chars().any(char::is_whitespace)
+}
+
+fn is_mime(mime: &str) -> bool {
+    // v2.2 style image formats are still seen in the wild
+    if mime == "-->" || mime.eq_ignore_ascii_case("PNG") || mime.eq_ignore_ascii_case("JPG") {
+        return true;
+    }
+    match mime.split_once('/') {
+        Some((kind, subtype)) => {
+            let token = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+            token(kind) && token(subtype)
+        }
+        None => false,
+    }
+}
+
+fn validate_frame(frame: &Frame, violations: &mut Vec<Violation>) {
+    let id = frame.id();
+    if id.starts_with('T')
+        && let Err(error) = frame.try_parse_text()
+    {
+        violations.push(Violation::Undecodable { id, error });
+        return;
+    }
+
+    let values = || text_values(frame).into_iter().filter(|value| !value.is_empty());
+    match id.as_str() {
+        "TRCK" | "TPOS" => {
+            for value in values().filter(|value| !is_position(value.trim())) {
+                violations.push(Violation::InvalidPosition { id: id.clone(), value });
+            }
+        }
+        "TBPM" | "TLEN" | "TDLY" | "TYER" | "TORY" | "TSIZ" => {
+            for value in values().filter(|value| !is_number(value.trim())) {
+                violations.push(Violation::InvalidNumber { id: id.clone(), value });
+            }
+        }
+        "TDRC" | "TDOR" | "TDRL" | "TDEN" | "TDTG" => {
+            for value in values().filter(|value| !is_timestamp(value.trim())) {
+                violations.push(Violation::InvalidTimestamp { id: id.clone(), value });
+            }
+        }
+        "TCOP" | "TPRO" => {
+            for value in values().filter(|value| !is_copyright(value)) {
+                violations.push(Violation::InvalidCopyright { id: id.clone(), value });
+            }
+        }
+        "TLAN" => {
+            for code in values().filter(|code| Language::new(code).is_none()) {
+                violations.push(Violation::InvalidLanguage { id: id.clone(), code });
+            }
+        }
+        "COMM" | "USLT" | "SYLT" | "USER" => {
+            let data = frame.data();
+            if data.len() >= 4 && Language::from_bytes([data[1], data[2], data[3]]).is_none() {
+                let code = data[1..4].iter().map(|x| *x as char).collect();
+                violations.push(Violation::InvalidLanguage { id, code });
+            }
+        }
+        "APIC" => {
+            if let Some(picture) = Picture::from_frame(frame).filter(|picture| !is_mime(picture.mime())) {
+                violations.push(Violation::InvalidMime { mime: picture.mime().to_string() });
+            }
+        }
+        "WXXX" => {
+            if let Some(link) = UserLink::from_frame(frame).filter(|link| !is_url(link.url())) {
+                violations.push(Violation::InvalidUrl { id, url: link.url().to_string() });
+            }
+        }
+        _ if id.starts_with('W') => {
+            let url: String = frame.data().iter().take_while(|x| **x != 0).map(|x| *x as char).collect();
+            if !is_url(&url) {
+                violations.push(Violation::InvalidUrl { id, url });
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Tag {
+    // Checks frame contents against what the spec allows for each frame type
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for frame in self.frames() {
+            validate_frame(frame, &mut violations);
+        }
+        for picture in self.clone().dedup_pictures() {
+            violations.push(Violation::DuplicatePicture { picture_type: picture.picture_type(), description: picture.description().to_string() });
+        }
+        for description in self.duplicate_user_texts() {
+            violations.push(Violation::DuplicateUserText { description });
+        }
+        violations
+    }
+}
+
+// The tag's violations plus any audio missing from the file
+pub fn validate_file(filename: impl AsRef<Path>) -> io::Result<Vec<Violation>> {
+    let filename = filename.as_ref();
+    let mut violations = Tag::from_file(filename).map(|tag| tag.validate()).unwrap_or_default();
+    let analysis = analyze::analyze(filename)?;
+    if let Some(expected_ms) = analysis.expected_ms().filter(|_| analysis.is_truncated()) {
+        violations.push(Violation::TruncatedAudio { expected_ms, actual_ms: analysis.duration_ms, severity: analysis.severity() });
+    }
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_path;
+    use crate::frames::UserText;
+
+    #[test]
+    fn test_file_is_valid() {
+        assert_eq!(Tag::from_file("test/Polygondwanaland.mp3").unwrap().validate(), []);
+        assert_eq!(validate_file("test/Polygondwanaland.mp3").unwrap(), []);
+    }
+
+    #[test]
+    fn truncated_file() {
+        let path = temp_path("validate.mp3");
+        let bytes = std::fs::read("test/Polygondwanaland.mp3").unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 4]).unwrap();
+        let violations = validate_file(&path).unwrap();
+        assert!(matches!(violations[..], [Violation::TruncatedAudio { expected_ms: 213002, severity: Severity::Critical, .. }]));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn timestamps() {
+        for valid in ["2017", "2017-11", "2017-11-17", "2017-11-17T09", "2017-11-17T09:30:05"] {
+            assert!(is_timestamp(valid), "{valid}");
+        }
+        for invalid in ["17", "2017-13", "2017-11-17T", "2017-11T09", "2017-11-17T24:00", "Nov 2017"] {
+            assert!(!is_timestamp(invalid), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn copyright_prefix() {
+        assert!(is_copyright("2017 Flightless Records"));
+        for invalid in ["2017", "2017 ", "(c) 2017 Flightless", "17 Flightless", "2017Flightless"] {
+            assert!(!is_copyright(invalid), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn urls() {
+        assert!(is_url("https://kinggizzardandthelizardwizard.com"));
+        assert!(is_url("mailto:band@example.com"));
+        assert!(!is_url("www.example.com"));
+        assert!(!is_url("https://example.com/a b"));
+    }
+
+    #[test]
+    fn frame_violations() {
+        let mut tag = Tag::new(4);
+        tag.set_text("TRCK", "2/10");
+        tag.set_text("TPOS", "one");
+        tag.set_text("TBPM", "120.5");
+        tag.set_text("TDRC", "2017-02-30T12");
+        tag.set_text("TCOP", "Flightless Records");
+        tag.set_text("TLAN", "english");
+        tag.add_frame(Frame::new("COMM", b"\x00e1g\x00text".to_vec()).unwrap());
+        tag.add_frame(Picture::new("jpeg", PictureType::FrontCover, "", vec![]).to_frame().unwrap());
+        tag.add_frame(Picture::new("image/jpeg", PictureType::FrontCover, "", vec![]).to_frame().unwrap());
+        tag.add_frame(Frame::new("WOAR", b"not a url".to_vec()).unwrap());
+        tag.add_frame(Frame::new("TIT2", vec![0x01, 0x41, 0x00]).unwrap());
+        tag.add_frame(UserText::new("MOOD", "Calm").to_frame().unwrap());
+        tag.add_frame(UserText::new("MOOD", "Restless").to_frame().unwrap());
+
+        let violations = tag.validate();
+        assert_eq!(violations, [
+            Violation::InvalidPosition { id: "TPOS".to_string(), value: "one".to_string() },
+            Violation::InvalidNumber { id: "TBPM".to_string(), value: "120.5".to_string() },
+            Violation::InvalidCopyright { id: "TCOP".to_string(), value: "Flightless Records".to_string() },
+            Violation::InvalidLanguage { id: "TLAN".to_string(), code: "english".to_string() },
+            Violation::InvalidLanguage { id: "COMM".to_string(), code: "e1g".to_string() },
+            Violation::InvalidMime { mime: "jpeg".to_string() },
+            Violation::InvalidUrl { id: "WOAR".to_string(), url: "not a url".to_string() },
+            Violation::Undecodable { id: "TIT2".to_string(), error: TextError::MissingBom },
+            Violation::DuplicatePicture { picture_type: PictureType::FrontCover, description: String::new() },
+            Violation::DuplicateUserText { description: "MOOD".to_string() },
+        ]);
+    }
+}
@@ -0,0 +1,372 @@
+use crate::digest::{crc32, sha1};
+use crate::paths::long_path;
+use crate::report::mp3_files;
+use crate::ID3::resynchronise;
+use crate::{Frame, Tag, WriteOptions, analyze, json, mpeg};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// PRIV owner of the frame holding a SHA-1 of the audio, see Tag::set_audio_hash
+pub const AUDIO_HASH_OWNER: &str = "mp3-tool/audio-sha1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    // The ID3v2 header is well formed and the tag fits in the file
+    Header,
+    // The tag reads without errors and its text decodes
+    Frames,
+    // The extended header CRC and the frame checksums, when the tag has them
+    Crc,
+    // There is audio and it is as long as the tag and Xing header claim
+    Audio,
+    // The audio still matches the hash stored in the tag
+    AudioHash,
+}
+
+impl Check {
+    pub const ALL: [Check; 5] = [Check::Header, Check::Frames, Check::Crc, Check::Audio, Check::AudioHash];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Check::Header => "header",
+            Check::Frames => "frames",
+            Check::Crc => "crc",
+            Check::Audio => "audio",
+            Check::AudioHash => "audio_hash",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    // Nothing to check, like a CRC on a tag without one
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verification {
+    pub path: PathBuf,
+    // One outcome for each of Check::ALL, in that order
+    pub results: Vec<(Check, Outcome)>,
+}
+
+impl Verification {
+    // Skipped checks don't fail a file
+    pub fn passed(&self) -> bool {
+        !self.results.iter().any(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+    }
+
+    pub fn outcome(&self, check: Check) -> &Outcome {
+        &self.results.iter().find(|(x, _)| *x == check).unwrap().1
+    }
+
+    // One JSON object on a single line, for JSON Lines output
+    pub fn to_json(&self) -> String {
+        let checks: Vec<String> = self
+            .results
+            .iter()
+            .map(|(check, outcome)| match outcome {
+                Outcome::Pass => format!("{{\"check\":\"{}\",\"status\":\"pass\"}}", check.name()),
+                Outcome::Skipped => format!("{{\"check\":\"{}\",\"status\":\"skipped\"}}", check.name()),
+                Outcome::Fail(detail) => format!("{{\"check\":\"{}\",\"status\":\"fail\",\"detail\":{}}}", check.name(), json::quote(detail)),
+            })
+            .collect();
+        let path = json::quote(&self.path.to_string_lossy());
+        format!("{{\"path\":{path},\"passed\":{},\"checks\":[{}]}}", self.passed(), checks.join(","))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifyOptions {
+    audio_hash: bool,
+}
+
+impl VerifyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Hash the audio and compare it with the hash stored in the tag, which reads the whole file
+    pub fn audio_hash(mut self, audio_hash: bool) -> Self {
+        self.audio_hash = audio_hash;
+        self
+    }
+}
+
+// The tag at the start of the file as it is on disk, None when there is none
+fn raw_tag(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(long_path(path))?;
+    let mut bytes = Vec::new();
+    (&mut file).take(10).read_to_end(&mut bytes)?;
+    if !bytes.starts_with(b"ID3") {
+        return Ok(None);
+    }
+    (&mut file).take(size(&bytes)).read_to_end(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+// Size from the header, counting the footer
+fn size(header: &[u8]) -> u64 {
+    let size = header.get(6..10).map_or(0, |size| size.iter().fold(0, |size, x| size << 7 | (*x & 0x7F) as u64));
+    size + if header.get(5).is_some_and(|flags| flags & 0x10 != 0) { 10 } else { 0 }
+}
+
+fn check_header(raw: &[u8], file_size: u64) -> Outcome {
+    if raw.len() < 10 {
+        return Outcome::Fail("file ends inside the ID3v2 header".to_string());
+    }
+    let (version, flags) = ((raw[3], raw[4]), raw[5]);
+    let unused = if version.0 == 4 { 0x0F } else { 0x1F };
+    if version != (3, 0) && version != (4, 0) {
+        Outcome::Fail(format!("unsupported version 2.{}.{}", version.0, version.1))
+    } else if flags & unused != 0 {
+        Outcome::Fail(format!("undefined header flags {flags:08b}"))
+    } else if raw[6..10].iter().any(|x| *x >= 0x80) {
+        Outcome::Fail("tag size isn't sync-safe".to_string())
+    } else if 10 + size(raw) > file_size {
+        Outcome::Fail(format!("tag of {} bytes runs past the end of the file", 10 + size(raw)))
+    } else {
+        Outcome::Pass
+    }
+}
+
+fn check_frames(tag: &io::Result<Tag>) -> Outcome {
+    let tag = match tag {
+        Ok(tag) => tag,
+        Err(error) => return Outcome::Fail(error.to_string()),
+    };
+    let text = tag.frames().iter().filter(|frame| frame.id().starts_with('T'));
+    for frame in text {
+        if let Err(error) = frame.try_parse_text() {
+            return Outcome::Fail(format!("{}: {error}", frame.id()));
+        }
+    }
+    Outcome::Pass
+}
+
+// The v2.3 CRC covers the frames, after the extended header and before the padding
+fn extended_header_crc(tag: &Tag, raw: &[u8]) -> Option<Outcome> {
+    let stored = u32::from_be_bytes(tag.extended_header()?.crc()?);
+    if tag.version() != 3 {
+        return None;
+    }
+    let mut body = raw.get(10..)?.to_vec();
+    if tag.header().unsynchronisation() {
+        body = resynchronise(&body);
+    }
+    let start = 4 + u32::from_be_bytes(body.get(..4)?.try_into().ok()?) as usize;
+    let end = body.len().checked_sub(tag.extended_header()?.padding_size() as usize)?;
+    let Some(frames) = body.get(start..end) else {
+        return Some(Outcome::Fail("padding size runs past the start of the frames".to_string()));
+    };
+    match crc32(frames) {
+        crc if crc == stored => Some(Outcome::Pass),
+        crc => Some(Outcome::Fail(format!("CRC is {crc:08x}, the extended header says {stored:08x}"))),
+    }
+}
+
+fn check_crc(tag: &Tag, raw: &[u8]) -> Outcome {
+    let header = extended_header_crc(tag, raw);
+    if let Some(Outcome::Fail(detail)) = header {
+        return Outcome::Fail(detail);
+    }
+    match tag.verify_checksums() {
+        Some(report) if !report.is_intact() => {
+            let frames = [report.corrupt, report.missing].concat();
+            Outcome::Fail(format!("frames don't match their checksums: {}", frames.join(", ")))
+        }
+        Some(_) => Outcome::Pass,
+        None => header.unwrap_or(Outcome::Skipped),
+    }
+}
+
+fn check_audio(path: &Path) -> Outcome {
+    match analyze::analyze(path) {
+        Err(error) => Outcome::Fail(error.to_string()),
+        Ok(analysis) if analysis.audio_frames == 0 => Outcome::Fail("no audio frames".to_string()),
+        Ok(analysis) if analysis.is_truncated() => Outcome::Fail(format!("{} ms of audio missing", analysis.missing_ms())),
+        Ok(_) => Outcome::Pass,
+    }
+}
+
+fn check_audio_hash(path: &Path, tag: &Tag) -> Outcome {
+    let Some(stored) = tag.audio_hash() else {
+        return Outcome::Skipped;
+    };
+    match audio_hash(path) {
+        Ok(hash) if hash == stored => Outcome::Pass,
+        Ok(_) => Outcome::Fail("audio doesn't match the stored hash".to_string()),
+        Err(error) => Outcome::Fail(error.to_string()),
+    }
+}
+
+// Runs every check, a file that can't be opened fails them all
+pub fn verify_file(path: impl AsRef<Path>, options: &VerifyOptions) -> Verification {
+    let path = path.as_ref();
+    let results = match fs::metadata(long_path(path)).and_then(|metadata| Ok((metadata.len(), raw_tag(path)?))) {
+        Err(error) => Check::ALL.iter().map(|check| (*check, Outcome::Fail(error.to_string()))).collect(),
+        Ok((file_size, raw)) => {
+            let tag = raw.as_ref().map(|_| Tag::from_file(path));
+            let parsed = tag.as_ref().and_then(|tag| tag.as_ref().ok()).zip(raw.as_deref());
+            vec![
+                (Check::Header, raw.as_ref().map_or(Outcome::Skipped, |raw| check_header(raw, file_size))),
+                (Check::Frames, tag.as_ref().map_or(Outcome::Skipped, check_frames)),
+                (Check::Crc, parsed.map_or(Outcome::Skipped, |(tag, raw)| check_crc(tag, raw))),
+                (Check::Audio, check_audio(path)),
+                (Check::AudioHash, match parsed.filter(|_| options.audio_hash) {
+                    Some((tag, _)) => check_audio_hash(path, tag),
+                    None => Outcome::Skipped,
+                }),
+            ]
+        }
+    };
+    Verification { path: path.to_path_buf(), results }
+}
+
+pub fn verify_files(files: &[impl AsRef<Path>], options: &VerifyOptions) -> Vec<Verification> {
+    files.iter().map(|file| verify_file(file, options)).collect()
+}
+
+// A file, or every MP3 below a directory
+pub fn verify(path: impl AsRef<Path>, options: &VerifyOptions) -> io::Result<Vec<Verification>> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(verify_files(&[path], options));
+    }
+    let mut files = Vec::new();
+    mp3_files(path, &mut files)?;
+    Ok(verify_files(&files, options))
+}
+
+// SHA-1 of the bytes between the tags, unchanged by rewriting them
+pub fn audio_hash(path: impl AsRef<Path>) -> io::Result<[u8; 20]> {
+    let mut file = File::open(long_path(path))?;
+    let range = mpeg::audio_range(&mut file)?;
+    let mut audio = Vec::new();
+    file.seek(SeekFrom::Start(range.start))?;
+    file.take(range.end - range.start).read_to_end(&mut audio)?;
+    Ok(sha1(&audio))
+}
+
+// Hashes the audio and writes the hash into the file's tag, creating one when there is none
+pub fn store_audio_hash(path: impl AsRef<Path>, options: &WriteOptions) -> io::Result<()> {
+    let path = path.as_ref();
+    let hash = audio_hash(path)?;
+    let mut tag = match Tag::from_file(path) {
+        Err(error) if error.kind() == io::ErrorKind::InvalidData && raw_tag(path)?.is_none() => Tag::new(4),
+        tag => tag?,
+    };
+    tag.set_audio_hash(hash);
+    tag.write_to_file(path, options)?;
+    Ok(())
+}
+
+fn is_audio_hash_frame(frame: &Frame) -> bool {
+    frame.id() == "PRIV" && frame.data().strip_prefix(AUDIO_HASH_OWNER.as_bytes()).is_some_and(|rest| rest.first() == Some(&0))
+}
+
+impl Tag {
+    pub fn audio_hash(&self) -> Option<[u8; 20]> {
+        let frame = self.frames().iter().find(|frame| is_audio_hash_frame(frame))?;
+        frame.data()[AUDIO_HASH_OWNER.len() + 1..].try_into().ok()
+    }
+
+    // Replaces any hash stored before
+    pub fn set_audio_hash(&mut self, hash: [u8; 20]) {
+        self.frames_mut().retain(|frame| !is_audio_hash_frame(frame));
+        let data = [AUDIO_HASH_OWNER.as_bytes(), &[0], &hash].concat();
+        self.add_frame(Frame::new("PRIV", data).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{copy_of_test_file, write_temp};
+
+
+    // The fixture's audio behind a v2.3 tag with an extended header holding the given CRC
+    fn with_crc(name: &str, crc: Option<u32>) -> PathBuf {
+        let fixture = fs::read("test/Polygondwanaland.mp3").unwrap();
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let frames = tag.to_bytes(0)[10..].to_vec();
+        let padding = [0u8; 16];
+        let crc = crc.unwrap_or(crc32(&frames));
+        let extended = [&[0, 0, 0, 10, 0x80, 0][..], &(padding.len() as u32).to_be_bytes(), &crc.to_be_bytes()].concat();
+        let body = [extended, frames, padding.to_vec()].concat();
+        let mut bytes = b"ID3\x03\x00\x40".to_vec();
+        bytes.extend((0..4).rev().map(|i| ((body.len() >> (7 * i)) & 0x7F) as u8));
+        bytes.extend(body);
+        bytes.extend_from_slice(&fixture[10 + size(&fixture) as usize..]);
+        write_temp(name, &bytes)
+    }
+
+    #[test]
+    fn fixture_passes() {
+        let verification = verify_file("test/Polygondwanaland.mp3", &VerifyOptions::new().audio_hash(true));
+        assert!(verification.passed());
+        let outcomes: Vec<&Outcome> = verification.results.iter().map(|(_, outcome)| outcome).collect();
+        assert_eq!(outcomes, [&Outcome::Pass, &Outcome::Pass, &Outcome::Skipped, &Outcome::Pass, &Outcome::Skipped]);
+        assert!(verification.to_json().starts_with("{\"path\":\"test/Polygondwanaland.mp3\",\"passed\":true,\"checks\":[{\"check\":\"header\",\"status\":\"pass\"}"));
+    }
+
+    #[test]
+    fn extended_header_crc() {
+        let good = with_crc("crc", None);
+        let verification = verify_file(&good, &VerifyOptions::new());
+        assert_eq!((verification.outcome(Check::Crc), verification.passed()), (&Outcome::Pass, true));
+
+        let bad = with_crc("bad-crc", Some(0xDEADBEEF));
+        let verification = verify_file(&bad, &VerifyOptions::new());
+        assert!(!verification.passed());
+        let Outcome::Fail(detail) = verification.outcome(Check::Crc) else { panic!() };
+        assert!(detail.ends_with("the extended header says deadbeef"));
+        assert!(verification.to_json().contains(&format!("{{\"check\":\"crc\",\"status\":\"fail\",\"detail\":\"{detail}\"}}")));
+        fs::remove_file(good).unwrap();
+        fs::remove_file(bad).unwrap();
+    }
+
+    #[test]
+    fn stored_audio_hash() {
+        let path = copy_of_test_file("hash");
+        store_audio_hash(&path, &WriteOptions::new()).unwrap();
+        let options = VerifyOptions::new().audio_hash(true);
+        assert_eq!(verify_file(&path, &options).outcome(Check::AudioHash), &Outcome::Pass);
+        assert_eq!(verify_file(&path, &VerifyOptions::new()).outcome(Check::AudioHash), &Outcome::Skipped);
+
+        // Rewriting the tag keeps the hash valid, changing the audio doesn't
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.write_to_file(&path, &WriteOptions::new()).unwrap();
+        assert!(verify_file(&path, &options).passed());
+        let mut bytes = fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        let verification = verify_file(&path, &options);
+        assert_eq!(verification.outcome(Check::AudioHash), &Outcome::Fail("audio doesn't match the stored hash".to_string()));
+        assert_eq!(verification.outcome(Check::Audio), &Outcome::Pass);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn broken_files() {
+        let version = write_temp("version", b"ID3\x05\x00\x00\x00\x00\x00\x00");
+        let verification = verify_file(&version, &VerifyOptions::new());
+        assert_eq!(verification.outcome(Check::Header), &Outcome::Fail("unsupported version 2.5.0".to_string()));
+        assert!(matches!(verification.outcome(Check::Frames), Outcome::Fail(_)));
+        assert_eq!(verification.outcome(Check::Audio), &Outcome::Fail("no audio frames".to_string()));
+
+        let short = write_temp("short", b"ID3\x03\x00\x00\x00\x00\x10\x00");
+        let outcome = verify_file(&short, &VerifyOptions::new()).outcome(Check::Header).clone();
+        assert_eq!(outcome, Outcome::Fail("tag of 2058 bytes runs past the end of the file".to_string()));
+
+        let missing = verify_file(std::env::temp_dir().join("mp3-tool-verify-missing.mp3"), &VerifyOptions::new());
+        assert!(missing.results.iter().all(|(_, outcome)| matches!(outcome, Outcome::Fail(_))));
+        fs::remove_file(version).unwrap();
+        fs::remove_file(short).unwrap();
+    }
+}
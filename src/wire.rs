@@ -0,0 +1,116 @@
+// Frames as each version of the spec lays them out. Frame holds what a frame means whatever
+// version it came from, these adapters turn it into one version's bytes and back
+mod v22;
+mod v23;
+mod v24;
+
+use crate::Frame;
+
+// Bytes in a frame header
+pub(crate) fn header_len(major_ver: u8) -> usize {
+    match major_ver {
+        2 => v22::HEADER_LEN,
+        _ => 10,
+    }
+}
+
+// Size of the body following the header, as the header states it
+pub(crate) fn body_size(header: &[u8], major_ver: u8) -> u32 {
+    match major_ver {
+        2 => v22::body_size(header),
+        4 => v24::body_size(header),
+        _ => v23::body_size(header),
+    }
+}
+
+// Frame ids are made of capital letters and digits only
+pub(crate) fn valid_id(id: &[u8]) -> bool {
+    id.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit())
+}
+
+// The four character id the header stands for, None when it has none or it isn't a valid id
+pub(crate) fn id(header: &[u8], major_ver: u8) -> Option<[u8; 4]> {
+    match major_ver {
+        2 => v22::id(header),
+        _ => header.get(..4)?.try_into().ok().filter(|id: &[u8; 4]| valid_id(id)),
+    }
+}
+
+// The v2.2 id of a v2.3 or v2.4 id, also what v2.3 LINK frames name frames by
+pub(crate) fn short_id(id: &str) -> Option<&'static str> {
+    v22::short_id(id)
+}
+
+// The v2.3 id of a v2.2 id
+pub(crate) fn long_id(id: &str) -> Option<String> {
+    v22::id(id.as_bytes()).map(|id| String::from_utf8_lossy(&id).into_owned())
+}
+
+// Whether the version has an id for the frame at all
+pub(crate) fn has_id(id: &str, major_ver: u8) -> bool {
+    major_ver != 2 || v22::short_id(id).is_some()
+}
+
+// A frame from its header and the body that followed it
+pub(crate) fn decode(header: &[u8], data: Vec<u8>, major_ver: u8) -> Option<Frame> {
+    let id = id(header, major_ver)?;
+    Some(match major_ver {
+        2 => v22::decode(id, data),
+        4 => v24::decode(id, header, data),
+        _ => v23::decode(id, header, data),
+    })
+}
+
+// Header and body. Empty for a frame the version has no id for
+pub(crate) fn encode(frame: &Frame, major_ver: u8) -> Vec<u8> {
+    match major_ver {
+        2 => v22::encode(frame),
+        4 => v24::encode(frame),
+        _ => v23::encode(frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{Diagnostics, Finding};
+    use crate::{FrameFlags, ReadOptions, Reader, Tag};
+    use std::io::Cursor;
+
+    #[test]
+    fn flags_move_between_versions() {
+        // Read only, compressed with a decompressed size of 64, encryption method 2 and group 9
+        let v23 = [b"PRIV".as_slice(), &[0, 0, 0, 9, 0b_00100000, 0b_11100000, 0, 0, 0, 64, 2, 9, 0x78, 0x9C, 0x03]].concat();
+        let frame = decode(&v23[..10], v23[10..].to_vec(), 3).unwrap();
+        assert_eq!(frame.flags(), FrameFlags { read_only: true, compressed: true, ..Default::default() });
+        assert_eq!((frame.data_length(), frame.encryption(), frame.group(), frame.data()), (Some(64), Some(2), Some(9), &[0x78, 0x9C, 0x03][..]));
+        assert_eq!(encode(&frame, 3), v23);
+
+        // v2.4 puts the group first and the length last, with every flag in another place
+        let v24 = [b"PRIV".as_slice(), &[0, 0, 0, 9, 0b_00010000, 0b_01001101, 9, 2, 0, 0, 0, 64, 0x78, 0x9C, 0x03]].concat();
+        assert_eq!(encode(&frame, 4), v24);
+        assert!(decode(&v24[..10], v24[10..].to_vec(), 4).unwrap() == frame);
+    }
+
+    #[test]
+    fn reads_v22_tags() {
+        let picture = b"PIC\x00\x00\x0A\x00PNG\x03\x00\x89PNG";
+        let mut body = [b"TT2\x00\x00\x06\x00Title".as_slice(), picture, b"CRM\x00\x00\x02\x00\x00", b"TAL\x00\x00\x06\x00Album"].concat();
+        body.extend([0; 8]);
+        let bytes = [b"ID3\x02\x00\x00\x00\x00\x00".as_slice(), &[body.len() as u8], &body].concat();
+
+        let diagnostics = Diagnostics::new();
+        let options = ReadOptions::new().diagnostics(diagnostics.clone());
+        let tag = Tag::from_reader_with(&mut Reader::from_stream(Cursor::new(bytes.clone())), &options).unwrap();
+        assert_eq!((tag.version(), tag.title().as_deref(), tag.album().as_deref()), (2, Some("Title"), Some("Album")));
+        assert_eq!(tag.padding(), 8);
+        assert!(diagnostics.findings().contains(&Finding::SkippedFrame { id: "CRM".into(), reason: "no ID3v2.3 equivalent".into() }));
+        assert_eq!(tag.pictures()[0].mime(), "image/png");
+        assert_eq!(tag.frame("APIC").unwrap().data(), b"\x00image/png\x00\x03\x00\x89PNG");
+
+        let written = tag.to_bytes(8);
+        assert_eq!(written[10..], [&body[..28], &body[36..]].concat());
+        assert_eq!(tag.frame("TIT2").unwrap().to_bytes(3)[..8], *b"TIT2\x00\x00\x00\x06");
+        assert!(Tag::from_reader(&mut Reader::from_stream(Cursor::new(b"ID3\x02\x00\x40\x00\x00\x00\x00".to_vec()))).is_err());
+    }
+}
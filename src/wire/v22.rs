@@ -0,0 +1,82 @@
+// https://id3.org/id3v2-00: three character ids, three byte sizes and no flags
+use crate::frames::{format_mime, image_format};
+use crate::ID3::split_terminated;
+use crate::Frame;
+
+pub(super) const HEADER_LEN: usize = 6;
+
+// Frames whose v2.3 counterpart holds the same data. PIC is read and written as APIC below, LNK
+// differs and CRM has none
+const IDS: [(&str, &str); 61] = [
+    ("BUF", "RBUF"), ("CNT", "PCNT"), ("COM", "COMM"), ("CRA", "AENC"), ("ETC", "ETCO"), ("EQU", "EQUA"),
+    ("GEO", "GEOB"), ("IPL", "IPLS"), ("MCI", "MCDI"), ("MLL", "MLLT"), ("POP", "POPM"), ("REV", "RVRB"),
+    ("RVA", "RVAD"), ("SLT", "SYLT"), ("STC", "SYTC"), ("TAL", "TALB"), ("TBP", "TBPM"), ("TCM", "TCOM"),
+    ("TCO", "TCON"), ("TCR", "TCOP"), ("TDA", "TDAT"), ("TDY", "TDLY"), ("TEN", "TENC"), ("TFT", "TFLT"),
+    ("TIM", "TIME"), ("TKE", "TKEY"), ("TLA", "TLAN"), ("TLE", "TLEN"), ("TMT", "TMED"), ("TOA", "TOPE"),
+    ("TOF", "TOFN"), ("TOL", "TOLY"), ("TOR", "TORY"), ("TOT", "TOAL"), ("TP1", "TPE1"), ("TP2", "TPE2"),
+    ("TP3", "TPE3"), ("TP4", "TPE4"), ("TPA", "TPOS"), ("TPB", "TPUB"), ("TRC", "TSRC"), ("TRD", "TRDA"),
+    ("TRK", "TRCK"), ("TSI", "TSIZ"), ("TSS", "TSSE"), ("TT1", "TIT1"), ("TT2", "TIT2"), ("TT3", "TIT3"),
+    ("TXT", "TEXT"), ("TXX", "TXXX"), ("TYE", "TYER"), ("UFI", "UFID"), ("ULT", "USLT"), ("WAF", "WOAF"),
+    ("WAR", "WOAR"), ("WAS", "WOAS"), ("WCM", "WCOM"), ("WCP", "WCOP"), ("WPB", "WPUB"), ("WXX", "WXXX"),
+    ("TCP", "TCMP"),
+];
+
+// The v2.2 id of a v2.3 id
+pub(super) fn short_id(id: &str) -> Option<&'static str> {
+    match id {
+        "APIC" => Some("PIC"),
+        _ => IDS.iter().find(|(_, long)| *long == id).map(|(short, _)| *short),
+    }
+}
+
+pub(super) fn body_size(header: &[u8]) -> u32 {
+    header[3..6].iter().fold(0, |size, x| size << 8 | *x as u32)
+}
+
+pub(super) fn id(header: &[u8]) -> Option<[u8; 4]> {
+    let short = header.get(..3)?;
+    if short == b"PIC" {
+        return Some(*b"APIC");
+    }
+    let (_, id) = IDS.iter().find(|(x, _)| x.as_bytes() == short)?;
+    id.as_bytes().try_into().ok()
+}
+
+// PIC has an encoding, a three character image format, the picture type, a description and the
+// image. APIC has a terminated MIME type in place of the format
+fn picture_from_v22(data: Vec<u8>) -> Vec<u8> {
+    let Some(format) = data.get(1..4) else {
+        return data;
+    };
+    let mime = format_mime(&String::from_utf8_lossy(format));
+    [&data[..1], mime.as_bytes(), &[0], &data[4..]].concat()
+}
+
+// None when the MIME type has no v2.2 format
+fn picture_to_v22(data: &[u8]) -> Option<Vec<u8>> {
+    let (encoding, rest) = data.split_first()?;
+    let (mime, rest) = split_terminated(0, rest);
+    let format = image_format(&String::from_utf8_lossy(mime))?;
+    Some([&[*encoding], format.as_bytes(), rest].concat())
+}
+
+pub(super) fn decode(id: [u8; 4], data: Vec<u8>) -> Frame {
+    let data = if &id == b"APIC" { picture_from_v22(data) } else { data };
+    Frame::from_parts(id, Default::default(), data)
+}
+
+// Flags, groups and data lengths have no place in v2.2 and are left out
+pub(super) fn encode(frame: &Frame) -> Vec<u8> {
+    let Some(short) = short_id(&frame.id()) else {
+        return Vec::new();
+    };
+    let data = match short {
+        "PIC" => match picture_to_v22(frame.data()) {
+            Some(data) => data,
+            None => return Vec::new(),
+        },
+        _ => frame.data().to_vec(),
+    };
+    let size = (data.len() as u32).to_be_bytes();
+    [short.as_bytes(), &size[1..], &data].concat()
+}
@@ -0,0 +1,65 @@
+// https://id3.org/id3v2.3.0#Frame_header_flags
+use crate::{Frame, FrameFlags};
+
+const TAG_ALTER: u8 = 0b_10000000;
+const FILE_ALTER: u8 = 0b_01000000;
+const READ_ONLY: u8 = 0b_00100000;
+const COMPRESSION: u8 = 0b_10000000;
+const ENCRYPTION: u8 = 0b_01000000;
+const GROUPING: u8 = 0b_00100000;
+
+pub(super) fn body_size(header: &[u8]) -> u32 {
+    u32::from_be_bytes(header[4..8].try_into().unwrap())
+}
+
+// Takes n bytes off the front of the data when the flag says they are there
+fn take(data: &mut Vec<u8>, present: bool, n: usize) -> Option<Vec<u8>> {
+    (present && data.len() >= n).then(|| data.drain(..n).collect())
+}
+
+// The decompressed size, encryption method and group come before the data in that order
+pub(super) fn decode(id: [u8; 4], header: &[u8], mut data: Vec<u8>) -> Frame {
+    let (status, format) = (header[8], header[9]);
+    let flags = FrameFlags {
+        discard_on_tag_change: status & TAG_ALTER != 0,
+        discard_on_file_change: status & FILE_ALTER != 0,
+        read_only: status & READ_ONLY != 0,
+        compressed: format & COMPRESSION != 0,
+    };
+    let data_length = take(&mut data, flags.compressed, 4).map(|size| u32::from_be_bytes(size.try_into().unwrap()));
+    let encryption = take(&mut data, format & ENCRYPTION != 0, 1).map(|method| method[0]);
+    let group = take(&mut data, format & GROUPING != 0, 1).map(|group| group[0]);
+    let mut frame = Frame::from_parts(id, flags, data);
+    frame.set_layout(encryption, group, data_length);
+    frame
+}
+
+pub(super) fn encode(frame: &Frame) -> Vec<u8> {
+    let flags = frame.flags();
+    let mut status = 0;
+    let mut format = 0;
+    let mut data = Vec::new();
+    for (set, bit) in [(flags.discard_on_tag_change, TAG_ALTER), (flags.discard_on_file_change, FILE_ALTER), (flags.read_only, READ_ONLY)] {
+        status |= if set { bit } else { 0 };
+    }
+    if flags.compressed {
+        format |= COMPRESSION;
+        data.extend(frame.data_length().unwrap_or(frame.data().len() as u32).to_be_bytes());
+    }
+    if let Some(method) = frame.encryption() {
+        format |= ENCRYPTION;
+        data.push(method);
+    }
+    if let Some(group) = frame.group() {
+        format |= GROUPING;
+        data.push(group);
+    }
+    data.extend_from_slice(frame.data());
+
+    let mut bytes = Vec::with_capacity(10 + data.len());
+    bytes.extend_from_slice(frame.id().as_bytes());
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&[status, format]);
+    bytes.extend_from_slice(&data);
+    bytes
+}
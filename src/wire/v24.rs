@@ -0,0 +1,78 @@
+// https://id3.org/id3v2.4.0-structure section 4.1
+use crate::ID3::{resynchronise, sync_safe_from_u32, u32_from_sync_safe};
+use crate::{Frame, FrameFlags};
+
+const TAG_ALTER: u8 = 0b_01000000;
+const FILE_ALTER: u8 = 0b_00100000;
+const READ_ONLY: u8 = 0b_00010000;
+const GROUPING: u8 = 0b_01000000;
+const COMPRESSION: u8 = 0b_00001000;
+const ENCRYPTION: u8 = 0b_00000100;
+const UNSYNCHRONISATION: u8 = 0b_00000010;
+const DATA_LENGTH_INDICATOR: u8 = 0b_00000001;
+
+pub(super) fn body_size(header: &[u8]) -> u32 {
+    u32_from_sync_safe(&header[4..8])
+}
+
+fn take(data: &mut Vec<u8>, present: bool, n: usize) -> Option<Vec<u8>> {
+    (present && data.len() >= n).then(|| data.drain(..n).collect())
+}
+
+// The group, encryption method and data length indicator come before the data in that order.
+// Unsynchronisation is undone and not written again
+pub(super) fn decode(id: [u8; 4], header: &[u8], mut data: Vec<u8>) -> Frame {
+    let (status, format) = (header[8], header[9]);
+    if format & UNSYNCHRONISATION != 0 {
+        data = resynchronise(&data);
+    }
+    let flags = FrameFlags {
+        discard_on_tag_change: status & TAG_ALTER != 0,
+        discard_on_file_change: status & FILE_ALTER != 0,
+        read_only: status & READ_ONLY != 0,
+        compressed: format & COMPRESSION != 0,
+    };
+    let group = take(&mut data, format & GROUPING != 0, 1).map(|group| group[0]);
+    let encryption = take(&mut data, format & ENCRYPTION != 0, 1).map(|method| method[0]);
+    let data_length = take(&mut data, format & DATA_LENGTH_INDICATOR != 0, 4).map(|length| u32_from_sync_safe(&length));
+    let mut frame = Frame::from_parts(id, flags, data);
+    frame.set_layout(encryption, group, data_length);
+    frame
+}
+
+// Compressed and encrypted frames need the indicator since their data can't be measured, and
+// keep the length they were read with. Other frames get it when they had it, measured again
+pub(super) fn encode(frame: &Frame) -> Vec<u8> {
+    let flags = frame.flags();
+    let mut status = 0;
+    let mut format = 0;
+    let mut data = Vec::new();
+    for (set, bit) in [(flags.discard_on_tag_change, TAG_ALTER), (flags.discard_on_file_change, FILE_ALTER), (flags.read_only, READ_ONLY)] {
+        status |= if set { bit } else { 0 };
+    }
+    if let Some(group) = frame.group() {
+        format |= GROUPING;
+        data.push(group);
+    }
+    if flags.compressed {
+        format |= COMPRESSION;
+    }
+    if let Some(method) = frame.encryption() {
+        format |= ENCRYPTION;
+        data.push(method);
+    }
+    let opaque = flags.compressed || frame.encryption().is_some();
+    if opaque || frame.data_length().is_some() {
+        format |= DATA_LENGTH_INDICATOR;
+        let length = frame.data_length().filter(|_| opaque).unwrap_or(frame.data().len() as u32);
+        data.extend(sync_safe_from_u32(length));
+    }
+    data.extend_from_slice(frame.data());
+
+    let mut bytes = Vec::with_capacity(10 + data.len());
+    bytes.extend_from_slice(frame.id().as_bytes());
+    bytes.extend_from_slice(&sync_safe_from_u32(data.len() as u32));
+    bytes.extend_from_slice(&[status, format]);
+    bytes.extend_from_slice(&data);
+    bytes
+}
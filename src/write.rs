@@ -0,0 +1,900 @@
+use crate::convert::{CompatibilityReport, text_values};
+use crate::budget::{BudgetReport, SizeBudget};
+use crate::convert::Change;
+use crate::diagnostics::{Diagnostics, Finding};
+use crate::id3v1::{self, Id3v1};
+use crate::journal::{self, Journal};
+use crate::order::FrameOrder;
+use crate::ID3::{FrameHook, read_terminated, utf16_bytes};
+use crate::paths::long_path;
+use crate::sidecar;
+use crate::{Frame, Header, Tag, Timestamp};
+use std::fs::{self, File, FileTimes, Metadata};
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind};
+use std::io::prelude::*;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// How UTF-16 text is laid out, every string gets its own BOM when BOMs are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Utf16Policy {
+    big_endian: bool,
+    bom: bool,
+}
+
+impl Utf16Policy {
+    pub fn new() -> Self {
+        Self {
+            big_endian: false,
+            bom: true,
+        }
+    }
+
+    // v2.4 writes big endian text as encoding 2 which never has a BOM
+    pub fn big_endian(mut self, big_endian: bool) -> Self {
+        self.big_endian = big_endian;
+        self
+    }
+
+    // Leaving out the BOM breaks the spec but some players need it
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    fn encoding(&self, major_ver: u8) -> u8 {
+        if self.big_endian && major_ver == 4 { 2 } else { 1 }
+    }
+
+    fn bytes(&self, text: &str, major_ver: u8) -> Vec<u8> {
+        utf16_bytes(text, self.big_endian, self.bom && self.encoding(major_ver) == 1)
+    }
+
+    // Re-encode a UTF-16 frame, None when the frame isn't UTF-16, has no known layout or is unchanged
+    fn apply(&self, frame: &Frame, major_ver: u8) -> Option<Frame> {
+        let encoding = *frame.data().first()?;
+        if encoding != 1 && encoding != 2 {
+            return None;
+        }
+        let data = reencoded(frame, self.encoding(major_ver), |text| self.bytes(text, major_ver))?;
+        if data == frame.data() {
+            return None;
+        }
+        let mut reencoded = Frame::new(&frame.id(), data)?;
+        reencoded.set_group(frame.group());
+        Some(reencoded)
+    }
+}
+
+fn terminator(encoding: u8) -> &'static [u8] {
+    if encoding == 1 || encoding == 2 { &[0, 0] } else { &[0] }
+}
+
+// The frame's data with its strings written by encode and the encoding byte set to
+// new_encoding. None for frames without text whose layout is known
+pub(crate) fn reencoded(frame: &Frame, new_encoding: u8, mut encode: impl FnMut(&str) -> Vec<u8>) -> Option<Vec<u8>> {
+    let (&encoding, rest) = frame.data().split_first()?;
+    let id = frame.id();
+    let (prefix, strings, tail) = match id.as_str() {
+        "COMM" | "USLT" if rest.len() >= 3 => {
+            let (description, text) = read_terminated(encoding, &rest[3..]);
+            let (text, _) = read_terminated(encoding, text);
+            (&rest[..3], vec![description, text], &[][..])
+        }
+        "APIC" => {
+            // MIME type and picture type sit between the encoding and the description
+            let (_, after) = read_terminated(0, rest);
+            let (_, after) = after.split_first()?;
+            let (description, data) = read_terminated(encoding, after);
+            (&rest[..rest.len() - after.len()], vec![description], data)
+        }
+        _ if id.starts_with('T') => (&[][..], text_values(frame), &[][..]),
+        _ => return None,
+    };
+
+    // Keep a terminator after the last string if the frame had one
+    let terminated = tail.is_empty() && rest.ends_with(terminator(encoding));
+    let mut data = vec![new_encoding];
+    data.extend_from_slice(prefix);
+    for (i, string) in strings.iter().enumerate() {
+        data.extend(encode(string));
+        if i + 1 < strings.len() || !tail.is_empty() || terminated {
+            data.extend_from_slice(terminator(new_encoding));
+        }
+    }
+    data.extend_from_slice(tail);
+    Some(data)
+}
+
+impl Default for Utf16Policy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Hidden file next to the original named after all of it, so song.mp3 and song.flac in the same
+// directory never share one
+fn temp_file(filename: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(filename.file_name().unwrap_or_default());
+    name.push(".mp3-tool.tmp");
+    filename.with_file_name(name)
+}
+
+// Shared flag to stop a write, checked between chunks
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Called with (bytes written, total bytes) while the file is rewritten
+pub type WriteProgress = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+pub struct WriteOptions {
+    version: Option<u8>,
+    padding: usize,
+    preserve: bool,
+    hooks: Vec<FrameHook>,
+    utf16: Option<Utf16Policy>,
+    progress: Option<WriteProgress>,
+    cancel: Option<CancelToken>,
+    preserve_mtime: bool,
+    preserve_permissions: bool,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    preserve_ownership: bool,
+    write_id3v1: bool,
+    remove_id3v1: bool,
+    diagnostics: Option<Diagnostics>,
+    journal: Option<Journal>,
+    order: FrameOrder,
+    stamp_tagging_time: bool,
+    checksums: bool,
+    budget: Option<SizeBudget>,
+    sidecar: bool,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self {
+            version: None,
+            padding: 1024,
+            preserve: false,
+            hooks: Vec::new(),
+            utf16: None,
+            progress: None,
+            cancel: None,
+            preserve_mtime: false,
+            preserve_permissions: true,
+            preserve_ownership: false,
+            write_id3v1: false,
+            remove_id3v1: false,
+            diagnostics: None,
+            journal: None,
+            order: FrameOrder::Keep,
+            stamp_tagging_time: false,
+            checksums: false,
+            budget: None,
+            sidecar: false,
+        }
+    }
+
+    // Version to write, defaults to the version the tag was read as
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    // Write untouched frames byte for byte as they were read, only when the version is unchanged
+    pub fn preserve(mut self, preserve: bool) -> Self {
+        self.preserve = preserve;
+        self
+    }
+
+    // Hooks see frames in the tag's own version, before any conversion
+    pub fn before_frame_written(mut self, hook: impl Fn(&Frame) -> Option<Frame> + Send + Sync + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    // Rewrite all UTF-16 text with the policy, by default UTF-16 frames are written as they are
+    pub fn utf16(mut self, policy: Utf16Policy) -> Self {
+        self.utf16 = Some(policy);
+        self
+    }
+
+    pub fn on_progress(mut self, callback: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    // A cancelled write leaves the original file untouched and fails with ErrorKind::Interrupted
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    // Keep the original modification and access times so the file doesn't look changed. TagCache and
+    // the SQLite export look at the change time too, so on Unix they still see the edit
+    pub fn preserve_mtime(mut self, preserve: bool) -> Self {
+        self.preserve_mtime = preserve;
+        self
+    }
+
+    // On by default, the rewritten file otherwise gets the default permissions for new files
+    pub fn preserve_permissions(mut self, preserve: bool) -> Self {
+        self.preserve_permissions = preserve;
+        self
+    }
+
+    // Unix only, changing the owner usually needs elevated privileges
+    #[cfg(unix)]
+    pub fn preserve_ownership(mut self, preserve: bool) -> Self {
+        self.preserve_ownership = preserve;
+        self
+    }
+
+    // Keep an ID3v1.1 tag at the end of the file in step with the v2 tag, replacing any old one
+    pub fn write_id3v1(mut self, write: bool) -> Self {
+        self.write_id3v1 = write;
+        self
+    }
+
+    // Remove an existing ID3v1 tag, by default it is left alone unless write_id3v1 replaces it
+    pub fn remove_id3v1(mut self, remove: bool) -> Self {
+        self.remove_id3v1 = remove;
+        self
+    }
+
+    // Collects frames lost in conversion and other findings that don't fail the write
+    pub fn diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    // Some players only read the first few KB of a tag, ordering puts what they need there
+    pub fn frame_order(mut self, order: FrameOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    // Record every field the write changes, see journal::history to read it back
+    pub fn journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    // Set TDTG to the current UTC time on every v2.4 tag written. Writes skipped because
+    // nothing changed don't count, and the stamp isn't recorded in the journal
+    pub fn stamp_tagging_time(mut self, stamp: bool) -> Self {
+        self.stamp_tagging_time = stamp;
+        self
+    }
+
+    // Store a CRC of every frame in a PRIV frame so a damaged frame can be found,
+    // see ReadOptions::verify_checksums and Tag::verify_checksums
+    pub fn frame_checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    // Shrink tags over the budget before writing them, padding is cut to fit as well. Trimmed
+    // frames are reported to the diagnostics and a tag that still doesn't fit fails the write
+    pub fn size_budget(mut self, budget: SizeBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    // Write the tag to a sidecar next to the file and leave the file alone, see sidecar::merge
+    // to move it into the file later. ID3v1 options don't apply to sidecars
+    pub fn sidecar(mut self, sidecar: bool) -> Self {
+        self.sidecar = sidecar;
+        self
+    }
+
+    pub(crate) fn writes_sidecar(&self) -> bool {
+        self.sidecar
+    }
+
+    fn restore_metadata(&self, file: &File, metadata: &Metadata) -> io::Result<()> {
+        if self.preserve_permissions {
+            file.set_permissions(metadata.permissions())?;
+        }
+        if self.preserve_mtime {
+            file.set_times(FileTimes::new().set_accessed(metadata.accessed()?).set_modified(metadata.modified()?))?;
+        }
+        #[cfg(unix)]
+        if self.preserve_ownership {
+            use std::os::unix::fs::MetadataExt;
+            std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()))?;
+        }
+        Ok(())
+    }
+
+    // Whether writing the edited tag would leave the original's frames as they are, so the write can be skipped
+    pub(crate) fn is_no_op(&self, original: &Tag, edited: &Tag) -> bool {
+        self.version.is_none_or(|version| version == original.version())
+            && !self.write_id3v1
+            && !self.remove_id3v1
+            && self.prepare(edited.with_hooks(&self.hooks)).fingerprint() == original.fingerprint()
+    }
+
+    // Changes made to every tag on its way to the file
+    fn prepare(&self, tag: Tag) -> Tag {
+        self.prepare_reporting(tag).0
+    }
+
+    fn prepare_reporting(&self, mut tag: Tag) -> (Tag, Option<BudgetReport>) {
+        self.apply_utf16(&mut tag);
+        self.order.sort(tag.frames_mut());
+        let report = self.budget.as_ref().map(|budget| tag.fit_to_budget(budget));
+        if self.checksums {
+            tag.add_checksums();
+        }
+        (tag, report)
+    }
+
+    fn apply_utf16(&self, tag: &mut Tag) {
+        let Some(policy) = &self.utf16 else {
+            return;
+        };
+        let major_ver = tag.version();
+        for frame in tag.frames_mut() {
+            if let Some(reencoded) = policy.apply(frame, major_ver) {
+                *frame = reencoded;
+            }
+        }
+    }
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Offsets of every ID3v2 tag at the start of the file, broken tools sometimes stack several
+pub(crate) fn tag_offsets(file: &mut File) -> io::Result<Vec<(u64, Header)>> {
+    let length = file.metadata()?.len();
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    while offset + 10 <= length {
+        let mut bytes = [0u8; 10];
+        file.seek(io::SeekFrom::Start(offset))?;
+        file.read_exact(&mut bytes)?;
+        let Some(header) = Header::from_bytes(&bytes) else {
+            break;
+        };
+        let size = header.tag_size();
+        offsets.push((offset, header));
+        offset += size;
+    }
+    file.seek(io::SeekFrom::Start(0))?;
+    Ok(offsets)
+}
+
+// Number of bytes the existing tags take up at the start of the file, stacked tags are all replaced
+pub(crate) fn existing_tag_size(file: &mut File) -> io::Result<u64> {
+    Ok(tag_offsets(file)?.last().map(|(offset, header)| offset + header.tag_size()).unwrap_or(0))
+}
+
+// Fills as much of buffer as input has left, a short count only at the end of input
+fn read_up_to(input: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(filled)
+}
+
+// Copies input to output with new_tag in place of the tags at its start, for input that can't
+// be seeked like an upload. Old tags are read past without being kept and the audio goes through
+// a fixed size buffer, so memory use doesn't grow with the file. The tag is written as it is,
+// with its own padding. Returns the number of bytes written
+pub fn retag_stream(mut input: impl Read, mut output: impl Write, new_tag: &Tag) -> io::Result<u64> {
+    let mut header = [0u8; 10];
+    let mut read = read_up_to(&mut input, &mut header)?;
+    while let Some(existing) = Header::from_bytes(&header[..read]) {
+        let skip = existing.tag_size() - 10;
+        if io::copy(&mut (&mut input).take(skip), &mut io::sink())? < skip {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Input ends inside its ID3v2 tag"));
+        }
+        read = read_up_to(&mut input, &mut header)?;
+    }
+
+    let loaded;
+    let new_tag = if new_tag.lazy_frames().is_empty() {
+        new_tag
+    } else {
+        let mut tag = new_tag.clone();
+        tag.load_lazy_frames()?;
+        loaded = tag;
+        &loaded
+    };
+    let bytes = new_tag.to_bytes(new_tag.padding() as usize);
+    output.write_all(&bytes)?;
+    output.write_all(&header[..read])?;
+    let audio = io::copy(&mut input, &mut output)?;
+    output.flush()?;
+    Ok(bytes.len() as u64 + read as u64 + audio)
+}
+
+impl Tag {
+    pub fn write_to_file(&self, filename: impl AsRef<Path>, options: &WriteOptions) -> io::Result<CompatibilityReport> {
+        let filename = filename.as_ref();
+        let target = options.version.unwrap_or(self.version());
+        if target != 3 && target != 4 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Only ID3v2.3 and ID3v2.4 can be written"));
+        }
+
+        // Lazy frames are read back in while their file is still there to read them from
+        let loaded;
+        let this = if self.lazy_frames().is_empty() {
+            self
+        } else {
+            let mut tag = self.clone();
+            tag.load_lazy_frames()?;
+            loaded = tag;
+            &loaded
+        };
+
+        // Compared against the tag in the file before it is replaced
+        let entries = options.journal.map(|_| journal::pending(filename, this)).unwrap_or_default();
+        let journaled;
+        let this = if options.journal == Some(Journal::Tag) && !entries.is_empty() {
+            journaled = journal::with_history(this, &entries);
+            &journaled
+        } else {
+            this
+        };
+        let stamped;
+        let this = if options.stamp_tagging_time && target == 4 {
+            let mut tag = this.clone();
+            tag.set_tagging_time(&Timestamp::now());
+            stamped = tag;
+            &stamped
+        } else {
+            this
+        };
+        // Padding only goes in as far as the budget leaves room for it
+        let room = |tag: &Tag, padding: u64| match &options.budget {
+            Some(budget) => padding.min(budget.max_size().saturating_sub(tag.to_bytes(0).len()) as u64),
+            None => padding,
+        };
+        let (bytes, report, budget_report, v1) = if options.preserve && target == this.version() {
+            let (mut tag, budget_report) = options.prepare_reporting(this.with_hooks(&options.hooks));
+            tag.set_padding(room(&tag, tag.padding()));
+            (tag.to_bytes_preserving(), CompatibilityReport::new(target), budget_report, Id3v1::from_tag(&tag))
+        } else {
+            let (tag, report) = this.with_hooks(&options.hooks).convert(target);
+            let (tag, budget_report) = options.prepare_reporting(tag);
+            let padding = room(&tag, options.padding as u64) as usize;
+            (tag.to_bytes(padding), report, budget_report, Id3v1::from_tag(&tag))
+        };
+        if let Some(diagnostics) = &options.diagnostics {
+            for change in report.changes() {
+                if let Change::Dropped { id, reason } = change {
+                    diagnostics.report(Finding::DroppedOnWrite { id: id.clone(), reason: reason.clone() });
+                }
+            }
+            for trimmed in budget_report.iter().flat_map(|report| &report.trimmed) {
+                diagnostics.report(match trimmed.dropped {
+                    true => Finding::DroppedOnWrite { id: trimmed.id.clone(), reason: "over the size budget".to_string() },
+                    false => Finding::TrimmedOnWrite { id: trimmed.id.clone(), bytes: trimmed.bytes },
+                });
+            }
+        }
+        if let Some(budget) = options.budget.as_ref().filter(|budget| bytes.len() > budget.max_size()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Tag is {} bytes, over the budget of {}", bytes.len(), budget.max_size()),
+            ));
+        }
+        if options.sidecar {
+            sidecar::write(filename, &bytes)?;
+            return Ok(report);
+        }
+        let v1 = if options.write_id3v1 { v1.to_bytes().to_vec() } else { Vec::new() };
+
+        let mut original = File::open(long_path(filename))?;
+        let audio_start = existing_tag_size(&mut original)?;
+        let has_v1 = id3v1::read(&mut original)?.is_some();
+        original.seek(io::SeekFrom::Start(audio_start))?;
+
+        // Write next to the original and rename over it so a failure never leaves a half written file
+        let temp_path = temp_file(filename);
+        let metadata = original.metadata()?;
+        let strip_v1 = has_v1 && (options.write_id3v1 || options.remove_id3v1);
+        let audio_end = metadata.len() - if strip_v1 { id3v1::SIZE } else { 0 };
+        let total = (bytes.len() + v1.len()) as u64 + audio_end.saturating_sub(audio_start);
+        let result = (|| {
+            let mut writer = BufWriter::new(File::create(long_path(&temp_path))?);
+            writer.write_all(&bytes)?;
+            let mut written = bytes.len() as u64;
+            let mut reader = BufReader::new(original).take(audio_end.saturating_sub(audio_start));
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                if options.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                    return Err(Error::new(ErrorKind::Interrupted, "Write cancelled"));
+                }
+                if let Some(callback) = &options.progress {
+                    callback(written, total);
+                }
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                writer.write_all(&buffer[..read])?;
+                written += read as u64;
+            }
+            if !v1.is_empty() {
+                writer.write_all(&v1)?;
+                if let Some(callback) = &options.progress {
+                    callback(written + v1.len() as u64, total);
+                }
+            }
+            let file = writer.into_inner().map_err(|error| error.into_error())?;
+            options.restore_metadata(&file, &metadata)
+        })();
+
+        if let Err(error) = result {
+            let _ = fs::remove_file(long_path(&temp_path));
+            return Err(error);
+        }
+        fs::rename(long_path(&temp_path), long_path(filename))?;
+        if options.journal == Some(Journal::Sidecar) && !entries.is_empty() {
+            journal::append_sidecar(filename, &entries)?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{copy_of_test_file, temp_path};
+
+    fn audio(filename: impl AsRef<Path>) -> Vec<u8> {
+        let bytes = fs::read(filename).unwrap();
+        let start = Header::from_bytes(&bytes).unwrap().tag_size() as usize;
+        bytes[start..].to_vec()
+    }
+
+    #[test]
+    fn rewrite_keeps_audio() {
+        let path = copy_of_test_file("keeps-audio");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.frames_mut().retain(|frame| frame.id() != "APIC");
+        tag.write_to_file(&path, &WriteOptions::new()).unwrap();
+
+        let written = Tag::from_file(&path).unwrap();
+        assert_eq!(written.frames().len(), 8);
+        assert_eq!(audio(&path), audio("test/Polygondwanaland.mp3"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn retag_stream_replaces_stacked_tags() {
+        let original = fs::read("test/Polygondwanaland.mp3").unwrap();
+        let tag = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let mut new_tag = Tag::new(4);
+        new_tag.set_text("TIT2", "Crumbling Castle");
+
+        // A small tag stacked in front of the fixture's, both are replaced
+        let input = [Tag::new(3).to_bytes(16), original.clone()].concat();
+        let mut output = Vec::new();
+        let written = retag_stream(io::Cursor::new(input), &mut output, &new_tag).unwrap();
+        assert_eq!(written, output.len() as u64);
+        let start = Header::from_bytes(&output).unwrap().tag_size() as usize;
+        assert!(output[start..] == original[187217..]);
+        let parsed = Tag::from_reader(&mut crate::Reader::from_stream(io::Cursor::new(output.clone()))).unwrap();
+        assert_eq!(parsed.title().as_deref(), Some("Crumbling Castle"));
+
+        let mut untagged = Vec::new();
+        retag_stream(&original[187217..], &mut untagged, &tag).unwrap();
+        assert!(untagged == [tag.to_bytes(tag.padding() as usize), original[187217..].to_vec()].concat());
+        assert_eq!(retag_stream(&original[..1000], io::sink(), &tag).err().unwrap().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn write_in_frame_order() {
+        let path = copy_of_test_file("frame-order");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.frames_mut().reverse();
+        for preserve in [true, false] {
+            tag.write_to_file(&path, &WriteOptions::new().preserve(preserve).frame_order(FrameOrder::Recommended)).unwrap();
+            let ids: Vec<String> = Tag::from_file(&path).unwrap().frames().iter().map(Frame::id).collect();
+            assert_eq!(ids, ["TIT2", "TPE1", "TPE2", "TALB", "TRCK", "TYER", "TSRC", "COMM", "APIC"]);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn write_target_version() {
+        let path = copy_of_test_file("target-version");
+        let tag = Tag::from_file(&path).unwrap();
+        let report = tag.write_to_file(&path, &WriteOptions::new().version(4)).unwrap();
+
+        let written = Tag::from_file(&path).unwrap();
+        assert_eq!(written.version(), 4);
+        assert_eq!(report.target(), 4);
+        assert_eq!(written.frame("TDRC").unwrap().parse_text(), "2017");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn write_to_file_without_tag() {
+        let path = temp_path("untagged.mp3");
+        fs::write(&path, [0xFF, 0xFB, 0x90, 0x64]).unwrap();
+        let path = path.as_path();
+
+        let mut tag = Tag::new(3);
+        tag.add_frame(Frame::new("TIT2", b"\x00Title".to_vec()).unwrap());
+        tag.write_to_file(path, &WriteOptions::new().padding(0)).unwrap();
+
+        assert_eq!(Tag::from_file(path).unwrap().frame("TIT2").unwrap().parse_text(), "Title");
+        assert_eq!(audio(path), [0xFF, 0xFB, 0x90, 0x64]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn preserve_is_byte_stable() {
+        let path = copy_of_test_file("preserve");
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().preserve(true)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), fs::read("test/Polygondwanaland.mp3").unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn preserve_only_rewrites_modified() {
+        let path = copy_of_test_file("preserve-modified");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.frames_mut()[0] = Frame::new("TIT2", b"\x00Crumbling Castle".to_vec()).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().preserve(true)).unwrap();
+
+        let original = Tag::from_file("test/Polygondwanaland.mp3").unwrap();
+        let written = Tag::from_file(&path).unwrap();
+        assert_eq!(written.frame("TIT2").unwrap().parse_text(), "Crumbling Castle");
+        assert_eq!(written.frames()[1].raw_bytes(3), original.frames()[1].raw_bytes(3));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let tag = Tag::new(3);
+        let error = tag.write_to_file("test/Polygondwanaland.mp3", &WriteOptions::new().version(2)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn write_hooks_strip_frames() {
+        let path = copy_of_test_file("hooks");
+        let tag = Tag::from_file(&path).unwrap();
+        let options = WriteOptions::new().preserve(true).before_frame_written(|frame| match frame.id().as_str() {
+            "TSRC" => None,
+            "TIT2" => Frame::new("TIT2", b"\x00The Castle in the Air".to_vec()),
+            _ => Some(frame.clone()),
+        });
+        tag.write_to_file(&path, &options).unwrap();
+
+        let written = Tag::from_file(&path).unwrap();
+        assert!(written.frame("TSRC").is_none());
+        assert_eq!(written.title().unwrap(), "The Castle in the Air");
+        assert_eq!(tag.frames().len(), written.frames().len() + 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn utf16_big_endian_v23() {
+        let path = copy_of_test_file("utf16-be");
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().utf16(Utf16Policy::new().big_endian(true))).unwrap();
+
+        let written = Tag::from_file(&path).unwrap();
+        assert_eq!(written.frame("TIT2").unwrap().data()[..5], [0x01, 0xFE, 0xFF, 0x00, b'P']);
+        // The empty description and the text both get a BOM
+        assert_eq!(written.frame("COMM").unwrap().data()[..10], [0x01, b'e', b'n', b'g', 0xFE, 0xFF, 0x00, 0x00, 0xFE, 0xFF]);
+        for id in ["TIT2", "TPE1"] {
+            assert_eq!(written.frame(id).unwrap().parse_text(), tag.frame(id).unwrap().parse_text());
+        }
+        assert_eq!(written.comments()[0].text(), tag.comments()[0].text());
+        assert_eq!(written.pictures(), tag.pictures());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn utf16_big_endian_v24_uses_encoding_2() {
+        let path = copy_of_test_file("utf16-v24");
+        let tag = Tag::from_file(&path).unwrap();
+        let options = WriteOptions::new().version(4).utf16(Utf16Policy::new().big_endian(true));
+        tag.write_to_file(&path, &options).unwrap();
+
+        let written = Tag::from_file(&path).unwrap();
+        assert_eq!(written.frame("TPE1").unwrap().data()[..3], [0x02, 0x00, b'K']);
+        assert_eq!(written.frame("TPE1").unwrap().try_parse_text(), Ok(tag.artist().unwrap()));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn utf16_default_policy_keeps_preserved_bytes() {
+        let path = copy_of_test_file("utf16-default");
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().preserve(true).utf16(Utf16Policy::new())).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), fs::read("test/Polygondwanaland.mp3").unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn progress_reaches_total() {
+        let path = copy_of_test_file("progress");
+        let tag = Tag::from_file(&path).unwrap();
+        for write_id3v1 in [false, true] {
+            let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let recorded = calls.clone();
+            let options = WriteOptions::new().write_id3v1(write_id3v1).on_progress(move |written, total| recorded.lock().unwrap().push((written, total)));
+            tag.write_to_file(&path, &options).unwrap();
+
+            let calls = calls.lock().unwrap();
+            let size = fs::metadata(&path).unwrap().len();
+            assert_eq!(*calls.last().unwrap(), (size, size));
+            assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cancelled_write_leaves_file() {
+        let path = copy_of_test_file("cancelled");
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.set_text("TIT2", "Cancelled");
+        let options = WriteOptions::new().cancel_token(token).on_progress(move |_, _| cancel.cancel());
+
+        let error = tag.write_to_file(&path, &options).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Interrupted);
+        assert_eq!(fs::read(&path).unwrap(), fs::read("test/Polygondwanaland.mp3").unwrap());
+        assert!(!temp_file(&path).exists());
+        assert_ne!(temp_file(Path::new("song.mp3")), temp_file(Path::new("song.flac")));
+        assert_eq!(temp_file(Path::new("album/song.mp3")), Path::new("album/.song.mp3.mp3-tool.tmp"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn preserve_mtime() {
+        let path = copy_of_test_file("mtime");
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_500_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().preserve_mtime(true)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+
+        tag.write_to_file(&path, &WriteOptions::new()).unwrap();
+        assert_ne!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn preserve_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = copy_of_test_file("permissions");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().preserve_ownership(true)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn id3v1_kept_in_sync() {
+        let path = copy_of_test_file("id3v1");
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().write_id3v1(true)).unwrap();
+        tag.set_text("TIT2", "Crumbling Castle");
+        tag.write_to_file(&path, &WriteOptions::new().write_id3v1(true)).unwrap();
+
+        let v1 = id3v1::read_file(&path).unwrap().unwrap();
+        assert_eq!(v1.title, "Crumbling Castle");
+        // Only one v1 tag, the old one was replaced
+        let written = audio(&path);
+        assert_eq!(written[..written.len() - 128], audio("test/Polygondwanaland.mp3"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stale_id3v1_removed() {
+        let path = copy_of_test_file("id3v1-removed");
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().write_id3v1(true)).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new()).unwrap();
+        assert!(id3v1::read_file(&path).unwrap().is_some());
+
+        tag.write_to_file(&path, &WriteOptions::new().remove_id3v1(true)).unwrap();
+        assert!(id3v1::read_file(&path).unwrap().is_none());
+        assert_eq!(audio(&path), audio("test/Polygondwanaland.mp3"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn tagging_time_stamped() {
+        let path = copy_of_test_file("tagging-time");
+        let tag = Tag::from_file(&path).unwrap();
+        tag.write_to_file(&path, &WriteOptions::new().stamp_tagging_time(true)).unwrap();
+        assert_eq!(Tag::from_file(&path).unwrap().tagging_time(), None);
+
+        let before = Timestamp::now();
+        tag.write_to_file(&path, &WriteOptions::new().version(4).stamp_tagging_time(true)).unwrap();
+        let stamped = Tag::from_file(&path).unwrap().tagging_time().unwrap();
+        assert!(stamped >= before && stamped <= Timestamp::now());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn size_budget_enforced() {
+        let path = copy_of_test_file("budget");
+        let mut tag = Tag::from_file(&path).unwrap();
+        let unpadded = tag.to_bytes(0).len();
+        tag.add_frame(Frame::new("PRIV", [b"owner\0".to_vec(), vec![1; 2000]].concat()).unwrap());
+
+        let diagnostics = Diagnostics::new();
+        let options = WriteOptions::new().size_budget(SizeBudget::new(unpadded + 100)).diagnostics(diagnostics.clone());
+        tag.write_to_file(&path, &options).unwrap();
+        let written = Tag::from_file(&path).unwrap();
+        assert!(written.frame("PRIV").is_none());
+        assert_eq!(written.padding(), 100);
+        assert_eq!(diagnostics.findings(), [Finding::DroppedOnWrite { id: "PRIV".into(), reason: "over the size budget".into() }]);
+
+        let error = tag.write_to_file(&path, &WriteOptions::new().size_budget(SizeBudget::new(1000))).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_file_name() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Latin-1 é, which isn't valid UTF-8
+        let mut name = format!("mp3-tool-write-{}-caf", std::process::id()).into_bytes();
+        name.extend_from_slice(b"\xe9.mp3");
+        let path = std::env::temp_dir().join(OsStr::from_bytes(&name));
+        fs::copy("test/Polygondwanaland.mp3", &path).unwrap();
+        let mut tag = Tag::from_file(&path).unwrap();
+        tag.set_text("TIT2", "Caf\u{e9}");
+        tag.write_to_file(&path, &WriteOptions::new().journal(Journal::Sidecar)).unwrap();
+
+        assert_eq!(Tag::from_file(&path).unwrap().title().as_deref(), Some("Caf\u{e9}"));
+        let sidecar = journal::sidecar_path(&path);
+        assert!(sidecar.as_os_str().as_bytes().ends_with(b"caf\xe9.mp3.history"));
+        fs::remove_file(sidecar).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}